@@ -0,0 +1,481 @@
+use gdnative::prelude::Rid;
+
+use crate::effects::{Effect, StatBuff};
+
+/// Every ability a blueprint can carry. On-hit riders attach effects to the
+/// unit's basic attack; the rest become their own action entities in
+/// `spawn_unit`. Several variants are declared ahead of their wiring.
+#[derive(Clone)]
+pub enum UnitAbility {
+    // On-hit riders.
+    SlowPoison {
+        percent_damage: f32,
+        movement_debuff: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    Stun {
+        duration: f32,
+        texture: Rid,
+    },
+    Confusion {
+        duration: f32,
+        texture: Rid,
+    },
+    /// Flat Magic damage over time; the percent variant is `SlowPoison`.
+    Burn {
+        damage_per_second: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    AntiHeal {
+        percent: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    ArmorReduction {
+        duration: f32,
+        texture: Rid,
+    },
+    ChillOnHit {
+        slow_per_stack: f32,
+        max_stacks: i64,
+        freeze_duration: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    /// Bonus damage against low targets: hits that resolve while the victim
+    /// is below `threshold_percent` of max hp are multiplied by
+    /// `bonus_multiplier`. Rides the action entity rather than the effect
+    /// list; see `effects::ExecuteDamage`.
+    Execute {
+        threshold_percent: f32,
+        bonus_multiplier: f32,
+    },
+    /// Shuts down the target's basic attacks for the duration; its ability
+    /// casts proceed normally.
+    Disarm {
+        duration: f32,
+        texture: Rid,
+    },
+    /// Attack-speed slow: the victim's action cooldowns recover slower for
+    /// the duration.
+    AttackSlow {
+        percent: f32,
+        duration: f32,
+    },
+
+    // Active abilities with their own action entities.
+    Backstab {
+        damage: f32,
+        range: f32,
+        cooldown: f32,
+        /// Seconds of cooldown at spawn; 0.0 means ready immediately.
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    Cleanse {
+        heal_amount: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    Heal {
+        heal_amount: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    Fortify {
+        armor_buff: f32,
+        armor_mult: f32,
+        heal_per_second: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    BuffResistance {
+        armor_buff: f32,
+        armor_mult: f32,
+        magic_resist_buff: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    Overdrive {
+        percent_cooldown_reduction: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    DamageBuff {
+        percent: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    SpeedAndMassBuffAbility {
+        speed_buff: f32,
+        speed_mult: f32,
+        mass_buff: f32,
+        mass_mult: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    Whirlwind {
+        damage: f32,
+        radius: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        impact_delay: f32,
+        texture: Rid,
+    },
+    /// Passive: redirect a fraction of a nearby ally's incoming damage to
+    /// this unit.
+    Bodyguard {
+        redirect_fraction: f32,
+        radius: f32,
+        texture: Rid,
+    },
+    MagicMissile {
+        damage: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        impact_delay: f32,
+        projectile_speed: f32,
+        projectile_texture: Rid,
+        splash_radius: f32,
+    },
+
+    /// Summons a stationary heal-pulse totem; see `Effect::SummonTotemEffect`.
+    HealTotem {
+        heal_per_pulse: f32,
+        pulse_period: f32,
+        pulse_radius: f32,
+        totem_hitpoints: f32,
+        totem_radius: f32,
+        lifetime: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    /// Summons a stationary damage-pulse totem.
+    DamageTotem {
+        damage_per_pulse: f32,
+        pulse_period: f32,
+        pulse_radius: f32,
+        totem_hitpoints: f32,
+        totem_radius: f32,
+        lifetime: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+
+    /// Plants a proximity mine at the caster's feet; see
+    /// `Effect::PlaceMineEffect`.
+    PlaceMineAbility {
+        damage: f32,
+        trigger_radius: f32,
+        arm_time: f32,
+        cooldown: f32,
+        texture: Rid,
+    },
+
+    /// Lobbed stun grenade; damage and stun splash within `radius`.
+    BubbleBombAbility {
+        damage: f32,
+        range: f32,
+        radius: f32,
+        stun_duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        projectile_speed: f32,
+        effect_texture: Rid,
+    },
+    /// Ranged interrupt: instant magic damage plus a stun, preferring
+    /// targets that are mid-cast.
+    InstantStunAbility {
+        damage: f32,
+        range: f32,
+        stun_duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        effect_texture: Rid,
+    },
+    /// Ground-targeted artillery strike: autocast aims at the densest enemy
+    /// cluster in range and the blast lands around the point after the
+    /// wind-up, telegraphed by a sprite at the aim point.
+    GroundAoeAbility {
+        damage: f32,
+        radius: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        telegraph_texture: Rid,
+        effect_texture: Rid,
+    },
+    /// Ranged disarm cast: the target's weapon actions shut down for the
+    /// duration while its ability casts continue.
+    InstantDisarmAbility {
+        range: f32,
+        duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        effect_texture: Rid,
+    },
+    /// Converts an enemy to the caster's team until the buff expires.
+    HypnosisAbility {
+        range: f32,
+        duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    /// Self-cast heal, used whenever the unit is injured; subject to
+    /// antiheal and HealEfficacy like any other heal.
+    SelfHealAbility {
+        heal_amount: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    /// Self-cast attack-speed steroid: faster cooldown recovery on the
+    /// unit's own actions for the duration.
+    SelfOverclockAbility {
+        percent_cooldown_reduction: f32,
+        duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    /// Ally-targeted dispel: cleanses (and optionally heals) every allied
+    /// unit within `radius` of the primary target.
+    AreaCleanseAbility {
+        radius: f32,
+        heal_amount: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    /// Suicide bomber: closing to contact detonates the unit, splashing
+    /// `damage` within `radius` of the corpse. Allies are spared.
+    BanelingAttack {
+        damage: f32,
+        radius: f32,
+    },
+    /// On death, heal every living unit on the side the unit is on when it
+    /// dies.
+    HealBaneling {
+        heal_amount: f32,
+    },
+    /// On-kill sustain: the unit heals `heal_amount` whenever its own basic
+    /// attack lands a killing blow.
+    ExecutionAttack {
+        heal_amount: f32,
+    },
+    /// On death, splash a slow poison over everything within `radius` of the
+    /// corpse.
+    PoisonBaneling {
+        radius: f32,
+        duration: f32,
+        percent_damage: f32,
+        movement_debuff: f32,
+        texture: Rid,
+    },
+    /// Passive: heals the unit for `percent` of the post-mitigation damage
+    /// its hits deal.
+    Lifesteal {
+        percent: f32,
+    },
+    /// Ally-targeted thorns buff: the target reflects Normal damage back at
+    /// its attackers for the duration.
+    ThornsAbility {
+        percent: f32,
+        flat: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        initial_cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    /// Passive: the unit permanently reflects Normal damage; see
+    /// `ThornsBuff`.
+    ThornsPassive {
+        percent: f32,
+        flat: f32,
+    },
+    /// Passive: surge of speed and attack speed while the unit sits below an
+    /// hp threshold; see `effects::BerserkPassive`.
+    BerserkPassiveAbility {
+        hp_threshold_percent: f32,
+        attack_speed_bonus: f32,
+        speed_bonus: f32,
+    },
+    /// Periodically summons `count` short-lived copies of another blueprint
+    /// around the caster; each dies after `duration` seconds.
+    SummonAbility {
+        summon_blueprint_id: usize,
+        count: i64,
+        duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    /// Banner-carrier passive: everything on the chosen side within
+    /// `radius` holds `buff` while it stays in range; see `effects::Aura`.
+    AuraAbility {
+        buff: StatBuff,
+        radius: f32,
+        affects_allies: bool,
+    },
+    /// Dash at an enemy in `range` at `dash_speed`; contact delivers
+    /// `impact_damage` plus a stun, walls stop the charge short.
+    ChargeAbility {
+        dash_speed: f32,
+        max_distance: f32,
+        impact_damage: f32,
+        impact_stun: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+    /// Self-cast teleport `distance` toward the enemy — along the charge
+    /// target when one is in reach, otherwise the flow field — clamped to
+    /// pathable terrain.
+    BlinkAbility {
+        distance: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    },
+}
+
+impl UnitAbility {
+    /// The on-hit effect a rider contributes to its weapon's action, or None
+    /// for abilities that are not riders.
+    pub fn on_hit_effect(&self) -> Option<Effect> {
+        match self {
+            UnitAbility::SlowPoison {
+                percent_damage,
+                movement_debuff,
+                duration,
+                texture,
+            } => Some(Effect::PoisonEffect {
+                percent_damage: *percent_damage,
+                movement_debuff: *movement_debuff,
+                duration: *duration,
+                texture: *texture,
+            }),
+            UnitAbility::Stun { duration, texture } => Some(Effect::StunEffect {
+                duration: *duration,
+                texture: *texture,
+            }),
+            UnitAbility::Confusion { duration, texture } => Some(Effect::ConfusionEffect {
+                duration: *duration,
+                texture: *texture,
+            }),
+            UnitAbility::Burn {
+                damage_per_second,
+                duration,
+                texture,
+            } => Some(Effect::BurnEffect {
+                damage_per_second: *damage_per_second,
+                duration: *duration,
+                texture: *texture,
+            }),
+            UnitAbility::AntiHeal {
+                percent,
+                duration,
+                texture,
+            } => Some(Effect::AntihealOnHitEffect {
+                percent: *percent,
+                duration: *duration,
+                texture: *texture,
+            }),
+            UnitAbility::ArmorReduction { duration, texture } => Some(Effect::ShredArmorEffect {
+                duration: *duration,
+                texture: *texture,
+            }),
+            UnitAbility::ChillOnHit {
+                slow_per_stack,
+                max_stacks,
+                freeze_duration,
+                duration,
+                texture,
+            } => Some(Effect::Chill {
+                slow_per_stack: *slow_per_stack,
+                max_stacks: *max_stacks,
+                freeze_duration: *freeze_duration,
+                duration: *duration,
+                texture: *texture,
+            }),
+            UnitAbility::Disarm { duration, texture } => Some(Effect::Disarm {
+                duration: *duration,
+                texture: *texture,
+            }),
+            UnitAbility::AttackSlow { percent, duration } => Some(Effect::AttackSlowEffect {
+                percent: *percent,
+                duration: *duration,
+            }),
+            _ => None,
+        }
+    }
+}
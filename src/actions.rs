@@ -0,0 +1,3543 @@
+use bevy_ecs::prelude::*;
+use gdnative::prelude::*;
+use rand::Rng;
+
+use std::collections::HashMap;
+
+use crate::effects::{
+    BuffHolder, BuffType, Effect, ExecuteDamage, FlatDamageBuff, PercentDamageBuff, QueuedEffect,
+    ResolveEffectsBuffer, Stealthed, Stunned,
+};
+use crate::graphics::animation::{AnimationRole, PlayAnimationDirective};
+use crate::physics::{DeltaPhysics, Position, Radius, SpatialNeighborsCache};
+use crate::unit::{AttackTargetDirective, Evasion, Hitpoints, TeamAlignment};
+use crate::util::SimRng;
+
+/// How `target_units` ranks the qualifying in-range candidates.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum TargetSelection {
+    Nearest,
+    Furthest,
+    /// Lowest hp/max_hp wins, ties broken by distance: heals chase the
+    /// almost-dead unit behind the line, not the scratched frontliner.
+    LowestHpFraction,
+    /// Highest entry in the caster's [`ThreatTable`] wins, ties broken by
+    /// distance: tanks turn on whoever has been hurting them most.
+    HighestThreat,
+}
+
+/// Which units an action may pick as its target.
+#[derive(Component, Copy, Clone)]
+pub struct TargetFlags {
+    pub target_allies: bool,
+    pub target_enemies: bool,
+    /// Only consider targets below max hp (heals).
+    pub needs_injured: bool,
+    /// Only consider targets carrying a debuff (cleanses).
+    pub needs_debuff: bool,
+    /// Strongly prefer targets that are mid-cast (interrupts).
+    pub prefers_casting: bool,
+    /// Prefer targets already fighting: mid-cast, or holding an action on
+    /// cooldown (buffs).
+    pub prefers_combat: bool,
+    /// The caster is its own target; `target_units` skips the neighbor scan.
+    pub target_self: bool,
+    pub selection: TargetSelection,
+}
+
+impl TargetFlags {
+    pub fn normal_attack() -> Self {
+        Self {
+            target_allies: false,
+            target_enemies: true,
+            needs_injured: false,
+            needs_debuff: false,
+            prefers_casting: false,
+            prefers_combat: false,
+            target_self: false,
+            selection: TargetSelection::Nearest,
+        }
+    }
+
+    /// Normal-attack flags that home in on casting targets first.
+    pub fn interrupt() -> Self {
+        Self {
+            prefers_casting: true,
+            ..Self::normal_attack()
+        }
+    }
+
+    pub fn heal() -> Self {
+        Self {
+            target_allies: true,
+            target_enemies: false,
+            needs_injured: true,
+            selection: TargetSelection::LowestHpFraction,
+            ..Self::normal_attack()
+        }
+    }
+
+    pub fn cleanse() -> Self {
+        Self {
+            target_allies: true,
+            target_enemies: false,
+            needs_debuff: true,
+            ..Self::normal_attack()
+        }
+    }
+
+    /// Ally-buff flags: any living ally qualifies, but allies already
+    /// fighting sort first so the buff lands where it matters.
+    pub fn normal_buff() -> Self {
+        Self {
+            target_allies: true,
+            target_enemies: false,
+            prefers_combat: true,
+            ..Self::normal_attack()
+        }
+    }
+
+    /// Normal-attack flags that pick the farthest in-range enemy instead of
+    /// the nearest (backstab).
+    pub fn furthest_enemy() -> Self {
+        Self {
+            selection: TargetSelection::Furthest,
+            ..Self::normal_attack()
+        }
+    }
+
+    /// Self-cast flags: no neighbor search, the caster is its own target.
+    pub fn self_cast() -> Self {
+        Self {
+            target_enemies: false,
+            target_self: true,
+            ..Self::normal_attack()
+        }
+    }
+}
+
+/// Threat entries tied within this margin fall back to the lower entity
+/// index, like distance ties in target selection.
+pub const THREAT_TIE_EPSILON: f32 = 1e-3;
+
+/// Seconds for an untouched threat entry to halve.
+pub const THREAT_HALF_LIFE: f32 = 6.0;
+
+/// Entries decayed below this are dropped outright so tables stay small.
+const THREAT_FLOOR: f32 = 0.1;
+
+/// Aggro accumulated against this unit, per attacker. `apply_damages` adds
+/// the post-mitigation damage of every landing hit plus the attacker's flat
+/// [`ThreatBonus`]; heals add nothing. A taunt can simply write an
+/// arbitrarily large entry directly.
+#[derive(Component, Default)]
+pub struct ThreatTable {
+    pub map: HashMap<Entity, f32>,
+}
+
+impl ThreatTable {
+    pub fn add(&mut self, attacker: Entity, amount: f32) {
+        *self.map.entry(attacker).or_insert(0.0) += amount;
+    }
+
+    pub fn get(&self, attacker: Entity) -> f32 {
+        self.map.get(&attacker).copied().unwrap_or(0.0)
+    }
+
+    /// Highest-threat attacker; ties within [`THREAT_TIE_EPSILON`] go to the
+    /// lower entity index so the pick is stable across machines.
+    pub fn top(&self) -> Option<(Entity, f32)> {
+        let mut best: Option<(Entity, f32)> = None;
+        for (&attacker, &threat) in self.map.iter() {
+            best = match best {
+                None => Some((attacker, threat)),
+                Some((best_entity, best_threat)) => {
+                    let higher = threat > best_threat + THREAT_TIE_EPSILON;
+                    let tied = (threat - best_threat).abs() <= THREAT_TIE_EPSILON;
+                    if higher || (tied && attacker.id() < best_entity.id()) {
+                        Some((attacker, threat))
+                    } else {
+                        Some((best_entity, best_threat))
+                    }
+                }
+            };
+        }
+        best
+    }
+}
+
+/// Flat threat this unit generates on top of the damage every time one of
+/// its hits lands; tanks carry it to hold aggro against harder hitters.
+#[derive(Component, Copy, Clone)]
+pub struct ThreatBonus(pub f32);
+
+/// Halve every threat entry on the [`THREAT_HALF_LIFE`] cadence and drop
+/// the dust, so old grudges fade once the shooting stops.
+pub fn threat_decay(delta: Res<DeltaPhysics>, mut query: Query<&mut ThreatTable>) {
+    let factor = 0.5_f32.powf(delta.seconds / THREAT_HALF_LIFE);
+    for mut table in query.iter_mut() {
+        if table.map.is_empty() {
+            continue;
+        }
+        for threat in table.map.values_mut() {
+            *threat *= factor;
+        }
+        table.map.retain(|_, threat| *threat >= THREAT_FLOOR);
+    }
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct ActionRange(pub f32);
+
+/// Base cooldown of the action in seconds.
+#[derive(Component, Copy, Clone)]
+pub struct ActionCooldown(pub f32);
+
+/// Active countdown; present only while the action is recharging.
+#[derive(Component, Copy, Clone)]
+pub struct Cooldown(pub f32);
+
+#[derive(Component, Copy, Clone)]
+pub struct SwingDetails {
+    pub impact_time: f32,
+    pub swing_time: f32,
+}
+
+#[derive(Component, Copy, Clone, PartialEq)]
+pub enum ImpactType {
+    Instant,
+    Projectile,
+    /// Ground-targeted blast: autocast aims the action at a point — the
+    /// densest enemy cluster in range — and the effects land around it
+    /// once the wind-up crosses impact_time.
+    GroundArea,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct ActionProjectileDetails {
+    pub projectile_speed: f32,
+    pub projectile_texture: Rid,
+    pub projectile_scale: f32,
+    pub contact_distance: f32,
+}
+
+/// Effects pushed at the target when the swing lands.
+#[derive(Component, Clone)]
+pub struct OnHitEffects {
+    pub vec: Vec<Effect>,
+}
+
+/// Arc around the primary target that also receives the action's effects.
+#[derive(Component, Copy, Clone)]
+pub struct Cleave {
+    pub angle_degrees: f32,
+}
+
+/// Fraction of the blast radius inside which a falloff splash still carries
+/// its non-damage effects (stuns, poisons).
+const RIDER_SPLASH_FRACTION: f32 = 0.5;
+
+/// AoE radius applied around a projectile's impact point.
+#[derive(Component, Copy, Clone)]
+pub struct Splash {
+    pub radius: f32,
+    /// Damage fraction left at the blast's edge: damage scales linearly from
+    /// full at the impact point down to this. 1.0 means no falloff, the
+    /// historical behavior.
+    pub min_percent: f32,
+}
+
+impl Splash {
+    /// Linear damage multiplier at `distance` from the impact point.
+    pub fn damage_scale(&self, distance: f32) -> f32 {
+        if self.radius <= 0.0 {
+            return 1.0;
+        }
+        let t = (distance / self.radius).clamp(0.0, 1.0);
+        1.0 - (1.0 - self.min_percent) * t
+    }
+
+    /// Whether non-damage effects still land at `distance`: everywhere for a
+    /// flat splash, only the inner half of the blast once falloff is on.
+    pub fn carries_riders(&self, distance: f32) -> bool {
+        self.min_percent >= 1.0 || distance <= self.radius * RIDER_SPLASH_FRACTION
+    }
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct ChannelingDetails {
+    pub total_time_channeled: f32,
+}
+
+/// Impact visual spawned at the point of contact.
+#[derive(Component, Copy, Clone)]
+pub struct EffectTexture(pub Rid);
+
+/// Telegraph sprite a GroundArea action shows at its aim point for the whole
+/// wind-up before the blast lands.
+#[derive(Component, Copy, Clone)]
+pub struct TelegraphTexture(pub Rid);
+
+/// Where projectiles and caster-anchored impact visuals originate, relative
+/// to the performer's center. The x component mirrors with facing.
+#[derive(Component, Copy, Clone)]
+pub struct MuzzleOffset(pub Vector2);
+
+/// Where an instant action's impact visual spawns; absent means Target.
+#[derive(Component, Copy, Clone)]
+pub enum ImpactVisualAnchor {
+    Caster,
+    Target,
+    Midpoint,
+}
+
+/// A muzzle offset as applied in world space for the given facing.
+pub fn resolved_muzzle_offset(offset: Vector2, flipped: bool) -> Vector2 {
+    if flipped {
+        Vector2::new(-offset.x, offset.y)
+    } else {
+        offset
+    }
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct ActionOwner(pub Entity);
+
+#[derive(Component, Copy, Clone)]
+pub struct TargetEntity(pub Entity);
+
+/// The action's target from its previous swing, preferred while it stays
+/// valid so units stop ping-ponging between equidistant enemies.
+#[derive(Component, Copy, Clone)]
+pub struct LastTarget(pub Entity);
+
+/// Hysteresis knobs for target stickiness, settable from GDScript.
+pub struct TargetStickiness {
+    /// The remembered target stays valid up to range times this margin.
+    pub range_margin: f32,
+    /// A challenger must be closer than the remembered target's distance
+    /// times this factor to force a switch.
+    pub switch_factor: f32,
+}
+
+impl Default for TargetStickiness {
+    fn default() -> Self {
+        Self {
+            range_margin: 1.15,
+            switch_factor: 0.6,
+        }
+    }
+}
+
+/// Mid-swing range tolerance: a channel aborts when its target drifts past
+/// ActionRange times this margin before the hit lands. Wider than the
+/// stickiness margin so ordinary jostling does not cancel casts.
+pub struct ChannelInterruptLeeway {
+    pub range_margin: f32,
+}
+
+impl Default for ChannelInterruptLeeway {
+    fn default() -> Self {
+        Self { range_margin: 1.25 }
+    }
+}
+
+/// Ground-targeted actions carry this instead of TargetEntity while they
+/// swing; the AoE lands around the point.
+#[derive(Component, Copy, Clone)]
+pub struct TargetPosition(pub Vector2);
+
+/// Marker for actions that `command_cast_at_position` may aim at a point.
+#[derive(Component, Copy, Clone)]
+pub struct GroundTargetable;
+
+/// Role marker on actions spawned from a blueprint weapon; disarms hit these.
+#[derive(Component, Copy, Clone)]
+pub struct BasicAttack;
+
+/// On an action: its performer brakes to a standstill for the whole swing
+/// instead of drifting on boid forces (move-and-shoot off).
+#[derive(Component, Copy, Clone)]
+pub struct StationaryWhileActing;
+
+/// Scripted or debuff-driven off switch: targeting and autocast skip the
+/// action, but a swing already in progress is allowed to finish.
+#[derive(Component, Copy, Clone)]
+pub struct Disabled;
+
+/// Manual-control marker: `target_units` never picks this action, so it only
+/// fires through `command_cast_at_entity` / `command_cast_at_position`.
+#[derive(Component, Copy, Clone)]
+pub struct AutocastDisabled;
+
+#[derive(Component, Clone)]
+pub struct UnitActions {
+    pub vec: Vec<Entity>,
+}
+
+/// Present on a unit while it is mid-swing; points at the action entity.
+#[derive(Component, Copy, Clone)]
+pub struct PerformingActionState {
+    pub action: Entity,
+}
+
+#[derive(Bundle)]
+pub struct ActionBundle {
+    pub owner: ActionOwner,
+    pub range: ActionRange,
+    pub cooldown: ActionCooldown,
+    pub swing: SwingDetails,
+    pub impact_type: ImpactType,
+    pub effects: OnHitEffects,
+    pub flags: TargetFlags,
+    pub channeling: ChannelingDetails,
+    pub priority: ActionPriority,
+}
+
+/// Default priority for basic-attack actions.
+pub const BASIC_ATTACK_PRIORITY: i64 = 0;
+
+/// Default priority for ability actions, so a ready Heal or Cleanse
+/// pre-empts the auto-attack instead of queueing behind it forever.
+pub const ABILITY_PRIORITY: i64 = 10;
+
+/// Scan order of a unit's actions in `target_units`: higher fires first,
+/// ties go to the longer base cooldown on the theory that the scarcer
+/// action is the more valuable one.
+#[derive(Component, Copy, Clone)]
+pub struct ActionPriority(pub i64);
+
+/// Structures only get picked when no ordinary unit scores better; their
+/// distances are inflated by this factor before comparison.
+const STRUCTURE_TARGET_PENALTY: f32 = 3.0;
+
+/// For `prefers_casting` actions, a mid-cast target's distance shrinks by
+/// this factor so it beats idle targets well past it.
+const CASTING_TARGET_BONUS: f32 = 0.25;
+
+/// For `prefers_combat` actions, a fighting target's distance shrinks by
+/// this factor; milder than the casting bonus, since a buff on any engaged
+/// ally is usually fine.
+const COMBAT_TARGET_BONUS: f32 = 0.5;
+
+/// Pick a target for the first ready action of every idle unit. The action's
+/// LastTarget is kept while valid (hysteresis via TargetStickiness); forced
+/// targets from AttackTargetDirective override stickiness.
+pub fn target_units(
+    mut commands: Commands,
+    neighbors: Option<Res<SpatialNeighborsCache>>,
+    stickiness: Option<Res<TargetStickiness>>,
+    fog: Option<Res<crate::terrain::FogOfWar>>,
+    terrain: Option<Res<crate::terrain::TerrainMap>>,
+    position_query: Query<&Position>,
+    unit_query: Query<
+        (
+            Entity,
+            &UnitActions,
+            &TeamAlignment,
+            Option<&AttackTargetDirective>,
+        ),
+        (Without<Stunned>, Without<PerformingActionState>),
+    >,
+    spatial: Option<Res<crate::physics::SpatialHashTable>>,
+    action_query: Query<
+        (
+            &ActionRange,
+            &TargetFlags,
+            Option<&LastTarget>,
+            Option<&ImpactType>,
+        ),
+        (
+            With<ActionOwner>,
+            Without<Cooldown>,
+            Without<Disabled>,
+            Without<AutocastDisabled>,
+        ),
+    >,
+    hitpoints_query: Query<&Hitpoints>,
+    holder_query: Query<&BuffHolder>,
+    buff_type_query: Query<&BuffType>,
+    stealth_query: Query<(), With<Stealthed>>,
+    structure_query: Query<(), With<crate::unit::Structure>>,
+    casting_query: Query<(), (With<PerformingActionState>, Without<Stunned>)>,
+    neighbor_actions_query: Query<&UnitActions>,
+    on_cooldown_query: Query<(), With<Cooldown>>,
+    threat_query: Query<&ThreatTable>,
+    priority_query: Query<(&ActionPriority, &ActionCooldown)>,
+) {
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    let default_stickiness = TargetStickiness::default();
+    let stickiness = stickiness
+        .as_deref()
+        .unwrap_or(&default_stickiness);
+    for (entity, actions, alignment, forced) in unit_query.iter() {
+        // Drop a focus-fire order once its target is gone.
+        if let Some(forced) = forced {
+            let target_alive = hitpoints_query
+                .get(forced.target)
+                .map(|hitpoints| hitpoints.hp > 0.0)
+                .unwrap_or(false);
+            if !target_alive {
+                commands.entity(entity).remove::<AttackTargetDirective>();
+            }
+        }
+        // Scan actions by priority, not insertion order, so situational
+        // abilities get first refusal and the basic attack mops up.
+        let mut ordered: Vec<Entity> = actions.vec.clone();
+        ordered.sort_by(|a, b| {
+            let rank = |action: Entity| {
+                priority_query
+                    .get(action)
+                    .map(|(priority, cooldown)| (priority.0, cooldown.0))
+                    .unwrap_or((BASIC_ATTACK_PRIORITY, 0.0))
+            };
+            let (priority_a, cooldown_a) = rank(*a);
+            let (priority_b, cooldown_b) = rank(*b);
+            priority_b
+                .cmp(&priority_a)
+                .then(cooldown_b.total_cmp(&cooldown_a))
+        });
+        for action_entity in ordered.iter() {
+            let (range, flags, last_target, impact_type) =
+                match action_query.get(*action_entity) {
+                    Ok(parts) => parts,
+                    Err(_) => continue,
+                };
+            // Ground AoEs aim at mass, not at a unit: score every spatial
+            // cell in range by its enemy head count and target the best
+            // cell's enemy centroid.
+            if impact_type == Some(&ImpactType::GroundArea) {
+                let spatial = match spatial.as_ref() {
+                    Some(spatial) => spatial,
+                    None => continue,
+                };
+                let caster_position = match position_query.get(entity) {
+                    Ok(position) => position.pos,
+                    Err(_) => continue,
+                };
+                let mut best: Option<(usize, Vector2)> = None;
+                for hash in
+                    spatial.get_all_spatial_hashes_from_circle(caster_position, range.0)
+                {
+                    let entries = match spatial.table.get(&hash) {
+                        Some(entries) => entries,
+                        None => continue,
+                    };
+                    let mut count = 0;
+                    let mut sum = Vector2::ZERO;
+                    for entry in entries {
+                        if entry.team == alignment.alignment {
+                            continue;
+                        }
+                        if stealth_query.get(entry.entity).is_ok() {
+                            continue;
+                        }
+                        let alive = hitpoints_query
+                            .get(entry.entity)
+                            .map(|hitpoints| hitpoints.hp > 0.0)
+                            .unwrap_or(false);
+                        if !alive {
+                            continue;
+                        }
+                        count += 1;
+                        sum += entry.position;
+                    }
+                    if count == 0 {
+                        continue;
+                    }
+                    let centroid = sum / count as f32;
+                    if caster_position.distance_to(centroid) > range.0 {
+                        continue;
+                    }
+                    if best.map(|(most, _)| count > most).unwrap_or(true) {
+                        best = Some((count, centroid));
+                    }
+                }
+                if let Some((_, point)) = best {
+                    commands
+                        .entity(*action_entity)
+                        .insert(TargetPosition(point));
+                    break;
+                }
+                continue;
+            }
+            // Self-casts skip the neighbor scan; the only gate is the
+            // caster's own state.
+            if flags.target_self {
+                let ready = match hitpoints_query.get(entity) {
+                    Ok(hitpoints) => {
+                        hitpoints.hp > 0.0
+                            && (!flags.needs_injured || hitpoints.hp < hitpoints.max_hp)
+                    }
+                    Err(_) => false,
+                };
+                if ready {
+                    commands
+                        .entity(*action_entity)
+                        .insert(TargetEntity(entity))
+                        .insert(LastTarget(entity));
+                    break;
+                }
+                continue;
+            }
+            let mut candidates: Vec<(Entity, f32, f32)> = Vec::new();
+            let mut last: Option<(Entity, f32)> = None;
+            let mut forced_pick: Option<Entity> = None;
+            if let Some(neighbor_list) = neighbors.get_neighbors(&entity) {
+                for neighbor in neighbor_list.iter() {
+                    let is_ally = neighbor.team == alignment.alignment;
+                    if is_ally && !flags.target_allies {
+                        continue;
+                    }
+                    if !is_ally && !flags.target_enemies {
+                        continue;
+                    }
+                    if !is_ally {
+                        // Stealthed enemies are untargetable outright;
+                        // projectiles already in flight still land.
+                        if stealth_query.get(neighbor.entity).is_ok() {
+                            continue;
+                        }
+                        if let (Some(fog), Some(terrain)) = (fog.as_ref(), terrain.as_ref()) {
+                            let seen = position_query
+                                .get(neighbor.entity)
+                                .map(|p| fog.is_visible(alignment.alignment, terrain, p.pos))
+                                .unwrap_or(false);
+                            if !seen {
+                                continue;
+                            }
+                        }
+                    }
+                    let hitpoints = match hitpoints_query.get(neighbor.entity) {
+                        Ok(hp) => hp,
+                        Err(_) => continue,
+                    };
+                    if hitpoints.hp <= 0.0 {
+                        continue;
+                    }
+                    if flags.needs_injured && hitpoints.hp >= hitpoints.max_hp {
+                        continue;
+                    }
+                    if flags.needs_debuff {
+                        let debuffed = holder_query
+                            .get(neighbor.entity)
+                            .map(|holder| crate::effects::has_debuff(holder, &buff_type_query))
+                            .unwrap_or(false);
+                        if !debuffed {
+                            continue;
+                        }
+                    }
+                    // The remembered target tolerates a wider range band.
+                    if let Some(last_target) = last_target {
+                        if neighbor.entity == last_target.0
+                            && neighbor.distance <= range.0 * stickiness.range_margin
+                        {
+                            last = Some((neighbor.entity, neighbor.distance));
+                        }
+                    }
+                    if neighbor.distance > range.0 {
+                        continue;
+                    }
+                    if let Some(forced) = forced {
+                        if neighbor.entity == forced.target {
+                            forced_pick = Some(neighbor.entity);
+                        }
+                    }
+                    // Furthest flips the sign so the shared minimizing tail
+                    // keeps working; LowestHpFraction ranks by hp fraction
+                    // instead of distance, HighestThreat by negated aggro.
+                    // The structure and combat biases only shape the plain
+                    // nearest metric.
+                    let scored = match flags.selection {
+                        TargetSelection::Furthest => -neighbor.distance,
+                        TargetSelection::LowestHpFraction => hitpoints.hp / hitpoints.max_hp,
+                        TargetSelection::HighestThreat => -threat_query
+                            .get(entity)
+                            .map(|table| table.get(neighbor.entity))
+                            .unwrap_or(0.0),
+                        TargetSelection::Nearest => {
+                            let mut scored = if structure_query.get(neighbor.entity).is_ok() {
+                                neighbor.distance * STRUCTURE_TARGET_PENALTY
+                            } else {
+                                neighbor.distance
+                            };
+                            if flags.prefers_casting
+                                && casting_query.get(neighbor.entity).is_ok()
+                            {
+                                scored *= CASTING_TARGET_BONUS;
+                            }
+                            if flags.prefers_combat {
+                                let fighting = casting_query.get(neighbor.entity).is_ok()
+                                    || neighbor_actions_query
+                                        .get(neighbor.entity)
+                                        .map(|actions| {
+                                            actions.vec.iter().any(|action| {
+                                                on_cooldown_query.get(*action).is_ok()
+                                            })
+                                        })
+                                        .unwrap_or(false);
+                                if fighting {
+                                    scored *= COMBAT_TARGET_BONUS;
+                                }
+                            }
+                            scored
+                        }
+                    };
+                    candidates.push((neighbor.entity, scored, neighbor.distance));
+                }
+            }
+            let best = match flags.selection {
+                TargetSelection::LowestHpFraction | TargetSelection::HighestThreat => {
+                    crate::util::select_lowest_fraction(candidates.into_iter())
+                }
+                _ => crate::util::select_nearest(
+                    candidates.into_iter().map(|(entity, scored, _)| (entity, scored)),
+                ),
+            };
+            // Stickiness is a nearest-mode idea; the other selections
+            // re-rank every frame, so their remembered target gets no edge.
+            let last = if flags.selection == TargetSelection::Nearest {
+                last
+            } else {
+                None
+            };
+            let pick = if let Some(forced) = forced_pick {
+                Some(forced)
+            } else if let Some((last_entity, last_distance)) = last {
+                match best {
+                    Some((challenger, challenger_distance))
+                        if challenger != last_entity
+                            && challenger_distance
+                                < last_distance * stickiness.switch_factor =>
+                    {
+                        Some(challenger)
+                    }
+                    _ => Some(last_entity),
+                }
+            } else {
+                best.map(|(target, _)| target)
+            };
+            if let Some(target) = pick {
+                commands
+                    .entity(*action_entity)
+                    .insert(TargetEntity(target))
+                    .insert(LastTarget(target));
+                break;
+            }
+        }
+    }
+}
+
+/// Start the swing for any idle unit whose action acquired a target.
+pub fn perform_action(
+    mut commands: Commands,
+    mut log: Option<ResMut<crate::event::MatchLog>>,
+    mut events: Option<ResMut<crate::event::EventQueue>>,
+    unit_query: Query<
+        (Entity, &UnitActions),
+        (Without<Stunned>, Without<PerformingActionState>),
+    >,
+    mut action_query: Query<
+        (
+            &ActionCooldown,
+            &mut ChannelingDetails,
+            Option<&SwingDetails>,
+            Option<&OnHitEffects>,
+        ),
+        (
+            Or<(With<TargetEntity>, With<TargetPosition>)>,
+            Without<Cooldown>,
+            Without<Disabled>,
+        ),
+    >,
+) {
+    for (entity, actions) in unit_query.iter() {
+        for action_entity in actions.vec.iter() {
+            if let Ok((cooldown, mut channeling, swing, on_hit)) =
+                action_query.get_mut(*action_entity)
+            {
+                channeling.total_time_channeled = 0.0;
+                commands.entity(*action_entity).insert(Cooldown(cooldown.0));
+                if let Some(log) = log.as_mut() {
+                    let ability = on_hit
+                        .and_then(|on_hit| on_hit.vec.first())
+                        .map(|effect| effect.kind_id())
+                        .unwrap_or("");
+                    log.record_cast(
+                        crate::entity_handle(entity),
+                        crate::entity_handle(*action_entity),
+                        ability,
+                    );
+                }
+                if let Some(events) = events.as_mut() {
+                    events.0.push_back(crate::event::EventCue::CastStart(
+                        crate::event::CastStartCue {
+                            entity,
+                            action: *action_entity,
+                            impact_time: swing.map(|s| s.impact_time).unwrap_or(0.0),
+                            swing_time: swing.map(|s| s.swing_time).unwrap_or(0.0),
+                        },
+                    ));
+                }
+                commands.entity(entity).insert(PerformingActionState {
+                    action: *action_entity,
+                });
+                commands.entity(entity).insert(PlayAnimationDirective {
+                    animation: AnimationRole::Attack,
+                    loops: false,
+                });
+                break;
+            }
+        }
+    }
+}
+
+/// Advance every in-progress swing; apply effects (or launch the projectile)
+/// when the channel crosses impact_time, and release the unit at swing_time.
+pub fn performing_action_state(
+    mut commands: Commands,
+    delta: Res<DeltaPhysics>,
+    performer_query: Query<(
+        Entity,
+        &PerformingActionState,
+        &Position,
+        &Radius,
+        Option<&Stunned>,
+    )>,
+    leeway: Option<Res<ChannelInterruptLeeway>>,
+    mut action_query: Query<(
+        &SwingDetails,
+        &ImpactType,
+        &OnHitEffects,
+        &mut ChannelingDetails,
+        Option<&TargetEntity>,
+        Option<&TargetPosition>,
+        Option<&ExecuteDamage>,
+        Option<&Disabled>,
+        Option<&BasicAttack>,
+        Option<&ActionCooldown>,
+    )>,
+    details_query: Query<&ActionProjectileDetails>,
+    splash_query: Query<&Splash>,
+    cleave_query: Query<&Cleave>,
+    flags_query: Query<&TargetFlags>,
+    effect_texture_query: Query<&EffectTexture>,
+    range_query: Query<&ActionRange>,
+    visual_query: Query<(
+        Option<&MuzzleOffset>,
+        Option<&ImpactVisualAnchor>,
+        Option<&TelegraphTexture>,
+    )>,
+    flip_query: Query<&crate::graphics::FlippableSprite>,
+    neighbors: Option<Res<SpatialNeighborsCache>>,
+    alignment_query: Query<&TeamAlignment>,
+    position_query: Query<&Position>,
+    radius_query: Query<&Radius>,
+    hitpoints_query: Query<&Hitpoints>,
+    stealth_query: Query<(), With<Stealthed>>,
+    holder_query: Query<&BuffHolder>,
+    buff_type_query: Query<&BuffType>,
+    mut buffer_query: Query<&mut ResolveEffectsBuffer>,
+    evasion_query: Query<&Evasion>,
+    damage_buff_query: Query<
+        (&TargetEntity, Option<&FlatDamageBuff>, Option<&PercentDamageBuff>),
+        Or<(With<FlatDamageBuff>, With<PercentDamageBuff>)>,
+    >,
+    mut rng: Option<ResMut<SimRng>>,
+    mut events: Option<ResMut<crate::event::EventQueue>>,
+) {
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    let default_leeway = ChannelInterruptLeeway::default();
+    let leeway = leeway.as_deref().unwrap_or(&default_leeway);
+    for (performer, state, position, radius, stunned) in performer_query.iter() {
+        let (
+            swing,
+            impact_type,
+            effects,
+            mut channeling,
+            target,
+            target_point,
+            execute,
+            disabled,
+            basic_attack,
+            base_cooldown,
+        ) = match action_query.get_mut(state.action) {
+            Ok(parts) => parts,
+            Err(_) => {
+                commands.entity(performer).remove::<PerformingActionState>();
+                continue;
+            }
+        };
+        // An abort before the hit lands refunds the unspent share of the
+        // cooldown: the caster only pays for the time actually channeled.
+        let pre_impact = channeling.total_time_channeled < swing.impact_time;
+        let abort = |channeling: &mut ChannelingDetails,
+                     commands: &mut Commands,
+                     events: &mut Option<ResMut<crate::event::EventQueue>>| {
+            if pre_impact {
+                if let Some(base_cooldown) = base_cooldown {
+                    let spent =
+                        (channeling.total_time_channeled / swing.swing_time).clamp(0.0, 1.0);
+                    commands
+                        .entity(state.action)
+                        .insert(Cooldown(base_cooldown.0 * spent));
+                }
+                if let Some(events) = events.as_mut() {
+                    events.0.push_back(crate::event::EventCue::Audio(
+                        crate::event::AudioCue {
+                            kind: "interrupted".to_string(),
+                            position: position.pos,
+                        },
+                    ));
+                    // Close out the cast bar opened by the start cue; the
+                    // whiff sweep won't see this swing once the channel is
+                    // zeroed below.
+                    events.0.push_back(crate::event::EventCue::CastResolve(
+                        crate::event::CastResolveCue {
+                            entity: performer,
+                            action: state.action,
+                            hit: false,
+                        },
+                    ));
+                }
+            }
+            channeling.total_time_channeled = 0.0;
+            commands.entity(performer).remove::<PerformingActionState>();
+        };
+        // A stun aborts the wind-up outright; the channel starts over.
+        if stunned.is_some() {
+            abort(&mut channeling, &mut commands, &mut events);
+            continue;
+        }
+        // A disarm landing mid-swing cancels the weapon channel outright;
+        // a disabled ability cast still finishes.
+        if disabled.is_some() && basic_attack.is_some() {
+            abort(&mut channeling, &mut commands, &mut events);
+            continue;
+        }
+        // Until the hit lands the target has to stay valid: a dead target
+        // or one past range-times-leeway can't just let the effects land
+        // on thin air.
+        if pre_impact {
+            if let Some(target) = target {
+                let dead = position_query.get(target.0).is_err()
+                    || hitpoints_query
+                        .get(target.0)
+                        .map(|hitpoints| hitpoints.hp <= 0.0)
+                        .unwrap_or(false);
+                if dead {
+                    // A dead target needn't waste the whole wind-up: slide
+                    // the swing onto the nearest replacement that passes the
+                    // action's own targeting filters, aborting when nothing
+                    // qualifies. Projectile wind-ups always abort rather
+                    // than retarget; only a shot already in flight carries
+                    // on, homing to the target's last-known position.
+                    let mut replacement: Option<(Entity, f32)> = None;
+                    if *impact_type != ImpactType::Projectile {
+                        if let (Ok(flags), Ok(range), Ok(alignment), Some(neighbor_list)) = (
+                            flags_query.get(state.action),
+                            range_query.get(state.action),
+                            alignment_query.get(performer),
+                            neighbors.get_neighbors(&performer),
+                        ) {
+                            for neighbor in neighbor_list.iter() {
+                                let is_ally = neighbor.team == alignment.alignment;
+                                if is_ally && !flags.target_allies {
+                                    continue;
+                                }
+                                if !is_ally && !flags.target_enemies {
+                                    continue;
+                                }
+                                if !is_ally && stealth_query.get(neighbor.entity).is_ok() {
+                                    continue;
+                                }
+                                let hitpoints = match hitpoints_query.get(neighbor.entity) {
+                                    Ok(hitpoints) => hitpoints,
+                                    Err(_) => continue,
+                                };
+                                if hitpoints.hp <= 0.0 {
+                                    continue;
+                                }
+                                if flags.needs_injured && hitpoints.hp >= hitpoints.max_hp {
+                                    continue;
+                                }
+                                if flags.needs_debuff {
+                                    let debuffed = holder_query
+                                        .get(neighbor.entity)
+                                        .map(|holder| {
+                                            crate::effects::has_debuff(holder, &buff_type_query)
+                                        })
+                                        .unwrap_or(false);
+                                    if !debuffed {
+                                        continue;
+                                    }
+                                }
+                                if neighbor.distance > range.0 {
+                                    continue;
+                                }
+                                if replacement
+                                    .map(|(_, best)| neighbor.distance < best)
+                                    .unwrap_or(true)
+                                {
+                                    replacement = Some((neighbor.entity, neighbor.distance));
+                                }
+                            }
+                        }
+                    }
+                    match replacement {
+                        Some((new_target, _)) => {
+                            commands
+                                .entity(state.action)
+                                .insert(TargetEntity(new_target))
+                                .insert(LastTarget(new_target));
+                        }
+                        None => {
+                            abort(&mut channeling, &mut commands, &mut events);
+                            continue;
+                        }
+                    }
+                } else {
+                    let strayed = match (
+                        position_query.get(target.0),
+                        range_query.get(state.action),
+                    ) {
+                        (Ok(target_position), Ok(range)) => {
+                            let target_radius =
+                                radius_query.get(target.0).map(|r| r.r).unwrap_or(0.0);
+                            crate::util::true_distance(
+                                position.pos,
+                                target_position.pos,
+                                radius.r,
+                                target_radius,
+                            ) > range.0 * leeway.range_margin
+                        }
+                        _ => false,
+                    };
+                    if strayed {
+                        abort(&mut channeling, &mut commands, &mut events);
+                        continue;
+                    }
+                }
+            }
+        }
+        let before = channeling.total_time_channeled;
+        channeling.total_time_channeled += delta.seconds;
+        let crossed_impact =
+            before < swing.impact_time && channeling.total_time_channeled >= swing.impact_time;
+
+        // A ground blast telegraphs its aim point for the whole wind-up.
+        if before <= 0.0 && *impact_type == ImpactType::GroundArea {
+            if let (Some(point), Ok((_, _, Some(telegraph)))) =
+                (target_point, visual_query.get(state.action))
+            {
+                commands
+                    .spawn()
+                    .insert(Position { pos: point.0 })
+                    .insert(crate::graphics::NewCanvasItemDirective {})
+                    .insert(crate::graphics::animation::AnimatedSprite::new(telegraph.0))
+                    .insert(PlayAnimationDirective {
+                        animation: AnimationRole::Idle,
+                        loops: true,
+                    })
+                    .insert(crate::util::ExpirationTimer(swing.impact_time));
+            }
+        }
+
+        if crossed_impact {
+            // Damage buffs targeting this action apply now, at hit time; the
+            // stored OnHitEffects never change, so expiry reverts cleanly.
+            let mut flat_bonus = 0.0;
+            let mut percent_bonus = 0.0;
+            for (buff_target, flat, percent) in damage_buff_query.iter() {
+                if buff_target.0 != state.action {
+                    continue;
+                }
+                flat_bonus += flat.map(|flat| flat.0).unwrap_or(0.0);
+                percent_bonus += percent.map(|percent| percent.0).unwrap_or(0.0);
+            }
+            let buffed_effects = if flat_bonus != 0.0 || percent_bonus != 0.0 {
+                Some(OnHitEffects {
+                    vec: effects
+                        .vec
+                        .iter()
+                        .map(|effect| match effect {
+                            Effect::DamageEffect {
+                                damage,
+                                delay,
+                                damage_type,
+                            } => Effect::DamageEffect {
+                                damage: damage * (1.0 + percent_bonus) + flat_bonus,
+                                delay: *delay,
+                                damage_type: *damage_type,
+                            },
+                            other => other.clone(),
+                        })
+                        .collect(),
+                })
+            } else {
+                None
+            };
+            let effects = buffed_effects.as_ref().unwrap_or(effects);
+            let mut landed = false;
+            match impact_type {
+                ImpactType::Instant if target_point.is_some() => {
+                    // Ground cast: the effects land on everything near the
+                    // point, allies and enemies alike.
+                    let point = target_point.unwrap().0;
+                    if let Ok(texture) = effect_texture_query.get(state.action) {
+                        crate::graphics::spawn_impact_visual(&mut commands, texture.0, point);
+                    }
+                    let splash = splash_query.get(state.action).ok();
+                    let radius = splash.map(|splash| splash.radius).unwrap_or(0.0);
+                    if let Some(neighbor_list) = neighbors.get_neighbors(&performer) {
+                        for neighbor in neighbor_list.iter() {
+                            let distance = match position_query.get(neighbor.entity) {
+                                Ok(p) => p.pos.distance_to(point),
+                                Err(_) => continue,
+                            };
+                            if distance > radius {
+                                continue;
+                            }
+                            if let Ok(mut buffer) = buffer_query.get_mut(neighbor.entity) {
+                                for effect in effects.vec.iter() {
+                                    let effect = match (effect, splash) {
+                                        (
+                                            Effect::DamageEffect {
+                                                damage,
+                                                delay,
+                                                damage_type,
+                                            },
+                                            Some(splash),
+                                        ) => Effect::DamageEffect {
+                                            damage: damage * splash.damage_scale(distance),
+                                            delay: *delay,
+                                            damage_type: *damage_type,
+                                        },
+                                        (_, Some(splash))
+                                            if !splash.carries_riders(distance) =>
+                                        {
+                                            continue;
+                                        }
+                                        _ => effect.clone(),
+                                    };
+                                    buffer.vec.push(QueuedEffect {
+                                        effect,
+                                        originator: performer,
+                                        execute: execute.copied(),
+                                    });
+                                }
+                                landed = true;
+                            }
+                        }
+                    }
+                }
+                ImpactType::GroundArea if target_point.is_some() => {
+                    // The blast lands on enemies only, with the usual splash
+                    // falloff from the aim point.
+                    let point = target_point.unwrap().0;
+                    if let Ok(texture) = effect_texture_query.get(state.action) {
+                        crate::graphics::spawn_impact_visual(&mut commands, texture.0, point);
+                    }
+                    let splash = splash_query.get(state.action).ok();
+                    let radius = splash.map(|splash| splash.radius).unwrap_or(0.0);
+                    let team = alignment_query.get(performer).map(|a| a.alignment).ok();
+                    if let Some(neighbor_list) = neighbors.get_neighbors(&performer) {
+                        for neighbor in neighbor_list.iter() {
+                            if Some(neighbor.team) == team {
+                                continue;
+                            }
+                            let distance = match position_query.get(neighbor.entity) {
+                                Ok(p) => p.pos.distance_to(point),
+                                Err(_) => continue,
+                            };
+                            if distance > radius {
+                                continue;
+                            }
+                            if let Ok(mut buffer) = buffer_query.get_mut(neighbor.entity) {
+                                for effect in effects.vec.iter() {
+                                    let effect = match (effect, splash) {
+                                        (
+                                            Effect::DamageEffect {
+                                                damage,
+                                                delay,
+                                                damage_type,
+                                            },
+                                            Some(splash),
+                                        ) => Effect::DamageEffect {
+                                            damage: damage * splash.damage_scale(distance),
+                                            delay: *delay,
+                                            damage_type: *damage_type,
+                                        },
+                                        (_, Some(splash))
+                                            if !splash.carries_riders(distance) =>
+                                        {
+                                            continue;
+                                        }
+                                        _ => effect.clone(),
+                                    };
+                                    buffer.vec.push(QueuedEffect {
+                                        effect,
+                                        originator: performer,
+                                        execute: execute.copied(),
+                                    });
+                                }
+                                landed = true;
+                            }
+                        }
+                    }
+                }
+                ImpactType::Instant if target.is_some() => {
+                    let target = *target.unwrap();
+                    // Evasion: a Normal attack can miss outright, on-hit
+                    // riders and all. The decision lives here rather than in
+                    // apply_damages so the riders miss too; cleave and
+                    // splash below still land.
+                    let dodged = crate::effects::attack_is_dodgeable(&effects.vec)
+                        && evasion_query
+                            .get(target.0)
+                            .ok()
+                            .zip(rng.as_mut())
+                            .map(|(evasion, rng)| rng.0.gen::<f32>() < evasion.0)
+                            .unwrap_or(false);
+                    if dodged {
+                        if let (Some(events), Ok(target_position)) =
+                            (events.as_mut(), position_query.get(target.0))
+                        {
+                            events.0.push_back(crate::event::EventCue::Audio(
+                                crate::event::AudioCue {
+                                    kind: "dodge".to_string(),
+                                    position: target_position.pos,
+                                },
+                            ));
+                        }
+                    } else if let Ok(mut buffer) = buffer_query.get_mut(target.0) {
+                        // Push at the primary target; silently skip if it
+                        // despawned.
+                        for effect in effects.vec.iter() {
+                            buffer.vec.push(QueuedEffect {
+                                effect: effect.clone(),
+                                originator: performer,
+                                execute: execute.copied(),
+                            });
+                        }
+                        landed = true;
+                    }
+                    if let Ok(texture) = effect_texture_query.get(state.action) {
+                        if let Ok(target_position) = position_query.get(target.0) {
+                            let (muzzle, anchor, _) =
+                                visual_query.get(state.action).unwrap_or((None, None, None));
+                            let flipped = flip_query
+                                .get(performer)
+                                .map(|flip| flip.flipped)
+                                .unwrap_or(false);
+                            let origin = position.pos
+                                + muzzle
+                                    .map(|muzzle| resolved_muzzle_offset(muzzle.0, flipped))
+                                    .unwrap_or(Vector2::ZERO);
+                            let spot = match anchor.unwrap_or(&ImpactVisualAnchor::Target) {
+                                ImpactVisualAnchor::Target => target_position.pos,
+                                ImpactVisualAnchor::Caster => origin,
+                                ImpactVisualAnchor::Midpoint => {
+                                    (origin + target_position.pos) * 0.5
+                                }
+                            };
+                            crate::graphics::spawn_impact_visual(&mut commands, texture.0, spot);
+                        }
+                    }
+                    // Cleave: same effects to everything in the arc.
+                    if let Ok(cleave) = cleave_query.get(state.action) {
+                        if let (Ok(range), Ok(alignment), Ok(target_position)) = (
+                            range_query.get(state.action),
+                            alignment_query.get(performer),
+                            position_query.get(target.0),
+                        ) {
+                            let to_target = target_position.pos - position.pos;
+                            if let Some(neighbor_list) = neighbors.get_neighbors(&performer) {
+                                for neighbor in neighbor_list.iter() {
+                                    if neighbor.entity == target.0
+                                        || neighbor.distance > range.0
+                                        || neighbor.team == alignment.alignment
+                                    {
+                                        continue;
+                                    }
+                                    if let Ok(neighbor_position) =
+                                        position_query.get(neighbor.entity)
+                                    {
+                                        let to_neighbor = neighbor_position.pos - position.pos;
+                                        if to_target.angle_to(to_neighbor).abs().to_degrees()
+                                            > cleave.angle_degrees / 2.0
+                                        {
+                                            continue;
+                                        }
+                                    }
+                                    if let Ok(mut buffer) = buffer_query.get_mut(neighbor.entity)
+                                    {
+                                        for effect in effects.vec.iter() {
+                                            buffer.vec.push(QueuedEffect {
+                                                effect: effect.clone(),
+                                                originator: performer,
+                                                execute: execute.copied(),
+                                            });
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    // Splash on a unit target: the same effects land on
+                    // every unit of the targeted side within the radius of
+                    // the primary target.
+                    if let Ok(splash) = splash_query.get(state.action) {
+                        if let (Ok(flags), Ok(alignment), Ok(target_position)) = (
+                            flags_query.get(state.action),
+                            alignment_query.get(performer),
+                            position_query.get(target.0),
+                        ) {
+                            if let Some(neighbor_list) = neighbors.get_neighbors(&performer) {
+                                for neighbor in neighbor_list.iter() {
+                                    if neighbor.entity == target.0 {
+                                        continue;
+                                    }
+                                    let is_ally = neighbor.team == alignment.alignment;
+                                    if (is_ally && !flags.target_allies)
+                                        || (!is_ally && !flags.target_enemies)
+                                    {
+                                        continue;
+                                    }
+                                    let distance = match position_query.get(neighbor.entity) {
+                                        Ok(p) => p.pos.distance_to(target_position.pos),
+                                        Err(_) => continue,
+                                    };
+                                    if distance > splash.radius {
+                                        continue;
+                                    }
+                                    if let Ok(mut buffer) =
+                                        buffer_query.get_mut(neighbor.entity)
+                                    {
+                                        for effect in effects.vec.iter() {
+                                            let effect = match effect {
+                                                Effect::DamageEffect {
+                                                    damage,
+                                                    delay,
+                                                    damage_type,
+                                                } => Effect::DamageEffect {
+                                                    damage: damage
+                                                        * splash.damage_scale(distance),
+                                                    delay: *delay,
+                                                    damage_type: *damage_type,
+                                                },
+                                                _ if !splash.carries_riders(distance) => {
+                                                    continue;
+                                                }
+                                                _ => effect.clone(),
+                                            };
+                                            buffer.vec.push(QueuedEffect {
+                                                effect,
+                                                originator: performer,
+                                                execute: execute.copied(),
+                                            });
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                ImpactType::Projectile if target.is_some() => {
+                    let target = *target.unwrap();
+                    if let (Ok(details), Ok(target_position)) = (
+                        details_query.get(state.action),
+                        position_query.get(target.0),
+                    ) {
+                        let flipped = flip_query
+                            .get(performer)
+                            .map(|flip| flip.flipped)
+                            .unwrap_or(false);
+                        let origin = position.pos
+                            + visual_query
+                                .get(state.action)
+                                .ok()
+                                .and_then(|(muzzle, _, _)| muzzle)
+                                .map(|muzzle| resolved_muzzle_offset(muzzle.0, flipped))
+                                .unwrap_or(Vector2::ZERO);
+                        crate::projectiles::spawn_projectile(
+                            &mut commands,
+                            origin,
+                            target.0,
+                            target_position.pos,
+                            performer,
+                            details,
+                            effects.vec.clone(),
+                            splash_query.get(state.action).ok().copied(),
+                            execute.copied(),
+                        );
+                        landed = true;
+                    }
+                }
+                // No target left at impact: a whiff.
+                _ => {}
+            }
+            if let Some(events) = events.as_mut() {
+                events.0.push_back(crate::event::EventCue::CastResolve(
+                    crate::event::CastResolveCue {
+                        entity: performer,
+                        action: state.action,
+                        hit: landed,
+                    },
+                ));
+            }
+        }
+
+        if channeling.total_time_channeled >= swing.swing_time {
+            // Reset here so the interrupt sweep can tell a finished swing
+            // from an abandoned one.
+            channeling.total_time_channeled = 0.0;
+            commands.entity(performer).remove::<PerformingActionState>();
+            commands
+                .entity(state.action)
+                .remove::<TargetEntity>()
+                .remove::<TargetPosition>();
+        }
+    }
+}
+
+/// Emit one whiff resolution for swings whose performer lost its
+/// PerformingActionState mid-wind-up (stunned, died, retargeted by a buff).
+pub fn detect_interrupted_casts(
+    mut events: Option<ResMut<crate::event::EventQueue>>,
+    performer_query: Query<&PerformingActionState>,
+    mut action_query: Query<(Entity, &ActionOwner, &mut ChannelingDetails)>,
+) {
+    for (action, owner, mut channeling) in action_query.iter_mut() {
+        if channeling.total_time_channeled <= 0.0 {
+            continue;
+        }
+        let still_active = performer_query
+            .get(owner.0)
+            .map(|state| state.action == action)
+            .unwrap_or(false);
+        if !still_active {
+            channeling.total_time_channeled = 0.0;
+            if let Some(events) = events.as_mut() {
+                events.0.push_back(crate::event::EventCue::CastResolve(
+                    crate::event::CastResolveCue {
+                        entity: owner.0,
+                        action,
+                        hit: false,
+                    },
+                ));
+            }
+        }
+    }
+}
+
+/// Scripted ground-cast entry point; bypasses autocast entirely. Returns the
+/// rejection reason so the GDScript layer can surface it.
+pub fn command_cast_at_position(
+    world: &mut World,
+    unit: Entity,
+    action_index: usize,
+    point: Vector2,
+) -> Result<(), &'static str> {
+    if world.get::<Stunned>(unit).is_some() {
+        return Err("caster is stunned");
+    }
+    if world.get::<PerformingActionState>(unit).is_some() {
+        return Err("caster is already mid-swing");
+    }
+    let action = match world.get::<UnitActions>(unit) {
+        Some(actions) => match actions.vec.get(action_index) {
+            Some(action) => *action,
+            None => return Err("no action at that index"),
+        },
+        None => return Err("entity has no actions"),
+    };
+    if world.get::<GroundTargetable>(action).is_none() {
+        return Err("action does not support ground targeting");
+    }
+    if world.get::<Disabled>(action).is_some() {
+        return Err("action is disabled");
+    }
+    if world.get::<Cooldown>(action).is_some() {
+        return Err("action is on cooldown");
+    }
+    let caster_position = match world.get::<Position>(unit) {
+        Some(position) => position.pos,
+        None => return Err("entity has no position"),
+    };
+    let range = world.get::<ActionRange>(action).map(|r| r.0).unwrap_or(0.0);
+    if caster_position.distance_to(point) > range {
+        return Err("point is out of range");
+    }
+
+    let base_cooldown = world.get::<ActionCooldown>(action).map(|c| c.0).unwrap_or(0.0);
+    let swing = world.get::<SwingDetails>(action).copied();
+    world
+        .entity_mut(action)
+        .insert(TargetPosition(point))
+        .insert(Cooldown(base_cooldown));
+    world
+        .entity_mut(unit)
+        .insert(PerformingActionState { action })
+        .insert(PlayAnimationDirective {
+            animation: AnimationRole::Attack,
+            loops: false,
+        });
+    let ability = world
+        .get::<OnHitEffects>(action)
+        .and_then(|on_hit| on_hit.vec.first().map(|effect| effect.kind_id()))
+        .unwrap_or("");
+    if let Some(mut log) = world.get_resource_mut::<crate::event::MatchLog>() {
+        log.record_cast(
+            crate::entity_handle(unit),
+            crate::entity_handle(action),
+            ability,
+        );
+    }
+    if let Some(mut events) = world.get_resource_mut::<crate::event::EventQueue>() {
+        events
+            .0
+            .push_back(crate::event::EventCue::CastStart(crate::event::CastStartCue {
+                entity: unit,
+                action,
+                impact_time: swing.map(|s| s.impact_time).unwrap_or(0.0),
+                swing_time: swing.map(|s| s.swing_time).unwrap_or(0.0),
+            }));
+    }
+    Ok(())
+}
+
+/// Scripted entity-targeted cast: start the action's swing at the chosen
+/// unit, with the same gating `command_cast_at_position` applies plus a
+/// living-target and true-distance range check. Works on autocast-disabled
+/// actions; that marker only blinds `target_units`.
+pub fn command_cast_at_entity(
+    world: &mut World,
+    unit: Entity,
+    action_index: usize,
+    target: Entity,
+) -> Result<(), &'static str> {
+    if world.get::<Stunned>(unit).is_some() {
+        return Err("caster is stunned");
+    }
+    if world.get::<PerformingActionState>(unit).is_some() {
+        return Err("caster is already mid-swing");
+    }
+    let action = match world.get::<UnitActions>(unit) {
+        Some(actions) => match actions.vec.get(action_index) {
+            Some(action) => *action,
+            None => return Err("no action at that index"),
+        },
+        None => return Err("entity has no actions"),
+    };
+    if world.get::<Disabled>(action).is_some() {
+        return Err("action is disabled");
+    }
+    if world.get::<Cooldown>(action).is_some() {
+        return Err("action is on cooldown");
+    }
+    let alive = world
+        .get::<Hitpoints>(target)
+        .map(|hitpoints| hitpoints.hp > 0.0)
+        .unwrap_or(false);
+    if !alive {
+        return Err("target is dead or gone");
+    }
+    let caster_position = match world.get::<Position>(unit) {
+        Some(position) => position.pos,
+        None => return Err("entity has no position"),
+    };
+    let target_position = match world.get::<Position>(target) {
+        Some(position) => position.pos,
+        None => return Err("target has no position"),
+    };
+    let caster_radius = world.get::<Radius>(unit).map(|r| r.r).unwrap_or(0.0);
+    let target_radius = world.get::<Radius>(target).map(|r| r.r).unwrap_or(0.0);
+    let range = world.get::<ActionRange>(action).map(|r| r.0).unwrap_or(0.0);
+    if crate::util::true_distance(caster_position, target_position, caster_radius, target_radius)
+        > range
+    {
+        return Err("target is out of range");
+    }
+
+    let base_cooldown = world.get::<ActionCooldown>(action).map(|c| c.0).unwrap_or(0.0);
+    let swing = world.get::<SwingDetails>(action).copied();
+    world
+        .entity_mut(action)
+        .insert(TargetEntity(target))
+        .insert(Cooldown(base_cooldown));
+    world
+        .entity_mut(unit)
+        .insert(PerformingActionState { action })
+        .insert(PlayAnimationDirective {
+            animation: AnimationRole::Attack,
+            loops: false,
+        });
+    let ability = world
+        .get::<OnHitEffects>(action)
+        .and_then(|on_hit| on_hit.vec.first().map(|effect| effect.kind_id()))
+        .unwrap_or("");
+    if let Some(mut log) = world.get_resource_mut::<crate::event::MatchLog>() {
+        log.record_cast(
+            crate::entity_handle(unit),
+            crate::entity_handle(action),
+            ability,
+        );
+    }
+    if let Some(mut events) = world.get_resource_mut::<crate::event::EventQueue>() {
+        events
+            .0
+            .push_back(crate::event::EventCue::CastStart(crate::event::CastStartCue {
+                entity: unit,
+                action,
+                impact_time: swing.map(|s| s.impact_time).unwrap_or(0.0),
+                swing_time: swing.map(|s| s.swing_time).unwrap_or(0.0),
+            }));
+    }
+    Ok(())
+}
+
+pub fn tick_cooldowns(
+    mut commands: Commands,
+    delta: Res<DeltaPhysics>,
+    mut query: Query<(Entity, &mut Cooldown)>,
+) {
+    for (entity, mut cooldown) in query.iter_mut() {
+        cooldown.0 -= delta.seconds;
+        if cooldown.0 <= 0.0 {
+            commands.entity(entity).remove::<Cooldown>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::schedule::SystemStage;
+
+    fn unit_with_action(world: &mut World, initial_cooldown: f32) -> (Entity, Entity) {
+        let target = world.spawn().id();
+        let action = world
+            .spawn()
+            .insert(ActionCooldown(3.0))
+            .insert(ChannelingDetails {
+                total_time_channeled: 0.0,
+            })
+            .insert(TargetEntity(target))
+            .id();
+        if initial_cooldown > 0.0 {
+            world.entity_mut(action).insert(Cooldown(initial_cooldown));
+        }
+        let unit = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .id();
+        (unit, action)
+    }
+
+    fn cast_counts(world: &mut World) -> (usize, usize) {
+        let queue = world.resource::<crate::event::EventQueue>();
+        let mut starts = 0;
+        let mut resolves = 0;
+        for cue in queue.0.iter() {
+            match cue {
+                crate::event::EventCue::CastStart(_) => starts += 1,
+                crate::event::EventCue::CastResolve(_) => resolves += 1,
+                _ => {}
+            }
+        }
+        (starts, resolves)
+    }
+
+    fn swinging_unit(world: &mut World) -> (Entity, Entity) {
+        let target = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position { pos: Vector2::ZERO })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionCooldown(3.0))
+            .insert(ChannelingDetails {
+                total_time_channeled: 0.0,
+            })
+            .insert(TargetEntity(target))
+            .insert(SwingDetails {
+                impact_time: 0.5,
+                swing_time: 1.0,
+            })
+            .insert(ImpactType::Instant)
+            .insert(OnHitEffects { vec: Vec::new() })
+            .id();
+        let unit = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(Radius { r: 4.0 })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(unit));
+        (unit, action)
+    }
+
+    fn cast_world(delta: f32) -> World {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: delta });
+        world.insert_resource(crate::event::EventQueue::default());
+        world.insert_resource(SpatialNeighborsCache {
+            map: std::collections::HashMap::new(),
+        });
+        world
+    }
+
+    #[test]
+    fn completed_swing_emits_one_start_and_one_resolution() {
+        let mut world = cast_world(0.6);
+        let (_unit, _action) = swinging_unit(&mut world);
+
+        let mut perform = SystemStage::parallel();
+        perform.add_system(perform_action);
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+        let mut sweep = SystemStage::parallel();
+        sweep.add_system(detect_interrupted_casts);
+
+        perform.run(&mut world);
+        channel.run(&mut world);
+        channel.run(&mut world);
+        sweep.run(&mut world);
+        assert_eq!(cast_counts(&mut world), (1, 1));
+    }
+
+    #[test]
+    fn interrupted_swing_still_emits_exactly_one_resolution() {
+        let mut world = cast_world(0.2);
+        let (unit, _action) = swinging_unit(&mut world);
+
+        let mut perform = SystemStage::parallel();
+        perform.add_system(perform_action);
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+        let mut sweep = SystemStage::parallel();
+        sweep.add_system(detect_interrupted_casts);
+
+        perform.run(&mut world);
+        channel.run(&mut world);
+        // A stun lands mid-wind-up; the abort itself resolves the cast, and
+        // the whiff sweep must not add a second resolution on top.
+        world.entity_mut(unit).insert(crate::effects::Stunned);
+        channel.run(&mut world);
+        sweep.run(&mut world);
+        sweep.run(&mut world);
+        assert_eq!(cast_counts(&mut world), (1, 1));
+    }
+
+    fn targeting_world(dummies: &[(Entity, f32)], attacker: Entity) -> SpatialNeighborsCache {
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            attacker,
+            dummies
+                .iter()
+                .map(|(entity, distance)| crate::physics::SpatialNeighbor {
+                    entity: *entity,
+                    distance: *distance,
+                    team: 1,
+                })
+                .collect(),
+        );
+        SpatialNeighborsCache { map }
+    }
+
+    #[test]
+    fn unit_commits_to_its_last_target_until_it_dies() {
+        let mut world = World::default();
+        let dummy_a = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .id();
+        let dummy_b = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionRange(10.0))
+            .insert(TargetFlags::normal_attack())
+            .id();
+        let attacker = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(attacker));
+        world.insert_resource(targeting_world(&[(dummy_a, 5.0), (dummy_b, 5.0)], attacker));
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(target_units);
+        stage.run(&mut world);
+        let first_pick = world.get::<TargetEntity>(action).unwrap().0;
+
+        // The swing ends; the dummies drift so the other one is now nearer,
+        // but not by enough to beat the hysteresis.
+        world.entity_mut(action).remove::<TargetEntity>();
+        let other = if first_pick == dummy_a { dummy_b } else { dummy_a };
+        world.insert_resource(targeting_world(&[(first_pick, 6.0), (other, 5.0)], attacker));
+        stage.run(&mut world);
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, first_pick);
+
+        // Once the remembered target dies the unit moves on.
+        world.get_mut::<Hitpoints>(first_pick).unwrap().hp = 0.0;
+        world.entity_mut(action).remove::<TargetEntity>();
+        stage.run(&mut world);
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, other);
+    }
+
+    #[test]
+    fn interrupts_prefer_the_caster_over_nearer_idlers() {
+        let mut world = World::default();
+        let idler = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .id();
+        let fake_action = world.spawn().id();
+        let caster = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .insert(PerformingActionState {
+                action: fake_action,
+            })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionRange(20.0))
+            .insert(TargetFlags::interrupt())
+            .id();
+        let attacker = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(attacker));
+        world.insert_resource(targeting_world(&[(idler, 5.0), (caster, 15.0)], attacker));
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(target_units);
+        stage.run(&mut world);
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, caster);
+
+        // A stunned caster is no longer worth interrupting.
+        world.entity_mut(caster).insert(Stunned);
+        world.entity_mut(action).remove::<TargetEntity>().remove::<LastTarget>();
+        stage.run(&mut world);
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, idler);
+    }
+
+    #[test]
+    fn self_casts_fire_only_while_injured() {
+        let mut world = World::default();
+        let action = world
+            .spawn()
+            .insert(ActionRange(0.0))
+            .insert(TargetFlags {
+                needs_injured: true,
+                ..TargetFlags::self_cast()
+            })
+            .id();
+        let caster = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(caster));
+        world.insert_resource(targeting_world(&[], caster));
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(target_units);
+        stage.run(&mut world);
+        assert!(world.get::<TargetEntity>(action).is_none());
+
+        world.get_mut::<Hitpoints>(caster).unwrap().hp = 4.0;
+        stage.run(&mut world);
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, caster);
+    }
+
+    fn ground_caster(world: &mut World) -> (Entity, Entity, Entity) {
+        let victim = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(30.0, 0.0),
+            })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionRange(50.0))
+            .insert(ActionCooldown(3.0))
+            .insert(SwingDetails {
+                impact_time: 0.5,
+                swing_time: 1.0,
+            })
+            .insert(ImpactType::Instant)
+            .insert(OnHitEffects {
+                vec: vec![Effect::HealEffect { amount: 5.0 }],
+            })
+            .insert(ChannelingDetails {
+                total_time_channeled: 0.0,
+            })
+            .insert(GroundTargetable)
+            .insert(Splash {
+                radius: 8.0,
+                min_percent: 1.0,
+            })
+            .id();
+        let caster = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(Radius { r: 4.0 })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(caster));
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            caster,
+            vec![crate::physics::SpatialNeighbor {
+                entity: victim,
+                distance: 30.0,
+                team: 1,
+            }],
+        );
+        world.insert_resource(SpatialNeighborsCache { map });
+        (caster, action, victim)
+    }
+
+    #[test]
+    fn ground_cast_rejects_invalid_commands() {
+        let mut world = cast_world(0.6);
+        let (caster, action, _victim) = ground_caster(&mut world);
+        let point = Vector2::new(30.0, 0.0);
+
+        world.entity_mut(action).remove::<GroundTargetable>();
+        assert!(command_cast_at_position(&mut world, caster, 0, point).is_err());
+        world.entity_mut(action).insert(GroundTargetable);
+
+        assert!(command_cast_at_position(&mut world, caster, 1, point).is_err());
+        assert!(
+            command_cast_at_position(&mut world, caster, 0, Vector2::new(999.0, 0.0)).is_err()
+        );
+
+        world.entity_mut(action).insert(Cooldown(1.0));
+        assert!(command_cast_at_position(&mut world, caster, 0, point).is_err());
+        world.entity_mut(action).remove::<Cooldown>();
+
+        world.entity_mut(caster).insert(Stunned);
+        assert!(command_cast_at_position(&mut world, caster, 0, point).is_err());
+    }
+
+    #[test]
+    fn ground_cast_applies_effects_at_the_point() {
+        let mut world = cast_world(0.6);
+        let (caster, action, victim) = ground_caster(&mut world);
+
+        assert!(command_cast_at_position(&mut world, caster, 0, Vector2::new(30.0, 0.0)).is_ok());
+        assert!(world.get::<PerformingActionState>(caster).is_some());
+        assert!(world.get::<Cooldown>(action).is_some());
+
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+        channel.run(&mut world);
+        assert_eq!(world.get::<ResolveEffectsBuffer>(victim).unwrap().vec.len(), 1);
+
+        // Release at swing_time clears the ground target for the next cast.
+        channel.run(&mut world);
+        assert!(world.get::<TargetPosition>(action).is_none());
+        assert!(world.get::<PerformingActionState>(caster).is_none());
+    }
+
+    #[test]
+    fn manual_casts_work_while_autocast_is_off() {
+        let mut world = cast_world(0.6);
+        let (caster, action, victim) = ground_caster(&mut world);
+        world.entity_mut(victim).insert(Hitpoints {
+            hp: 10.0,
+            max_hp: 10.0,
+        });
+        world.entity_mut(action).insert(AutocastDisabled);
+
+        // Bad indices and targets without hitpoints are refused cleanly.
+        assert!(command_cast_at_entity(&mut world, caster, 1, victim).is_err());
+        assert!(command_cast_at_entity(&mut world, caster, 0, caster).is_err());
+
+        // The autocast marker only blinds target_units; manual casts land.
+        assert!(command_cast_at_entity(&mut world, caster, 0, victim).is_ok());
+        assert!(world.get::<PerformingActionState>(caster).is_some());
+        assert!(world.get::<TargetEntity>(action).is_some());
+        assert!(world.get::<Cooldown>(action).is_some());
+
+        // A second manual cast is blocked by the cooldown just set.
+        world.entity_mut(caster).remove::<PerformingActionState>();
+        assert!(command_cast_at_entity(&mut world, caster, 0, victim).is_err());
+    }
+
+    #[test]
+    fn ready_action_is_performed_immediately() {
+        let mut world = World::default();
+        let (unit, action) = unit_with_action(&mut world, 0.0);
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(perform_action);
+        stage.run(&mut world);
+
+        assert!(world.get::<PerformingActionState>(unit).is_some());
+        assert!(world.get::<Cooldown>(action).is_some());
+    }
+
+    #[test]
+    fn disabled_actions_are_skipped_until_reenabled() {
+        let mut world = World::default();
+        let (unit, action) = unit_with_action(&mut world, 0.0);
+        world.entity_mut(action).insert(Disabled);
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(perform_action);
+        stage.run(&mut world);
+        assert!(world.get::<PerformingActionState>(unit).is_none());
+
+        // Re-enabling does not touch cooldown state; the action fires again.
+        world.entity_mut(action).remove::<Disabled>();
+        stage.run(&mut world);
+        assert!(world.get::<PerformingActionState>(unit).is_some());
+    }
+
+    #[test]
+    fn initial_cooldown_delays_the_first_use() {
+        let mut world = World::default();
+        world.insert_resource(crate::physics::DeltaPhysics { seconds: 0.5 });
+        let (unit, action) = unit_with_action(&mut world, 1.0);
+
+        let mut perform = SystemStage::parallel();
+        perform.add_system(perform_action);
+        let mut tick = SystemStage::parallel();
+        tick.add_system(tick_cooldowns);
+
+        perform.run(&mut world);
+        assert!(world.get::<PerformingActionState>(unit).is_none());
+
+        // Two half-second ticks clear the initial cooldown.
+        tick.run(&mut world);
+        tick.run(&mut world);
+        perform.run(&mut world);
+        assert!(world.get::<PerformingActionState>(unit).is_some());
+    }
+    #[test]
+    fn structures_are_only_attacked_when_nothing_else_is_near() {
+        let mut world = World::default();
+        let totem = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .insert(crate::unit::Structure)
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionRange(30.0))
+            .insert(TargetFlags::normal_attack())
+            .id();
+        let attacker = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(attacker));
+        world.insert_resource(targeting_world(&[(totem, 5.0)], attacker));
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(target_units);
+        stage.run(&mut world);
+        // Alone in range, the totem does get attacked.
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, totem);
+
+        // A unit farther out still beats the nearer structure.
+        let soldier = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .id();
+        world
+            .entity_mut(action)
+            .remove::<TargetEntity>()
+            .remove::<LastTarget>();
+        world.insert_resource(targeting_world(&[(totem, 5.0), (soldier, 12.0)], attacker));
+        stage.run(&mut world);
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, soldier);
+    }
+    #[test]
+    fn muzzle_offsets_mirror_horizontally_with_facing() {
+        let offset = Vector2::new(6.0, -10.0);
+        assert_eq!(resolved_muzzle_offset(offset, false), offset);
+        assert_eq!(
+            resolved_muzzle_offset(offset, true),
+            Vector2::new(-6.0, -10.0)
+        );
+        // A centered muzzle never moves, whichever way the sprite faces.
+        assert_eq!(resolved_muzzle_offset(Vector2::ZERO, true), Vector2::ZERO);
+    }
+    #[test]
+    fn equal_distance_ties_break_the_same_way_every_run() {
+        for _ in 0..100 {
+            let mut world = World::default();
+            let dummy_a = world
+                .spawn()
+                .insert(Hitpoints {
+                    hp: 10.0,
+                    max_hp: 10.0,
+                })
+                .id();
+            let dummy_b = world
+                .spawn()
+                .insert(Hitpoints {
+                    hp: 10.0,
+                    max_hp: 10.0,
+                })
+                .id();
+            let action = world
+                .spawn()
+                .insert(ActionRange(10.0))
+                .insert(TargetFlags::normal_attack())
+                .id();
+            let attacker = world
+                .spawn()
+                .insert(UnitActions { vec: vec![action] })
+                .insert(TeamAlignment {
+                    alignment: 0,
+                    alignment_base: 0,
+                })
+                .id();
+            world.entity_mut(action).insert(ActionOwner(attacker));
+            // The cache hands the farther-indexed dummy over first; the
+            // index tie-break must still pick the lower one.
+            world.insert_resource(targeting_world(
+                &[(dummy_b, 5.0), (dummy_a, 5.0)],
+                attacker,
+            ));
+
+            let mut stage = SystemStage::parallel();
+            stage.add_system(target_units);
+            stage.run(&mut world);
+            assert_eq!(world.get::<TargetEntity>(action).unwrap().0, dummy_a);
+        }
+    }
+
+    #[test]
+    fn focus_fire_orders_override_distance_and_expire_on_death() {
+        let mut world = World::default();
+        let near = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .id();
+        let far = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionRange(30.0))
+            .insert(TargetFlags::normal_attack())
+            .id();
+        let attacker = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(AttackTargetDirective { target: far })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(attacker));
+        world.insert_resource(targeting_world(&[(near, 5.0), (far, 20.0)], attacker));
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(target_units);
+        stage.run(&mut world);
+        // The ordered target wins even though another enemy is much closer.
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, far);
+
+        // Once the ordered target dies the directive is dropped and the unit
+        // goes back to nearest-enemy targeting.
+        world.get_mut::<Hitpoints>(far).unwrap().hp = 0.0;
+        world
+            .entity_mut(action)
+            .remove::<TargetEntity>()
+            .remove::<LastTarget>();
+        stage.run(&mut world);
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, near);
+        assert!(world.get::<AttackTargetDirective>(attacker).is_none());
+    }
+
+    #[test]
+    fn entity_splash_only_hits_the_targeted_side() {
+        let mut world = cast_world(0.6);
+        let target = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(30.0, 0.0),
+            })
+            .id();
+        let ally = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(34.0, 0.0),
+            })
+            .id();
+        let enemy = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(32.0, 0.0),
+            })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionRange(50.0))
+            .insert(SwingDetails {
+                impact_time: 0.5,
+                swing_time: 1.0,
+            })
+            .insert(ImpactType::Instant)
+            .insert(OnHitEffects {
+                vec: vec![Effect::CleanseEffect],
+            })
+            .insert(ChannelingDetails {
+                total_time_channeled: 0.0,
+            })
+            .insert(TargetFlags::cleanse())
+            .insert(Splash {
+                radius: 8.0,
+                min_percent: 1.0,
+            })
+            .insert(TargetEntity(target))
+            .id();
+        let caster = world
+            .spawn()
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(Radius { r: 4.0 })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(PerformingActionState { action })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(caster));
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            caster,
+            vec![
+                crate::physics::SpatialNeighbor {
+                    entity: target,
+                    distance: 30.0,
+                    team: 1,
+                },
+                crate::physics::SpatialNeighbor {
+                    entity: ally,
+                    distance: 34.0,
+                    team: 1,
+                },
+                crate::physics::SpatialNeighbor {
+                    entity: enemy,
+                    distance: 32.0,
+                    team: 2,
+                },
+            ],
+        );
+        world.insert_resource(SpatialNeighborsCache { map });
+
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+        channel.run(&mut world);
+
+        // The primary target and the in-radius ally are cleansed; the enemy
+        // standing between them is not.
+        assert_eq!(world.get::<ResolveEffectsBuffer>(target).unwrap().vec.len(), 1);
+        assert_eq!(world.get::<ResolveEffectsBuffer>(ally).unwrap().vec.len(), 1);
+        assert!(world.get::<ResolveEffectsBuffer>(enemy).unwrap().vec.is_empty());
+    }
+
+    #[test]
+    fn evasion_dodges_normal_attacks_but_not_magic() {
+        let mut world = cast_world(0.6);
+        world.insert_resource(SimRng::default());
+        let dodger = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(10.0, 0.0),
+            })
+            .insert(Evasion(1.0))
+            .id();
+        let action = world
+            .spawn()
+            .insert(SwingDetails {
+                impact_time: 0.5,
+                swing_time: 1.0,
+            })
+            .insert(ImpactType::Instant)
+            .insert(OnHitEffects {
+                vec: vec![
+                    Effect::DamageEffect {
+                        damage: 10.0,
+                        delay: 0.0,
+                        damage_type: crate::effects::DamageType::Normal,
+                    },
+                    Effect::StunEffect {
+                        duration: 1.0,
+                        texture: Rid::new(),
+                    },
+                ],
+            })
+            .insert(ChannelingDetails {
+                total_time_channeled: 0.0,
+            })
+            .insert(TargetFlags::normal_attack())
+            .insert(TargetEntity(dodger))
+            .id();
+        let attacker = world
+            .spawn()
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(Radius { r: 4.0 })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(PerformingActionState { action })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(attacker));
+
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+        channel.run(&mut world);
+
+        // Guaranteed dodge: the damage and the stun rider both miss, and a
+        // "dodge" audio cue marks the whiff.
+        assert!(world.get::<ResolveEffectsBuffer>(dodger).unwrap().vec.is_empty());
+        let dodge_cues = world
+            .resource::<crate::event::EventQueue>()
+            .0
+            .iter()
+            .filter(|cue| {
+                matches!(
+                    cue,
+                    crate::event::EventCue::Audio(audio) if audio.kind == "dodge"
+                )
+            })
+            .count();
+        assert_eq!(dodge_cues, 1);
+
+        // The same swing with Magic damage cannot be dodged.
+        world.get_mut::<OnHitEffects>(action).unwrap().vec[0] = Effect::DamageEffect {
+            damage: 10.0,
+            delay: 0.0,
+            damage_type: crate::effects::DamageType::Magic,
+        };
+        world.get_mut::<ChannelingDetails>(action).unwrap().total_time_channeled = 0.0;
+        world
+            .entity_mut(attacker)
+            .insert(PerformingActionState { action });
+        world.entity_mut(action).insert(TargetEntity(dodger));
+        channel.run(&mut world);
+        assert_eq!(world.get::<ResolveEffectsBuffer>(dodger).unwrap().vec.len(), 2);
+    }
+
+    #[test]
+    fn stealthed_unit_walks_through_a_pack_until_it_attacks() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+
+        let sneak = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .insert(crate::effects::Stealthed)
+            .insert(crate::effects::BuffHolder { vec: Vec::new() })
+            .id();
+        let stealth_buff = world
+            .spawn()
+            .insert(crate::effects::BuffTimer(5.0))
+            .insert(crate::effects::BuffType { is_debuff: false })
+            .insert(TargetEntity(sneak))
+            .insert(crate::effects::StealthedBuff)
+            .id();
+        world
+            .get_mut::<crate::effects::BuffHolder>(sneak)
+            .unwrap()
+            .vec
+            .push(stealth_buff);
+
+        let action = world
+            .spawn()
+            .insert(ActionRange(10.0))
+            .insert(TargetFlags::normal_attack())
+            .id();
+        let attacker = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(attacker));
+        world.insert_resource(targeting_world(&[(sneak, 5.0)], attacker));
+
+        // The whole pack looks straight through the sneak.
+        let mut stage = SystemStage::parallel();
+        stage.add_system(target_units);
+        stage.run(&mut world);
+        assert!(world.get::<TargetEntity>(action).is_none());
+
+        // The sneak starts a swing of its own; acting breaks the stealth.
+        world.entity_mut(sneak).insert(PerformingActionState {
+            action: stealth_buff,
+        });
+        let mut reveal = SystemStage::parallel();
+        reveal.add_system(crate::effects::break_stealth_on_action);
+        reveal.run(&mut world);
+        let mut timers = SystemStage::parallel();
+        timers.add_system(crate::effects::buff_timer);
+        timers.run(&mut world);
+        assert!(world.get::<crate::effects::Stealthed>(sneak).is_none());
+
+        stage.run(&mut world);
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, sneak);
+    }
+
+    #[test]
+    fn splash_falloff_scales_damage_and_gates_riders() {
+        let mut world = cast_world(0.6);
+        let target = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(30.0, 0.0),
+            })
+            .id();
+        let mid = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(32.0, 0.0),
+            })
+            .id();
+        let edge = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(38.0, 0.0),
+            })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionRange(50.0))
+            .insert(SwingDetails {
+                impact_time: 0.5,
+                swing_time: 1.0,
+            })
+            .insert(ImpactType::Instant)
+            .insert(OnHitEffects {
+                vec: vec![
+                    Effect::DamageEffect {
+                        damage: 10.0,
+                        delay: 0.0,
+                        damage_type: crate::effects::DamageType::Normal,
+                    },
+                    Effect::StunEffect {
+                        duration: 1.0,
+                        texture: Rid::new(),
+                    },
+                ],
+            })
+            .insert(ChannelingDetails {
+                total_time_channeled: 0.0,
+            })
+            .insert(TargetFlags::normal_attack())
+            .insert(Splash {
+                radius: 8.0,
+                min_percent: 0.25,
+            })
+            .insert(TargetEntity(target))
+            .id();
+        let caster = world
+            .spawn()
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(Radius { r: 4.0 })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(PerformingActionState { action })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(caster));
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            caster,
+            vec![
+                crate::physics::SpatialNeighbor {
+                    entity: target,
+                    distance: 30.0,
+                    team: 2,
+                },
+                crate::physics::SpatialNeighbor {
+                    entity: mid,
+                    distance: 32.0,
+                    team: 2,
+                },
+                crate::physics::SpatialNeighbor {
+                    entity: edge,
+                    distance: 38.0,
+                    team: 2,
+                },
+            ],
+        );
+        world.insert_resource(SpatialNeighborsCache { map });
+
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+        channel.run(&mut world);
+
+        let splash_damage = |world: &World, entity: Entity| {
+            world
+                .get::<ResolveEffectsBuffer>(entity)
+                .unwrap()
+                .vec
+                .iter()
+                .find_map(|queued| match queued.effect {
+                    Effect::DamageEffect { damage, .. } => Some(damage),
+                    _ => None,
+                })
+        };
+
+        // The primary target takes the full hit, stun included.
+        assert_eq!(world.get::<ResolveEffectsBuffer>(target).unwrap().vec.len(), 2);
+        assert!((splash_damage(&world, target).unwrap() - 10.0).abs() < 1e-3);
+
+        // A quarter of the way out the damage thins linearly and the stun
+        // still lands; at the edge only min_percent of the damage remains.
+        assert_eq!(world.get::<ResolveEffectsBuffer>(mid).unwrap().vec.len(), 2);
+        assert!((splash_damage(&world, mid).unwrap() - 8.125).abs() < 1e-3);
+        assert_eq!(world.get::<ResolveEffectsBuffer>(edge).unwrap().vec.len(), 1);
+        assert!((splash_damage(&world, edge).unwrap() - 2.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn disarm_cancels_a_weapon_channel_but_not_a_cast() {
+        let mut world = cast_world(0.6);
+        let target = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(10.0, 0.0),
+            })
+            .id();
+        let mid_swing_action = |world: &mut World| {
+            world
+                .spawn()
+                .insert(SwingDetails {
+                    impact_time: 0.5,
+                    swing_time: 1.0,
+                })
+                .insert(ImpactType::Instant)
+                .insert(OnHitEffects {
+                    vec: vec![Effect::StunEffect {
+                        duration: 1.0,
+                        texture: Rid::new(),
+                    }],
+                })
+                .insert(ChannelingDetails {
+                    total_time_channeled: 0.3,
+                })
+                .insert(TargetFlags::normal_attack())
+                .insert(TargetEntity(target))
+                .insert(Disabled)
+                .id()
+        };
+        let performer = |world: &mut World, action: Entity| {
+            let performer = world
+                .spawn()
+                .insert(Position { pos: Vector2::ZERO })
+                .insert(Radius { r: 4.0 })
+                .insert(TeamAlignment {
+                    alignment: 1,
+                    alignment_base: 1,
+                })
+                .insert(PerformingActionState { action })
+                .id();
+            world.entity_mut(action).insert(ActionOwner(performer));
+            performer
+        };
+        // Both actions were disabled mid-swing; only one is a weapon.
+        let weapon = mid_swing_action(&mut world);
+        world.entity_mut(weapon).insert(BasicAttack);
+        let cast = mid_swing_action(&mut world);
+        let attacker = performer(&mut world, weapon);
+        let caster = performer(&mut world, cast);
+
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+        channel.run(&mut world);
+
+        // The weapon channel died without landing; the cast crossed impact.
+        assert!(world.get::<PerformingActionState>(attacker).is_none());
+        assert!(
+            world
+                .get::<ChannelingDetails>(weapon)
+                .unwrap()
+                .total_time_channeled
+                .abs()
+                < 1e-3
+        );
+        assert!(world.get::<PerformingActionState>(caster).is_some());
+        assert_eq!(world.get::<ResolveEffectsBuffer>(target).unwrap().vec.len(), 1);
+    }
+
+    #[test]
+    fn ground_aoe_aims_at_the_densest_cluster_and_lands_there() {
+        let mut world = cast_world(0.6);
+        let enemy = |world: &mut World, x: f32, y: f32| {
+            world
+                .spawn()
+                .insert(Position {
+                    pos: Vector2::new(x, y),
+                })
+                .insert(Hitpoints {
+                    hp: 100.0,
+                    max_hp: 100.0,
+                })
+                .insert(ResolveEffectsBuffer { vec: Vec::new() })
+                .id()
+        };
+        // Two enemies bunched up on the right, a loner on the left.
+        let near = enemy(&mut world, 100.0, 8.0);
+        let close = enemy(&mut world, 104.0, 12.0);
+        let loner = enemy(&mut world, -100.0, 0.0);
+        let caster = world
+            .spawn()
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(Radius { r: 4.0 })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionOwner(caster))
+            .insert(ActionRange(200.0))
+            .insert(ActionCooldown(3.0))
+            .insert(SwingDetails {
+                impact_time: 0.5,
+                swing_time: 1.0,
+            })
+            .insert(ImpactType::GroundArea)
+            .insert(OnHitEffects {
+                vec: vec![Effect::DamageEffect {
+                    damage: 10.0,
+                    delay: 0.0,
+                    damage_type: crate::effects::DamageType::Magic,
+                }],
+            })
+            .insert(TargetFlags::normal_attack())
+            .insert(ChannelingDetails {
+                total_time_channeled: 0.0,
+            })
+            .insert(Splash {
+                radius: 20.0,
+                min_percent: 1.0,
+            })
+            .insert(TelegraphTexture(Rid::new()))
+            .id();
+        world
+            .entity_mut(caster)
+            .insert(UnitActions { vec: vec![action] });
+
+        let mut spatial = crate::physics::SpatialHashTable::new(64.0);
+        let mut map = std::collections::HashMap::new();
+        let mut neighbor_list = Vec::new();
+        for (entity, x, y) in [
+            (near, 100.0f32, 8.0f32),
+            (close, 104.0, 12.0),
+            (loner, -100.0, 0.0),
+        ] {
+            let position = Vector2::new(x, y);
+            let hash = spatial.hash(position);
+            spatial
+                .table
+                .entry(hash)
+                .or_default()
+                .push(crate::physics::SpatialHashEntry {
+                    entity,
+                    position,
+                    radius: 4.0,
+                    team: 2,
+                });
+            neighbor_list.push(crate::physics::SpatialNeighbor {
+                entity,
+                distance: position.length(),
+                team: 2,
+            });
+        }
+        map.insert(caster, neighbor_list);
+        world.insert_resource(spatial);
+        world.insert_resource(SpatialNeighborsCache { map });
+
+        let mut target = SystemStage::parallel();
+        target.add_system(target_units);
+        target.run(&mut world);
+        let aim = world.get::<TargetPosition>(action).unwrap().0;
+        assert!(aim.distance_to(Vector2::new(102.0, 10.0)) < 1e-3);
+
+        // The swing starts off the point target and telegraphs the blast.
+        let mut perform = SystemStage::parallel();
+        perform.add_system(perform_action);
+        perform.run(&mut world);
+        assert!(world.get::<PerformingActionState>(caster).is_some());
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+        channel.run(&mut world);
+        let mut telegraphs = world.query::<&crate::util::ExpirationTimer>();
+        assert!(telegraphs.iter(&world).any(|timer| (timer.0 - 0.5).abs() < 1e-3));
+
+        // Impact hits the bunched enemies around the point, not the loner.
+        assert_eq!(world.get::<ResolveEffectsBuffer>(near).unwrap().vec.len(), 1);
+        assert_eq!(world.get::<ResolveEffectsBuffer>(close).unwrap().vec.len(), 1);
+        assert!(world.get::<ResolveEffectsBuffer>(loner).unwrap().vec.is_empty());
+    }
+
+    #[test]
+    fn stun_aborts_a_swing_before_impact_but_keeps_a_landed_hit() {
+        let mut world = cast_world(0.3);
+        let victim = world
+            .spawn()
+            .insert(Position {
+                pos: Vector2::new(2.0, 0.0),
+            })
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .id();
+        let action = world
+            .spawn()
+            .insert(SwingDetails {
+                impact_time: 0.5,
+                swing_time: 1.0,
+            })
+            .insert(ImpactType::Instant)
+            .insert(OnHitEffects {
+                vec: vec![Effect::DamageEffect {
+                    damage: 10.0,
+                    delay: 0.0,
+                    damage_type: crate::effects::DamageType::Normal,
+                }],
+            })
+            .insert(ChannelingDetails {
+                total_time_channeled: 0.0,
+            })
+            .insert(TargetEntity(victim))
+            .id();
+        let attacker = world
+            .spawn()
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(Radius { r: 1.0 })
+            .insert(PerformingActionState { action })
+            .id();
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+
+        // Stunned before impact_time: the wind-up aborts and nothing lands.
+        channel.run(&mut world);
+        world.entity_mut(attacker).insert(Stunned);
+        channel.run(&mut world);
+        assert!(world.get::<PerformingActionState>(attacker).is_none());
+        assert!(
+            world
+                .get::<ChannelingDetails>(action)
+                .unwrap()
+                .total_time_channeled
+                .abs()
+                < 1e-6
+        );
+        assert!(world.get::<ResolveEffectsBuffer>(victim).unwrap().vec.is_empty());
+
+        // Stunned only after the swing crossed impact_time: the landed
+        // effects stay put.
+        world.entity_mut(attacker).remove::<Stunned>();
+        world.entity_mut(attacker).insert(PerformingActionState { action });
+        channel.run(&mut world);
+        channel.run(&mut world);
+        assert_eq!(world.get::<ResolveEffectsBuffer>(victim).unwrap().vec.len(), 1);
+        world.entity_mut(attacker).insert(Stunned);
+        channel.run(&mut world);
+        assert!(world.get::<PerformingActionState>(attacker).is_none());
+        assert_eq!(world.get::<ResolveEffectsBuffer>(victim).unwrap().vec.len(), 1);
+    }
+
+    #[test]
+    fn cleanse_targeting_requires_a_real_debuff() {
+        let mut world = cast_world(0.1);
+        let debuff = world
+            .spawn()
+            .insert(crate::effects::BuffType { is_debuff: true })
+            .id();
+        let ally = world
+            .spawn()
+            .insert(Position {
+                pos: Vector2::new(10.0, 0.0),
+            })
+            .insert(Hitpoints {
+                hp: 50.0,
+                max_hp: 100.0,
+            })
+            .insert(crate::effects::BuffHolder { vec: Vec::new() })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionRange(50.0))
+            .insert(TargetFlags::cleanse())
+            .id();
+        let caster = world
+            .spawn()
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(UnitActions { vec: vec![action] })
+            .id();
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            caster,
+            vec![crate::physics::SpatialNeighbor {
+                entity: ally,
+                distance: 10.0,
+                team: 1,
+            }],
+        );
+        world.insert_resource(SpatialNeighborsCache { map });
+
+        let mut target = SystemStage::parallel();
+        target.add_system(target_units);
+
+        // A clean ally is no cleanse target.
+        target.run(&mut world);
+        assert!(world.get::<TargetEntity>(action).is_none());
+
+        // Any debuff in the holder makes it one — no per-debuff marker needed.
+        world
+            .get_mut::<crate::effects::BuffHolder>(ally)
+            .unwrap()
+            .vec
+            .push(debuff);
+        target.run(&mut world);
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, ally);
+    }
+
+    #[test]
+    fn furthest_enemy_actions_pick_the_farthest_in_range_target() {
+        let mut world = cast_world(0.1);
+        let enemy = |world: &mut World, x: f32| {
+            world
+                .spawn()
+                .insert(Position {
+                    pos: Vector2::new(x, 0.0),
+                })
+                .insert(Hitpoints {
+                    hp: 100.0,
+                    max_hp: 100.0,
+                })
+                .id()
+        };
+        let near = enemy(&mut world, 20.0);
+        let far = enemy(&mut world, 80.0);
+        let beyond = enemy(&mut world, 150.0);
+        let action = world
+            .spawn()
+            .insert(ActionRange(100.0))
+            .insert(TargetFlags::furthest_enemy())
+            .id();
+        let caster = world
+            .spawn()
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(UnitActions { vec: vec![action] })
+            .id();
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            caster,
+            [(near, 20.0), (far, 80.0), (beyond, 150.0)]
+                .into_iter()
+                .map(|(entity, distance)| crate::physics::SpatialNeighbor {
+                    entity,
+                    distance,
+                    team: 2,
+                })
+                .collect(),
+        );
+        world.insert_resource(SpatialNeighborsCache { map });
+
+        let mut target = SystemStage::parallel();
+        target.add_system(target_units);
+        target.run(&mut world);
+
+        // The farthest enemy still inside range wins; out-of-range stays out.
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, far);
+    }
+
+    #[test]
+    fn buff_actions_prefer_allies_already_fighting() {
+        let mut world = cast_world(0.1);
+        let idle = world
+            .spawn()
+            .insert(Position {
+                pos: Vector2::new(40.0, 0.0),
+            })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .id();
+        let spent_action = world.spawn().insert(Cooldown(1.0)).id();
+        let fighting = world
+            .spawn()
+            .insert(Position {
+                pos: Vector2::new(60.0, 0.0),
+            })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .insert(UnitActions {
+                vec: vec![spent_action],
+            })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionRange(100.0))
+            .insert(TargetFlags::normal_buff())
+            .id();
+        let caster = world
+            .spawn()
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(UnitActions { vec: vec![action] })
+            .id();
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            caster,
+            [(idle, 40.0), (fighting, 60.0)]
+                .into_iter()
+                .map(|(entity, distance)| crate::physics::SpatialNeighbor {
+                    entity,
+                    distance,
+                    team: 1,
+                })
+                .collect(),
+        );
+        world.insert_resource(SpatialNeighborsCache { map });
+
+        let mut target = SystemStage::parallel();
+        target.add_system(target_units);
+        target.run(&mut world);
+
+        // 60 * COMBAT_TARGET_BONUS beats the idle ally's plain 40: the buff
+        // goes to the ally whose action is already on cooldown.
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, fighting);
+    }
+
+    #[test]
+    fn heals_chase_the_lowest_hp_fraction_not_the_nearest_scratch() {
+        let mut world = cast_world(0.1);
+        let ally = |world: &mut World, x: f32, hp: f32| {
+            world
+                .spawn()
+                .insert(Position {
+                    pos: Vector2::new(x, 0.0),
+                })
+                .insert(Hitpoints { hp, max_hp: 100.0 })
+                .id()
+        };
+        // The scratched frontliner stands between the healer and the tank
+        // that is actually being focused down.
+        let scratched = ally(&mut world, 20.0, 90.0);
+        let tank = ally(&mut world, 60.0, 15.0);
+        let healing = world
+            .spawn()
+            .insert(ActionRange(100.0))
+            .insert(TargetFlags::heal())
+            .id();
+        let legacy = world
+            .spawn()
+            .insert(ActionRange(100.0))
+            .insert(TargetFlags {
+                selection: TargetSelection::Nearest,
+                ..TargetFlags::heal()
+            })
+            .id();
+        let mut map = std::collections::HashMap::new();
+        for action in [healing, legacy] {
+            let caster = world
+                .spawn()
+                .insert(Position { pos: Vector2::ZERO })
+                .insert(TeamAlignment {
+                    alignment: 1,
+                    alignment_base: 1,
+                })
+                .insert(UnitActions { vec: vec![action] })
+                .id();
+            map.insert(
+                caster,
+                [(scratched, 20.0), (tank, 60.0)]
+                    .into_iter()
+                    .map(|(entity, distance)| crate::physics::SpatialNeighbor {
+                        entity,
+                        distance,
+                        team: 1,
+                    })
+                    .collect(),
+            );
+        }
+        world.insert_resource(SpatialNeighborsCache { map });
+
+        let mut target = SystemStage::parallel();
+        target.add_system(target_units);
+        target.run(&mut world);
+
+        // Fraction mode reaches past the scratch to the 15% tank; pinned to
+        // the old nearest mode the heal would have been wasted up front.
+        assert_eq!(world.get::<TargetEntity>(healing).unwrap().0, tank);
+        assert_eq!(world.get::<TargetEntity>(legacy).unwrap().0, scratched);
+    }
+
+    #[test]
+    fn highest_threat_mode_turns_on_the_aggro_leader() {
+        let mut world = World::default();
+        let mut enemy = || {
+            world
+                .spawn()
+                .insert(Hitpoints {
+                    hp: 10.0,
+                    max_hp: 10.0,
+                })
+                .id()
+        };
+        let scratcher = enemy();
+        let bruiser = enemy();
+        let action = world
+            .spawn()
+            .insert(ActionRange(20.0))
+            .insert(TargetFlags {
+                selection: TargetSelection::HighestThreat,
+                ..TargetFlags::normal_attack()
+            })
+            .id();
+        let mut table = ThreatTable::default();
+        table.add(bruiser, 40.0);
+        let attacker = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(table)
+            .id();
+        world.entity_mut(action).insert(ActionOwner(attacker));
+        // The bruiser has been pounding the attacker from farther away.
+        world.insert_resource(targeting_world(
+            &[(scratcher, 5.0), (bruiser, 15.0)],
+            attacker,
+        ));
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(target_units);
+        stage.run(&mut world);
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, bruiser);
+    }
+
+    #[test]
+    fn ready_heal_pre_empts_the_basic_attack_on_a_dying_ally() {
+        let mut world = World::default();
+        let enemy = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .id();
+        let dying_ally = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 2.0,
+                max_hp: 10.0,
+            })
+            .id();
+        let attack = world
+            .spawn()
+            .insert(ActionRange(20.0))
+            .insert(TargetFlags::normal_attack())
+            .insert(ActionCooldown(1.0))
+            .insert(ActionPriority(BASIC_ATTACK_PRIORITY))
+            .id();
+        let heal = world
+            .spawn()
+            .insert(ActionRange(20.0))
+            .insert(TargetFlags::heal())
+            .insert(ActionCooldown(8.0))
+            .insert(ActionPriority(ABILITY_PRIORITY))
+            .id();
+        // The attack was registered first; priority has to outrank vec order.
+        let paladin = world
+            .spawn()
+            .insert(UnitActions {
+                vec: vec![attack, heal],
+            })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        for action in [attack, heal] {
+            world.entity_mut(action).insert(ActionOwner(paladin));
+        }
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            paladin,
+            vec![
+                crate::physics::SpatialNeighbor {
+                    entity: enemy,
+                    distance: 5.0,
+                    team: 1,
+                },
+                crate::physics::SpatialNeighbor {
+                    entity: dying_ally,
+                    distance: 10.0,
+                    team: 0,
+                },
+            ],
+        );
+        world.insert_resource(SpatialNeighborsCache { map });
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(target_units);
+        stage.run(&mut world);
+
+        // The heal fires and locks the unit; the attack never acquires.
+        assert_eq!(world.get::<TargetEntity>(heal).unwrap().0, dying_ally);
+        assert!(world.get::<TargetEntity>(attack).is_none());
+    }
+
+    #[test]
+    fn equal_priority_ties_go_to_the_longer_cooldown() {
+        let mut world = World::default();
+        let enemy = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .id();
+        let jab = world
+            .spawn()
+            .insert(ActionRange(20.0))
+            .insert(TargetFlags::normal_attack())
+            .insert(ActionCooldown(1.0))
+            .insert(ActionPriority(BASIC_ATTACK_PRIORITY))
+            .id();
+        let haymaker = world
+            .spawn()
+            .insert(ActionRange(20.0))
+            .insert(TargetFlags::normal_attack())
+            .insert(ActionCooldown(6.0))
+            .insert(ActionPriority(BASIC_ATTACK_PRIORITY))
+            .id();
+        let attacker = world
+            .spawn()
+            .insert(UnitActions {
+                vec: vec![jab, haymaker],
+            })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        for action in [jab, haymaker] {
+            world.entity_mut(action).insert(ActionOwner(attacker));
+        }
+        world.insert_resource(targeting_world(&[(enemy, 5.0)], attacker));
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(target_units);
+        stage.run(&mut world);
+
+        assert_eq!(world.get::<TargetEntity>(haymaker).unwrap().0, enemy);
+        assert!(world.get::<TargetEntity>(jab).is_none());
+    }
+
+    #[test]
+    fn threat_decays_on_the_half_life_and_prunes_the_dust() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics {
+            seconds: THREAT_HALF_LIFE,
+        });
+        let bruiser = world.spawn().id();
+        let scratcher = world.spawn().id();
+        let mut table = ThreatTable::default();
+        table.add(bruiser, 40.0);
+        table.add(scratcher, 0.15);
+        let unit = world.spawn().insert(table).id();
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(threat_decay);
+        stage.run(&mut world);
+
+        let table = world.get::<ThreatTable>(unit).unwrap();
+        assert!((table.get(bruiser) - 20.0).abs() < 1e-3);
+        // The scratcher's halved entry falls under the floor and is dropped.
+        assert!(!table.map.contains_key(&scratcher));
+    }
+
+    fn interrupted_cues(world: &mut World) -> usize {
+        let queue = world.resource::<crate::event::EventQueue>();
+        queue
+            .0
+            .iter()
+            .filter(|cue| {
+                matches!(cue, crate::event::EventCue::Audio(audio) if audio.kind == "interrupted")
+            })
+            .count()
+    }
+
+    #[test]
+    fn stun_mid_windup_refunds_the_unspent_cooldown() {
+        let mut world = cast_world(0.2);
+        let (unit, action) = swinging_unit(&mut world);
+
+        let mut perform = SystemStage::parallel();
+        perform.add_system(perform_action);
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+
+        perform.run(&mut world);
+        channel.run(&mut world);
+        world.entity_mut(unit).insert(crate::effects::Stunned);
+        channel.run(&mut world);
+
+        assert!(world.get::<PerformingActionState>(unit).is_none());
+        assert_eq!(
+            world.get::<ChannelingDetails>(action).unwrap().total_time_channeled,
+            0.0
+        );
+        // 0.2s of a 1.0s swing channeled: the caster owes a fifth of the
+        // 3.0s cooldown, not all of it.
+        assert!((world.get::<Cooldown>(action).unwrap().0 - 0.6).abs() < 1e-6);
+        assert_eq!(interrupted_cues(&mut world), 1);
+    }
+
+    #[test]
+    fn stun_after_impact_keeps_the_full_cooldown_and_stays_quiet() {
+        let mut world = cast_world(0.6);
+        let (unit, action) = swinging_unit(&mut world);
+
+        let mut perform = SystemStage::parallel();
+        perform.add_system(perform_action);
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+
+        perform.run(&mut world);
+        channel.run(&mut world);
+        world.entity_mut(unit).insert(crate::effects::Stunned);
+        channel.run(&mut world);
+
+        // The hit already landed, so there is nothing to refund or announce.
+        assert!(world.get::<PerformingActionState>(unit).is_none());
+        assert!((world.get::<Cooldown>(action).unwrap().0 - 3.0).abs() < 1e-6);
+        assert_eq!(interrupted_cues(&mut world), 0);
+    }
+
+    #[test]
+    fn losing_the_target_mid_windup_aborts_the_swing() {
+        let mut world = cast_world(0.2);
+        let (unit, action) = swinging_unit(&mut world);
+        let target = world.get::<TargetEntity>(action).unwrap().0;
+
+        let mut perform = SystemStage::parallel();
+        perform.add_system(perform_action);
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+
+        perform.run(&mut world);
+        channel.run(&mut world);
+        world.despawn(target);
+        channel.run(&mut world);
+
+        assert!(world.get::<PerformingActionState>(unit).is_none());
+        assert!((world.get::<Cooldown>(action).unwrap().0 - 0.6).abs() < 1e-6);
+        assert_eq!(interrupted_cues(&mut world), 1);
+    }
+
+    #[test]
+    fn target_may_stray_within_the_leeway_but_not_past_it() {
+        let mut world = cast_world(0.1);
+        let (unit, action) = swinging_unit(&mut world);
+        let target = world.get::<TargetEntity>(action).unwrap().0;
+        world.entity_mut(action).insert(ActionRange(10.0));
+
+        let mut perform = SystemStage::parallel();
+        perform.add_system(perform_action);
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+
+        perform.run(&mut world);
+        channel.run(&mut world);
+        // Edge-to-edge distance 12.0 against range 10.0 * 1.25 leeway: the
+        // target is drifting but the swing holds.
+        world.get_mut::<Position>(target).unwrap().pos = Vector2::new(16.0, 0.0);
+        channel.run(&mut world);
+        assert!(world.get::<PerformingActionState>(unit).is_some());
+
+        // One more step out and it is past the leeway.
+        world.get_mut::<Position>(target).unwrap().pos = Vector2::new(18.0, 0.0);
+        channel.run(&mut world);
+        assert!(world.get::<PerformingActionState>(unit).is_none());
+        assert_eq!(interrupted_cues(&mut world), 1);
+    }
+
+    #[test]
+    fn swings_slide_onto_the_next_enemy_when_the_target_falls_early() {
+        let mut world = cast_world(0.2);
+        let victim = world
+            .spawn()
+            .insert(Hitpoints { hp: 5.0, max_hp: 5.0 })
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position { pos: Vector2::ZERO })
+            .id();
+        let backup = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position { pos: Vector2::new(2.0, 0.0) })
+            .id();
+        let mut map = std::collections::HashMap::new();
+        let mut pack = Vec::new();
+        for _ in 0..3 {
+            let action = world
+                .spawn()
+                .insert(ActionCooldown(1.0))
+                .insert(ChannelingDetails {
+                    total_time_channeled: 0.0,
+                })
+                .insert(TargetEntity(victim))
+                .insert(SwingDetails {
+                    impact_time: 0.5,
+                    swing_time: 1.0,
+                })
+                .insert(ImpactType::Instant)
+                .insert(OnHitEffects {
+                    vec: vec![Effect::DamageEffect {
+                        damage: 2.0,
+                        delay: 0.0,
+                        damage_type: crate::effects::DamageType::Normal,
+                    }],
+                })
+                .insert(ActionRange(10.0))
+                .insert(TargetFlags::normal_attack())
+                .id();
+            let unit = world
+                .spawn()
+                .insert(UnitActions { vec: vec![action] })
+                .insert(Position { pos: Vector2::ZERO })
+                .insert(Radius { r: 1.0 })
+                .insert(TeamAlignment {
+                    alignment: 0,
+                    alignment_base: 0,
+                })
+                .id();
+            world.entity_mut(action).insert(ActionOwner(unit));
+            map.insert(
+                unit,
+                vec![
+                    crate::physics::SpatialNeighbor {
+                        entity: victim,
+                        distance: 1.0,
+                        team: 1,
+                    },
+                    crate::physics::SpatialNeighbor {
+                        entity: backup,
+                        distance: 2.0,
+                        team: 1,
+                    },
+                ],
+            );
+            pack.push((unit, action));
+        }
+        world.insert_resource(SpatialNeighborsCache { map });
+
+        let mut perform = SystemStage::parallel();
+        perform.add_system(perform_action);
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+
+        perform.run(&mut world);
+        channel.run(&mut world);
+        // The focus target drops mid-wind-up; everyone slides to the backup
+        // instead of eating the whole swing.
+        world.get_mut::<Hitpoints>(victim).unwrap().hp = 0.0;
+        channel.run(&mut world);
+        for (unit, action) in pack.iter() {
+            assert!(world.get::<PerformingActionState>(*unit).is_some());
+            assert_eq!(world.get::<TargetEntity>(*action).unwrap().0, backup);
+        }
+
+        // The swings cross impact_time on the replacement: three hits land.
+        channel.run(&mut world);
+        assert_eq!(
+            world.get::<ResolveEffectsBuffer>(backup).unwrap().vec.len(),
+            3
+        );
+        assert!(world
+            .get::<ResolveEffectsBuffer>(victim)
+            .unwrap()
+            .vec
+            .is_empty());
+    }
+
+    #[test]
+    fn projectile_windups_abort_rather_than_retarget() {
+        let mut world = cast_world(0.2);
+        let victim = world
+            .spawn()
+            .insert(Hitpoints { hp: 5.0, max_hp: 5.0 })
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position { pos: Vector2::ZERO })
+            .id();
+        let backup = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position { pos: Vector2::new(2.0, 0.0) })
+            .id();
+        let action = world
+            .spawn()
+            .insert(ActionCooldown(1.0))
+            .insert(ChannelingDetails {
+                total_time_channeled: 0.0,
+            })
+            .insert(TargetEntity(victim))
+            .insert(SwingDetails {
+                impact_time: 0.5,
+                swing_time: 1.0,
+            })
+            .insert(ImpactType::Projectile)
+            .insert(OnHitEffects { vec: Vec::new() })
+            .insert(ActionRange(10.0))
+            .insert(TargetFlags::normal_attack())
+            .id();
+        let unit = world
+            .spawn()
+            .insert(UnitActions { vec: vec![action] })
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(Radius { r: 1.0 })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        world.entity_mut(action).insert(ActionOwner(unit));
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            unit,
+            vec![
+                crate::physics::SpatialNeighbor {
+                    entity: victim,
+                    distance: 1.0,
+                    team: 1,
+                },
+                crate::physics::SpatialNeighbor {
+                    entity: backup,
+                    distance: 2.0,
+                    team: 1,
+                },
+            ],
+        );
+        world.insert_resource(SpatialNeighborsCache { map });
+
+        let mut perform = SystemStage::parallel();
+        perform.add_system(perform_action);
+        let mut channel = SystemStage::parallel();
+        channel.add_system(performing_action_state);
+
+        perform.run(&mut world);
+        channel.run(&mut world);
+        world.get_mut::<Hitpoints>(victim).unwrap().hp = 0.0;
+        channel.run(&mut world);
+
+        // Even with a valid replacement in range, the projectile wind-up
+        // stands down instead of switching aim.
+        assert!(world.get::<PerformingActionState>(unit).is_none());
+        assert_eq!(world.get::<TargetEntity>(action).unwrap().0, victim);
+        assert_eq!(interrupted_cues(&mut world), 1);
+    }
+}
@@ -0,0 +1,1110 @@
+use bevy_ecs::prelude::*;
+use gdnative::prelude::Vector2;
+
+use std::collections::HashMap;
+
+use crate::actions::{Cooldown, UnitActions};
+use crate::effects::{Stealthed, Stunned};
+use crate::graphics::animation::{AnimationRole, PlayAnimationDirective};
+use crate::graphics::FlippableSprite;
+use crate::physics::{DeltaPhysics, Position, SpatialNeighborsCache, Velocity};
+use crate::terrain::{FlowFieldsTowardsEnemies, TerrainMap};
+use crate::unit::{Acceleration, AttackTargetDirective, Hitpoints, Speed, TeamAlignment};
+use crate::util::normalized_or_zero;
+
+/// How a unit's accumulated boid forces are combined each tick.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum BoidBlendMode {
+    /// Raw sum of weighted forces, clamped to max_force. Adding a boid makes
+    /// every other boid's multiplier relatively weaker.
+    Additive,
+    /// Weighted average of the raw forces, scaled to max_force, so
+    /// multipliers are relative to each other and self-normalizing.
+    WeightedAverage,
+}
+
+/// Balanced-profile boid defaults used by spawn_unit; the AI profile scales
+/// them per team.
+pub const BASE_SEEK_MULTIPLIER: f32 = 2.0;
+pub const BASE_CHARGE_RADIUS: f32 = 160.0;
+pub const BASE_AVOID_WALLS_MULTIPLIER: f32 = 6.0;
+
+/// Coarse per-team aggression setting, settable from GDScript as 0/1/2.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum AIProfile {
+    Defensive,
+    Balanced,
+    Aggressive,
+}
+
+impl AIProfile {
+    pub fn from_i64(value: i64) -> Self {
+        match value {
+            0 => AIProfile::Defensive,
+            2 => AIProfile::Aggressive,
+            _ => AIProfile::Balanced,
+        }
+    }
+
+    /// Scale on SeekEnemiesBoid::multiplier.
+    pub fn seek_factor(&self) -> f32 {
+        match self {
+            AIProfile::Defensive => 0.75,
+            AIProfile::Balanced => 1.0,
+            AIProfile::Aggressive => 1.5,
+        }
+    }
+
+    /// Scale on ChargeAtEnemyBoid::radius.
+    pub fn charge_radius_factor(&self) -> f32 {
+        match self {
+            AIProfile::Defensive => 0.5,
+            AIProfile::Balanced => 1.0,
+            AIProfile::Aggressive => 1.5,
+        }
+    }
+
+    /// Scale on AvoidWallsBoid::multiplier and StoppingBoid::multiplier.
+    pub fn caution_factor(&self) -> f32 {
+        match self {
+            AIProfile::Defensive => 1.5,
+            AIProfile::Balanced => 1.0,
+            AIProfile::Aggressive => 1.0,
+        }
+    }
+}
+
+/// Per-team AI profile; teams without an entry behave Balanced.
+#[derive(Default)]
+pub struct TeamAIProfiles {
+    pub map: HashMap<i64, AIProfile>,
+}
+
+impl TeamAIProfiles {
+    pub fn get(&self, team: i64) -> AIProfile {
+        self.map.get(&team).copied().unwrap_or(AIProfile::Balanced)
+    }
+}
+
+/// Rewrite the profile-scaled boid fields on every live unit of the team;
+/// used when the profile changes mid-match.
+pub fn apply_team_ai_profile(world: &mut World, team: i64) {
+    let profile = match world.get_resource::<TeamAIProfiles>() {
+        Some(profiles) => profiles.get(team),
+        None => AIProfile::Balanced,
+    };
+    let mut query = world.query::<(
+        &TeamAlignment,
+        Option<&mut SeekEnemiesBoid>,
+        Option<&mut ChargeAtEnemyBoid>,
+        Option<&mut AvoidWallsBoid>,
+        Option<&mut StoppingBoid>,
+    )>();
+    for (alignment, seek, charge, walls, stopping) in query.iter_mut(world) {
+        if alignment.alignment != team {
+            continue;
+        }
+        if let Some(mut seek) = seek {
+            seek.multiplier = BASE_SEEK_MULTIPLIER * profile.seek_factor();
+        }
+        if let Some(mut charge) = charge {
+            charge.radius = BASE_CHARGE_RADIUS * profile.charge_radius_factor();
+        }
+        if let Some(mut walls) = walls {
+            walls.multiplier = BASE_AVOID_WALLS_MULTIPLIER * profile.caution_factor();
+        }
+        if let Some(mut stopping) = stopping {
+            stopping.multiplier = stopping.base_multiplier * profile.caution_factor();
+        }
+    }
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct BoidParams {
+    pub max_speed: f32,
+    pub max_force: f32,
+    pub blend_mode: BoidBlendMode,
+}
+
+/// Every boid's steering contribution this tick, cleared by
+/// `boid_apply_params`. Boids record (raw force, weight) pairs; the sum keeps
+/// the weighted total for the additive path.
+#[derive(Component, Clone, Default)]
+pub struct AppliedBoidForces {
+    pub sum: Vector2,
+    pub contributions: Vec<(Vector2, f32)>,
+}
+
+impl AppliedBoidForces {
+    pub fn add_force(&mut self, force: Vector2, weight: f32) {
+        self.sum += force * weight;
+        self.contributions.push((force, weight));
+    }
+
+    /// Combine this tick's contributions according to the blend mode. The
+    /// result is still subject to the max_force clamp in `boid_apply_params`.
+    pub fn resolve(&self, params: &BoidParams) -> Vector2 {
+        match params.blend_mode {
+            BoidBlendMode::Additive => self.sum,
+            BoidBlendMode::WeightedAverage => {
+                let total_weight: f32 = self.contributions.iter().map(|(_, w)| w).sum();
+                if total_weight <= 0.0 {
+                    return Vector2::ZERO;
+                }
+                self.sum / total_weight * params.max_force
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.sum = Vector2::ZERO;
+        self.contributions.clear();
+    }
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct SeparationBoid {
+    pub radius: f32,
+    pub multiplier: f32,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct CohesionBoid {
+    pub radius: f32,
+    pub multiplier: f32,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct AlignmentBoid {
+    pub radius: f32,
+    pub multiplier: f32,
+}
+
+/// Follow the team flow field toward the nearest enemies.
+#[derive(Component, Copy, Clone)]
+pub struct SeekEnemiesBoid {
+    pub multiplier: f32,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct AvoidWallsBoid {
+    pub multiplier: f32,
+    pub look_ahead: f32,
+}
+
+/// Brake toward standstill; used as a stance and while stunned.
+#[derive(Component, Copy, Clone)]
+pub struct StoppingBoid {
+    pub multiplier: f32,
+    /// Unscaled per-unit value; `apply_team_ai_profile` rewrites
+    /// `multiplier` from this so repeated profile switches never compound.
+    pub base_multiplier: f32,
+}
+
+/// Beeline at the nearest enemy once it is inside radius. With
+/// `chase_top_threat` set the unit runs down its aggro leader instead,
+/// falling back to the nearest scan when the leader is out of reach.
+#[derive(Component, Copy, Clone)]
+pub struct ChargeAtEnemyBoid {
+    pub radius: f32,
+    pub multiplier: f32,
+    pub chase_top_threat: bool,
+}
+
+/// Back away from the nearest enemy inside radius.
+#[derive(Component, Copy, Clone)]
+pub struct KiteNearestEnemyBoid {
+    pub radius: f32,
+    pub multiplier: f32,
+}
+
+/// Optional overrides for the boid tuning `spawn_unit` hardcodes; `None`
+/// fields keep the defaults. Stored on the blueprint and applicable to live
+/// units via `apply_boid_tuning`.
+#[derive(Copy, Clone, Default)]
+pub struct BoidTuningOverrides {
+    pub separation_radius: Option<f32>,
+    pub separation_multiplier: Option<f32>,
+    pub cohesion_radius: Option<f32>,
+    pub cohesion_multiplier: Option<f32>,
+    pub alignment_radius: Option<f32>,
+    pub alignment_multiplier: Option<f32>,
+    pub avoid_walls_multiplier: Option<f32>,
+    pub stopping_multiplier: Option<f32>,
+    pub seek_multiplier: Option<f32>,
+}
+
+/// Push tuning overrides onto a live unit's boid components. `None` fields
+/// and overrides for boids the unit does not carry are ignored, except
+/// stopping, which is added on demand since spawn never inserts it. Returns
+/// false for missing entities.
+pub fn apply_boid_tuning(world: &mut World, entity: Entity, tuning: &BoidTuningOverrides) -> bool {
+    if world.get_entity(entity).is_none() {
+        return false;
+    }
+    if let Some(mut boid) = world.get_mut::<SeparationBoid>(entity) {
+        if let Some(radius) = tuning.separation_radius {
+            boid.radius = radius;
+        }
+        if let Some(multiplier) = tuning.separation_multiplier {
+            boid.multiplier = multiplier;
+        }
+    }
+    if let Some(mut boid) = world.get_mut::<CohesionBoid>(entity) {
+        if let Some(radius) = tuning.cohesion_radius {
+            boid.radius = radius;
+        }
+        if let Some(multiplier) = tuning.cohesion_multiplier {
+            boid.multiplier = multiplier;
+        }
+    }
+    if let Some(mut boid) = world.get_mut::<AlignmentBoid>(entity) {
+        if let Some(radius) = tuning.alignment_radius {
+            boid.radius = radius;
+        }
+        if let Some(multiplier) = tuning.alignment_multiplier {
+            boid.multiplier = multiplier;
+        }
+    }
+    if let Some(multiplier) = tuning.avoid_walls_multiplier {
+        if let Some(mut boid) = world.get_mut::<AvoidWallsBoid>(entity) {
+            boid.multiplier = multiplier;
+        }
+    }
+    if let Some(multiplier) = tuning.seek_multiplier {
+        if let Some(mut boid) = world.get_mut::<SeekEnemiesBoid>(entity) {
+            boid.multiplier = multiplier;
+        }
+    }
+    if let Some(multiplier) = tuning.stopping_multiplier {
+        match world.get_mut::<StoppingBoid>(entity) {
+            Some(mut boid) => {
+                boid.multiplier = multiplier;
+                boid.base_multiplier = multiplier;
+            }
+            None => {
+                world.entity_mut(entity).insert(StoppingBoid {
+                    multiplier,
+                    base_multiplier: multiplier,
+                });
+            }
+        }
+    }
+    true
+}
+
+/// Scripted move order: steer to the destination, then resume normal
+/// behavior. Survives stuns; yields to ChargeAtEnemyBoid once an enemy is
+/// inside the unit's charge radius.
+#[derive(Component, Copy, Clone)]
+pub struct MoveOrder {
+    pub destination: Vector2,
+    pub arrive_radius: f32,
+}
+
+/// Ranged-unit stance: kite while the weapon recharges, seek otherwise.
+/// The conductor owns the Seek/Kite components on its unit.
+#[derive(Component, Copy, Clone)]
+pub struct KiteBehavior {
+    pub trigger_radius: f32,
+    pub seek_multiplier: f32,
+    pub kite_multiplier: f32,
+}
+
+/// Bookkeeping for `set_unit_boid_enabled`: which boids are externally
+/// disabled on this unit, plus the removed components' parameters so
+/// re-enabling restores the exact values.
+#[derive(Component, Clone, Default)]
+pub struct BoidStash {
+    pub disabled: Vec<String>,
+    pub separation: Option<SeparationBoid>,
+    pub cohesion: Option<CohesionBoid>,
+    pub alignment: Option<AlignmentBoid>,
+    pub seek_enemies: Option<SeekEnemiesBoid>,
+    pub avoid_walls: Option<AvoidWallsBoid>,
+    pub stopping: Option<StoppingBoid>,
+    pub charge_at_enemy: Option<ChargeAtEnemyBoid>,
+    pub kite_nearest_enemy: Option<KiteNearestEnemyBoid>,
+}
+
+impl BoidStash {
+    pub fn is_disabled(&self, boid_name: &str) -> bool {
+        self.disabled.iter().any(|name| name == boid_name)
+    }
+}
+
+/// Enable or disable one of the built-in boids on a unit, stashing the
+/// removed component so re-enabling restores its parameters. Returns false
+/// for unknown boid names.
+pub fn set_boid_enabled(world: &mut World, entity: Entity, boid_name: &str, enabled: bool) -> bool {
+    const KNOWN: [&str; 8] = [
+        "separation",
+        "cohesion",
+        "alignment",
+        "seek_enemies",
+        "avoid_walls",
+        "stopping",
+        "charge_at_enemy",
+        "kite_nearest_enemy",
+    ];
+    if !KNOWN.contains(&boid_name) {
+        return false;
+    }
+    if world.get_entity(entity).is_none() {
+        return false;
+    }
+    if world.get::<BoidStash>(entity).is_none() {
+        world.entity_mut(entity).insert(BoidStash::default());
+    }
+
+    macro_rules! toggle {
+        ($field:ident, $component:ty) => {{
+            if enabled {
+                let stashed = {
+                    let mut stash = world.get_mut::<BoidStash>(entity).unwrap();
+                    stash.disabled.retain(|name| name != boid_name);
+                    stash.$field.take()
+                };
+                if let Some(params) = stashed {
+                    world.entity_mut(entity).insert(params);
+                }
+            } else {
+                let removed = world.entity_mut(entity).remove::<$component>();
+                let mut stash = world.get_mut::<BoidStash>(entity).unwrap();
+                if !stash.is_disabled(boid_name) {
+                    stash.disabled.push(boid_name.to_string());
+                }
+                if removed.is_some() {
+                    stash.$field = removed;
+                }
+            }
+        }};
+    }
+
+    match boid_name {
+        "separation" => toggle!(separation, SeparationBoid),
+        "cohesion" => toggle!(cohesion, CohesionBoid),
+        "alignment" => toggle!(alignment, AlignmentBoid),
+        "seek_enemies" => toggle!(seek_enemies, SeekEnemiesBoid),
+        "avoid_walls" => toggle!(avoid_walls, AvoidWallsBoid),
+        "stopping" => toggle!(stopping, StoppingBoid),
+        "charge_at_enemy" => toggle!(charge_at_enemy, ChargeAtEnemyBoid),
+        "kite_nearest_enemy" => toggle!(kite_nearest_enemy, KiteNearestEnemyBoid),
+        _ => unreachable!(),
+    }
+    true
+}
+
+pub fn separation_boid(
+    neighbors: Option<Res<SpatialNeighborsCache>>,
+    positions: Query<&Position>,
+    mut query: Query<(Entity, &Position, &SeparationBoid, &mut AppliedBoidForces)>,
+) {
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    for (entity, position, boid, mut forces) in query.iter_mut() {
+        if let Some(neighbor_list) = neighbors.get_neighbors(&entity) {
+            for neighbor in neighbor_list.iter() {
+                if neighbor.distance > boid.radius {
+                    continue;
+                }
+                if let Ok(neighbor_position) = positions.get(neighbor.entity) {
+                    let away = normalized_or_zero(position.pos - neighbor_position.pos);
+                    let strength = 1.0 - (neighbor.distance / boid.radius).clamp(0.0, 1.0);
+                    forces.add_force(away * strength, boid.multiplier);
+                }
+            }
+        }
+    }
+}
+
+pub fn cohesion_boid(
+    neighbors: Option<Res<SpatialNeighborsCache>>,
+    positions: Query<&Position>,
+    mut query: Query<(
+        Entity,
+        &Position,
+        &TeamAlignment,
+        &CohesionBoid,
+        &mut AppliedBoidForces,
+    )>,
+) {
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    for (entity, position, alignment, boid, mut forces) in query.iter_mut() {
+        let mut center = Vector2::ZERO;
+        let mut count = 0;
+        if let Some(neighbor_list) = neighbors.get_neighbors(&entity) {
+            for neighbor in neighbor_list.iter() {
+                if neighbor.distance > boid.radius || neighbor.team != alignment.alignment {
+                    continue;
+                }
+                if let Ok(neighbor_position) = positions.get(neighbor.entity) {
+                    center += neighbor_position.pos;
+                    count += 1;
+                }
+            }
+        }
+        if count > 0 {
+            center /= count as f32;
+            forces.add_force(normalized_or_zero(center - position.pos), boid.multiplier);
+        }
+    }
+}
+
+pub fn alignment_boid(
+    neighbors: Option<Res<SpatialNeighborsCache>>,
+    velocities: Query<&Velocity>,
+    mut query: Query<(
+        Entity,
+        &TeamAlignment,
+        &AlignmentBoid,
+        &mut AppliedBoidForces,
+    )>,
+) {
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    for (entity, alignment, boid, mut forces) in query.iter_mut() {
+        let mut heading = Vector2::ZERO;
+        let mut count = 0;
+        if let Some(neighbor_list) = neighbors.get_neighbors(&entity) {
+            for neighbor in neighbor_list.iter() {
+                if neighbor.distance > boid.radius || neighbor.team != alignment.alignment {
+                    continue;
+                }
+                if let Ok(velocity) = velocities.get(neighbor.entity) {
+                    heading += velocity.v;
+                    count += 1;
+                }
+            }
+        }
+        if count > 0 {
+            forces.add_force(normalized_or_zero(heading), boid.multiplier);
+        }
+    }
+}
+
+/// Sample the team flow field at the unit's center cell.
+pub fn seek_enemies_boid(
+    terrain: Res<TerrainMap>,
+    fields: Option<Res<FlowFieldsTowardsEnemies>>,
+    mut query: Query<
+        (
+            &Position,
+            &Velocity,
+            &crate::physics::Radius,
+            &TeamAlignment,
+            &SeekEnemiesBoid,
+            &mut AppliedBoidForces,
+        ),
+        (Without<Stunned>, Without<MoveOrder>),
+    >,
+) {
+    let fields = match fields {
+        Some(fields) => fields,
+        None => return,
+    };
+    for (position, velocity, radius, alignment, boid, mut forces) in query.iter_mut() {
+        let heading = normalized_or_zero(velocity.v);
+        let flow = fields.sample_with_radius(
+            alignment.alignment,
+            &terrain,
+            position.pos,
+            radius.r,
+            heading,
+        );
+        forces.add_force(flow, boid.multiplier);
+    }
+}
+
+/// Steer ordered units toward their destination; the order is dropped on
+/// arrival. Flow-field seeking is suppressed while the order is active, and
+/// the order force stands down when an enemy enters charge radius so it
+/// never fights the charge.
+pub fn move_to_point_boid(
+    mut commands: Commands,
+    neighbors: Option<Res<SpatialNeighborsCache>>,
+    mut query: Query<
+        (
+            Entity,
+            &Position,
+            &TeamAlignment,
+            &MoveOrder,
+            Option<&SeekEnemiesBoid>,
+            Option<&ChargeAtEnemyBoid>,
+            &mut AppliedBoidForces,
+        ),
+        Without<Stunned>,
+    >,
+) {
+    for (entity, position, alignment, order, seek, charge, mut forces) in query.iter_mut() {
+        let offset = order.destination - position.pos;
+        if offset.length() <= order.arrive_radius {
+            commands.entity(entity).remove::<MoveOrder>();
+            continue;
+        }
+        if let (Some(neighbors), Some(charge)) = (neighbors.as_ref(), charge) {
+            if let Some(neighbor_list) = neighbors.get_neighbors(&entity) {
+                if neighbor_list.iter().any(|neighbor| {
+                    neighbor.team != alignment.alignment && neighbor.distance <= charge.radius
+                }) {
+                    continue;
+                }
+            }
+        }
+        let multiplier = match seek {
+            Some(boid) => boid.multiplier,
+            None => BASE_SEEK_MULTIPLIER,
+        };
+        forces.add_force(normalized_or_zero(offset), multiplier);
+    }
+}
+
+pub fn avoid_walls_boid(
+    terrain: Res<TerrainMap>,
+    mut query: Query<(&Position, &Velocity, &AvoidWallsBoid, &mut AppliedBoidForces)>,
+) {
+    for (position, velocity, boid, mut forces) in query.iter_mut() {
+        let heading = normalized_or_zero(velocity.v);
+        if heading == Vector2::ZERO {
+            continue;
+        }
+        let probe = position.pos + heading * boid.look_ahead;
+        if terrain.get_cell(probe) == 0 {
+            forces.add_force(-heading, boid.multiplier);
+        }
+    }
+}
+
+pub fn stopping_boid(mut query: Query<(&Velocity, &StoppingBoid, &mut AppliedBoidForces)>) {
+    for (velocity, boid, mut forces) in query.iter_mut() {
+        forces.add_force(-velocity.v, boid.multiplier);
+    }
+}
+
+pub fn charge_at_enemy_boid(
+    neighbors: Option<Res<SpatialNeighborsCache>>,
+    fog: Option<Res<crate::terrain::FogOfWar>>,
+    terrain: Option<Res<crate::terrain::TerrainMap>>,
+    positions: Query<&Position>,
+    alive_query: Query<&Hitpoints>,
+    stealth_query: Query<(), With<Stealthed>>,
+    threat_query: Query<&crate::actions::ThreatTable>,
+    mut query: Query<
+        (
+            Entity,
+            &Position,
+            &TeamAlignment,
+            &ChargeAtEnemyBoid,
+            Option<&AttackTargetDirective>,
+            &mut AppliedBoidForces,
+        ),
+        Without<Stunned>,
+    >,
+) {
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    for (entity, position, alignment, boid, directive, mut forces) in query.iter_mut() {
+        // A focus-fire order outranks proximity: chase the ordered target
+        // from anywhere while it lives.
+        if let Some(directive) = directive {
+            let alive = alive_query
+                .get(directive.target)
+                .map(|hitpoints| hitpoints.hp > 0.0)
+                .unwrap_or(false);
+            // A stealthed order target falls back to the proximity scan.
+            if alive && stealth_query.get(directive.target).is_err() {
+                if let Ok(target_position) = positions.get(directive.target) {
+                    forces.add_force(
+                        normalized_or_zero(target_position.pos - position.pos),
+                        boid.multiplier,
+                    );
+                    continue;
+                }
+            }
+        }
+        if let Some(neighbor_list) = neighbors.get_neighbors(&entity) {
+            let qualifies = |neighbor: &crate::physics::SpatialNeighbor| {
+                if neighbor.team == alignment.alignment || neighbor.distance > boid.radius {
+                    return false;
+                }
+                if stealth_query.get(neighbor.entity).is_ok() {
+                    return false;
+                }
+                let target_position = match positions.get(neighbor.entity) {
+                    Ok(target_position) => target_position,
+                    Err(_) => return false,
+                };
+                if let (Some(fog), Some(terrain)) = (fog.as_ref(), terrain.as_ref()) {
+                    if !fog.is_visible(alignment.alignment, terrain, target_position.pos) {
+                        return false;
+                    }
+                }
+                true
+            };
+            // The aggro leader outranks proximity when the boid chases
+            // threat, as long as it is still a legal in-range target.
+            let mut chased: Option<Entity> = None;
+            if boid.chase_top_threat {
+                if let Some((leader, _)) =
+                    threat_query.get(entity).ok().and_then(|table| table.top())
+                {
+                    if neighbor_list
+                        .iter()
+                        .any(|neighbor| neighbor.entity == leader && qualifies(neighbor))
+                    {
+                        chased = Some(leader);
+                    }
+                }
+            }
+            let pick = chased.or_else(|| {
+                crate::util::select_nearest(neighbor_list.iter().filter_map(|neighbor| {
+                    if qualifies(neighbor) {
+                        Some((neighbor.entity, neighbor.distance))
+                    } else {
+                        None
+                    }
+                }))
+                .map(|(enemy, _)| enemy)
+            });
+            if let Some(enemy) = pick {
+                if let Ok(target_position) = positions.get(enemy) {
+                    forces.add_force(
+                        normalized_or_zero(target_position.pos - position.pos),
+                        boid.multiplier,
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub fn kite_enemies_boid(
+    neighbors: Option<Res<SpatialNeighborsCache>>,
+    positions: Query<&Position>,
+    stealth_query: Query<(), With<Stealthed>>,
+    mut query: Query<
+        (
+            Entity,
+            &Position,
+            &TeamAlignment,
+            &KiteNearestEnemyBoid,
+            &mut AppliedBoidForces,
+        ),
+        Without<Stunned>,
+    >,
+) {
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    for (entity, position, alignment, boid, mut forces) in query.iter_mut() {
+        if let Some(neighbor_list) = neighbors.get_neighbors(&entity) {
+            let nearest = crate::util::select_nearest(neighbor_list.iter().filter_map(
+                |neighbor| {
+                    if neighbor.team == alignment.alignment
+                        || neighbor.distance > boid.radius
+                        || stealth_query.get(neighbor.entity).is_ok()
+                    {
+                        None
+                    } else {
+                        Some((neighbor.entity, neighbor.distance))
+                    }
+                },
+            ));
+            if let Some((enemy, _)) = nearest {
+                if let Ok(enemy_position) = positions.get(enemy) {
+                    forces.add_force(
+                        normalized_or_zero(position.pos - enemy_position.pos),
+                        boid.multiplier,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Swap a ranged unit between seeking and kiting depending on whether its
+/// weapon is recharging and an enemy is inside the trigger radius.
+pub fn kite_conductor(
+    mut commands: Commands,
+    neighbors: Option<Res<SpatialNeighborsCache>>,
+    query: Query<(
+        Entity,
+        &KiteBehavior,
+        &TeamAlignment,
+        &UnitActions,
+        Option<&BoidStash>,
+    )>,
+    cooldown_query: Query<&Cooldown>,
+) {
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    for (entity, behavior, alignment, actions, stash) in query.iter() {
+        // Externally disabled boids are not ours to re-insert.
+        let seek_disabled = stash.map(|s| s.is_disabled("seek_enemies")).unwrap_or(false);
+        let kite_disabled = stash
+            .map(|s| s.is_disabled("kite_nearest_enemy"))
+            .unwrap_or(false);
+        let recharging = actions
+            .vec
+            .first()
+            .map(|action| cooldown_query.get(*action).is_ok())
+            .unwrap_or(false);
+        let mut enemy_close = false;
+        if let Some(neighbor_list) = neighbors.get_neighbors(&entity) {
+            for neighbor in neighbor_list.iter() {
+                if neighbor.team != alignment.alignment
+                    && neighbor.distance <= behavior.trigger_radius
+                {
+                    enemy_close = true;
+                    break;
+                }
+            }
+        }
+        if recharging && enemy_close {
+            commands.entity(entity).remove::<SeekEnemiesBoid>();
+            if !kite_disabled {
+                commands.entity(entity).insert(KiteNearestEnemyBoid {
+                    radius: behavior.trigger_radius,
+                    multiplier: behavior.kite_multiplier,
+                });
+            }
+        } else {
+            commands.entity(entity).remove::<KiteNearestEnemyBoid>();
+            if !seek_disabled {
+                commands.entity(entity).insert(SeekEnemiesBoid {
+                    multiplier: behavior.seek_multiplier,
+                });
+            }
+        }
+    }
+}
+
+/// Copy buffed stats into the boid tuning each tick.
+pub fn update_boid_params_to_stats(
+    mut query: Query<(&Speed, &Acceleration, &mut BoidParams)>,
+) {
+    for (speed, acceleration, mut params) in query.iter_mut() {
+        params.max_speed = speed.value;
+        params.max_force = acceleration.value;
+    }
+}
+
+/// Clamp and integrate the accumulated forces into velocity, and drive the
+/// run/idle animation from the result.
+pub fn boid_apply_params(
+    mut commands: Commands,
+    delta: Res<DeltaPhysics>,
+    mut query: Query<(
+        Entity,
+        &BoidParams,
+        &mut AppliedBoidForces,
+        &mut Velocity,
+        Option<&mut FlippableSprite>,
+        Option<&crate::actions::PerformingActionState>,
+    )>,
+    stationary_query: Query<(), With<crate::actions::StationaryWhileActing>>,
+) {
+    for (entity, params, mut forces, mut velocity, flippable, performing) in query.iter_mut() {
+        // A swing on a stationary action plants the unit for its duration.
+        if let Some(state) = performing {
+            if stationary_query.get(state.action).is_ok() {
+                forces.clear();
+                velocity.v = Vector2::ZERO;
+                commands.entity(entity).insert(PlayAnimationDirective {
+                    animation: AnimationRole::Idle,
+                    loops: true,
+                });
+                continue;
+            }
+        }
+        let mut force = forces.resolve(params);
+        if force.length() > params.max_force {
+            force = force.normalized() * params.max_force;
+        }
+        forces.clear();
+
+        velocity.v += force * delta.seconds;
+        if velocity.v.length() > params.max_speed {
+            velocity.v = velocity.v.normalized() * params.max_speed;
+        }
+
+        if let Some(mut flippable) = flippable {
+            if velocity.v.x.abs() > 0.1 {
+                flippable.flipped = velocity.v.x < 0.0;
+            }
+        }
+        if velocity.v.length() > params.max_speed * 0.1 {
+            commands.entity(entity).insert(PlayAnimationDirective {
+                animation: AnimationRole::Run,
+                loops: true,
+            });
+        } else {
+            commands.entity(entity).insert(PlayAnimationDirective {
+                animation: AnimationRole::Idle,
+                loops: true,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(blend_mode: BoidBlendMode) -> BoidParams {
+        BoidParams {
+            max_speed: 10.0,
+            max_force: 5.0,
+            blend_mode,
+        }
+    }
+
+    fn clamped(force: Vector2, max_force: f32) -> Vector2 {
+        if force.length() > max_force {
+            force.normalized() * max_force
+        } else {
+            force
+        }
+    }
+
+    #[test]
+    fn single_boid_is_identical_in_both_modes() {
+        let direction = Vector2::new(0.0, 1.0);
+        let mut forces = AppliedBoidForces::default();
+        forces.add_force(direction, 3.0);
+
+        let additive = clamped(forces.resolve(&params(BoidBlendMode::Additive)), 5.0);
+        let weighted = clamped(forces.resolve(&params(BoidBlendMode::WeightedAverage)), 5.0);
+        assert!((additive - weighted).length() < 1e-4);
+    }
+
+    #[test]
+    fn switching_profiles_retunes_live_units_of_that_team_only() {
+        let mut world = World::default();
+        let mut profiles = TeamAIProfiles::default();
+        profiles.map.insert(0, AIProfile::Aggressive);
+        world.insert_resource(profiles);
+        let mut spawn_team_unit = |team| {
+            world
+                .spawn()
+                .insert(TeamAlignment {
+                    alignment: team,
+                    alignment_base: team,
+                })
+                .insert(SeekEnemiesBoid {
+                    multiplier: BASE_SEEK_MULTIPLIER,
+                })
+                .insert(ChargeAtEnemyBoid {
+                    radius: BASE_CHARGE_RADIUS,
+                    multiplier: 3.0,
+                    chase_top_threat: false,
+                })
+                .insert(StoppingBoid {
+                    multiplier: 2.0,
+                    base_multiplier: 2.0,
+                })
+                .id()
+        };
+        let aggressor = spawn_team_unit(0);
+        let bystander = spawn_team_unit(1);
+
+        apply_team_ai_profile(&mut world, 0);
+
+        let charge = world.get::<ChargeAtEnemyBoid>(aggressor).unwrap();
+        assert!((charge.radius - BASE_CHARGE_RADIUS * 1.5).abs() < 1e-3);
+        assert!(
+            world.get::<SeekEnemiesBoid>(aggressor).unwrap().multiplier
+                > BASE_SEEK_MULTIPLIER
+        );
+        // The other team keeps its balanced numbers.
+        let other = world.get::<ChargeAtEnemyBoid>(bystander).unwrap();
+        assert!((other.radius - BASE_CHARGE_RADIUS).abs() < 1e-3);
+
+        // Re-applying a profile is idempotent: stopping rewrites from its
+        // stored base instead of compounding, and Balanced reverts it.
+        world
+            .resource_mut::<TeamAIProfiles>()
+            .map
+            .insert(0, AIProfile::Defensive);
+        apply_team_ai_profile(&mut world, 0);
+        apply_team_ai_profile(&mut world, 0);
+        let stopping = world.get::<StoppingBoid>(aggressor).unwrap();
+        assert!((stopping.multiplier - 3.0).abs() < 1e-3);
+
+        world
+            .resource_mut::<TeamAIProfiles>()
+            .map
+            .insert(0, AIProfile::Balanced);
+        apply_team_ai_profile(&mut world, 0);
+        let stopping = world.get::<StoppingBoid>(aggressor).unwrap();
+        assert!((stopping.multiplier - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn stationary_actions_plant_the_unit_during_a_swing() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+
+        let planted_action = world
+            .spawn()
+            .insert(crate::actions::StationaryWhileActing)
+            .id();
+        let mobile_action = world.spawn().id();
+        let mut spawn_swinger = |action| {
+            world
+                .spawn()
+                .insert(params(BoidBlendMode::Additive))
+                .insert(AppliedBoidForces::default())
+                .insert(Velocity {
+                    v: Vector2::new(8.0, 0.0),
+                })
+                .insert(crate::actions::PerformingActionState { action })
+                .id()
+        };
+        let planted = spawn_swinger(planted_action);
+        let mobile = spawn_swinger(mobile_action);
+
+        let mut stage = bevy_ecs::schedule::SystemStage::parallel();
+        stage.add_system(boid_apply_params);
+        stage.run(&mut world);
+
+        assert_eq!(world.get::<Velocity>(planted).unwrap().v, Vector2::ZERO);
+        // Move-and-shoot keeps its momentum.
+        assert!(world.get::<Velocity>(mobile).unwrap().v.length() > 7.0);
+    }
+
+    #[test]
+    fn toggling_seek_stashes_and_restores_parameters() {
+        let mut world = World::default();
+        let unit = world
+            .spawn()
+            .insert(SeekEnemiesBoid { multiplier: 3.5 })
+            .id();
+
+        assert!(set_boid_enabled(&mut world, unit, "seek_enemies", false));
+        assert!(world.get::<SeekEnemiesBoid>(unit).is_none());
+        assert!(world.get::<BoidStash>(unit).unwrap().is_disabled("seek_enemies"));
+
+        // Scripted pause over; re-enabling restores the exact multiplier.
+        assert!(set_boid_enabled(&mut world, unit, "seek_enemies", true));
+        let seek = world.get::<SeekEnemiesBoid>(unit).unwrap();
+        assert!((seek.multiplier - 3.5).abs() < f32::EPSILON);
+        assert!(!world.get::<BoidStash>(unit).unwrap().is_disabled("seek_enemies"));
+    }
+
+    #[test]
+    fn unknown_boid_name_returns_false() {
+        let mut world = World::default();
+        let unit = world.spawn().id();
+        assert!(!set_boid_enabled(&mut world, unit, "warp_drive", false));
+    }
+
+    #[test]
+    fn weighted_average_is_self_normalizing() {
+        // Two opposing boids with equal weights cancel out regardless of how
+        // many more are added at the same weight.
+        let mut forces = AppliedBoidForces::default();
+        forces.add_force(Vector2::new(1.0, 0.0), 2.0);
+        forces.add_force(Vector2::new(-1.0, 0.0), 2.0);
+        let weighted = forces.resolve(&params(BoidBlendMode::WeightedAverage));
+        assert!(weighted.length() < 1e-4);
+    }
+
+    #[test]
+    fn tuning_overrides_touch_only_named_fields() {
+        let mut world = World::default();
+        let unit = world
+            .spawn()
+            .insert(SeparationBoid {
+                radius: 4.0,
+                multiplier: 24.0,
+            })
+            .insert(SeekEnemiesBoid {
+                multiplier: BASE_SEEK_MULTIPLIER,
+            })
+            .id();
+
+        let tuning = BoidTuningOverrides {
+            separation_radius: Some(12.0),
+            stopping_multiplier: Some(2.0),
+            ..Default::default()
+        };
+        assert!(apply_boid_tuning(&mut world, unit, &tuning));
+
+        let separation = world.get::<SeparationBoid>(unit).unwrap();
+        assert!((separation.radius - 12.0).abs() < f32::EPSILON);
+        assert!((separation.multiplier - 24.0).abs() < f32::EPSILON);
+        let seek = world.get::<SeekEnemiesBoid>(unit).unwrap();
+        assert!((seek.multiplier - BASE_SEEK_MULTIPLIER).abs() < f32::EPSILON);
+        // Stopping is inserted on demand since spawn never adds it.
+        assert!(world.get::<StoppingBoid>(unit).is_some());
+
+        world.despawn(unit);
+        assert!(!apply_boid_tuning(&mut world, unit, &tuning));
+    }
+
+    #[test]
+    fn buffed_speed_reaches_the_boid_params() {
+        let mut world = World::default();
+        let unit = world
+            .spawn()
+            .insert(crate::unit::Speed {
+                base: 10.0,
+                value: 10.0,
+            })
+            .insert(crate::unit::Acceleration {
+                base: 5.0,
+                value: 5.0,
+            })
+            .insert(crate::unit::Armor {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(crate::unit::MagicResist {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(crate::unit::HealEfficacy(1.0))
+            .insert(crate::physics::Mass(4.0))
+            .insert(crate::unit::BaseMass(4.0))
+            .insert(crate::effects::BuffHolder { vec: Vec::new() })
+            .insert(params(BoidBlendMode::Additive))
+            .id();
+        let buff = world
+            .spawn()
+            .insert(crate::effects::StatBuff {
+                speed_buff: 6.0,
+                mass_buff: 2.0,
+                ..Default::default()
+            })
+            .id();
+        world
+            .get_mut::<crate::effects::BuffHolder>(unit)
+            .unwrap()
+            .vec
+            .push(buff);
+
+        let mut buffs = SystemStage::parallel();
+        buffs.add_system(crate::effects::apply_stat_buffs);
+        let mut prepare = SystemStage::parallel();
+        prepare.add_system(update_boid_params_to_stats);
+        buffs.run(&mut world);
+        prepare.run(&mut world);
+
+        // Buffed Speed drives steering; buffed Mass drives collisions.
+        assert!((world.get::<BoidParams>(unit).unwrap().max_speed - 16.0).abs() < 1e-3);
+        assert!((world.get::<crate::physics::Mass>(unit).unwrap().0 - 6.0).abs() < 1e-3);
+    }
+}
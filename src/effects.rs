@@ -0,0 +1,6342 @@
+use bevy_ecs::prelude::*;
+use gdnative::prelude::*;
+
+use crate::actions::{
+    ActionCooldown, BasicAttack, Cooldown, Disabled, Splash, TargetEntity, ThreatBonus,
+    ThreatTable, UnitActions,
+};
+use crate::event::{
+    AudioCue, DamageCue, DeathCue, EventCue, EventQueue, KillCue, MatchLog, MatchStats,
+};
+use crate::graphics::animation::{AnimatedSprite, AnimationRole, PlayAnimationDirective};
+use crate::graphics::{
+    AlphaSprite, CleanupCanvasItem, MirrorTargetPosition, ModulateSprite, NewCanvasItemDirective,
+    Renderable,
+};
+use crate::physics::{DeltaPhysics, Position, SpatialHashTable, TeleportDirective};
+use crate::unit::{
+    Acceleration, Armor, BaseMass, BaseMaxHp, BlueprintId, HealEfficacy, Hitpoints, MagicResist,
+    Speed, TeamAlignment,
+};
+use crate::util::normalized_or_zero;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DamageType {
+    Normal,
+    Magic,
+    Poison,
+    Heal,
+}
+
+impl DamageType {
+    /// Numeric code used where only f32 parameters travel (tooltips, logs).
+    pub fn code(&self) -> i64 {
+        match self {
+            DamageType::Normal => 0,
+            DamageType::Magic => 1,
+            DamageType::Poison => 2,
+            DamageType::Heal => 3,
+        }
+    }
+}
+
+/// One pending damage (or heal) application sitting in a unit's AppliedDamage
+/// queue until its delay lapses.
+#[derive(Copy, Clone)]
+pub struct DamageInstance {
+    pub damage: f32,
+    pub delay: f32,
+    pub damage_type: DamageType,
+    pub originator: Entity,
+    /// How many reactive hops (bodyguard redirects, and in future thorns or
+    /// lifesteal) already produced this instance; derived damage caps at 1.
+    pub depth: u8,
+    /// Execute rider of the weapon that landed the hit; checked against the
+    /// victim's hp when the instance resolves.
+    pub execute: Option<ExecuteDamage>,
+}
+
+#[derive(Component)]
+pub struct AppliedDamage {
+    pub vec: Vec<DamageInstance>,
+}
+
+/// Everything an action or projectile can do to its target.
+#[derive(Clone)]
+pub enum Effect {
+    DamageEffect {
+        damage: f32,
+        delay: f32,
+        damage_type: DamageType,
+    },
+    HealEffect {
+        amount: f32,
+    },
+    PoisonEffect {
+        percent_damage: f32,
+        movement_debuff: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    /// Flat damage per second that ticks as Magic, so resist mitigates it
+    /// and swarms are not punished the way the percent poison punishes them.
+    BurnEffect {
+        damage_per_second: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    /// Disable the victim's basic attacks for the duration.
+    Disarm {
+        duration: f32,
+        texture: Rid,
+    },
+    StunEffect {
+        duration: f32,
+        texture: Rid,
+    },
+    /// Stacking slow; at max_stacks the stacks convert into a freeze stun.
+    Chill {
+        slow_per_stack: f32,
+        max_stacks: i64,
+        freeze_duration: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    ConfusionEffect {
+        duration: f32,
+        texture: Rid,
+    },
+    AntihealOnHitEffect {
+        percent: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    ShredArmorEffect {
+        duration: f32,
+        texture: Rid,
+    },
+    CleanseEffect,
+    ApplyStatBuffEffect {
+        buff: StatBuff,
+        duration: f32,
+        texture: Rid,
+    },
+    AttackSpeedBuff {
+        percent: f32,
+        duration: f32,
+    },
+    /// Inverse of AttackSpeedBuff: the victim's weapon cooldowns recover
+    /// slower for the duration.
+    AttackSlowEffect {
+        percent: f32,
+        duration: f32,
+    },
+    OverdriveEffect {
+        percent: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    DamageBuffEffect {
+        percent: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    HealOverTimeEffect {
+        amount_per_second: f32,
+        duration: f32,
+        texture: Rid,
+    },
+    /// Temporary damage absorption; the shield soaks post-mitigation damage
+    /// before it reaches hitpoints.
+    ApplyShieldEffect {
+        amount: f32,
+        duration: f32,
+    },
+    /// Temporary damage reflection; see `ThornsBuff`.
+    ApplyThornsEffect {
+        percent: f32,
+        flat: f32,
+        duration: f32,
+    },
+    /// Spawn `count` short-lived copies of another blueprint around the
+    /// target; resolves into a `SummonDirective` consumed between ticks.
+    SummonEffect {
+        blueprint_id: usize,
+        count: i64,
+        duration: f32,
+    },
+    /// Drop a proximity mine at the target's feet; it arms after `arm_time`
+    /// and detonates on the first enemy inside `trigger_radius`. See `Mine`
+    /// and `mine_trigger`.
+    PlaceMineEffect {
+        damage: f32,
+        trigger_radius: f32,
+        arm_time: f32,
+        texture: Rid,
+    },
+    Hypnosis {
+        new_alignment: i64,
+        duration: f32,
+    },
+    SuicideEffect,
+    HealOnDeathEffect {
+        amount: f32,
+        target: Entity,
+    },
+    TeleportToPointEffect {
+        destination: Vector2,
+    },
+    TeleportBehindTargetEffect {
+        distance: f32,
+    },
+    /// Short self-teleport toward the enemy; resolves into a
+    /// `BlinkDirective` so terrain clamping happens with map access.
+    BlinkEffect {
+        distance: f32,
+        texture: Rid,
+    },
+    /// Drop the target out of enemy targeting until it acts; see `Stealthed`.
+    StealthEffect {
+        duration: f32,
+    },
+    /// Launch the originator at the target; see `Dashing`.
+    ChargeEffect {
+        dash_speed: f32,
+        max_distance: f32,
+        impact_damage: f32,
+        impact_stun: f32,
+        texture: Rid,
+    },
+    VisualEffect {
+        texture: Rid,
+        duration: f32,
+    },
+    /// Plant a stationary totem structure at the target that pulses
+    /// `pulse_amount` healing (negative for damage) to everything within
+    /// `pulse_radius` every `pulse_period`; see `TotemPulse`.
+    SummonTotemEffect {
+        hitpoints: f32,
+        radius: f32,
+        lifetime: f32,
+        pulse_amount: f32,
+        pulse_radius: f32,
+        pulse_period: f32,
+        texture: Rid,
+    },
+}
+
+impl Effect {
+    /// Stable identifier the UI and match log key tooltips on. Exhaustive on
+    /// purpose: adding a variant without naming it here fails to compile.
+    pub fn kind_id(&self) -> &'static str {
+        match self {
+            Effect::DamageEffect { .. } => "damage",
+            Effect::HealEffect { .. } => "heal",
+            Effect::PoisonEffect { .. } => "slow_poison",
+            Effect::BurnEffect { .. } => "burn",
+            Effect::Disarm { .. } => "disarm",
+            Effect::StunEffect { .. } => "stun",
+            Effect::Chill { .. } => "chill",
+            Effect::ConfusionEffect { .. } => "confusion",
+            Effect::AntihealOnHitEffect { .. } => "antiheal",
+            Effect::ShredArmorEffect { .. } => "shred_armor",
+            Effect::CleanseEffect => "cleanse",
+            Effect::ApplyStatBuffEffect { .. } => "stat_buff",
+            Effect::AttackSpeedBuff { .. } => "attack_speed_buff",
+            Effect::AttackSlowEffect { .. } => "attack_slow",
+            Effect::OverdriveEffect { .. } => "overdrive",
+            Effect::DamageBuffEffect { .. } => "damage_buff",
+            Effect::HealOverTimeEffect { .. } => "heal_over_time",
+            Effect::ApplyShieldEffect { .. } => "shield",
+            Effect::ApplyThornsEffect { .. } => "thorns",
+            Effect::SummonEffect { .. } => "summon",
+            Effect::PlaceMineEffect { .. } => "place_mine",
+            Effect::Hypnosis { .. } => "hypnosis",
+            Effect::SuicideEffect => "suicide",
+            Effect::HealOnDeathEffect { .. } => "heal_on_death",
+            Effect::TeleportToPointEffect { .. } => "teleport_to_point",
+            Effect::TeleportBehindTargetEffect { .. } => "teleport_behind_target",
+            Effect::BlinkEffect { .. } => "blink",
+            Effect::StealthEffect { .. } => "stealth",
+            Effect::ChargeEffect { .. } => "charge",
+            Effect::VisualEffect { .. } => "visual",
+            Effect::SummonTotemEffect { .. } => "summon_totem",
+        }
+    }
+
+    /// The `DebuffImmunities` flag that blocks this effect outright, if any.
+    /// `resolve_effects` consults it before the effect spawns anything, so an
+    /// immune hypnosis never places its `SetAlignment` buff.
+    pub fn immunity_flag(&self) -> Option<u32> {
+        match self {
+            Effect::StunEffect { .. } => Some(DebuffImmunities::STUN),
+            Effect::Chill { .. } | Effect::AttackSlowEffect { .. } => Some(DebuffImmunities::SLOW),
+            Effect::PoisonEffect { .. } => Some(DebuffImmunities::POISON),
+            Effect::Disarm { .. } => Some(DebuffImmunities::SILENCE),
+            Effect::Hypnosis { .. } => Some(DebuffImmunities::HYPNOSIS),
+            _ => None,
+        }
+    }
+
+    /// Kind id plus the numeric parameters a tooltip needs, in a
+    /// Dictionary-friendly shape. Textures and entity handles are omitted.
+    pub fn describe(&self) -> (&'static str, Vec<(&'static str, f32)>) {
+        let params = match self {
+            Effect::DamageEffect {
+                damage,
+                delay,
+                damage_type,
+            } => vec![
+                ("damage", *damage),
+                ("delay", *delay),
+                ("damage_type", damage_type.code() as f32),
+            ],
+            Effect::HealEffect { amount } => vec![("amount", *amount)],
+            Effect::PoisonEffect {
+                percent_damage,
+                movement_debuff,
+                duration,
+                ..
+            } => vec![
+                ("percent_damage", *percent_damage),
+                ("movement_debuff", *movement_debuff),
+                ("duration", *duration),
+            ],
+            Effect::BurnEffect {
+                damage_per_second,
+                duration,
+                ..
+            } => vec![
+                ("damage_per_second", *damage_per_second),
+                ("duration", *duration),
+            ],
+            Effect::Disarm { duration, .. } => vec![("duration", *duration)],
+            Effect::StunEffect { duration, .. } => vec![("duration", *duration)],
+            Effect::Chill {
+                slow_per_stack,
+                max_stacks,
+                freeze_duration,
+                duration,
+                ..
+            } => vec![
+                ("slow_per_stack", *slow_per_stack),
+                ("max_stacks", *max_stacks as f32),
+                ("freeze_duration", *freeze_duration),
+                ("duration", *duration),
+            ],
+            Effect::ConfusionEffect { duration, .. } => vec![("duration", *duration)],
+            Effect::AntihealOnHitEffect {
+                percent, duration, ..
+            } => vec![("percent", *percent), ("duration", *duration)],
+            Effect::ShredArmorEffect { duration, .. } => vec![("duration", *duration)],
+            Effect::CleanseEffect => Vec::new(),
+            Effect::ApplyStatBuffEffect { buff, duration, .. } => vec![
+                ("armor_buff", buff.armor_buff),
+                ("magic_resist_buff", buff.magic_resist_buff),
+                ("speed_buff", buff.speed_buff),
+                ("acceleration_buff", buff.acceleration_buff),
+                ("mass_buff", buff.mass_buff),
+                ("heal_efficacy_mult_buff", buff.heal_efficacy_mult_buff),
+                ("max_hp_buff", buff.max_hp_buff),
+                ("speed_mult", buff.speed_mult),
+                ("armor_mult", buff.armor_mult),
+                ("acceleration_mult", buff.acceleration_mult),
+                ("mass_mult", buff.mass_mult),
+                ("duration", *duration),
+            ],
+            Effect::AttackSpeedBuff { percent, duration } => {
+                vec![("percent", *percent), ("duration", *duration)]
+            }
+            Effect::AttackSlowEffect { percent, duration } => {
+                vec![("percent", *percent), ("duration", *duration)]
+            }
+            Effect::OverdriveEffect {
+                percent, duration, ..
+            } => vec![("percent", *percent), ("duration", *duration)],
+            Effect::DamageBuffEffect {
+                percent, duration, ..
+            } => vec![("percent", *percent), ("duration", *duration)],
+            Effect::HealOverTimeEffect {
+                amount_per_second,
+                duration,
+                ..
+            } => vec![
+                ("amount_per_second", *amount_per_second),
+                ("duration", *duration),
+            ],
+            Effect::ApplyShieldEffect { amount, duration } => {
+                vec![("amount", *amount), ("duration", *duration)]
+            }
+            Effect::ApplyThornsEffect {
+                percent,
+                flat,
+                duration,
+            } => vec![
+                ("percent", *percent),
+                ("flat", *flat),
+                ("duration", *duration),
+            ],
+            Effect::SummonEffect {
+                blueprint_id,
+                count,
+                duration,
+            } => vec![
+                ("blueprint_id", *blueprint_id as f32),
+                ("count", *count as f32),
+                ("duration", *duration),
+            ],
+            Effect::PlaceMineEffect {
+                damage,
+                trigger_radius,
+                arm_time,
+                ..
+            } => vec![
+                ("damage", *damage),
+                ("trigger_radius", *trigger_radius),
+                ("arm_time", *arm_time),
+            ],
+            Effect::Hypnosis {
+                new_alignment,
+                duration,
+            } => vec![
+                ("new_alignment", *new_alignment as f32),
+                ("duration", *duration),
+            ],
+            Effect::SuicideEffect => Vec::new(),
+            Effect::HealOnDeathEffect { amount, .. } => vec![("amount", *amount)],
+            Effect::TeleportToPointEffect { destination } => {
+                vec![("x", destination.x), ("y", destination.y)]
+            }
+            Effect::TeleportBehindTargetEffect { distance } => {
+                vec![("distance", *distance)]
+            }
+            Effect::BlinkEffect { distance, .. } => vec![("distance", *distance)],
+            Effect::StealthEffect { duration } => vec![("duration", *duration)],
+            Effect::ChargeEffect {
+                dash_speed,
+                max_distance,
+                impact_damage,
+                impact_stun,
+                ..
+            } => vec![
+                ("dash_speed", *dash_speed),
+                ("max_distance", *max_distance),
+                ("impact_damage", *impact_damage),
+                ("impact_stun", *impact_stun),
+            ],
+            Effect::VisualEffect { duration, .. } => vec![("duration", *duration)],
+            Effect::SummonTotemEffect {
+                hitpoints,
+                radius,
+                lifetime,
+                pulse_amount,
+                pulse_radius,
+                pulse_period,
+                ..
+            } => vec![
+                ("hitpoints", *hitpoints),
+                ("radius", *radius),
+                ("lifetime", *lifetime),
+                ("pulse_amount", *pulse_amount),
+                ("pulse_radius", *pulse_radius),
+                ("pulse_period", *pulse_period),
+            ],
+        };
+        (self.kind_id(), params)
+    }
+}
+
+/// True when an on-hit payload is the kind of physical hit evasion can
+/// dodge, i.e. it carries Normal-type damage. Magic, poison and
+/// pure-utility casts always connect.
+pub fn attack_is_dodgeable(effects: &[Effect]) -> bool {
+    effects.iter().any(|effect| {
+        matches!(
+            effect,
+            Effect::DamageEffect {
+                damage_type: DamageType::Normal,
+                ..
+            }
+        )
+    })
+}
+
+/// Effects triggered when a unit dies.
+#[derive(Clone)]
+pub enum DeathEffect {
+    SplashDamage {
+        damage: f32,
+        radius: f32,
+    },
+    HealAllies {
+        amount: f32,
+        alignment: i64,
+    },
+    PoisonSplash {
+        radius: f32,
+        duration: f32,
+        percent_damage: f32,
+        movement_debuff: f32,
+        texture: Rid,
+    },
+    HealTarget {
+        amount: f32,
+        target: Entity,
+    },
+}
+
+impl DeathEffect {
+    pub fn kind_id(&self) -> &'static str {
+        match self {
+            DeathEffect::SplashDamage { .. } => "death_splash_damage",
+            DeathEffect::HealAllies { .. } => "death_heal_allies",
+            DeathEffect::PoisonSplash { .. } => "death_poison_splash",
+            DeathEffect::HealTarget { .. } => "death_heal_target",
+        }
+    }
+
+    pub fn describe(&self) -> (&'static str, Vec<(&'static str, f32)>) {
+        let params = match self {
+            DeathEffect::SplashDamage { damage, radius } => {
+                vec![("damage", *damage), ("radius", *radius)]
+            }
+            DeathEffect::HealAllies { amount, alignment } => {
+                vec![("amount", *amount), ("alignment", *alignment as f32)]
+            }
+            DeathEffect::PoisonSplash {
+                radius,
+                duration,
+                percent_damage,
+                movement_debuff,
+                ..
+            } => vec![
+                ("radius", *radius),
+                ("duration", *duration),
+                ("percent_damage", *percent_damage),
+                ("movement_debuff", *movement_debuff),
+            ],
+            DeathEffect::HealTarget { amount, .. } => vec![("amount", *amount)],
+        };
+        (self.kind_id(), params)
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct OnDeathEffects {
+    pub vec: Vec<DeathEffect>,
+}
+
+/// Kill rewards staked on a unit by execution attackers: `(beneficiary,
+/// heal amount)` pairs. `apply_damages` pays one out when the beneficiary's
+/// own damage lands the killing blow.
+#[derive(Component, Default)]
+pub struct ExecutionMarks {
+    pub vec: Vec<(Entity, f32)>,
+}
+
+/// Execute rider on a weapon's action: hits that resolve while the victim is
+/// strictly below `threshold_percent` of max hp deal `bonus_multiplier` times
+/// the damage. Rides the DamageInstance, since mitigation and current hp are
+/// only known in `apply_damages`.
+#[derive(Component, Copy, Clone)]
+pub struct ExecuteDamage {
+    pub threshold_percent: f32,
+    pub bonus_multiplier: f32,
+}
+
+pub struct QueuedEffect {
+    pub effect: Effect,
+    pub originator: Entity,
+    /// Execute rider of the weapon that produced this effect, if any.
+    pub execute: Option<ExecuteDamage>,
+}
+
+/// Inbox of effects to resolve against this entity next `resolve_effects` run.
+#[derive(Component)]
+pub struct ResolveEffectsBuffer {
+    pub vec: Vec<QueuedEffect>,
+}
+
+/// Stat modifiers carried by a buff entity. The `_buff` fields are flat and
+/// additive; the `_mult` fields are fractions (0.2 reads as +20%) that
+/// multiply after every flat buff has summed, so "+20% speed" scales with
+/// the unit it lands on.
+#[derive(Component, Copy, Clone, Default)]
+pub struct StatBuff {
+    pub armor_buff: f32,
+    pub magic_resist_buff: f32,
+    pub speed_buff: f32,
+    pub acceleration_buff: f32,
+    pub mass_buff: f32,
+    pub heal_efficacy_mult_buff: f32,
+    pub max_hp_buff: f32,
+    pub speed_mult: f32,
+    pub armor_mult: f32,
+    pub acceleration_mult: f32,
+    pub mass_mult: f32,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct BuffType {
+    pub is_debuff: bool,
+}
+
+/// The unit that applied this buff. Reapplication from the same source can
+/// then refresh the running timer instead of stacking a twin entity.
+#[derive(Component, Copy, Clone)]
+pub struct BuffOriginator(pub Entity);
+
+/// Identity of an on-hit debuff for stacking purposes. Rides the buff entity
+/// next to `BuffOriginator` so a reapplication can find its twin in the
+/// target's BuffHolder.
+#[derive(Component, Copy, Clone, PartialEq, Eq)]
+pub enum BuffKind {
+    SlowPoison,
+    Burn,
+    Stun,
+    Confusion,
+    Antiheal,
+    ShredArmor,
+    HealOverTime,
+}
+
+/// What a reapplication of the same kind from the same source does.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum StackPolicy {
+    /// Wind the running BuffTimer back up; one buff entity ever.
+    Refresh,
+    /// Refresh the timer and grow `BuffStacks` up to the cap.
+    StackUpTo(i64),
+    /// Every application is its own buff entity with its own timer.
+    Independent,
+}
+
+impl BuffKind {
+    /// The stacking registry: how each kind behaves under reapplication.
+    pub fn stack_policy(self) -> StackPolicy {
+        match self {
+            BuffKind::SlowPoison => StackPolicy::StackUpTo(3),
+            BuffKind::Burn
+            | BuffKind::Confusion
+            | BuffKind::Antiheal
+            | BuffKind::ShredArmor
+            | BuffKind::HealOverTime => StackPolicy::Refresh,
+            BuffKind::Stun => StackPolicy::Independent,
+        }
+    }
+}
+
+/// Stack counter on a buff entity whose kind is `StackUpTo`; per-stack
+/// values multiply in `apply_stat_buffs` and the damage-over-time ticks.
+#[derive(Component, Copy, Clone)]
+pub struct BuffStacks {
+    pub count: i64,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct BuffTimer(pub f32);
+
+/// Buff entities attached to this unit.
+#[derive(Component)]
+pub struct BuffHolder {
+    pub vec: Vec<Entity>,
+}
+
+/// Whether any buff in the holder is a debuff. Cleanse targeting consults
+/// this rather than per-debuff markers, so every new debuff automatically
+/// counts.
+pub fn has_debuff(holder: &BuffHolder, buff_type_query: &Query<&BuffType>) -> bool {
+    holder.vec.iter().any(|buff| {
+        buff_type_query
+            .get(*buff)
+            .map(|buff_type| buff_type.is_debuff)
+            .unwrap_or(false)
+    })
+}
+
+/// Ward side of a bodyguard link: a fraction of incoming damage is re-queued
+/// onto the guard instead, while the guard is alive and within radius.
+#[derive(Component, Copy, Clone)]
+pub struct DamageRedirect {
+    pub target: Entity,
+    pub fraction: f32,
+    pub radius: f32,
+}
+
+/// Passive lifesteal on the attacker: a fraction of the post-mitigation
+/// damage it deals comes back as a heal. It lives in `apply_damages` rather
+/// than `resolve_effects` because only the former knows what the victim's
+/// armor left of the hit.
+#[derive(Component, Copy, Clone)]
+pub struct LifestealOnHit {
+    pub percent: f32,
+}
+
+/// Banner-carrier passive: everything on the chosen side within `radius`
+/// holds a constantly-refreshed copy of `buff` while it stays in range.
+#[derive(Component, Copy, Clone)]
+pub struct Aura {
+    pub buff: StatBuff,
+    pub radius: f32,
+    pub affects_allies: bool,
+}
+
+/// Marks a buff entity as aura-granted so reapplication can tell it apart
+/// from a cast StatBuff from the same unit.
+#[derive(Component, Copy, Clone)]
+pub struct AuraBuff;
+
+/// Berserker passive, stamped from the blueprint ability: dropping strictly
+/// below `hp_threshold_percent` of max hp grants a speed StatBuff plus an
+/// attack-speed surge until healing carries the unit back above the line.
+#[derive(Component, Copy, Clone)]
+pub struct BerserkPassive {
+    pub hp_threshold_percent: f32,
+    pub attack_speed_bonus: f32,
+    pub speed_bonus: f32,
+}
+
+/// Tracks an active berserk surge so each threshold crossing grants its
+/// buffs exactly once; holds the buff entities for instant teardown.
+#[derive(Component)]
+pub struct Berserking {
+    pub buffs: Vec<Entity>,
+}
+
+/// Guard side of a bodyguard link, stamped from the blueprint ability.
+#[derive(Component, Copy, Clone)]
+pub struct BodyguardParams {
+    pub fraction: f32,
+    pub radius: f32,
+    pub texture: Rid,
+}
+
+/// Hard stun; boids and targeting filter on it.
+#[derive(Component, Copy, Clone)]
+pub struct Stunned;
+
+/// Debuff families that never land on this unit. A bitmask of the flag
+/// constants below so a blueprint can combine them freely; a held
+/// `DivineShieldBuff` implies all of them for its duration.
+#[derive(Component, Copy, Clone, Default)]
+pub struct DebuffImmunities(pub u32);
+
+impl DebuffImmunities {
+    pub const STUN: u32 = 1 << 0;
+    /// Movement and attack-speed slows: chill and attack-slow.
+    pub const SLOW: u32 = 1 << 1;
+    pub const POISON: u32 = 1 << 2;
+    /// The sim's disarm is its silence: the unit keeps moving but cannot act.
+    pub const SILENCE: u32 = 1 << 3;
+    /// Blocks the `SetAlignment` buff itself, not just its visuals.
+    pub const HYPNOSIS: u32 = 1 << 4;
+    /// Reserved: no displacement effect exists yet, but the bit is pinned so
+    /// saved blueprints stay stable when one does.
+    pub const KNOCKBACK: u32 = 1 << 5;
+    pub const ALL: u32 =
+        Self::STUN | Self::SLOW | Self::POISON | Self::SILENCE | Self::HYPNOSIS | Self::KNOCKBACK;
+}
+
+/// Recent stun exposure. Each stun that resolves against the unit bumps the
+/// count and restarts the window; `crowd_control_decay` drops the whole
+/// component once the window lapses without a fresh stun.
+#[derive(Component, Copy, Clone)]
+pub struct CrowdControlResistance {
+    pub exposures: u32,
+    pub window_remaining: f32,
+}
+
+/// Diminishing returns on stuns. `falloff[n]` scales the duration of the
+/// n-th stun landing inside the rolling `window`; the last entry repeats for
+/// deeper chains, so ending on 0.0 means outright immunity. Without this
+/// resource stuns land at full duration.
+pub struct CrowdControlTuning {
+    pub window: f32,
+    pub falloff: Vec<f32>,
+}
+
+impl Default for CrowdControlTuning {
+    fn default() -> Self {
+        Self {
+            window: 10.0,
+            falloff: vec![1.0, 0.5, 0.25, 0.0],
+        }
+    }
+}
+
+/// Invisible to enemy targeting and the chase boids; projectiles already in
+/// flight still land. Acting breaks it — see `break_stealth_on_action`.
+#[derive(Component, Copy, Clone)]
+pub struct Stealthed;
+
+/// One chill buff entity per victim; tracks the stack count and the shared
+/// per-stack decay timer.
+#[derive(Component)]
+pub struct ChillStacks {
+    pub stacks: i64,
+    pub max_stacks: i64,
+    pub slow_per_stack: f32,
+    pub freeze_duration: f32,
+    /// Lifetime of a stack without refresh; also the decay cadence.
+    pub stack_duration: f32,
+    pub timer: f32,
+}
+
+/// Freeze stun whose ice-blue tint must be cleared when it expires.
+#[derive(Component, Copy, Clone)]
+pub struct FreezeTint;
+
+/// Absorb shield: `apply_damages` drains it before touching hitpoints. A
+/// drained shield zeroes its own BuffTimer so teardown stays in `buff_timer`.
+#[derive(Component, Copy, Clone)]
+pub struct ShieldBuff {
+    pub remaining: f32,
+}
+
+/// Shield buff whose pale tint must be cleared when it expires.
+#[derive(Component, Copy, Clone)]
+pub struct ShieldTint;
+
+/// Damage reflection: Normal-type hits on the holder bounce `percent` of the
+/// pre-mitigation damage plus `flat` back at the attacker. Sits on a buff
+/// entity for the castable version or directly on the unit for the permanent
+/// one.
+#[derive(Component, Copy, Clone)]
+pub struct ThornsBuff {
+    pub percent: f32,
+    pub flat: f32,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct StunnedBuff;
+
+/// Marker on a stealth buff entity; expiry restores targetability and alpha.
+#[derive(Component, Copy, Clone)]
+pub struct StealthedBuff;
+
+/// Marker on a disarm buff entity; expiry re-enables the basic attacks.
+#[derive(Component, Copy, Clone)]
+pub struct DisarmedBuff;
+
+/// Periodic area effect carried by a totem structure.
+#[derive(Component)]
+pub struct TotemPulse {
+    pub amount: f32,
+    pub radius: f32,
+    pub period: f32,
+    pub elapsed: f32,
+}
+
+/// Remaining seconds before a structure tears itself down.
+#[derive(Component, Copy, Clone)]
+pub struct StructureLifetime(pub f32);
+
+/// A planted proximity mine. It carries no Mass or Velocity, so the collision
+/// pass never pushes units around it; `mine_trigger` arms it once `armed_in`
+/// runs out and detonates it on the first enemy inside `trigger_radius`.
+#[derive(Component, Copy, Clone)]
+pub struct Mine {
+    pub damage: f32,
+    pub trigger_radius: f32,
+    pub armed_in: f32,
+}
+
+/// Deferred summon left behind by `Effect::SummonEffect`. Spawning a full
+/// unit needs the blueprint list and animation library, which systems cannot
+/// reach, so `ECSWorld` consumes these between ticks.
+#[derive(Component, Copy, Clone)]
+pub struct SummonDirective {
+    pub blueprint_id: usize,
+    pub count: i64,
+    pub duration: f32,
+    pub team: i64,
+    pub position: Vector2,
+}
+
+/// Pending blink left behind by `Effect::BlinkEffect`; `resolve_blinks`
+/// clamps the destination to pathable terrain and hands the move to
+/// `apply_teleport`.
+#[derive(Component, Copy, Clone)]
+pub struct BlinkDirective {
+    pub distance: f32,
+    pub texture: Rid,
+}
+
+/// Active charge: `apply_dashes` overrides the unit's velocity toward
+/// `target` each tick — after the boids have had their say, so steering,
+/// stuns and channel filters cannot cancel it — until enemy contact, a
+/// wall, or an empty distance budget ends the dash.
+#[derive(Component, Copy, Clone)]
+pub struct Dashing {
+    pub target: Entity,
+    pub dash_speed: f32,
+    pub remaining: f32,
+    pub impact_damage: f32,
+    pub impact_stun: f32,
+    pub stun_texture: Rid,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct DivineShieldBuff;
+
+/// Flat bonus damage on a buff entity targeting an action; read at hit time
+/// by `performing_action_state`, so it vanishes when the buff expires.
+#[derive(Component, Copy, Clone)]
+pub struct FlatDamageBuff(pub f32);
+
+/// Percent bonus damage on a buff entity targeting an action; same hit-time
+/// read as `FlatDamageBuff`.
+#[derive(Component, Copy, Clone)]
+pub struct PercentDamageBuff(pub f32);
+
+#[derive(Component, Copy, Clone)]
+pub struct PercentCooldownReduction(pub f32);
+
+/// Inverse of `PercentCooldownReduction`, carried by attack-slow debuffs
+/// targeting the victim's action entities.
+#[derive(Component, Copy, Clone)]
+pub struct PercentCooldownIncrease(pub f32);
+
+#[derive(Component, Copy, Clone)]
+pub struct HealingPerSecond(pub f32);
+
+#[derive(Component, Copy, Clone)]
+pub struct PercentDamageOverTime {
+    pub percent_per_second: f32,
+    pub originator: Entity,
+}
+
+/// Flat-rate cousin of `PercentDamageOverTime`, carried by burn buffs.
+#[derive(Component, Copy, Clone)]
+pub struct FlatDamageOverTime {
+    pub damage_per_second: f32,
+    pub originator: Entity,
+}
+
+/// Buff components that overwrite a stat outright each frame.
+#[derive(Component, Copy, Clone)]
+pub struct SetArmor(pub f32);
+
+#[derive(Component, Copy, Clone)]
+pub struct SetAcceleration(pub f32);
+
+#[derive(Component, Copy, Clone)]
+pub struct SetAlignment(pub i64);
+
+/// Unit (or corpse, or buff) should be torn down by `resolve_death`.
+#[derive(Component, Copy, Clone)]
+pub struct DeathApproaches;
+
+/// The last unit whose hit damaged this one, with its blueprint and team
+/// cached at damage time: a damage-over-time death can land after the
+/// originator despawned, so death-time lookups cannot attribute the kill.
+#[derive(Component, Copy, Clone)]
+pub struct LastDamagedBy {
+    pub entity: Entity,
+    pub blueprint: usize,
+    pub team: i64,
+    pub at_clock: i64,
+}
+
+/// Current winner; -1 while the battle is still going.
+/// Winning team id once the battle is decided: -1 while undecided, -2 for
+/// a mutual wipe. `engaged` latches once two or more teams are alive, so a
+/// lone team on the first tick is not an instant victory.
+pub struct Victor {
+    pub team: i64,
+    pub engaged: bool,
+}
+
+impl Default for Victor {
+    fn default() -> Self {
+        Self {
+            team: -1,
+            engaged: false,
+        }
+    }
+}
+
+fn spawn_visual_buff(
+    commands: &mut Commands,
+    target: Entity,
+    texture: Rid,
+    duration: f32,
+    is_debuff: bool,
+) -> Entity {
+    commands
+        .spawn()
+        .insert(BuffTimer(duration))
+        .insert(BuffType { is_debuff })
+        .insert(TargetEntity(target))
+        .insert(MirrorTargetPosition(target))
+        .insert(Position { pos: Vector2::ZERO })
+        .insert(NewCanvasItemDirective {})
+        .insert(AnimatedSprite::new(texture))
+        .insert(PlayAnimationDirective {
+            animation: AnimationRole::Idle,
+            loops: true,
+        })
+        .id()
+}
+
+/// Stuns insert the real `Stunned` marker: boids, targeting and
+/// `performing_action_state` all filter on it, and the buff's expiry lifts
+/// it again.
+pub fn apply_stun_buff(
+    commands: &mut Commands,
+    target: Entity,
+    duration: f32,
+    texture: Rid,
+) -> Entity {
+    let buff = spawn_visual_buff(commands, target, texture, duration, true);
+    commands.entity(buff).insert(StunnedBuff);
+    commands.entity(target).insert(Stunned);
+    buff
+}
+
+pub fn spawn_hypnosis_buff(
+    commands: &mut Commands,
+    target: Entity,
+    new_alignment: i64,
+    duration: f32,
+) -> Entity {
+    commands
+        .spawn()
+        .insert(BuffTimer(duration))
+        .insert(BuffType { is_debuff: true })
+        .insert(TargetEntity(target))
+        .insert(SetAlignment(new_alignment))
+        .id()
+}
+
+pub fn spawn_armor_shred_debuff(
+    commands: &mut Commands,
+    target: Entity,
+    originator: Entity,
+    duration: f32,
+    texture: Rid,
+) -> Entity {
+    let buff = spawn_visual_buff(commands, target, texture, duration, true);
+    commands
+        .entity(buff)
+        .insert(SetArmor(0.0))
+        .insert(BuffKind::ShredArmor)
+        .insert(BuffOriginator(originator));
+    buff
+}
+
+/// Search `holder` for a buff of the same kind from the same source and apply
+/// the kind's stack policy to it. Returns true when the reapplication was
+/// absorbed — timer refreshed, stacks grown — and no new buff entity should
+/// spawn.
+fn absorb_reapplication(
+    holder: &BuffHolder,
+    refresh_query: &mut Query<(
+        &mut BuffTimer,
+        &BuffOriginator,
+        &BuffKind,
+        Option<&mut BuffStacks>,
+    )>,
+    kind: BuffKind,
+    originator: Entity,
+    duration: f32,
+) -> bool {
+    let policy = kind.stack_policy();
+    if policy == StackPolicy::Independent {
+        return false;
+    }
+    for buff in holder.vec.iter() {
+        if let Ok((mut timer, buff_originator, buff_kind, stacks)) = refresh_query.get_mut(*buff) {
+            if *buff_kind != kind || buff_originator.0 != originator {
+                continue;
+            }
+            if let (StackPolicy::StackUpTo(max), Some(mut stacks)) = (policy, stacks) {
+                stacks.count = (stacks.count + 1).min(max);
+            }
+            timer.0 = duration;
+            return true;
+        }
+    }
+    false
+}
+
+/// Drain every ResolveEffectsBuffer and turn effects into damage, buffs and
+/// markers.
+pub fn resolve_effects(
+    mut commands: Commands,
+    cc_tuning: Option<Res<CrowdControlTuning>>,
+    mut query: Query<(Entity, &mut ResolveEffectsBuffer, Option<&DebuffImmunities>)>,
+    mut damage_query: Query<&mut AppliedDamage>,
+    mut holder_query: Query<&mut BuffHolder>,
+    actions_query: Query<&UnitActions>,
+    cooldown_query: Query<&Cooldown>,
+    position_query: Query<&Position>,
+    buff_type_query: Query<&BuffType>,
+    mut chill_query: Query<&mut ChillStacks>,
+    renderable_query: Query<&Renderable>,
+    basic_attack_query: Query<(), With<BasicAttack>>,
+    alignment_query: Query<&TeamAlignment>,
+    mut marks_query: Query<&mut ExecutionMarks>,
+    mut refresh_query: Query<(
+        &mut BuffTimer,
+        &BuffOriginator,
+        &BuffKind,
+        Option<&mut BuffStacks>,
+    )>,
+    marker_query: Query<(
+        Option<&StunnedBuff>,
+        Option<&FreezeTint>,
+        Option<&ShieldTint>,
+        Option<&DisarmedBuff>,
+        Option<&StealthedBuff>,
+    )>,
+    mut resistance_query: Query<&mut CrowdControlResistance>,
+    divine_query: Query<(), With<DivineShieldBuff>>,
+    mut events: Option<ResMut<EventQueue>>,
+) {
+    for (target, mut buffer, immunities) in query.iter_mut() {
+        // A held divine shield blankets every immunity; otherwise the unit's
+        // own flags decide.
+        let mut immune = immunities.map(|i| i.0).unwrap_or(0);
+        if let Ok(holder) = holder_query.get(target) {
+            if holder.vec.iter().any(|buff| divine_query.get(*buff).is_ok()) {
+                immune = DebuffImmunities::ALL;
+            }
+        }
+        for queued in buffer.vec.drain(..) {
+            let originator = queued.originator;
+            let execute = queued.execute;
+            if queued
+                .effect
+                .immunity_flag()
+                .map(|flag| immune & flag != 0)
+                .unwrap_or(false)
+            {
+                // The debuff never spawns; the cue lets the UI show why.
+                if let (Some(events), Ok(position)) =
+                    (events.as_mut(), position_query.get(target))
+                {
+                    events.0.push_back(EventCue::Audio(AudioCue {
+                        kind: "immune".to_string(),
+                        position: position.pos,
+                    }));
+                }
+                continue;
+            }
+            match queued.effect {
+                Effect::DamageEffect {
+                    damage,
+                    delay,
+                    damage_type,
+                } => {
+                    if let Ok(mut applied) = damage_query.get_mut(target) {
+                        applied.vec.push(DamageInstance {
+                            damage,
+                            delay,
+                            damage_type,
+                            originator,
+                            depth: 0,
+                            execute,
+                        });
+                    }
+                }
+                Effect::HealEffect { amount } => {
+                    if let Ok(mut applied) = damage_query.get_mut(target) {
+                        applied.vec.push(DamageInstance {
+                            damage: amount,
+                            delay: 0.0,
+                            damage_type: DamageType::Heal,
+                            originator,
+                            depth: 0,
+                            execute: None,
+                        });
+                    }
+                }
+                Effect::PoisonEffect {
+                    percent_damage,
+                    movement_debuff,
+                    duration,
+                    texture,
+                } => {
+                    if let Ok(holder) = holder_query.get_mut(target) {
+                        if absorb_reapplication(
+                            &holder,
+                            &mut refresh_query,
+                            BuffKind::SlowPoison,
+                            originator,
+                            duration,
+                        ) {
+                            continue;
+                        }
+                    }
+                    let buff = spawn_visual_buff(&mut commands, target, texture, duration, true);
+                    commands
+                        .entity(buff)
+                        .insert(PercentDamageOverTime {
+                            percent_per_second: percent_damage,
+                            originator,
+                        })
+                        .insert(StatBuff {
+                            speed_buff: -movement_debuff,
+                            ..Default::default()
+                        })
+                        .insert(BuffKind::SlowPoison)
+                        .insert(BuffOriginator(originator))
+                        .insert(BuffStacks { count: 1 });
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::BurnEffect {
+                    damage_per_second,
+                    duration,
+                    texture,
+                } => {
+                    if let Ok(holder) = holder_query.get_mut(target) {
+                        if absorb_reapplication(
+                            &holder,
+                            &mut refresh_query,
+                            BuffKind::Burn,
+                            originator,
+                            duration,
+                        ) {
+                            continue;
+                        }
+                    }
+                    let buff = spawn_visual_buff(&mut commands, target, texture, duration, true);
+                    commands
+                        .entity(buff)
+                        .insert(FlatDamageOverTime {
+                            damage_per_second,
+                            originator,
+                        })
+                        .insert(BuffKind::Burn)
+                        .insert(BuffOriginator(originator));
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::Disarm { duration, texture } => {
+                    let buff = spawn_visual_buff(&mut commands, target, texture, duration, true);
+                    commands.entity(buff).insert(DisarmedBuff);
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                    if let Ok(actions) = actions_query.get(target) {
+                        for action in actions.vec.iter() {
+                            if basic_attack_query.get(*action).is_ok() {
+                                commands.entity(*action).insert(Disabled);
+                            }
+                        }
+                    }
+                }
+                Effect::SummonTotemEffect {
+                    hitpoints,
+                    radius,
+                    lifetime,
+                    pulse_amount,
+                    pulse_radius,
+                    pulse_period,
+                    texture,
+                } => {
+                    let (position, alignment) = match (
+                        position_query.get(target),
+                        alignment_query.get(target),
+                    ) {
+                        (Ok(position), Ok(alignment)) => (position.pos, alignment.alignment),
+                        _ => continue,
+                    };
+                    commands
+                        .spawn()
+                        .insert(Position { pos: position })
+                        .insert(crate::physics::Velocity { v: Vector2::ZERO })
+                        .insert(crate::physics::Radius { r: radius })
+                        .insert(crate::physics::Mass(1000.0))
+                        .insert(crate::physics::Immobile)
+                        .insert(crate::unit::Structure)
+                        .insert(StructureLifetime(lifetime))
+                        .insert(TotemPulse {
+                            amount: pulse_amount,
+                            radius: pulse_radius,
+                            period: pulse_period,
+                            elapsed: 0.0,
+                        })
+                        .insert(Hitpoints {
+                            hp: hitpoints,
+                            max_hp: hitpoints,
+                        })
+                        .insert(TeamAlignment {
+                            alignment,
+                            alignment_base: alignment,
+                        })
+                        .insert(crate::unit::SpatialAwareness {
+                            radius: pulse_radius,
+                        })
+                        .insert(Armor {
+                            base: 0.0,
+                            value: 0.0,
+                        })
+                        .insert(MagicResist {
+                            base: 0.0,
+                            value: 0.0,
+                        })
+                        .insert(HealEfficacy(1.0))
+                        .insert(AppliedDamage { vec: Vec::new() })
+                        .insert(ResolveEffectsBuffer { vec: Vec::new() })
+                        .insert(BuffHolder { vec: Vec::new() })
+                        .insert(NewCanvasItemDirective {})
+                        .insert(AnimatedSprite::new(texture))
+                        .insert(PlayAnimationDirective {
+                            animation: AnimationRole::Idle,
+                            loops: true,
+                        });
+                }
+                Effect::PlaceMineEffect {
+                    damage,
+                    trigger_radius,
+                    arm_time,
+                    texture,
+                } => {
+                    let (position, alignment) = match (
+                        position_query.get(target),
+                        alignment_query.get(target),
+                    ) {
+                        (Ok(position), Ok(alignment)) => (position.pos, alignment.alignment),
+                        _ => continue,
+                    };
+                    commands
+                        .spawn()
+                        .insert(Position { pos: position })
+                        // Footprint only; without Mass or Velocity the
+                        // collision pass never sees the mine.
+                        .insert(crate::physics::Radius { r: 2.0 })
+                        .insert(Mine {
+                            damage,
+                            trigger_radius,
+                            armed_in: arm_time,
+                        })
+                        .insert(Splash {
+                            radius: trigger_radius,
+                            min_percent: 1.0,
+                        })
+                        .insert(TeamAlignment {
+                            alignment,
+                            alignment_base: alignment,
+                        })
+                        .insert(NewCanvasItemDirective {})
+                        .insert(AnimatedSprite::new(texture))
+                        .insert(PlayAnimationDirective {
+                            animation: AnimationRole::Idle,
+                            loops: true,
+                        });
+                }
+                Effect::StunEffect { duration, texture } => {
+                    // Diminishing returns: each stun inside the rolling
+                    // window lands one falloff step deeper. A fully resisted
+                    // stun still restarts the window, so chain-stunning an
+                    // immune target does not free it early.
+                    let mut scale = 1.0;
+                    if let Some(tuning) = cc_tuning.as_ref() {
+                        scale = if let Ok(mut resistance) = resistance_query.get_mut(target) {
+                            let step = (resistance.exposures as usize)
+                                .min(tuning.falloff.len().saturating_sub(1));
+                            resistance.exposures += 1;
+                            resistance.window_remaining = tuning.window;
+                            tuning.falloff.get(step).copied().unwrap_or(1.0)
+                        } else {
+                            commands.entity(target).insert(CrowdControlResistance {
+                                exposures: 1,
+                                window_remaining: tuning.window,
+                            });
+                            *tuning.falloff.first().unwrap_or(&1.0)
+                        };
+                    }
+                    if scale <= 0.0 {
+                        continue;
+                    }
+                    // Stuns are Independent in the stacking registry:
+                    // overlapping hits each run out their own timer.
+                    let buff = apply_stun_buff(&mut commands, target, duration * scale, texture);
+                    commands
+                        .entity(buff)
+                        .insert(BuffKind::Stun)
+                        .insert(BuffOriginator(originator));
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::ApplyThornsEffect {
+                    percent,
+                    flat,
+                    duration,
+                } => {
+                    let buff = commands
+                        .spawn()
+                        .insert(BuffTimer(duration))
+                        .insert(BuffType { is_debuff: false })
+                        .insert(TargetEntity(target))
+                        .insert(ThornsBuff { percent, flat })
+                        .id();
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::SummonEffect {
+                    blueprint_id,
+                    count,
+                    duration,
+                } => {
+                    // Systems cannot reach the blueprint list or animation
+                    // library; the actual spawn happens between ticks in
+                    // `ECSWorld::process_summon_directives`.
+                    let (position, alignment) =
+                        match (position_query.get(target), alignment_query.get(target)) {
+                            (Ok(position), Ok(alignment)) => (position.pos, alignment.alignment),
+                            _ => continue,
+                        };
+                    commands.spawn().insert(SummonDirective {
+                        blueprint_id,
+                        count,
+                        duration,
+                        team: alignment,
+                        position,
+                    });
+                }
+                Effect::ApplyShieldEffect { amount, duration } => {
+                    let buff = commands
+                        .spawn()
+                        .insert(BuffTimer(duration))
+                        .insert(BuffType { is_debuff: false })
+                        .insert(TargetEntity(target))
+                        .insert(ShieldBuff { remaining: amount })
+                        .insert(ShieldTint)
+                        .id();
+                    // No buff icon; a pale tint on the unit marks the shield.
+                    commands.entity(target).insert(ModulateSprite {
+                        r: 0.85,
+                        g: 0.9,
+                        b: 1.0,
+                    });
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::Chill {
+                    slow_per_stack,
+                    max_stacks,
+                    freeze_duration,
+                    duration,
+                    texture,
+                } => {
+                    let mut existing: Option<Entity> = None;
+                    if let Ok(holder) = holder_query.get_mut(target) {
+                        for buff in holder.vec.iter() {
+                            if chill_query.get(*buff).is_ok() {
+                                existing = Some(*buff);
+                                break;
+                            }
+                        }
+                    }
+                    match existing {
+                        Some(buff) => {
+                            let (stacks, at_max) = {
+                                let mut chill = chill_query.get_mut(buff).unwrap();
+                                chill.stacks = (chill.stacks + 1).min(chill.max_stacks);
+                                chill.timer = chill.stack_duration;
+                                (chill.stacks, chill.stacks >= chill.max_stacks)
+                            };
+                            if at_max {
+                                // Consume every stack into a freeze.
+                                let freeze = apply_stun_buff(
+                                    &mut commands,
+                                    target,
+                                    freeze_duration,
+                                    texture,
+                                );
+                                commands
+                                    .entity(freeze)
+                                    .insert(FreezeTint)
+                                    .insert(BuffType { is_debuff: true });
+                                commands.entity(target).insert(ModulateSprite {
+                                    r: 0.55,
+                                    g: 0.8,
+                                    b: 1.0,
+                                });
+                                if let Ok(renderable) = renderable_query.get(buff) {
+                                    commands
+                                        .spawn()
+                                        .insert(CleanupCanvasItem(renderable.canvas_item));
+                                }
+                                commands.entity(buff).despawn();
+                                if let Ok(mut holder) = holder_query.get_mut(target) {
+                                    holder.vec.retain(|b| *b != buff);
+                                    holder.vec.push(freeze);
+                                }
+                            } else {
+                                let slow = slow_per_stack * stacks as f32;
+                                commands.entity(buff).insert(StatBuff {
+                                    speed_buff: -slow,
+                                    acceleration_buff: -slow,
+                                    ..Default::default()
+                                });
+                            }
+                        }
+                        None => {
+                            let buff = commands
+                                .spawn()
+                                .insert(BuffType { is_debuff: true })
+                                .insert(TargetEntity(target))
+                                .insert(MirrorTargetPosition(target))
+                                .insert(Position { pos: Vector2::ZERO })
+                                .insert(NewCanvasItemDirective {})
+                                .insert(AnimatedSprite::new(texture))
+                                .insert(PlayAnimationDirective {
+                                    animation: AnimationRole::Idle,
+                                    loops: true,
+                                })
+                                .insert(ChillStacks {
+                                    stacks: 1,
+                                    max_stacks,
+                                    slow_per_stack,
+                                    freeze_duration,
+                                    stack_duration: duration,
+                                    timer: duration,
+                                })
+                                .insert(StatBuff {
+                                    speed_buff: -slow_per_stack,
+                                    acceleration_buff: -slow_per_stack,
+                                    ..Default::default()
+                                })
+                                .id();
+                            if let Ok(mut holder) = holder_query.get_mut(target) {
+                                holder.vec.push(buff);
+                            }
+                        }
+                    }
+                }
+                Effect::ConfusionEffect { duration, texture } => {
+                    if let Ok(holder) = holder_query.get_mut(target) {
+                        if absorb_reapplication(
+                            &holder,
+                            &mut refresh_query,
+                            BuffKind::Confusion,
+                            originator,
+                            duration,
+                        ) {
+                            continue;
+                        }
+                    }
+                    let buff = spawn_visual_buff(&mut commands, target, texture, duration, true);
+                    commands
+                        .entity(buff)
+                        .insert(SetAlignment(-1))
+                        .insert(BuffKind::Confusion)
+                        .insert(BuffOriginator(originator));
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::AntihealOnHitEffect {
+                    percent,
+                    duration,
+                    texture,
+                } => {
+                    if let Ok(holder) = holder_query.get_mut(target) {
+                        if absorb_reapplication(
+                            &holder,
+                            &mut refresh_query,
+                            BuffKind::Antiheal,
+                            originator,
+                            duration,
+                        ) {
+                            continue;
+                        }
+                    }
+                    let buff = spawn_visual_buff(&mut commands, target, texture, duration, true);
+                    commands
+                        .entity(buff)
+                        .insert(StatBuff {
+                            heal_efficacy_mult_buff: percent,
+                            ..Default::default()
+                        })
+                        .insert(BuffKind::Antiheal)
+                        .insert(BuffOriginator(originator));
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::ShredArmorEffect { duration, texture } => {
+                    if let Ok(holder) = holder_query.get_mut(target) {
+                        if absorb_reapplication(
+                            &holder,
+                            &mut refresh_query,
+                            BuffKind::ShredArmor,
+                            originator,
+                            duration,
+                        ) {
+                            continue;
+                        }
+                    }
+                    let buff = spawn_armor_shred_debuff(
+                        &mut commands,
+                        target,
+                        originator,
+                        duration,
+                        texture,
+                    );
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::CleanseEffect => {
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.retain(|buff| {
+                            let is_debuff = buff_type_query
+                                .get(*buff)
+                                .map(|t| t.is_debuff)
+                                .unwrap_or(false);
+                            if is_debuff {
+                                // Full teardown on the spot, so stun markers,
+                                // disables and set-stat values lift with the
+                                // buff instead of lingering a frame.
+                                let (stun, freeze, shield, disarm, stealth) = marker_query
+                                    .get(*buff)
+                                    .unwrap_or((None, None, None, None, None));
+                                tear_down_buff(
+                                    &mut commands,
+                                    *buff,
+                                    target,
+                                    renderable_query.get(*buff).ok(),
+                                    stun,
+                                    freeze,
+                                    shield,
+                                    disarm,
+                                    stealth,
+                                    &actions_query,
+                                    &basic_attack_query,
+                                );
+                            }
+                            !is_debuff
+                        });
+                    }
+                }
+                Effect::ApplyStatBuffEffect {
+                    buff,
+                    duration,
+                    texture,
+                } => {
+                    let buff_entity =
+                        spawn_visual_buff(&mut commands, target, texture, duration, false);
+                    commands.entity(buff_entity).insert(buff);
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff_entity);
+                    }
+                }
+                Effect::AttackSpeedBuff { percent, duration } => {
+                    if let Ok(actions) = actions_query.get(target) {
+                        for action in actions.vec.iter() {
+                            commands
+                                .spawn()
+                                .insert(BuffTimer(duration))
+                                .insert(BuffType { is_debuff: false })
+                                .insert(TargetEntity(*action))
+                                .insert(PercentCooldownReduction(percent));
+                        }
+                    }
+                }
+                Effect::AttackSlowEffect { percent, duration } => {
+                    // The slow buffs live in the victim's holder even though
+                    // they target its action entities, so cleanse can find
+                    // them.
+                    if let Ok(actions) = actions_query.get(target) {
+                        for action in actions.vec.iter() {
+                            let buff = commands
+                                .spawn()
+                                .insert(BuffTimer(duration))
+                                .insert(BuffType { is_debuff: true })
+                                .insert(TargetEntity(*action))
+                                .insert(PercentCooldownIncrease(percent))
+                                .id();
+                            if let Ok(mut holder) = holder_query.get_mut(target) {
+                                holder.vec.push(buff);
+                            }
+                        }
+                    }
+                }
+                Effect::OverdriveEffect {
+                    percent,
+                    duration,
+                    texture,
+                } => {
+                    if let Ok(actions) = actions_query.get(target) {
+                        for action in actions.vec.iter() {
+                            if cooldown_query.get(*action).is_ok() {
+                                commands
+                                    .spawn()
+                                    .insert(BuffTimer(duration))
+                                    .insert(BuffType { is_debuff: false })
+                                    .insert(TargetEntity(*action))
+                                    .insert(PercentCooldownReduction(percent));
+                            }
+                        }
+                    }
+                    let buff = spawn_visual_buff(&mut commands, target, texture, duration, false);
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::DamageBuffEffect {
+                    percent,
+                    duration,
+                    texture,
+                } => {
+                    // The stored weapon damage stays untouched; the bonus
+                    // lives on buff entities targeting the action entities
+                    // and is read at hit time, so expiry reverts it.
+                    if let Ok(actions) = actions_query.get(target) {
+                        for action in actions.vec.iter() {
+                            let buff = commands
+                                .spawn()
+                                .insert(BuffTimer(duration))
+                                .insert(BuffType { is_debuff: false })
+                                .insert(TargetEntity(*action))
+                                .insert(PercentDamageBuff(percent))
+                                .id();
+                            if let Ok(mut holder) = holder_query.get_mut(target) {
+                                holder.vec.push(buff);
+                            }
+                        }
+                    }
+                    let buff = spawn_visual_buff(&mut commands, target, texture, duration, false);
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::HealOverTimeEffect {
+                    amount_per_second,
+                    duration,
+                    texture,
+                } => {
+                    // One buff on the unit, whatever its actions are doing;
+                    // a repeat cast from the same source refreshes it.
+                    if let Ok(holder) = holder_query.get_mut(target) {
+                        if absorb_reapplication(
+                            &holder,
+                            &mut refresh_query,
+                            BuffKind::HealOverTime,
+                            originator,
+                            duration,
+                        ) {
+                            continue;
+                        }
+                    }
+                    let buff = spawn_visual_buff(&mut commands, target, texture, duration, false);
+                    commands
+                        .entity(buff)
+                        .insert(HealingPerSecond(amount_per_second))
+                        .insert(BuffKind::HealOverTime)
+                        .insert(BuffOriginator(originator));
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::Hypnosis {
+                    new_alignment,
+                    duration,
+                } => {
+                    let buff =
+                        spawn_hypnosis_buff(&mut commands, target, new_alignment, duration);
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::SuicideEffect => {
+                    commands.entity(originator).insert(DeathApproaches);
+                }
+                Effect::HealOnDeathEffect {
+                    amount,
+                    target: beneficiary,
+                } => {
+                    // Staked on the victim, not paid immediately:
+                    // `apply_damages` releases it only if the beneficiary's
+                    // own hit lands the killing blow.
+                    if let Ok(mut marks) = marks_query.get_mut(target) {
+                        if let Some(mark) =
+                            marks.vec.iter_mut().find(|(b, _)| *b == beneficiary)
+                        {
+                            mark.1 = amount;
+                        } else {
+                            marks.vec.push((beneficiary, amount));
+                        }
+                    } else {
+                        commands.entity(target).insert(ExecutionMarks {
+                            vec: vec![(beneficiary, amount)],
+                        });
+                    }
+                }
+                Effect::TeleportToPointEffect { destination } => {
+                    commands
+                        .entity(target)
+                        .insert(TeleportDirective { destination });
+                }
+                Effect::TeleportBehindTargetEffect { distance } => {
+                    if let (Ok(target_position), Ok(origin_position)) =
+                        (position_query.get(target), position_query.get(originator))
+                    {
+                        let through = normalized_or_zero(target_position.pos - origin_position.pos);
+                        commands.entity(originator).insert(TeleportDirective {
+                            destination: target_position.pos + through * distance,
+                        });
+                    }
+                }
+                Effect::BlinkEffect { distance, texture } => {
+                    // Picking a destination needs the terrain map and flow
+                    // fields; `resolve_blinks` does that next stage, still
+                    // ahead of `apply_teleport`.
+                    commands
+                        .entity(target)
+                        .insert(BlinkDirective { distance, texture });
+                }
+                Effect::StealthEffect { duration } => {
+                    let buff = commands
+                        .spawn()
+                        .insert(BuffTimer(duration))
+                        .insert(BuffType { is_debuff: false })
+                        .insert(TargetEntity(target))
+                        .insert(StealthedBuff)
+                        .id();
+                    // One canvas layer for everyone, so the reduced alpha is
+                    // global; per-team invisibility is the game layer's job.
+                    commands
+                        .entity(target)
+                        .insert(Stealthed)
+                        .insert(AlphaSprite(0.4));
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+                Effect::ChargeEffect {
+                    dash_speed,
+                    max_distance,
+                    impact_damage,
+                    impact_stun,
+                    texture,
+                } => {
+                    commands.entity(originator).insert(Dashing {
+                        target,
+                        dash_speed,
+                        remaining: max_distance,
+                        impact_damage,
+                        impact_stun,
+                        stun_texture: texture,
+                    });
+                }
+                Effect::VisualEffect { texture, duration } => {
+                    let buff = spawn_visual_buff(&mut commands, target, texture, duration, false);
+                    if let Ok(mut holder) = holder_query.get_mut(target) {
+                        holder.vec.push(buff);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Turn pending blinks into teleports. Direction follows the charge target
+/// when one is inside charge radius, otherwise the flow field toward
+/// enemies; the destination walks back along the line until
+/// `TerrainMap::get_cell` reports pathable ground, which also keeps it
+/// inside max_bounds since out-of-bounds reads as unpathable.
+pub fn resolve_blinks(
+    mut commands: Commands,
+    terrain: Option<Res<crate::terrain::TerrainMap>>,
+    flow_fields: Option<Res<crate::terrain::FlowFieldsTowardsEnemies>>,
+    neighbors: Option<Res<crate::physics::SpatialNeighborsCache>>,
+    positions: Query<&Position>,
+    query: Query<(
+        Entity,
+        &Position,
+        &TeamAlignment,
+        &BlinkDirective,
+        Option<&crate::boids::ChargeAtEnemyBoid>,
+    )>,
+) {
+    let terrain = match terrain {
+        Some(terrain) => terrain,
+        None => return,
+    };
+    for (entity, position, alignment, blink, charge) in query.iter() {
+        commands.entity(entity).remove::<BlinkDirective>();
+        let mut direction = Vector2::ZERO;
+        if let (Some(neighbors), Some(charge)) = (neighbors.as_ref(), charge) {
+            if let Some(neighbor_list) = neighbors.get_neighbors(&entity) {
+                let nearest =
+                    crate::util::select_nearest(neighbor_list.iter().filter_map(|neighbor| {
+                        if neighbor.team == alignment.alignment
+                            || neighbor.distance > charge.radius
+                        {
+                            return None;
+                        }
+                        Some((neighbor.entity, neighbor.distance))
+                    }));
+                if let Some((enemy, _)) = nearest {
+                    if let Ok(enemy_position) = positions.get(enemy) {
+                        direction = normalized_or_zero(enemy_position.pos - position.pos);
+                    }
+                }
+            }
+        }
+        if direction == Vector2::ZERO {
+            if let Some(fields) = flow_fields.as_ref() {
+                direction = fields.sample(alignment.alignment, &terrain, position.pos);
+            }
+        }
+        if direction == Vector2::ZERO {
+            // Nowhere to go; the cast fizzles in place.
+            continue;
+        }
+        // Walk back in quarter-cell steps; the caster's own cell is
+        // pathable, so the loop always lands somewhere legal.
+        let step = terrain.cell_size * 0.25;
+        let mut destination = position.pos + direction * blink.distance;
+        while terrain.get_cell(destination) == 0
+            && (destination - position.pos).length() > step
+        {
+            destination -= direction * step;
+        }
+        if terrain.get_cell(destination) == 0 {
+            continue;
+        }
+        crate::graphics::spawn_impact_visual(&mut commands, blink.texture, position.pos);
+        crate::graphics::spawn_impact_visual(&mut commands, blink.texture, destination);
+        // Blinking breaks whatever the caster was winding up.
+        commands
+            .entity(entity)
+            .insert(TeleportDirective { destination })
+            .remove::<crate::actions::PerformingActionState>();
+    }
+}
+
+/// Drive active charges. Runs with the contact systems, well after
+/// `boid_apply_params`, so the velocity write below tramples whatever the
+/// boids or a mid-flight stun wanted. Contact with the target or any enemy
+/// delivers the impact through the buffer; walls and an exhausted distance
+/// budget end the dash quietly.
+pub fn apply_dashes(
+    mut commands: Commands,
+    delta: Res<DeltaPhysics>,
+    terrain: Option<Res<crate::terrain::TerrainMap>>,
+    spatial: Option<Res<SpatialHashTable>>,
+    positions: Query<&Position>,
+    mut buffer_query: Query<&mut ResolveEffectsBuffer>,
+    mut query: Query<(
+        Entity,
+        &Position,
+        &crate::physics::Radius,
+        &TeamAlignment,
+        &mut crate::physics::Velocity,
+        &mut Dashing,
+    )>,
+) {
+    for (entity, position, radius, alignment, mut velocity, mut dashing) in query.iter_mut() {
+        let target_position = match positions.get(dashing.target) {
+            Ok(target_position) => target_position.pos,
+            Err(_) => {
+                // Target despawned mid-flight; coast to a stop.
+                commands.entity(entity).remove::<Dashing>();
+                velocity.v = Vector2::ZERO;
+                continue;
+            }
+        };
+        // Anything hostile the dasher is touching counts as the impact,
+        // not just the named target.
+        let mut victim: Option<Entity> = None;
+        if let Some(spatial) = spatial.as_ref() {
+            for hash in spatial.get_all_spatial_hashes_from_circle(position.pos, radius.r) {
+                if let Some(entries) = spatial.table.get(&hash) {
+                    for entry in entries {
+                        if entry.entity == entity || entry.team == alignment.alignment {
+                            continue;
+                        }
+                        if crate::util::true_distance(
+                            position.pos,
+                            entry.position,
+                            radius.r,
+                            entry.radius,
+                        ) <= 0.0
+                        {
+                            victim = Some(entry.entity);
+                            break;
+                        }
+                    }
+                }
+                if victim.is_some() {
+                    break;
+                }
+            }
+        }
+        if let Some(victim) = victim {
+            if let Ok(mut buffer) = buffer_query.get_mut(victim) {
+                buffer.vec.push(QueuedEffect {
+                    effect: Effect::DamageEffect {
+                        damage: dashing.impact_damage,
+                        delay: 0.0,
+                        damage_type: DamageType::Normal,
+                    },
+                    originator: entity,
+                    execute: None,
+                });
+                if dashing.impact_stun > 0.0 {
+                    buffer.vec.push(QueuedEffect {
+                        effect: Effect::StunEffect {
+                            duration: dashing.impact_stun,
+                            texture: dashing.stun_texture,
+                        },
+                        originator: entity,
+                        execute: None,
+                    });
+                }
+            }
+            commands.entity(entity).remove::<Dashing>();
+            velocity.v = Vector2::ZERO;
+            continue;
+        }
+        velocity.v = normalized_or_zero(target_position - position.pos) * dashing.dash_speed;
+        if let Some(terrain) = terrain.as_ref() {
+            if terrain.get_cell(position.pos + velocity.v * delta.seconds) == 0 {
+                // Wall: stop short rather than grind along it.
+                commands.entity(entity).remove::<Dashing>();
+                velocity.v = Vector2::ZERO;
+                continue;
+            }
+        }
+        dashing.remaining -= dashing.dash_speed * delta.seconds;
+        if dashing.remaining <= 0.0 {
+            commands.entity(entity).remove::<Dashing>();
+        }
+    }
+}
+
+/// Acting reveals the unit: any PerformingActionState on a stealthed unit
+/// zeroes its stealth buff's timer, and `buff_timer` handles the teardown.
+pub fn break_stealth_on_action(
+    query: Query<
+        &BuffHolder,
+        (
+            With<Stealthed>,
+            With<crate::actions::PerformingActionState>,
+        ),
+    >,
+    mut buff_query: Query<&mut BuffTimer, With<StealthedBuff>>,
+) {
+    for holder in query.iter() {
+        for buff in holder.vec.iter() {
+            if let Ok(mut timer) = buff_query.get_mut(*buff) {
+                timer.0 = 0.0;
+            }
+        }
+    }
+}
+
+/// Tick pending DamageInstances, mitigate, and apply to hitpoints.
+pub fn apply_damages(
+    mut commands: Commands,
+    delta: Res<DeltaPhysics>,
+    clock: Option<Res<crate::physics::Clock>>,
+    mut events: ResMut<EventQueue>,
+    mut stats: ResMut<MatchStats>,
+    mut log: Option<ResMut<MatchLog>>,
+    mut query: Query<(
+        Entity,
+        &mut AppliedDamage,
+        &mut Hitpoints,
+        &Armor,
+        &MagicResist,
+        &HealEfficacy,
+        &TeamAlignment,
+        &Position,
+        Option<&DamageRedirect>,
+        Option<&BuffHolder>,
+        Option<&ThornsBuff>,
+        Option<&mut ThreatTable>,
+    )>,
+    originator_query: Query<(&BlueprintId, &TeamAlignment, Option<&ThreatBonus>), With<UnitActions>>,
+    lifesteal_query: Query<&LifestealOnHit>,
+    mut shield_query: Query<(&mut ShieldBuff, &mut BuffTimer)>,
+    thorns_query: Query<&ThornsBuff>,
+    mut marks_query: Query<&mut ExecutionMarks>,
+) {
+    // Snapshot guard liveness and positions so ward processing can split
+    // damage without borrowing the query twice.
+    let mut guard_entities: Vec<Entity> = Vec::new();
+    for (.., redirect, _, _, _) in query.iter() {
+        if let Some(redirect) = redirect {
+            guard_entities.push(redirect.target);
+        }
+    }
+    let mut guards: std::collections::HashMap<Entity, (Vector2, f32)> =
+        std::collections::HashMap::new();
+    for guard in guard_entities {
+        if let Ok((_, _, hitpoints, _, _, _, _, position, _, _, _, _)) = query.get(guard) {
+            guards.insert(guard, (position.pos, hitpoints.hp));
+        }
+    }
+    // Instances bound for other units — bodyguard redirects and execution
+    // payouts — applied after the main pass releases the query.
+    let mut requeue: Vec<(Entity, DamageInstance)> = Vec::new();
+
+    for (
+        entity,
+        mut damages,
+        mut hitpoints,
+        armor,
+        magic_resist,
+        efficacy,
+        alignment,
+        position,
+        redirect,
+        holder,
+        own_thorns,
+        mut threat,
+    ) in query.iter_mut()
+    {
+        let mut resolved: Vec<DamageInstance> = Vec::new();
+        damages.vec.retain_mut(|instance| {
+            instance.delay -= delta.seconds;
+            if instance.delay > 0.0 {
+                true
+            } else {
+                resolved.push(*instance);
+                false
+            }
+        });
+
+        for mut instance in resolved {
+            let was_alive = hitpoints.hp > 0.0;
+            // Execute riders key off the victim's hp at the moment the
+            // instance resolves: strictly below the threshold the hit is
+            // amplified, exactly at it the hit is ordinary.
+            let mut executed = false;
+            if let Some(execute) = instance.execute {
+                if instance.damage_type != DamageType::Heal
+                    && hitpoints.hp < hitpoints.max_hp * execute.threshold_percent
+                {
+                    instance.damage *= execute.bonus_multiplier;
+                    executed = true;
+                }
+            }
+            // Bodyguard split happens before mitigation; the guard takes the
+            // redirected portion through its own armor next tick.
+            let mut redirected_away = 0.0;
+            if instance.depth == 0 && instance.damage_type != DamageType::Heal {
+                if let Some(redirect) = redirect {
+                    if let Some((guard_pos, guard_hp)) = guards.get(&redirect.target) {
+                        let in_range =
+                            (*guard_pos - position.pos).length() <= redirect.radius;
+                        if *guard_hp > 0.0 && in_range {
+                            redirected_away = instance.damage * redirect.fraction;
+                            instance.damage -= redirected_away;
+                            requeue.push((
+                                redirect.target,
+                                DamageInstance {
+                                    damage: redirected_away,
+                                    delay: 0.0,
+                                    damage_type: instance.damage_type,
+                                    originator: instance.originator,
+                                    depth: instance.depth + 1,
+                                    execute: None,
+                                },
+                            ));
+                        }
+                    }
+                }
+            }
+            let amount = match instance.damage_type {
+                DamageType::Normal => instance.damage * 100.0 / (100.0 + armor.value),
+                DamageType::Magic => instance.damage * 100.0 / (100.0 + magic_resist.value),
+                DamageType::Poison => instance.damage,
+                DamageType::Heal => -instance.damage * efficacy.0,
+            };
+            let mitigated = match instance.damage_type {
+                DamageType::Normal | DamageType::Magic => instance.damage - amount,
+                DamageType::Poison | DamageType::Heal => 0.0,
+            };
+            // Shields soak post-mitigation damage before it reaches
+            // hitpoints; a drained shield zeroes its own timer and lets
+            // `buff_timer` tear it down.
+            let mut absorbed = 0.0;
+            if instance.damage_type != DamageType::Heal && amount > 0.0 {
+                if let Some(holder) = holder {
+                    for buff_entity in holder.vec.iter() {
+                        if let Ok((mut shield, mut timer)) = shield_query.get_mut(*buff_entity) {
+                            if shield.remaining <= 0.0 {
+                                continue;
+                            }
+                            let soak = shield.remaining.min(amount - absorbed);
+                            shield.remaining -= soak;
+                            absorbed += soak;
+                            if shield.remaining <= 0.0 {
+                                timer.0 = 0.0;
+                            }
+                            if absorbed >= amount {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            hitpoints.hp = (hitpoints.hp - (amount - absorbed)).min(hitpoints.max_hp);
+
+            if mitigated > 0.0 {
+                *stats
+                    .mitigated_by_team
+                    .entry(alignment.alignment)
+                    .or_insert(0.0) += mitigated;
+            }
+            if instance.damage_type != DamageType::Heal {
+                stats.record_damage_position(position.pos);
+            }
+
+            // Thorns bounce a share of the pre-mitigation hit back at the
+            // attacker. Reflections carry depth like bodyguard hops, so a
+            // reflected hit never reflects again.
+            if instance.damage_type == DamageType::Normal && instance.depth == 0 {
+                let mut thorns = own_thorns.copied();
+                if thorns.is_none() {
+                    if let Some(holder) = holder {
+                        for buff_entity in holder.vec.iter() {
+                            if let Ok(buff) = thorns_query.get(*buff_entity) {
+                                thorns = Some(*buff);
+                                break;
+                            }
+                        }
+                    }
+                }
+                if let Some(thorns) = thorns {
+                    let reflected = instance.damage * thorns.percent + thorns.flat;
+                    if reflected > 0.0 {
+                        requeue.push((
+                            instance.originator,
+                            DamageInstance {
+                                damage: reflected,
+                                delay: 0.0,
+                                damage_type: DamageType::Normal,
+                                // The victim is the attacker of record for
+                                // the reflected cue.
+                                originator: entity,
+                                depth: instance.depth + 1,
+                                execute: None,
+                            },
+                        ));
+                    }
+                }
+            }
+
+            // Lifesteal keys off what the armor let through, never off heals.
+            // The heal goes back through the queue so the attacker's own
+            // HealEfficacy (antiheal and the like) applies as usual. Depth
+            // gated like thorns and redirects: a reflected or redirected hit
+            // must not feed its originator lifesteal.
+            if instance.damage_type != DamageType::Heal && amount > 0.0 && instance.depth == 0 {
+                if let Ok(lifesteal) = lifesteal_query.get(instance.originator) {
+                    requeue.push((
+                        instance.originator,
+                        DamageInstance {
+                            damage: amount * lifesteal.percent,
+                            delay: 0.0,
+                            damage_type: DamageType::Heal,
+                            originator: instance.originator,
+                            depth: instance.depth,
+                            execute: None,
+                        },
+                    ));
+                }
+            }
+
+            // Cue is dropped when the originator is not a unit anymore.
+            if let Ok((blueprint, originator_alignment, threat_bonus)) =
+                originator_query.get(instance.originator)
+            {
+                if instance.damage_type == DamageType::Heal {
+                    *stats
+                        .healing_by_team
+                        .entry(originator_alignment.alignment)
+                        .or_insert(0.0) += -amount;
+                } else {
+                    *stats
+                        .damage_by_team
+                        .entry(originator_alignment.alignment)
+                        .or_insert(0.0) += amount;
+                    if let Some(log) = log.as_mut() {
+                        log.record_damage(originator_alignment.alignment, amount);
+                    }
+                    // Kill attribution is cached here, not looked up at death
+                    // time: the originator may be gone when a delayed or
+                    // over-time death finally lands.
+                    commands.entity(entity).insert(LastDamagedBy {
+                        entity: instance.originator,
+                        blueprint: blueprint.0,
+                        team: originator_alignment.alignment,
+                        at_clock: clock.as_ref().map(|clock| clock.tick).unwrap_or(0),
+                    });
+                    // Aggro tracks what actually got through, plus the
+                    // attacker's flat bonus; heals generate none.
+                    if let Some(table) = threat.as_mut() {
+                        let bonus = threat_bonus.map(|bonus| bonus.0).unwrap_or(0.0);
+                        table.add(instance.originator, amount.max(0.0) + bonus);
+                    }
+                }
+                events.0.push_back(EventCue::Damage(DamageCue {
+                    attacker: instance.originator,
+                    victim: entity,
+                    attacker_blueprint: blueprint.0,
+                    attacker_team: originator_alignment.alignment,
+                    victim_team: alignment.alignment,
+                    damage: amount,
+                    damage_type: instance.damage_type,
+                    position: position.pos,
+                    pre_mitigation: instance.damage,
+                    mitigated,
+                    absorbed,
+                    redirected: if instance.depth > 0 {
+                        instance.damage
+                    } else {
+                        redirected_away
+                    },
+                    crit: executed,
+                }));
+            }
+
+            if hitpoints.hp <= 0.0 {
+                // Only the instance that crossed the threshold counts as the
+                // killing blow, and only its originator's mark pays out.
+                if was_alive {
+                    if let Ok(mut marks) = marks_query.get_mut(entity) {
+                        if let Some(index) = marks
+                            .vec
+                            .iter()
+                            .position(|(beneficiary, _)| *beneficiary == instance.originator)
+                        {
+                            let (beneficiary, amount) = marks.vec.remove(index);
+                            requeue.push((
+                                beneficiary,
+                                DamageInstance {
+                                    damage: amount,
+                                    delay: 0.0,
+                                    damage_type: DamageType::Heal,
+                                    originator: beneficiary,
+                                    depth: 0,
+                                    execute: None,
+                                },
+                            ));
+                        }
+                    }
+                }
+                commands.entity(entity).insert(DeathApproaches);
+            }
+        }
+    }
+
+    for (unit, instance) in requeue {
+        if let Ok((_, mut damages, _, _, _, _, _, _, _, _, _, _)) = query.get_mut(unit) {
+            damages.vec.push(instance);
+        }
+    }
+}
+
+/// Grant every unit in aura range a short StatBuff, on the cache cadence.
+/// The half-second timer outlasts the six-tick refresh, so standing in the
+/// aura reads as permanent while a dead carrier's buffs lapse on their own.
+pub fn apply_auras(
+    mut commands: Commands,
+    clock: Res<crate::physics::Clock>,
+    neighbors: Option<Res<crate::physics::SpatialNeighborsCache>>,
+    query: Query<(Entity, &Aura, &TeamAlignment)>,
+    mut holder_query: Query<&mut BuffHolder>,
+    mut aura_buff_query: Query<(&mut BuffTimer, &BuffOriginator), With<AuraBuff>>,
+) {
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    if clock.tick % 6 != 0 {
+        return;
+    }
+    for (carrier, aura, alignment) in query.iter() {
+        let neighbor_list = match neighbors.get_neighbors(&carrier) {
+            Some(neighbor_list) => neighbor_list,
+            None => continue,
+        };
+        for neighbor in neighbor_list.iter() {
+            let allied = neighbor.team == alignment.alignment;
+            if allied != aura.affects_allies || neighbor.distance > aura.radius {
+                continue;
+            }
+            let mut holder = match holder_query.get_mut(neighbor.entity) {
+                Ok(holder) => holder,
+                Err(_) => continue,
+            };
+            // The same carrier refreshes its running buff; overlapping
+            // auras from different carriers stack as separate entities.
+            let mut refreshed = false;
+            for buff in holder.vec.iter() {
+                if let Ok((mut timer, originator)) = aura_buff_query.get_mut(*buff) {
+                    if originator.0 == carrier {
+                        timer.0 = 0.5;
+                        refreshed = true;
+                        break;
+                    }
+                }
+            }
+            if refreshed {
+                continue;
+            }
+            let buff = commands
+                .spawn()
+                .insert(BuffTimer(0.5))
+                .insert(BuffType {
+                    is_debuff: !aura.affects_allies,
+                })
+                .insert(TargetEntity(neighbor.entity))
+                .insert(BuffOriginator(carrier))
+                .insert(AuraBuff)
+                .insert(aura.buff)
+                .id();
+            holder.vec.push(buff);
+        }
+    }
+}
+
+/// Watch berserker hitpoints and flip the surge on or off at the threshold.
+/// Crossing below grants one speed StatBuff plus an attack-speed buff per
+/// action; healing back above zeroes their timers so `buff_timer` tears the
+/// whole surge down on its usual path.
+pub fn berserk_passive(
+    mut commands: Commands,
+    query: Query<(
+        Entity,
+        &Hitpoints,
+        &BerserkPassive,
+        Option<&UnitActions>,
+        Option<&Berserking>,
+    )>,
+    mut holder_query: Query<&mut BuffHolder>,
+    mut timer_query: Query<&mut BuffTimer>,
+) {
+    for (unit, hitpoints, passive, actions, berserking) in query.iter() {
+        let below = hitpoints.hp < hitpoints.max_hp * passive.hp_threshold_percent;
+        match berserking {
+            None if below && hitpoints.hp > 0.0 => {
+                let mut buffs: Vec<Entity> = Vec::new();
+                // The surge has no clock of its own; teardown is the heal
+                // branch below zeroing these timers.
+                let stat_buff = commands
+                    .spawn()
+                    .insert(BuffTimer(f32::MAX))
+                    .insert(BuffType { is_debuff: false })
+                    .insert(TargetEntity(unit))
+                    .insert(StatBuff {
+                        speed_buff: passive.speed_bonus,
+                        ..Default::default()
+                    })
+                    .id();
+                if let Ok(mut holder) = holder_query.get_mut(unit) {
+                    holder.vec.push(stat_buff);
+                }
+                buffs.push(stat_buff);
+                if let Some(actions) = actions {
+                    for action in actions.vec.iter() {
+                        buffs.push(
+                            commands
+                                .spawn()
+                                .insert(BuffTimer(f32::MAX))
+                                .insert(BuffType { is_debuff: false })
+                                .insert(TargetEntity(*action))
+                                .insert(PercentCooldownReduction(passive.attack_speed_bonus))
+                                .id(),
+                        );
+                    }
+                }
+                commands.entity(unit).insert(Berserking { buffs });
+            }
+            Some(berserking) if !below => {
+                for buff in berserking.buffs.iter() {
+                    if let Ok(mut timer) = timer_query.get_mut(*buff) {
+                        timer.0 = 0.0;
+                    }
+                }
+                commands.entity(unit).remove::<Berserking>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Recompute unit stats from their bases plus every held StatBuff.
+pub fn apply_stat_buffs(
+    mut query: Query<(
+        &mut Speed,
+        &mut Armor,
+        &mut MagicResist,
+        &mut Acceleration,
+        &mut HealEfficacy,
+        &mut crate::physics::Mass,
+        &BaseMass,
+        &BuffHolder,
+        Option<&mut Hitpoints>,
+        Option<&BaseMaxHp>,
+    )>,
+    buff_query: Query<(&StatBuff, Option<&BuffStacks>)>,
+) {
+    for (
+        mut speed,
+        mut armor,
+        mut magic_resist,
+        mut acceleration,
+        mut efficacy,
+        mut mass,
+        base_mass,
+        holder,
+        hitpoints,
+        base_max_hp,
+    ) in query.iter_mut()
+    {
+        speed.value = speed.base;
+        armor.value = armor.base;
+        magic_resist.value = magic_resist.base;
+        acceleration.value = acceleration.base;
+        efficacy.0 = 1.0;
+        mass.0 = base_mass.0;
+        let mut max_hp_bonus = 0.0;
+        // Flat buffs sum first; percent buffs then multiply the summed
+        // result, so "+20%" means the same thing on every unit.
+        let mut speed_factor = 1.0;
+        let mut armor_factor = 1.0;
+        let mut acceleration_factor = 1.0;
+        let mut mass_factor = 1.0;
+        for buff_entity in holder.vec.iter() {
+            if let Ok((buff, stacks)) = buff_query.get(*buff_entity) {
+                let stacks = stacks.map(|s| s.count).unwrap_or(1) as f32;
+                speed.value += buff.speed_buff * stacks;
+                armor.value += buff.armor_buff * stacks;
+                magic_resist.value += buff.magic_resist_buff * stacks;
+                acceleration.value += buff.acceleration_buff * stacks;
+                mass.0 += buff.mass_buff * stacks;
+                efficacy.0 *= 1.0 - buff.heal_efficacy_mult_buff * stacks;
+                max_hp_bonus += buff.max_hp_buff * stacks;
+                speed_factor *= 1.0 + buff.speed_mult * stacks;
+                armor_factor *= 1.0 + buff.armor_mult * stacks;
+                acceleration_factor *= 1.0 + buff.acceleration_mult * stacks;
+                mass_factor *= 1.0 + buff.mass_mult * stacks;
+            }
+        }
+        speed.value *= speed_factor;
+        armor.value *= armor_factor;
+        acceleration.value *= acceleration_factor;
+        mass.0 *= mass_factor;
+        speed.value = speed.value.max(1.0);
+        acceleration.value = acceleration.value.max(1.0);
+        // Antiheal bottoms out at "no healing": an over-100% value or several
+        // multiplied sources must never flip heals into damage.
+        efficacy.0 = efficacy.0.clamp(0.0, 1.0);
+        if let (Some(mut hitpoints), Some(base_max_hp)) = (hitpoints, base_max_hp) {
+            let new_max = (base_max_hp.0 + max_hp_bonus).max(1.0);
+            if hitpoints.max_hp > 0.0 && (new_max - hitpoints.max_hp).abs() > f32::EPSILON {
+                // Keep the injured fraction constant so a vitality buff
+                // never makes its holder look freshly wounded — and losing
+                // one clamps hp back under the lower ceiling.
+                hitpoints.hp = (hitpoints.hp * new_max / hitpoints.max_hp).min(new_max);
+                hitpoints.max_hp = new_max;
+            }
+        }
+    }
+}
+
+/// Buffs that overwrite stats outright (armor shred, hypnosis).
+pub fn set_stats_directly(
+    buff_query: Query<(
+        Entity,
+        &TargetEntity,
+        Option<&SetArmor>,
+        Option<&SetAcceleration>,
+        Option<&SetAlignment>,
+    )>,
+    holder_query: Query<&BuffHolder>,
+    mut armor_query: Query<&mut Armor>,
+    mut acceleration_query: Query<&mut Acceleration>,
+    mut alignment_query: Query<&mut TeamAlignment>,
+) {
+    for (buff, target, set_armor, set_acceleration, set_alignment) in buff_query.iter() {
+        // A buff cleansed this frame is already out of its target's holder;
+        // stop applying it even while its entity waits on the despawn.
+        if holder_query
+            .get(target.0)
+            .map(|holder| !holder.vec.contains(&buff))
+            .unwrap_or(false)
+        {
+            continue;
+        }
+        if let Some(set_armor) = set_armor {
+            if let Ok(mut armor) = armor_query.get_mut(target.0) {
+                armor.value = set_armor.0;
+            }
+        }
+        if let Some(set_acceleration) = set_acceleration {
+            if let Ok(mut acceleration) = acceleration_query.get_mut(target.0) {
+                acceleration.value = set_acceleration.0;
+            }
+        }
+        if let Some(set_alignment) = set_alignment {
+            if let Ok(mut alignment) = alignment_query.get_mut(target.0) {
+                alignment.alignment = set_alignment.0;
+            }
+        }
+    }
+}
+
+/// Restore alignment to its base before SetAlignment buffs re-apply; runs a
+/// stage ahead of `set_stats_directly` so an expired hypnosis reverts.
+pub fn reset_alignment(mut query: Query<&mut TeamAlignment>) {
+    for mut alignment in query.iter_mut() {
+        alignment.alignment = alignment.alignment_base;
+    }
+}
+
+pub fn percent_damage_over_time(
+    delta: Res<DeltaPhysics>,
+    buff_query: Query<(
+        Entity,
+        &PercentDamageOverTime,
+        &TargetEntity,
+        Option<&BuffStacks>,
+    )>,
+    holder_query: Query<&BuffHolder>,
+    mut target_query: Query<(&mut AppliedDamage, &Hitpoints)>,
+) {
+    for (buff, dot, target, stacks) in buff_query.iter() {
+        // Same holder-membership gate as `set_stats_directly`: a cleansed
+        // poison must not get one last tick in.
+        if holder_query
+            .get(target.0)
+            .map(|holder| !holder.vec.contains(&buff))
+            .unwrap_or(false)
+        {
+            continue;
+        }
+        let stacks = stacks.map(|s| s.count).unwrap_or(1) as f32;
+        if let Ok((mut damages, hitpoints)) = target_query.get_mut(target.0) {
+            damages.vec.push(DamageInstance {
+                damage: hitpoints.max_hp * dot.percent_per_second * stacks * delta.seconds,
+                delay: 0.0,
+                damage_type: DamageType::Poison,
+                originator: dot.originator,
+                depth: 0,
+                execute: None,
+            });
+        }
+    }
+}
+
+/// Burns push flat Magic instances, so magic resist mitigates every tick in
+/// `apply_damages` and tanks are not the only ones who can shrug them off.
+pub fn flat_damage_over_time(
+    delta: Res<DeltaPhysics>,
+    buff_query: Query<(&FlatDamageOverTime, &TargetEntity)>,
+    mut target_query: Query<&mut AppliedDamage>,
+) {
+    for (dot, target) in buff_query.iter() {
+        if let Ok(mut damages) = target_query.get_mut(target.0) {
+            damages.vec.push(DamageInstance {
+                damage: dot.damage_per_second * delta.seconds,
+                delay: 0.0,
+                damage_type: DamageType::Magic,
+                originator: dot.originator,
+                depth: 0,
+                execute: None,
+            });
+        }
+    }
+}
+
+pub fn heal_over_time(
+    delta: Res<DeltaPhysics>,
+    buff_query: Query<(&HealingPerSecond, &TargetEntity)>,
+    mut target_query: Query<&mut AppliedDamage>,
+) {
+    for (heal, target) in buff_query.iter() {
+        if let Ok(mut damages) = target_query.get_mut(target.0) {
+            damages.vec.push(DamageInstance {
+                damage: heal.0 * delta.seconds,
+                delay: 0.0,
+                damage_type: DamageType::Heal,
+                originator: target.0,
+                depth: 0,
+                execute: None,
+            });
+        }
+    }
+}
+
+/// Extra cooldown recovery from Overdrive-style buffs targeting actions.
+pub fn percent_cooldown_speedup(
+    delta: Res<DeltaPhysics>,
+    buff_query: Query<(&PercentCooldownReduction, &TargetEntity)>,
+    mut cooldown_query: Query<&mut Cooldown>,
+) {
+    for (reduction, target) in buff_query.iter() {
+        if let Ok(mut cooldown) = cooldown_query.get_mut(target.0) {
+            cooldown.0 -= delta.seconds * reduction.0;
+        }
+    }
+}
+
+/// Attack-slow counterpart of `percent_cooldown_speedup`: give cooldown time
+/// back each tick, capped at the action's own base cooldown so stacked slows
+/// can never grow a cooldown without bound.
+pub fn percent_cooldown_slowdown(
+    delta: Res<DeltaPhysics>,
+    buff_query: Query<(&PercentCooldownIncrease, &TargetEntity)>,
+    mut cooldown_query: Query<(&mut Cooldown, &ActionCooldown)>,
+) {
+    for (increase, target) in buff_query.iter() {
+        if let Ok((mut cooldown, base)) = cooldown_query.get_mut(target.0) {
+            cooldown.0 = (cooldown.0 + delta.seconds * increase.0).min(base.0);
+        }
+    }
+}
+
+/// Tick totem pulses: heal nearby allies for positive amounts, damage nearby
+/// enemies for negative ones. Pulses ride the spatial neighbors cache, so a
+/// totem's reach is bounded by its SpatialAwareness.
+pub fn totem_pulse(
+    delta: Res<DeltaPhysics>,
+    neighbors: Option<Res<crate::physics::SpatialNeighborsCache>>,
+    mut totem_query: Query<(Entity, &mut TotemPulse, &TeamAlignment)>,
+    mut buffer_query: Query<&mut ResolveEffectsBuffer>,
+    hitpoints_query: Query<&Hitpoints>,
+) {
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    for (totem, mut pulse, alignment) in totem_query.iter_mut() {
+        pulse.elapsed += delta.seconds;
+        if pulse.elapsed < pulse.period {
+            continue;
+        }
+        pulse.elapsed -= pulse.period;
+        let neighbor_list = match neighbors.get_neighbors(&totem) {
+            Some(list) => list,
+            None => continue,
+        };
+        for neighbor in neighbor_list.iter() {
+            if neighbor.distance > pulse.radius {
+                continue;
+            }
+            let alive = hitpoints_query
+                .get(neighbor.entity)
+                .map(|hitpoints| hitpoints.hp > 0.0)
+                .unwrap_or(false);
+            if !alive {
+                continue;
+            }
+            let is_ally = neighbor.team == alignment.alignment;
+            let effect = if pulse.amount >= 0.0 {
+                if !is_ally {
+                    continue;
+                }
+                Effect::HealEffect {
+                    amount: pulse.amount,
+                }
+            } else {
+                if is_ally {
+                    continue;
+                }
+                Effect::DamageEffect {
+                    damage: -pulse.amount,
+                    delay: 0.0,
+                    damage_type: DamageType::Magic,
+                }
+            };
+            if let Ok(mut buffer) = buffer_query.get_mut(neighbor.entity) {
+                buffer.vec.push(QueuedEffect {
+                    effect,
+                    originator: totem,
+                    execute: None,
+                });
+            }
+        }
+    }
+}
+
+/// Arm planted mines and detonate them on proximity. A triggered mine pushes
+/// splash-scaled damage to every living enemy inside its trigger radius —
+/// the same falloff projectiles use — then folds through the normal death
+/// path so its sprite plays out and its canvas item gets freed.
+pub fn mine_trigger(
+    mut commands: Commands,
+    delta: Res<DeltaPhysics>,
+    spatial: Option<Res<SpatialHashTable>>,
+    mut mine_query: Query<
+        (Entity, &mut Mine, &Position, &TeamAlignment, Option<&Splash>),
+        Without<DeathApproaches>,
+    >,
+    mut buffer_query: Query<&mut ResolveEffectsBuffer>,
+    hitpoints_query: Query<&Hitpoints>,
+) {
+    let spatial = match spatial {
+        Some(spatial) => spatial,
+        None => return,
+    };
+    for (entity, mut mine, position, alignment, splash) in mine_query.iter_mut() {
+        if mine.armed_in > 0.0 {
+            mine.armed_in -= delta.seconds;
+            continue;
+        }
+        let mut victims: Vec<(Entity, f32)> = Vec::new();
+        for hash in
+            spatial.get_all_spatial_hashes_from_circle(position.pos, mine.trigger_radius)
+        {
+            let entries = match spatial.table.get(&hash) {
+                Some(entries) => entries,
+                None => continue,
+            };
+            for entry in entries {
+                if entry.team == alignment.alignment {
+                    continue;
+                }
+                let alive = hitpoints_query
+                    .get(entry.entity)
+                    .map(|hitpoints| hitpoints.hp > 0.0)
+                    .unwrap_or(false);
+                if !alive {
+                    continue;
+                }
+                let distance = crate::util::true_distance(
+                    position.pos,
+                    entry.position,
+                    0.0,
+                    entry.radius,
+                );
+                if distance > mine.trigger_radius {
+                    continue;
+                }
+                victims.push((entry.entity, distance));
+            }
+        }
+        if victims.is_empty() {
+            continue;
+        }
+        for (victim, distance) in victims {
+            let damage = splash
+                .map(|splash| mine.damage * splash.damage_scale(distance))
+                .unwrap_or(mine.damage);
+            if let Ok(mut buffer) = buffer_query.get_mut(victim) {
+                buffer.vec.push(QueuedEffect {
+                    effect: Effect::DamageEffect {
+                        damage,
+                        delay: 0.0,
+                        damage_type: DamageType::Normal,
+                    },
+                    originator: entity,
+                    execute: None,
+                });
+            }
+        }
+        commands.entity(entity).insert(DeathApproaches);
+    }
+}
+
+/// Structures that outlive their lifetime fold through the normal death path;
+/// destruction by damage already goes through it.
+pub fn structure_lifetime(
+    mut commands: Commands,
+    delta: Res<DeltaPhysics>,
+    mut query: Query<(Entity, &mut StructureLifetime)>,
+) {
+    for (entity, mut lifetime) in query.iter_mut() {
+        lifetime.0 -= delta.seconds;
+        if lifetime.0 <= 0.0 {
+            commands.entity(entity).insert(DeathApproaches);
+        }
+    }
+}
+
+/// Drop one chill stack per lapsed per-stack timer; the buff entity despawns
+/// when the last stack decays.
+pub fn chill_decay(
+    mut commands: Commands,
+    delta: Res<DeltaPhysics>,
+    mut query: Query<(
+        Entity,
+        &mut ChillStacks,
+        Option<&TargetEntity>,
+        Option<&Renderable>,
+    )>,
+    mut holder_query: Query<&mut BuffHolder>,
+) {
+    for (entity, mut chill, target, renderable) in query.iter_mut() {
+        chill.timer -= delta.seconds;
+        if chill.timer > 0.0 {
+            continue;
+        }
+        chill.stacks -= 1;
+        chill.timer = chill.stack_duration;
+        if chill.stacks > 0 {
+            let slow = chill.slow_per_stack * chill.stacks as f32;
+            commands.entity(entity).insert(StatBuff {
+                speed_buff: -slow,
+                acceleration_buff: -slow,
+                ..Default::default()
+            });
+            continue;
+        }
+        if let Some(target) = target {
+            if let Ok(mut holder) = holder_query.get_mut(target.0) {
+                holder.vec.retain(|b| *b != entity);
+            }
+        }
+        if let Some(renderable) = renderable {
+            commands
+                .spawn()
+                .insert(CleanupCanvasItem(renderable.canvas_item));
+        }
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Tick down crowd-control exposure windows. Once a window lapses without a
+/// fresh stun the component comes off and the falloff starts over from the
+/// first step.
+pub fn crowd_control_decay(
+    mut commands: Commands,
+    delta: Res<DeltaPhysics>,
+    mut query: Query<(Entity, &mut CrowdControlResistance)>,
+) {
+    for (entity, mut resistance) in query.iter_mut() {
+        resistance.window_remaining -= delta.seconds;
+        if resistance.window_remaining <= 0.0 {
+            commands.entity(entity).remove::<CrowdControlResistance>();
+        }
+    }
+}
+
+/// End one buff right now: lift whatever markers it put on its live target,
+/// free its canvas item and despawn it. Cleanse and `buff_timer` both finish
+/// buffs through here so neither path leaves side effects behind.
+#[allow(clippy::too_many_arguments)]
+fn tear_down_buff(
+    commands: &mut Commands,
+    buff: Entity,
+    target: Entity,
+    renderable: Option<&Renderable>,
+    stun: Option<&StunnedBuff>,
+    freeze: Option<&FreezeTint>,
+    shield: Option<&ShieldTint>,
+    disarm: Option<&DisarmedBuff>,
+    stealth: Option<&StealthedBuff>,
+    actions_query: &Query<&UnitActions>,
+    basic_attack_query: &Query<(), With<BasicAttack>>,
+) {
+    if stun.is_some() {
+        commands.entity(target).remove::<Stunned>();
+    }
+    if freeze.is_some() || shield.is_some() {
+        commands.entity(target).remove::<ModulateSprite>();
+    }
+    if stealth.is_some() {
+        commands
+            .entity(target)
+            .remove::<Stealthed>()
+            .remove::<AlphaSprite>();
+    }
+    if disarm.is_some() {
+        if let Ok(actions) = actions_query.get(target) {
+            for action in actions.vec.iter() {
+                // Only the attacks the disarm switched off; a scripted
+                // disable on an ability stays put.
+                if basic_attack_query.get(*action).is_ok() {
+                    commands.entity(*action).remove::<Disabled>();
+                }
+            }
+        }
+    }
+    if let Some(renderable) = renderable {
+        commands
+            .spawn()
+            .insert(CleanupCanvasItem(renderable.canvas_item));
+    }
+    commands.entity(buff).despawn();
+}
+
+/// Tick buff lifetimes; tear down expired buffs and buffs whose target is gone.
+pub fn buff_timer(
+    mut commands: Commands,
+    delta: Res<DeltaPhysics>,
+    mut query: Query<(
+        Entity,
+        &mut BuffTimer,
+        Option<&TargetEntity>,
+        Option<&Renderable>,
+        Option<&StunnedBuff>,
+        Option<&FreezeTint>,
+        Option<&ShieldTint>,
+        Option<&DisarmedBuff>,
+        Option<&StealthedBuff>,
+    )>,
+    mut holder_query: Query<&mut BuffHolder>,
+    // Buff targets are units or their action entities; both despawn with the
+    // unit, so bare existence is the liveness check. Requiring Hitpoints
+    // here would kill action-targeted buffs (Overdrive, AttackSpeedBuff) on
+    // their first tick.
+    alive_query: Query<Entity>,
+    actions_query: Query<&UnitActions>,
+    basic_attack_query: Query<(), With<BasicAttack>>,
+) {
+    for (entity, mut timer, target, renderable, stun, freeze, shield, disarm, stealth) in
+        query.iter_mut()
+    {
+        timer.0 -= delta.seconds;
+        let mut expired = timer.0 <= 0.0;
+        let mut live_target = None;
+        if let Some(target) = target {
+            if alive_query.get(target.0).is_err() {
+                // NoSuchEntity: the target despawned out from under the buff.
+                expired = true;
+            } else {
+                live_target = Some(target.0);
+            }
+        }
+        if !expired {
+            continue;
+        }
+        match live_target {
+            Some(target) => {
+                if let Ok(mut holder) = holder_query.get_mut(target) {
+                    holder.vec.retain(|b| *b != entity);
+                }
+                tear_down_buff(
+                    &mut commands,
+                    entity,
+                    target,
+                    renderable,
+                    stun,
+                    freeze,
+                    shield,
+                    disarm,
+                    stealth,
+                    &actions_query,
+                    &basic_attack_query,
+                );
+            }
+            None => {
+                if let Some(renderable) = renderable {
+                    commands
+                        .spawn()
+                        .insert(CleanupCanvasItem(renderable.canvas_item));
+                }
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+/// Tear down anything marked with DeathApproaches: fire death effects, free
+/// visuals, drop the unit's action entities, and leave a corpse sprite.
+pub fn resolve_death(
+    mut commands: Commands,
+    mut events: ResMut<EventQueue>,
+    mut stats: ResMut<MatchStats>,
+    mut log: Option<ResMut<MatchLog>>,
+    spatial: Option<Res<SpatialHashTable>>,
+    query: Query<(
+        Entity,
+        &DeathApproaches,
+        Option<&Position>,
+        Option<&OnDeathEffects>,
+        Option<&UnitActions>,
+        Option<&Renderable>,
+        Option<&AnimatedSprite>,
+        Option<&BlueprintId>,
+        Option<&TeamAlignment>,
+        Option<&LastDamagedBy>,
+    )>,
+    mut damage_query: Query<&mut AppliedDamage>,
+    alignment_query: Query<(Entity, &TeamAlignment), With<Hitpoints>>,
+) {
+    for (
+        entity,
+        _death,
+        position,
+        death_effects,
+        actions,
+        renderable,
+        sprite,
+        blueprint,
+        alignment,
+        last_hit,
+    ) in query.iter()
+    {
+        if let (Some(death_effects), Some(position)) = (death_effects, position) {
+            for effect in death_effects.vec.iter() {
+                match effect {
+                    DeathEffect::SplashDamage { damage, radius } => {
+                        if let Some(spatial) = &spatial {
+                            for hash in
+                                spatial.get_all_spatial_hashes_from_circle(position.pos, *radius)
+                            {
+                                if let Some(entries) = spatial.table.get(&hash) {
+                                    for entry in entries {
+                                        if entry.entity == entity {
+                                            continue;
+                                        }
+                                        // The blast spares the dying unit's
+                                        // own side.
+                                        if let Some(alignment) = alignment {
+                                            if entry.team == alignment.alignment {
+                                                continue;
+                                            }
+                                        }
+                                        if crate::util::true_distance(
+                                            position.pos,
+                                            entry.position,
+                                            0.0,
+                                            entry.radius,
+                                        ) > *radius
+                                        {
+                                            continue;
+                                        }
+                                        if let Ok(mut damages) =
+                                            damage_query.get_mut(entry.entity)
+                                        {
+                                            damages.vec.push(DamageInstance {
+                                                damage: *damage,
+                                                delay: 0.0,
+                                                damage_type: DamageType::Normal,
+                                                originator: entity,
+                                                depth: 0,
+                                                execute: None,
+                                            });
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    DeathEffect::HealAllies {
+                        amount,
+                        alignment: spawn_alignment,
+                    } => {
+                        // Heal the side the unit is on *now* — hypnosis may
+                        // have moved it since spawn — and never the dying
+                        // unit itself, which despawns this frame anyway.
+                        let team = alignment
+                            .map(|a| a.alignment)
+                            .unwrap_or(*spawn_alignment);
+                        for (ally, ally_alignment) in alignment_query.iter() {
+                            if ally == entity || ally_alignment.alignment != team {
+                                continue;
+                            }
+                            if let Ok(mut damages) = damage_query.get_mut(ally) {
+                                damages.vec.push(DamageInstance {
+                                    damage: *amount,
+                                    delay: 0.0,
+                                    damage_type: DamageType::Heal,
+                                    originator: entity,
+                                    depth: 0,
+                                    execute: None,
+                                });
+                            }
+                        }
+                    }
+                    DeathEffect::PoisonSplash {
+                        radius,
+                        duration,
+                        percent_damage,
+                        movement_debuff,
+                        texture,
+                    } => {
+                        // Instant "projectile" that immediately splashes.
+                        commands
+                            .spawn()
+                            .insert(crate::projectiles::Projectile {
+                                target: entity,
+                                last_known_position: position.pos,
+                                originator: entity,
+                                speed: 0.0,
+                                contact_distance: *radius,
+                                on_hit: vec![Effect::PoisonEffect {
+                                    percent_damage: *percent_damage,
+                                    movement_debuff: *movement_debuff,
+                                    duration: *duration,
+                                    texture: *texture,
+                                }],
+                                splash: Some(crate::actions::Splash {
+                                    radius: *radius,
+                                    min_percent: 1.0,
+                                }),
+                                execute: None,
+                            })
+                            .insert(Position { pos: position.pos })
+                            .insert(crate::physics::Velocity { v: Vector2::ZERO })
+                            .insert(NewCanvasItemDirective {})
+                            // The blast reuses the poison texture so the
+                            // splash is visible for its one frame of life.
+                            .insert(AnimatedSprite::new(*texture));
+                    }
+                    DeathEffect::HealTarget { amount, target } => {
+                        if let Ok(mut damages) = damage_query.get_mut(*target) {
+                            damages.vec.push(DamageInstance {
+                                damage: *amount,
+                                delay: 0.0,
+                                damage_type: DamageType::Heal,
+                                originator: entity,
+                                depth: 0,
+                                execute: None,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(actions) = actions {
+            for action in actions.vec.iter() {
+                commands.entity(*action).despawn();
+            }
+        }
+
+        if let (Some(blueprint), Some(alignment), Some(position)) = (blueprint, alignment, position)
+        {
+            *stats
+                .deaths_by_team
+                .entry(alignment.alignment)
+                .or_insert(0) += 1;
+            events.0.push_back(EventCue::Death(DeathCue {
+                entity,
+                blueprint: blueprint.0,
+                team: alignment.alignment,
+                position: position.pos,
+            }));
+            // Killer data comes straight off the cached last hit; structures
+            // and scripted deaths without one emit no kill cue.
+            if let Some(last_hit) = last_hit {
+                events.0.push_back(EventCue::Kill(KillCue {
+                    victim: entity,
+                    victim_blueprint: blueprint.0,
+                    victim_team: alignment.alignment,
+                    killer: last_hit.entity,
+                    killer_blueprint: last_hit.blueprint,
+                    killer_team: last_hit.team,
+                    position: position.pos,
+                    at_clock: last_hit.at_clock,
+                }));
+            }
+            if let Some(log) = log.as_mut() {
+                log.record_death(crate::entity_handle(entity), blueprint.0, alignment.alignment);
+            }
+        }
+
+        // Hand the canvas item over to a corpse entity that plays the death
+        // animation and expires.
+        if let (Some(renderable), Some(sprite), Some(position)) = (renderable, sprite, position) {
+            if blueprint.is_some() {
+                commands
+                    .spawn()
+                    .insert(*renderable)
+                    .insert(sprite.clone())
+                    .insert(Position { pos: position.pos })
+                    .insert(PlayAnimationDirective {
+                        animation: AnimationRole::Death,
+                        loops: false,
+                    })
+                    .insert(crate::util::ExpirationTimer(2.0));
+            } else {
+                commands.spawn().insert(CleanupCanvasItem(renderable.canvas_item));
+            }
+        }
+
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Declare a winner as soon as only one team has living units.
+/// Establish and tear down bodyguard links. A guard wards its nearest living
+/// ally; wards drop their link when the guard dies.
+pub fn assign_bodyguard_links(
+    mut commands: Commands,
+    neighbors: Option<Res<crate::physics::SpatialNeighborsCache>>,
+    guard_query: Query<(Entity, &BodyguardParams, &TeamAlignment, &Hitpoints)>,
+    ward_query: Query<(Entity, &DamageRedirect)>,
+    alive_query: Query<&Hitpoints>,
+) {
+    for (ward, redirect) in ward_query.iter() {
+        let guard_alive = alive_query
+            .get(redirect.target)
+            .map(|hitpoints| hitpoints.hp > 0.0)
+            .unwrap_or(false);
+        if !guard_alive {
+            commands.entity(ward).remove::<DamageRedirect>();
+        }
+    }
+
+    let neighbors = match neighbors {
+        Some(neighbors) => neighbors,
+        None => return,
+    };
+    for (guard, params, alignment, hitpoints) in guard_query.iter() {
+        if hitpoints.hp <= 0.0 {
+            continue;
+        }
+        if ward_query.iter().any(|(_, redirect)| redirect.target == guard) {
+            continue;
+        }
+        let mut best: Option<(Entity, f32)> = None;
+        if let Some(list) = neighbors.get_neighbors(&guard) {
+            for neighbor in list.iter() {
+                if neighbor.team != alignment.alignment || neighbor.entity == guard {
+                    continue;
+                }
+                if best.map(|(_, d)| neighbor.distance < d).unwrap_or(true) {
+                    best = Some((neighbor.entity, neighbor.distance));
+                }
+            }
+        }
+        if let Some((ward, _)) = best {
+            commands.entity(ward).insert(DamageRedirect {
+                target: guard,
+                fraction: params.fraction,
+                radius: params.radius,
+            });
+            spawn_visual_buff(&mut commands, ward, params.texture, 2.0, false);
+        }
+    }
+}
+
+/// Declare a winner when an engaged battle collapses to one team — or to
+/// none, a draw — and queue the battle_ended cue exactly once.
+pub fn update_victor(
+    mut victor: ResMut<Victor>,
+    mut events: ResMut<EventQueue>,
+    query: Query<&TeamAlignment, With<Hitpoints>>,
+) {
+    if victor.team != -1 {
+        return;
+    }
+    let mut living_teams: Vec<i64> = Vec::new();
+    for alignment in query.iter() {
+        if !living_teams.contains(&alignment.alignment) {
+            living_teams.push(alignment.alignment);
+        }
+    }
+    if living_teams.len() >= 2 {
+        victor.engaged = true;
+        return;
+    }
+    if !victor.engaged {
+        return;
+    }
+    victor.team = match living_teams.first() {
+        Some(team) => *team,
+        None => -2,
+    };
+    events.0.push_back(EventCue::BattleEnded(victor.team));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::schedule::SystemStage;
+
+    fn damaged_unit(world: &mut World, delay: f32) -> Entity {
+        world
+            .spawn()
+            .insert(AppliedDamage {
+                vec: vec![DamageInstance {
+                    damage: 10.0,
+                    delay,
+                    damage_type: DamageType::Poison,
+                    originator: Entity::from_raw(9999),
+                    depth: 0,
+                    execute: None,
+                }],
+            })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .insert(Armor {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(MagicResist {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(HealEfficacy(1.0))
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(Position {
+                pos: Vector2::new(0.0, 0.0),
+            })
+            .id()
+    }
+
+    #[test]
+    fn delayed_damage_applies_only_after_delay_elapses() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let unit = damaged_unit(&mut world, 0.25);
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(apply_damages);
+
+        // Two ticks leave 0.05s of delay; hp untouched, instance still queued.
+        stage.run(&mut world);
+        stage.run(&mut world);
+        assert!((world.get::<Hitpoints>(unit).unwrap().hp - 100.0).abs() < f32::EPSILON);
+        assert_eq!(world.get::<AppliedDamage>(unit).unwrap().vec.len(), 1);
+
+        stage.run(&mut world);
+        assert!((world.get::<Hitpoints>(unit).unwrap().hp - 90.0).abs() < 1e-3);
+        assert!(world.get::<AppliedDamage>(unit).unwrap().vec.is_empty());
+    }
+
+    fn run_damage(world: &mut World) {
+        let mut stage = SystemStage::parallel();
+        stage.add_system(apply_damages);
+        stage.run(world);
+    }
+
+    #[test]
+    fn armor_mitigates_normal_damage_and_is_credited() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let unit = damaged_unit(&mut world, 0.0);
+        world.get_mut::<Armor>(unit).unwrap().value = 100.0;
+        world.get_mut::<AppliedDamage>(unit).unwrap().vec[0].damage_type = DamageType::Normal;
+
+        run_damage(&mut world);
+
+        // 10 damage into 100 armor halves: 5 applied, 5 mitigated.
+        assert!((world.get::<Hitpoints>(unit).unwrap().hp - 95.0).abs() < 1e-3);
+        let stats = world.resource::<MatchStats>();
+        assert!((stats.mitigated_by_team.get(&0).copied().unwrap() - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn magic_resist_mitigates_magic_damage() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let unit = damaged_unit(&mut world, 0.0);
+        world.get_mut::<MagicResist>(unit).unwrap().value = 25.0;
+        world.get_mut::<AppliedDamage>(unit).unwrap().vec[0].damage_type = DamageType::Magic;
+
+        run_damage(&mut world);
+
+        assert!((world.get::<Hitpoints>(unit).unwrap().hp - 92.0).abs() < 1e-3);
+        let stats = world.resource::<MatchStats>();
+        assert!((stats.mitigated_by_team.get(&0).copied().unwrap() - 2.0).abs() < 1e-3);
+    }
+
+    fn queue_chill(world: &mut World, target: Entity) {
+        let originator = Entity::from_raw(9999);
+        world
+            .get_mut::<ResolveEffectsBuffer>(target)
+            .unwrap()
+            .vec
+            .push(QueuedEffect {
+                effect: Effect::Chill {
+                    slow_per_stack: 5.0,
+                    max_stacks: 3,
+                    freeze_duration: 2.0,
+                    duration: 1.0,
+                    texture: Rid::new(),
+                },
+                originator,
+                execute: None,
+            });
+    }
+
+    fn chill_buff_of(world: &mut World, target: Entity) -> Option<Entity> {
+        let holder: Vec<Entity> = world.get::<BuffHolder>(target).unwrap().vec.clone();
+        holder
+            .into_iter()
+            .find(|buff| world.get::<ChillStacks>(*buff).is_some())
+    }
+
+    #[test]
+    fn chill_stacks_then_freezes_then_decays() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.1 });
+        let target = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .id();
+
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut decay = SystemStage::parallel();
+        decay.add_system(chill_decay);
+
+        // Two applications: two stacks, scaled slow.
+        queue_chill(&mut world, target);
+        resolve.run(&mut world);
+        queue_chill(&mut world, target);
+        resolve.run(&mut world);
+        let buff = chill_buff_of(&mut world, target).unwrap();
+        assert_eq!(world.get::<ChillStacks>(buff).unwrap().stacks, 2);
+        assert!((world.get::<StatBuff>(buff).unwrap().speed_buff + 10.0).abs() < 1e-3);
+
+        // One lapsed per-stack timer drops a single stack.
+        decay.run(&mut world);
+        assert_eq!(world.get::<ChillStacks>(buff).unwrap().stacks, 1);
+        assert!((world.get::<StatBuff>(buff).unwrap().speed_buff + 5.0).abs() < 1e-3);
+
+        // Two more applications reach max_stacks and convert into a freeze.
+        queue_chill(&mut world, target);
+        resolve.run(&mut world);
+        queue_chill(&mut world, target);
+        resolve.run(&mut world);
+        assert!(chill_buff_of(&mut world, target).is_none());
+        assert!(world.get::<Stunned>(target).is_some());
+        assert!(world.get::<ModulateSprite>(target).is_some());
+
+        // The freeze expires through the normal buff path and clears the tint.
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+        timers.run(&mut world);
+        timers.run(&mut world);
+        assert!(world.get::<Stunned>(target).is_none());
+        assert!(world.get::<ModulateSprite>(target).is_none());
+    }
+
+    #[test]
+    fn speed_and_mass_buff_lasts_exactly_its_duration() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.1 });
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(Speed {
+                base: 50.0,
+                value: 50.0,
+            })
+            .insert(Armor {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(MagicResist {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(Acceleration {
+                base: 10.0,
+                value: 10.0,
+            })
+            .insert(HealEfficacy(1.0))
+            .insert(crate::physics::Mass(4.0))
+            .insert(BaseMass(4.0))
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .id();
+        world
+            .get_mut::<ResolveEffectsBuffer>(unit)
+            .unwrap()
+            .vec
+            .push(QueuedEffect {
+                effect: Effect::ApplyStatBuffEffect {
+                    buff: StatBuff {
+                        speed_buff: 20.0,
+                        mass_buff: 6.0,
+                        ..Default::default()
+                    },
+                    duration: 1.0,
+                    texture: Rid::new(),
+                },
+                originator: Entity::from_raw(9999),
+                execute: None,
+            });
+
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut stats = SystemStage::parallel();
+        stats.add_system(apply_stat_buffs);
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+
+        resolve.run(&mut world);
+        stats.run(&mut world);
+        assert!((world.get::<Speed>(unit).unwrap().value - 70.0).abs() < 1e-3);
+        assert!((world.get::<crate::physics::Mass>(unit).unwrap().0 - 10.0).abs() < 1e-3);
+
+        // One 1.1s tick outlives the 1.0s buff; stats revert to base.
+        timers.run(&mut world);
+        stats.run(&mut world);
+        assert!((world.get::<Speed>(unit).unwrap().value - 50.0).abs() < 1e-3);
+        assert!((world.get::<crate::physics::Mass>(unit).unwrap().0 - 4.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn pending_canvas_directives_cancel_without_queueing_cleanup() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.1 });
+        let unit = world
+            .spawn()
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .id();
+        // A buff expiring before its first render frame: directive pending,
+        // no Renderable yet.
+        let buff = world
+            .spawn()
+            .insert(BuffTimer(1.0))
+            .insert(BuffType { is_debuff: false })
+            .insert(TargetEntity(unit))
+            .insert(NewCanvasItemDirective {})
+            .id();
+        world.get_mut::<BuffHolder>(unit).unwrap().vec.push(buff);
+
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+        timers.run(&mut world);
+
+        assert!(world.get_entity(buff).is_none());
+        assert!(world.get::<BuffHolder>(unit).unwrap().vec.is_empty());
+        // No canvas item was ever created, so nothing must be queued to free.
+        assert_eq!(
+            world.query::<&CleanupCanvasItem>().iter(&world).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn disarm_disables_basic_attacks_for_its_duration() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.1 });
+        let attack = world.spawn().insert(BasicAttack).id();
+        let ability = world.spawn().id();
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(UnitActions {
+                vec: vec![attack, ability],
+            })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .id();
+        world
+            .get_mut::<ResolveEffectsBuffer>(unit)
+            .unwrap()
+            .vec
+            .push(QueuedEffect {
+                effect: Effect::Disarm {
+                    duration: 1.0,
+                    texture: Rid::new(),
+                },
+                originator: Entity::from_raw(9999),
+                execute: None,
+            });
+
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+
+        // Only the basic attack is switched off, not the ability action.
+        resolve.run(&mut world);
+        assert!(world.get::<Disabled>(attack).is_some());
+        assert!(world.get::<Disabled>(ability).is_none());
+
+        timers.run(&mut world);
+        assert!(world.get::<Disabled>(attack).is_none());
+    }
+
+    #[test]
+    fn bodyguard_link_splits_damage_before_mitigation() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let ward = damaged_unit(&mut world, 0.0);
+        let guard = damaged_unit(&mut world, 0.0);
+        world.get_mut::<AppliedDamage>(guard).unwrap().vec.clear();
+        world.entity_mut(ward).insert(DamageRedirect {
+            target: guard,
+            fraction: 0.4,
+            radius: 50.0,
+        });
+
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(ward).unwrap().hp - 94.0).abs() < 1e-3);
+        // The guard's share is re-queued and lands on the next tick.
+        assert_eq!(world.get::<AppliedDamage>(guard).unwrap().vec.len(), 1);
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(guard).unwrap().hp - 96.0).abs() < 1e-3);
+        // Redirected damage is not redirected again.
+        assert!(world.get::<AppliedDamage>(ward).unwrap().vec.is_empty());
+    }
+
+    #[test]
+    fn bodyguard_out_of_radius_takes_nothing() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let ward = damaged_unit(&mut world, 0.0);
+        let guard = damaged_unit(&mut world, 0.0);
+        world.get_mut::<AppliedDamage>(guard).unwrap().vec.clear();
+        world.get_mut::<Position>(guard).unwrap().pos = Vector2::new(100.0, 0.0);
+        world.entity_mut(ward).insert(DamageRedirect {
+            target: guard,
+            fraction: 0.4,
+            radius: 50.0,
+        });
+
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(ward).unwrap().hp - 90.0).abs() < 1e-3);
+        assert!(world.get::<AppliedDamage>(guard).unwrap().vec.is_empty());
+    }
+
+    #[test]
+    fn zero_delay_damage_applies_on_the_same_tick() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let unit = damaged_unit(&mut world, 0.0);
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(apply_damages);
+        stage.run(&mut world);
+        assert!((world.get::<Hitpoints>(unit).unwrap().hp - 90.0).abs() < 1e-3);
+    }
+    #[test]
+    fn totem_pulses_heal_allies_on_each_period() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.6 });
+        let ally = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 50.0,
+                max_hp: 100.0,
+            })
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .id();
+        let totem = world
+            .spawn()
+            .insert(TotemPulse {
+                amount: 5.0,
+                radius: 40.0,
+                period: 1.0,
+                elapsed: 0.0,
+            })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            totem,
+            vec![crate::physics::SpatialNeighbor {
+                entity: ally,
+                distance: 10.0,
+                team: 0,
+            }],
+        );
+        world.insert_resource(crate::physics::SpatialNeighborsCache { map });
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(totem_pulse);
+        stage.run(&mut world);
+        assert!(world.get::<ResolveEffectsBuffer>(ally).unwrap().vec.is_empty());
+
+        // The second tick crosses the period boundary.
+        stage.run(&mut world);
+        let buffer = world.get::<ResolveEffectsBuffer>(ally).unwrap();
+        assert_eq!(buffer.vec.len(), 1);
+        assert!(matches!(
+            buffer.vec[0].effect,
+            Effect::HealEffect { amount } if amount == 5.0
+        ));
+    }
+
+    #[test]
+    fn expired_structures_head_into_the_death_path() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.6 });
+        let totem = world.spawn().insert(StructureLifetime(1.0)).id();
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(structure_lifetime);
+        stage.run(&mut world);
+        assert!(world.get::<DeathApproaches>(totem).is_none());
+        stage.run(&mut world);
+        assert!(world.get::<DeathApproaches>(totem).is_some());
+    }
+    #[test]
+    fn mutual_bodyguards_cannot_bounce_damage_forever() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let ward = damaged_unit(&mut world, 0.0);
+        let guard = damaged_unit(&mut world, 0.0);
+        world.get_mut::<AppliedDamage>(guard).unwrap().vec.clear();
+        world.entity_mut(ward).insert(DamageRedirect {
+            target: guard,
+            fraction: 0.5,
+            radius: 50.0,
+        });
+        world.entity_mut(guard).insert(DamageRedirect {
+            target: ward,
+            fraction: 0.5,
+            radius: 50.0,
+        });
+
+        // One 10-damage hit may spawn at most one derived instance; without
+        // the depth cap this loop would queue new damage every tick.
+        let mut derived = 0;
+        for _ in 0..20 {
+            run_damage(&mut world);
+            derived += world.get::<AppliedDamage>(ward).unwrap().vec.len()
+                + world.get::<AppliedDamage>(guard).unwrap().vec.len();
+        }
+        assert!(derived <= 1);
+    }
+    #[test]
+    fn every_effect_variant_has_a_stable_kind_id() {
+        let rid = Rid::new();
+        let cases: Vec<(Effect, &'static str)> = vec![
+            (
+                Effect::DamageEffect {
+                    damage: 1.0,
+                    delay: 0.0,
+                    damage_type: DamageType::Normal,
+                },
+                "damage",
+            ),
+            (Effect::HealEffect { amount: 1.0 }, "heal"),
+            (
+                Effect::PoisonEffect {
+                    percent_damage: 0.04,
+                    movement_debuff: 10.0,
+                    duration: 5.0,
+                    texture: rid,
+                },
+                "slow_poison",
+            ),
+            (
+                Effect::Disarm {
+                    duration: 1.0,
+                    texture: rid,
+                },
+                "disarm",
+            ),
+            (
+                Effect::StunEffect {
+                    duration: 1.0,
+                    texture: rid,
+                },
+                "stun",
+            ),
+            (
+                Effect::Chill {
+                    slow_per_stack: 5.0,
+                    max_stacks: 4,
+                    freeze_duration: 1.0,
+                    duration: 3.0,
+                    texture: rid,
+                },
+                "chill",
+            ),
+            (
+                Effect::ConfusionEffect {
+                    duration: 1.0,
+                    texture: rid,
+                },
+                "confusion",
+            ),
+            (
+                Effect::AntihealOnHitEffect {
+                    percent: 0.5,
+                    duration: 2.0,
+                    texture: rid,
+                },
+                "antiheal",
+            ),
+            (
+                Effect::ShredArmorEffect {
+                    duration: 2.0,
+                    texture: rid,
+                },
+                "shred_armor",
+            ),
+            (Effect::CleanseEffect, "cleanse"),
+            (
+                Effect::ApplyStatBuffEffect {
+                    buff: StatBuff::default(),
+                    duration: 2.0,
+                    texture: rid,
+                },
+                "stat_buff",
+            ),
+            (
+                Effect::AttackSpeedBuff {
+                    percent: 0.1,
+                    duration: 2.0,
+                },
+                "attack_speed_buff",
+            ),
+            (
+                Effect::OverdriveEffect {
+                    percent: 0.2,
+                    duration: 2.0,
+                    texture: rid,
+                },
+                "overdrive",
+            ),
+            (
+                Effect::DamageBuffEffect {
+                    percent: 0.2,
+                    duration: 2.0,
+                    texture: rid,
+                },
+                "damage_buff",
+            ),
+            (
+                Effect::HealOverTimeEffect {
+                    amount_per_second: 2.0,
+                    duration: 3.0,
+                    texture: rid,
+                },
+                "heal_over_time",
+            ),
+            (
+                Effect::Hypnosis {
+                    new_alignment: 1,
+                    duration: 3.0,
+                },
+                "hypnosis",
+            ),
+            (Effect::SuicideEffect, "suicide"),
+            (
+                Effect::HealOnDeathEffect {
+                    amount: 5.0,
+                    target: Entity::from_raw(1),
+                },
+                "heal_on_death",
+            ),
+            (
+                Effect::TeleportToPointEffect {
+                    destination: Vector2::new(1.0, 2.0),
+                },
+                "teleport_to_point",
+            ),
+            (
+                Effect::TeleportBehindTargetEffect { distance: 8.0 },
+                "teleport_behind_target",
+            ),
+            (
+                Effect::VisualEffect {
+                    texture: rid,
+                    duration: 1.0,
+                },
+                "visual",
+            ),
+            (
+                Effect::SummonTotemEffect {
+                    hitpoints: 40.0,
+                    radius: 8.0,
+                    lifetime: 10.0,
+                    pulse_amount: 5.0,
+                    pulse_radius: 60.0,
+                    pulse_period: 1.0,
+                    texture: rid,
+                },
+                "summon_totem",
+            ),
+        ];
+        for (effect, expected) in cases {
+            assert_eq!(effect.kind_id(), expected);
+            assert_eq!(effect.describe().0, expected);
+        }
+
+        let death_cases: Vec<(DeathEffect, &'static str)> = vec![
+            (
+                DeathEffect::SplashDamage {
+                    damage: 10.0,
+                    radius: 20.0,
+                },
+                "death_splash_damage",
+            ),
+            (
+                DeathEffect::HealAllies {
+                    amount: 5.0,
+                    alignment: 0,
+                },
+                "death_heal_allies",
+            ),
+            (
+                DeathEffect::PoisonSplash {
+                    radius: 20.0,
+                    duration: 3.0,
+                    percent_damage: 0.02,
+                    movement_debuff: 10.0,
+                    texture: rid,
+                },
+                "death_poison_splash",
+            ),
+            (
+                DeathEffect::HealTarget {
+                    amount: 5.0,
+                    target: Entity::from_raw(1),
+                },
+                "death_heal_target",
+            ),
+        ];
+        for (effect, expected) in death_cases {
+            assert_eq!(effect.kind_id(), expected);
+            assert_eq!(effect.describe().0, expected);
+        }
+    }
+
+    #[test]
+    fn describe_snapshots_tooltip_parameters() {
+        let poison = Effect::PoisonEffect {
+            percent_damage: 0.04,
+            movement_debuff: 10.0,
+            duration: 5.0,
+            texture: Rid::new(),
+        };
+        assert_eq!(
+            poison.describe(),
+            (
+                "slow_poison",
+                vec![
+                    ("percent_damage", 0.04),
+                    ("movement_debuff", 10.0),
+                    ("duration", 5.0),
+                ]
+            )
+        );
+
+        let damage = Effect::DamageEffect {
+            damage: 7.0,
+            delay: 0.25,
+            damage_type: DamageType::Magic,
+        };
+        assert_eq!(
+            damage.describe(),
+            (
+                "damage",
+                vec![("damage", 7.0), ("delay", 0.25), ("damage_type", 1.0)]
+            )
+        );
+    }
+
+    #[test]
+    fn lone_teams_only_win_after_a_real_fight() {
+        let mut world = World::default();
+        world.insert_resource(Victor::default());
+        world.insert_resource(crate::event::EventQueue::default());
+        let mut stage = SystemStage::parallel();
+        stage.add_system(update_victor);
+
+        // One team alone on the field is not an instant victory.
+        let soloist = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        stage.run(&mut world);
+        assert_eq!(world.resource::<Victor>().team, -1);
+
+        // An opponent shows up; the battle is now engaged.
+        let opponent = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .id();
+        stage.run(&mut world);
+        assert_eq!(world.resource::<Victor>().team, -1);
+        assert!(world.resource::<Victor>().engaged);
+
+        // The opponent dies: team 0 wins, and the cue fires exactly once.
+        world.despawn(opponent);
+        stage.run(&mut world);
+        stage.run(&mut world);
+        assert_eq!(world.resource::<Victor>().team, 0);
+        let cues = world
+            .resource::<crate::event::EventQueue>()
+            .0
+            .iter()
+            .filter(|cue| matches!(cue, crate::event::EventCue::BattleEnded(0)))
+            .count();
+        assert_eq!(cues, 1);
+        let _ = soloist;
+    }
+
+    #[test]
+    fn mutual_wipes_are_declared_a_draw() {
+        let mut world = World::default();
+        world.insert_resource(Victor::default());
+        world.insert_resource(crate::event::EventQueue::default());
+        let mut stage = SystemStage::parallel();
+        stage.add_system(update_victor);
+        let a = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        let b = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .id();
+        stage.run(&mut world);
+        world.despawn(a);
+        world.despawn(b);
+        stage.run(&mut world);
+        assert_eq!(world.resource::<Victor>().team, -2);
+    }
+
+    #[test]
+    fn hypnosis_converts_and_reverts_with_the_buff() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.0 });
+        let victim = world
+            .spawn()
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            })
+            .id();
+        world
+            .spawn()
+            .insert(BuffTimer(1.5))
+            .insert(BuffType { is_debuff: true })
+            .insert(TargetEntity(victim))
+            .insert(SetAlignment(2));
+
+        let mut reset = SystemStage::parallel();
+        reset.add_system(reset_alignment);
+        let mut apply = SystemStage::parallel();
+        apply.add_system(set_stats_directly);
+        let mut timer = SystemStage::parallel();
+        timer.add_system(buff_timer);
+
+        // While the buff lives the victim fights for the converting team.
+        reset.run(&mut world);
+        apply.run(&mut world);
+        timer.run(&mut world);
+        assert_eq!(world.get::<TeamAlignment>(victim).unwrap().alignment, 2);
+
+        // Second tick expires and despawns the buff; the next reset pass
+        // reverts the victim and nothing re-applies the alignment.
+        reset.run(&mut world);
+        apply.run(&mut world);
+        timer.run(&mut world);
+        reset.run(&mut world);
+        apply.run(&mut world);
+        assert_eq!(world.get::<TeamAlignment>(victim).unwrap().alignment, 0);
+    }
+
+    #[test]
+    fn attack_speed_buffs_expire_after_their_duration() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.0 });
+        let action = world
+            .spawn()
+            .insert(crate::actions::Cooldown(10.0))
+            .id();
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(UnitActions { vec: vec![action] })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .id();
+        world
+            .get_mut::<ResolveEffectsBuffer>(unit)
+            .unwrap()
+            .vec
+            .push(QueuedEffect {
+                effect: Effect::AttackSpeedBuff {
+                    percent: 0.5,
+                    duration: 2.0,
+                },
+                originator: Entity::from_raw(9999),
+                execute: None,
+            });
+
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut speedup = SystemStage::parallel();
+        speedup.add_system(percent_cooldown_speedup);
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+
+        resolve.run(&mut world);
+        // Two buffed seconds each shave an extra 0.5s off the cooldown.
+        speedup.run(&mut world);
+        timers.run(&mut world);
+        speedup.run(&mut world);
+        timers.run(&mut world);
+        let buffed = world.get::<crate::actions::Cooldown>(action).unwrap().0;
+        assert!((buffed - 9.0).abs() < 1e-3);
+
+        // The buff died with its timer; further seconds recover plain rate.
+        speedup.run(&mut world);
+        assert!((world.get::<crate::actions::Cooldown>(action).unwrap().0 - buffed).abs() < 1e-3);
+    }
+
+    #[test]
+    fn death_splash_spares_the_dying_units_side() {
+        let mut world = World::default();
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let ally = world
+            .spawn()
+            .insert(AppliedDamage { vec: Vec::new() })
+            .id();
+        let enemy = world
+            .spawn()
+            .insert(AppliedDamage { vec: Vec::new() })
+            .id();
+        let bomber = world
+            .spawn()
+            .insert(DeathApproaches)
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(OnDeathEffects {
+                vec: vec![DeathEffect::SplashDamage {
+                    damage: 25.0,
+                    radius: 20.0,
+                }],
+            })
+            .id();
+        let mut spatial = crate::physics::SpatialHashTable::new(64.0);
+        for (entity, x, team) in [(ally, 5.0f32, 1), (enemy, 8.0f32, 2)] {
+            let position = Vector2::new(x, 0.0);
+            let hash = spatial.hash(position);
+            spatial
+                .table
+                .entry(hash)
+                .or_insert_with(Vec::new)
+                .push(crate::physics::SpatialHashEntry {
+                    entity,
+                    position,
+                    radius: 2.0,
+                    team,
+                });
+        }
+        world.insert_resource(spatial);
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(resolve_death);
+        stage.run(&mut world);
+
+        assert!(world.get_entity(bomber).is_none());
+        assert!(world.get::<AppliedDamage>(ally).unwrap().vec.is_empty());
+        assert_eq!(world.get::<AppliedDamage>(enemy).unwrap().vec.len(), 1);
+    }
+
+    #[test]
+    fn death_heal_goes_to_the_side_held_at_death() {
+        let mut world = World::default();
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let make_unit = |world: &mut World, team: i64| {
+            world
+                .spawn()
+                .insert(AppliedDamage { vec: Vec::new() })
+                .insert(Hitpoints {
+                    hp: 50.0,
+                    max_hp: 100.0,
+                })
+                .insert(TeamAlignment {
+                    alignment: team,
+                    alignment_base: team,
+                })
+                .id()
+        };
+        let old_friend = make_unit(&mut world, 1);
+        let new_friend = make_unit(&mut world, 2);
+        // Spawned on team 1, hypnotized onto team 2, dies there.
+        world
+            .spawn()
+            .insert(DeathApproaches)
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(TeamAlignment {
+                alignment: 2,
+                alignment_base: 1,
+            })
+            .insert(OnDeathEffects {
+                vec: vec![DeathEffect::HealAllies {
+                    amount: 10.0,
+                    alignment: 1,
+                }],
+            });
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(resolve_death);
+        stage.run(&mut world);
+
+        assert!(world.get::<AppliedDamage>(old_friend).unwrap().vec.is_empty());
+        assert_eq!(world.get::<AppliedDamage>(new_friend).unwrap().vec.len(), 1);
+    }
+
+    #[test]
+    fn execution_attack_sustains_the_killer_through_kills() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let attacker = damaged_unit(&mut world, 0.0);
+        world.get_mut::<AppliedDamage>(attacker).unwrap().vec.clear();
+        world.get_mut::<Hitpoints>(attacker).unwrap().hp = 40.0;
+        let mut spawn_victim = |world: &mut World| {
+            let victim = damaged_unit(world, 0.0);
+            world.get_mut::<Hitpoints>(victim).unwrap().hp = 5.0;
+            world.get_mut::<AppliedDamage>(victim).unwrap().vec[0].originator = attacker;
+            world.entity_mut(victim).insert(ResolveEffectsBuffer {
+                vec: vec![QueuedEffect {
+                    effect: Effect::HealOnDeathEffect {
+                        amount: 15.0,
+                        target: attacker,
+                    },
+                    originator: attacker,
+                    execute: None,
+                }],
+            });
+            victim
+        };
+        let first = spawn_victim(&mut world);
+        let second = spawn_victim(&mut world);
+
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut damage = SystemStage::parallel();
+        damage.add_system(apply_damages);
+
+        // The mark is staked by the hit, paid only once the kill lands.
+        resolve.run(&mut world);
+        damage.run(&mut world);
+        assert!(world.get::<Hitpoints>(first).unwrap().hp <= 0.0);
+        assert!(world.get::<Hitpoints>(second).unwrap().hp <= 0.0);
+        assert!((world.get::<Hitpoints>(attacker).unwrap().hp - 40.0).abs() < 1e-3);
+
+        // Both payouts were queued as heals; the next pass lands them.
+        damage.run(&mut world);
+        assert!((world.get::<Hitpoints>(attacker).unwrap().hp - 70.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn repeated_armor_shreds_refresh_instead_of_stacking() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.0 });
+        let attacker = world.spawn().id();
+        let rival = world.spawn().id();
+        let victim = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .id();
+        let shred = |world: &mut World, from: Entity| {
+            world
+                .get_mut::<ResolveEffectsBuffer>(victim)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect: Effect::ShredArmorEffect {
+                        duration: 3.0,
+                        texture: Rid::new(),
+                    },
+                    originator: from,
+                    execute: None,
+                });
+        };
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+
+        shred(&mut world, attacker);
+        resolve.run(&mut world);
+        assert_eq!(world.get::<BuffHolder>(victim).unwrap().vec.len(), 1);
+        let buff = world.get::<BuffHolder>(victim).unwrap().vec[0];
+
+        // A second application from the same attacker only winds the timer
+        // back up.
+        timers.run(&mut world);
+        assert!((world.get::<BuffTimer>(buff).unwrap().0 - 2.0).abs() < 1e-3);
+        shred(&mut world, attacker);
+        resolve.run(&mut world);
+        assert_eq!(world.get::<BuffHolder>(victim).unwrap().vec.len(), 1);
+        assert!((world.get::<BuffTimer>(buff).unwrap().0 - 3.0).abs() < 1e-3);
+
+        // A different attacker still gets its own debuff entity.
+        shred(&mut world, rival);
+        resolve.run(&mut world);
+        assert_eq!(world.get::<BuffHolder>(victim).unwrap().vec.len(), 2);
+    }
+
+    #[test]
+    fn poison_splash_debuffs_the_blast_from_the_corpse() {
+        let mut world = World::default();
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let caught = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(6.0, 0.0),
+            })
+            .id();
+        let distant = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(50.0, 0.0),
+            })
+            .id();
+        let bomber = world
+            .spawn()
+            .insert(DeathApproaches)
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(OnDeathEffects {
+                vec: vec![DeathEffect::PoisonSplash {
+                    radius: 10.0,
+                    duration: 3.0,
+                    percent_damage: 0.02,
+                    movement_debuff: 5.0,
+                    texture: Rid::new(),
+                }],
+            })
+            .id();
+        let mut spatial = crate::physics::SpatialHashTable::new(64.0);
+        for (entity, x) in [(caught, 6.0f32), (distant, 50.0f32)] {
+            let position = Vector2::new(x, 0.0);
+            let hash = spatial.hash(position);
+            spatial
+                .table
+                .entry(hash)
+                .or_insert_with(Vec::new)
+                .push(crate::physics::SpatialHashEntry {
+                    entity,
+                    position,
+                    radius: 2.0,
+                    team: 2,
+                });
+        }
+        world.insert_resource(spatial);
+
+        let mut death = SystemStage::parallel();
+        death.add_system(resolve_death);
+        death.run(&mut world);
+        let mut contact = SystemStage::parallel();
+        contact.add_system(crate::projectiles::projectile_contact);
+        contact.run(&mut world);
+
+        let queued = &world.get::<ResolveEffectsBuffer>(caught).unwrap().vec;
+        assert_eq!(queued.len(), 1);
+        assert!(matches!(queued[0].effect, Effect::PoisonEffect { .. }));
+        // Damage attribution points at the dead unit.
+        assert_eq!(queued[0].originator, bomber);
+        assert!(world.get::<ResolveEffectsBuffer>(distant).unwrap().vec.is_empty());
+    }
+
+    #[test]
+    fn lifesteal_heals_from_post_mitigation_damage() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+
+        let attacker = damaged_unit(&mut world, 0.0);
+        world.get_mut::<AppliedDamage>(attacker).unwrap().vec.clear();
+        world.get_mut::<Hitpoints>(attacker).unwrap().hp = 50.0;
+        world
+            .entity_mut(attacker)
+            .insert(LifestealOnHit { percent: 0.5 });
+
+        let victim = damaged_unit(&mut world, 0.0);
+        world.get_mut::<Armor>(victim).unwrap().value = 100.0;
+        {
+            let mut damages = world.get_mut::<AppliedDamage>(victim).unwrap();
+            damages.vec[0].damage = 20.0;
+            damages.vec[0].damage_type = DamageType::Normal;
+            damages.vec[0].originator = attacker;
+        }
+
+        // First pass lands the hit and queues the heal; the second applies it.
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(attacker).unwrap().hp - 50.0).abs() < 1e-3);
+        run_damage(&mut world);
+
+        // 20 damage into 100 armor lands 10; half of *that* comes back, not
+        // half of the raw 20.
+        assert!((world.get::<Hitpoints>(victim).unwrap().hp - 90.0).abs() < 1e-3);
+        assert!((world.get::<Hitpoints>(attacker).unwrap().hp - 55.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn lifesteal_does_not_trigger_off_heals() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+
+        let healer = damaged_unit(&mut world, 0.0);
+        world.get_mut::<AppliedDamage>(healer).unwrap().vec.clear();
+        world
+            .entity_mut(healer)
+            .insert(LifestealOnHit { percent: 0.5 });
+
+        let patient = damaged_unit(&mut world, 0.0);
+        world.get_mut::<Hitpoints>(patient).unwrap().hp = 60.0;
+        {
+            let mut damages = world.get_mut::<AppliedDamage>(patient).unwrap();
+            damages.vec[0].damage = 20.0;
+            damages.vec[0].damage_type = DamageType::Heal;
+            damages.vec[0].originator = healer;
+        }
+
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(patient).unwrap().hp - 80.0).abs() < 1e-3);
+        // Healing an ally queued nothing back on the healer.
+        assert!(world.get::<AppliedDamage>(healer).unwrap().vec.is_empty());
+    }
+
+    #[test]
+    fn absorb_shield_soaks_damage_before_hitpoints() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+
+        let unit = damaged_unit(&mut world, 0.0);
+        world
+            .entity_mut(unit)
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(ResolveEffectsBuffer {
+                vec: vec![QueuedEffect {
+                    effect: Effect::ApplyShieldEffect {
+                        amount: 15.0,
+                        duration: 5.0,
+                    },
+                    originator: unit,
+                    execute: None,
+                }],
+            });
+
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        resolve.run(&mut world);
+
+        assert!(world.get::<ModulateSprite>(unit).is_some());
+        let buff = world.get::<BuffHolder>(unit).unwrap().vec[0];
+
+        // The queued 10 poison sinks into the shield; hitpoints untouched.
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(unit).unwrap().hp - 100.0).abs() < 1e-3);
+        assert!((world.get::<ShieldBuff>(buff).unwrap().remaining - 5.0).abs() < 1e-3);
+
+        // The next 10 drain the shield and spill the remainder through.
+        world
+            .get_mut::<AppliedDamage>(unit)
+            .unwrap()
+            .vec
+            .push(DamageInstance {
+                damage: 10.0,
+                delay: 0.0,
+                damage_type: DamageType::Poison,
+                originator: Entity::from_raw(9999),
+                depth: 0,
+                execute: None,
+            });
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(unit).unwrap().hp - 95.0).abs() < 1e-3);
+
+        // Draining zeroed the shield's timer; buff_timer tears it down and
+        // clears the tint.
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+        timers.run(&mut world);
+        assert!(world.get::<ShieldBuff>(buff).is_none());
+        assert!(world.get::<ModulateSprite>(unit).is_none());
+        assert!(world.get::<BuffHolder>(unit).unwrap().vec.is_empty());
+    }
+
+    #[test]
+    fn summon_effect_leaves_a_directive_for_the_spawner() {
+        let mut world = World::default();
+        let caster = world
+            .spawn()
+            .insert(ResolveEffectsBuffer {
+                vec: vec![QueuedEffect {
+                    effect: Effect::SummonEffect {
+                        blueprint_id: 3,
+                        count: 2,
+                        duration: 8.0,
+                    },
+                    originator: Entity::from_raw(9999),
+                    execute: None,
+                }],
+            })
+            .insert(Position {
+                pos: Vector2::new(40.0, 12.0),
+            })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .id();
+        world.entity_mut(caster).insert(BuffHolder { vec: Vec::new() });
+
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        resolve.run(&mut world);
+
+        let mut query = world.query::<&SummonDirective>();
+        let directives: Vec<SummonDirective> = query.iter(&world).copied().collect();
+        assert_eq!(directives.len(), 1);
+        assert_eq!(directives[0].blueprint_id, 3);
+        assert_eq!(directives[0].count, 2);
+        assert_eq!(directives[0].team, 1);
+        assert!((directives[0].position.x - 40.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn expired_summons_head_into_the_death_path() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.6 });
+        let skeleton = world
+            .spawn()
+            .insert(crate::util::ExpirationTimer(1.0))
+            .id();
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(crate::util::expire_timers);
+        stage.run(&mut world);
+        assert!(world.get::<DeathApproaches>(skeleton).is_none());
+
+        stage.run(&mut world);
+        assert!(world.get::<DeathApproaches>(skeleton).is_some());
+    }
+
+    #[test]
+    fn thorns_reflect_normal_damage_without_ping_pong() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+
+        // Both sides are thorned; only the original hit may reflect.
+        let attacker = damaged_unit(&mut world, 0.0);
+        world.get_mut::<AppliedDamage>(attacker).unwrap().vec.clear();
+        world.entity_mut(attacker).insert(ThornsBuff {
+            percent: 0.5,
+            flat: 0.0,
+        });
+
+        let victim = damaged_unit(&mut world, 0.0);
+        world.entity_mut(victim).insert(ThornsBuff {
+            percent: 0.5,
+            flat: 2.0,
+        });
+        {
+            let mut damages = world.get_mut::<AppliedDamage>(victim).unwrap();
+            damages.vec[0].damage = 20.0;
+            damages.vec[0].damage_type = DamageType::Normal;
+            damages.vec[0].originator = attacker;
+        }
+
+        // The hit lands and queues the reflection back at the attacker.
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(victim).unwrap().hp - 80.0).abs() < 1e-3);
+
+        // Half of the raw 20 plus the flat 2 come back; the attacker's own
+        // thorns stay quiet because the reflection carries depth.
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(attacker).unwrap().hp - 88.0).abs() < 1e-3);
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(victim).unwrap().hp - 80.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn reflected_hits_do_not_feed_lifesteal() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+
+        let attacker = damaged_unit(&mut world, 0.0);
+        world.get_mut::<AppliedDamage>(attacker).unwrap().vec.clear();
+
+        // The victim is thorned and lifestealing; its reflection carries
+        // depth, so it hurts the attacker but must heal nothing back.
+        let victim = damaged_unit(&mut world, 0.0);
+        world.get_mut::<Hitpoints>(victim).unwrap().hp = 50.0;
+        world.entity_mut(victim).insert(ThornsBuff {
+            percent: 0.5,
+            flat: 0.0,
+        });
+        world
+            .entity_mut(victim)
+            .insert(LifestealOnHit { percent: 0.5 });
+        {
+            let mut damages = world.get_mut::<AppliedDamage>(victim).unwrap();
+            damages.vec[0].damage = 20.0;
+            damages.vec[0].damage_type = DamageType::Normal;
+            damages.vec[0].originator = attacker;
+        }
+
+        // The hit lands and queues the reflection back at the attacker.
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(victim).unwrap().hp - 30.0).abs() < 1e-3);
+
+        // The reflection lands at depth 1, so the victim's lifesteal stays
+        // quiet: no heal ever enters the queue.
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(attacker).unwrap().hp - 90.0).abs() < 1e-3);
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(victim).unwrap().hp - 30.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn blink_clamps_to_pathable_terrain_and_breaks_casts() {
+        let mut world = World::default();
+        // A wall column at cell x = 5 sits inside the full blink line.
+        let mut terrain = crate::terrain::TerrainMap::new(8, 8, 36.0);
+        for y in 0..8 {
+            terrain.set_cell(5, y, 0);
+        }
+        world.insert_resource(terrain);
+        // The flow field pulls team 0 straight right everywhere.
+        let mut fields = crate::terrain::FlowFieldsTowardsEnemies::default();
+        fields.map.insert(
+            0,
+            crate::terrain::FlowField {
+                flow: vec![Vector2::new(1.0, 0.0); 64],
+                integration: vec![0.0; 64],
+            },
+        );
+        world.insert_resource(fields);
+
+        let action = world.spawn().id();
+        let caster = world
+            .spawn()
+            .insert(Position {
+                pos: Vector2::new(54.0, 54.0),
+            })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(BlinkDirective {
+                distance: 144.0,
+                texture: Rid::new(),
+            })
+            .insert(crate::actions::PerformingActionState { action })
+            .id();
+
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_blinks);
+        resolve.run(&mut world);
+        let mut movement = SystemStage::parallel();
+        movement.add_system(crate::physics::apply_teleport);
+        movement.run(&mut world);
+
+        // The full 144 would land in the wall; the destination walked back
+        // to the last pathable cell along the line.
+        let position = world.get::<Position>(caster).unwrap();
+        assert!((position.pos.y - 54.0).abs() < 1e-3);
+        assert!(position.pos.x > 54.0 && position.pos.x < 180.0);
+        let terrain = world.resource::<crate::terrain::TerrainMap>();
+        assert_ne!(terrain.get_cell(position.pos), 0);
+        // The wind-up broke and the directives were consumed.
+        assert!(world
+            .get::<crate::actions::PerformingActionState>(caster)
+            .is_none());
+        assert!(world.get::<BlinkDirective>(caster).is_none());
+        assert!(world.get::<TeleportDirective>(caster).is_none());
+        // One visual at the origin, one at the destination.
+        let mut visuals = world.query::<(&crate::graphics::NewCanvasItemDirective, &Position)>();
+        assert_eq!(visuals.iter(&world).count(), 2);
+    }
+
+    #[test]
+    fn overlapping_auras_stack_but_one_carrier_never_doubles() {
+        let mut world = World::default();
+        world.insert_resource(crate::physics::Clock { tick: 0 });
+
+        let aura = Aura {
+            buff: StatBuff {
+                speed_buff: 10.0,
+                ..Default::default()
+            },
+            radius: 50.0,
+            affects_allies: true,
+        };
+        let ally = world.spawn().insert(BuffHolder { vec: Vec::new() }).id();
+        let mut carriers = Vec::new();
+        for _ in 0..2 {
+            let carrier = world
+                .spawn()
+                .insert(aura)
+                .insert(TeamAlignment {
+                    alignment: 0,
+                    alignment_base: 0,
+                })
+                .id();
+            carriers.push(carrier);
+        }
+        let mut cache = crate::physics::SpatialNeighborsCache::default();
+        for carrier in carriers.iter() {
+            cache.map.insert(
+                *carrier,
+                vec![crate::physics::SpatialNeighbor {
+                    entity: ally,
+                    distance: 30.0,
+                    team: 0,
+                }],
+            );
+        }
+        world.insert_resource(cache);
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(apply_auras);
+        stage.run(&mut world);
+
+        // One buff per carrier, each carrying the aura's stats.
+        let holder = world.get::<BuffHolder>(ally).unwrap();
+        assert_eq!(holder.vec.len(), 2);
+        let buffs: Vec<Entity> = holder.vec.clone();
+        for buff in buffs.iter() {
+            assert!((world.get::<StatBuff>(*buff).unwrap().speed_buff - 10.0).abs() < 1e-6);
+            assert!(world.get::<AuraBuff>(*buff).is_some());
+        }
+
+        // A second pulse refreshes the running timers instead of stacking.
+        world.get_mut::<BuffTimer>(buffs[0]).unwrap().0 = 0.1;
+        stage.run(&mut world);
+        let holder = world.get::<BuffHolder>(ally).unwrap();
+        assert_eq!(holder.vec.len(), 2);
+        assert!((world.get::<BuffTimer>(buffs[0]).unwrap().0 - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn burns_tick_flat_magic_damage_and_refresh_per_source() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.5 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+
+        let victim = damaged_unit(&mut world, 0.0);
+        world.get_mut::<AppliedDamage>(victim).unwrap().vec.clear();
+        world.get_mut::<MagicResist>(victim).unwrap().value = 100.0;
+        world.entity_mut(victim).insert(BuffHolder { vec: Vec::new() });
+
+        let burn = |originator: Entity| QueuedEffect {
+            effect: Effect::BurnEffect {
+                damage_per_second: 10.0,
+                duration: 3.0,
+                texture: Rid::new(),
+            },
+            originator,
+            execute: None,
+        };
+        world.entity_mut(victim).insert(ResolveEffectsBuffer {
+            vec: vec![burn(Entity::from_raw(77))],
+        });
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        resolve.run(&mut world);
+        assert_eq!(world.get::<BuffHolder>(victim).unwrap().vec.len(), 1);
+
+        // The same attacker refreshes the running burn; a different one
+        // stacks its own.
+        let buff = world.get::<BuffHolder>(victim).unwrap().vec[0];
+        world.get_mut::<BuffTimer>(buff).unwrap().0 = 0.5;
+        world
+            .get_mut::<ResolveEffectsBuffer>(victim)
+            .unwrap()
+            .vec
+            .push(burn(Entity::from_raw(77)));
+        resolve.run(&mut world);
+        assert_eq!(world.get::<BuffHolder>(victim).unwrap().vec.len(), 1);
+        assert!((world.get::<BuffTimer>(buff).unwrap().0 - 3.0).abs() < 1e-6);
+
+        world
+            .get_mut::<ResolveEffectsBuffer>(victim)
+            .unwrap()
+            .vec
+            .push(burn(Entity::from_raw(78)));
+        resolve.run(&mut world);
+        assert_eq!(world.get::<BuffHolder>(victim).unwrap().vec.len(), 2);
+
+        // Two burns at 10/s over half a second, halved by 100 magic resist.
+        let mut tick = SystemStage::parallel();
+        tick.add_system(flat_damage_over_time);
+        tick.run(&mut world);
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(victim).unwrap().hp - 95.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn dashes_override_velocity_and_impact_on_contact() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        let mut terrain = crate::terrain::TerrainMap::new(8, 8, 36.0);
+        terrain.set_cell(2, 2, 0);
+        world.insert_resource(terrain);
+
+        let target = world
+            .spawn()
+            .insert(Position {
+                pos: Vector2::new(150.0, 50.0),
+            })
+            .insert(crate::physics::Radius { r: 10.0 })
+            .insert(crate::physics::Velocity { v: Vector2::ZERO })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .id();
+        let dasher = world
+            .spawn()
+            .insert(Position {
+                pos: Vector2::new(50.0, 50.0),
+            })
+            .insert(crate::physics::Radius { r: 10.0 })
+            .insert(crate::physics::Velocity { v: Vector2::ZERO })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .insert(Dashing {
+                target,
+                dash_speed: 100.0,
+                remaining: 500.0,
+                impact_damage: 20.0,
+                impact_stun: 1.0,
+                stun_texture: Rid::new(),
+            })
+            .id();
+        // This one dashes straight into the wall cell at (2, 2).
+        let wall_target = world
+            .spawn()
+            .insert(Position {
+                pos: Vector2::new(290.0, 90.0),
+            })
+            .insert(crate::physics::Radius { r: 10.0 })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .id();
+        let wall_dasher = world
+            .spawn()
+            .insert(Position {
+                pos: Vector2::new(68.0, 90.0),
+            })
+            .insert(crate::physics::Radius { r: 10.0 })
+            .insert(crate::physics::Velocity {
+                v: Vector2::new(0.0, 5.0),
+            })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .insert(Dashing {
+                target: wall_target,
+                dash_speed: 100.0,
+                remaining: 500.0,
+                impact_damage: 20.0,
+                impact_stun: 0.0,
+                stun_texture: Rid::new(),
+            })
+            .id();
+
+        let mut prepare = SystemStage::parallel();
+        prepare.add_system(crate::physics::build_spatial_hash);
+        let mut contact = SystemStage::parallel();
+        contact.add_system(apply_dashes);
+
+        prepare.run(&mut world);
+        contact.run(&mut world);
+
+        // The open dash tramples whatever velocity was there before.
+        let v = world.get::<crate::physics::Velocity>(dasher).unwrap().v;
+        assert!((v - Vector2::new(100.0, 0.0)).length() < 1e-3);
+        assert!(world.get::<Dashing>(dasher).is_some());
+        // The wall dash stopped short of the unpathable cell, no impact.
+        assert!(world.get::<Dashing>(wall_dasher).is_none());
+        assert_eq!(
+            world
+                .get::<crate::physics::Velocity>(wall_dasher)
+                .unwrap()
+                .v,
+            Vector2::ZERO
+        );
+
+        // Close the gap until the bodies touch; contact delivers the hit.
+        world.get_mut::<Position>(dasher).unwrap().pos = Vector2::new(135.0, 50.0);
+        prepare.run(&mut world);
+        contact.run(&mut world);
+        let buffer = world.get::<ResolveEffectsBuffer>(target).unwrap();
+        assert_eq!(buffer.vec.len(), 2);
+        assert!(matches!(
+            buffer.vec[0].effect,
+            Effect::DamageEffect { damage, .. } if (damage - 20.0).abs() < 1e-6
+        ));
+        assert!(matches!(
+            buffer.vec[1].effect,
+            Effect::StunEffect { duration, .. } if (duration - 1.0).abs() < 1e-6
+        ));
+        assert!(world.get::<Dashing>(dasher).is_none());
+        assert_eq!(
+            world.get::<crate::physics::Velocity>(dasher).unwrap().v,
+            Vector2::ZERO
+        );
+    }
+
+    #[test]
+    fn execute_bonus_applies_strictly_below_the_threshold() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let unit = damaged_unit(&mut world, 0.0);
+        let execute = ExecuteDamage {
+            threshold_percent: 0.5,
+            bonus_multiplier: 3.0,
+        };
+        world.get_mut::<Hitpoints>(unit).unwrap().hp = 50.0;
+        world.get_mut::<AppliedDamage>(unit).unwrap().vec[0].execute = Some(execute);
+
+        // Exactly at the threshold is not "below": the hit stays ordinary.
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(unit).unwrap().hp - 40.0).abs() < 1e-3);
+
+        // At 40% of max hp the same hit resolves amplified.
+        world
+            .get_mut::<AppliedDamage>(unit)
+            .unwrap()
+            .vec
+            .push(DamageInstance {
+                damage: 10.0,
+                delay: 0.0,
+                damage_type: DamageType::Poison,
+                originator: Entity::from_raw(9999),
+                depth: 0,
+                execute: Some(execute),
+            });
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(unit).unwrap().hp - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn berserk_surge_flips_once_per_threshold_crossing() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        let unit = world
+            .spawn()
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .insert(BerserkPassive {
+                hp_threshold_percent: 0.3,
+                attack_speed_bonus: 0.5,
+                speed_bonus: 20.0,
+            })
+            .insert(BuffHolder { vec: Vec::new() })
+            .id();
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(berserk_passive);
+
+        // Healthy: no surge.
+        stage.run(&mut world);
+        assert!(world.get::<Berserking>(unit).is_none());
+
+        // Below the line: exactly one surge, however many ticks pass.
+        world.get_mut::<Hitpoints>(unit).unwrap().hp = 20.0;
+        stage.run(&mut world);
+        stage.run(&mut world);
+        let buffs = world.get::<Berserking>(unit).unwrap().buffs.clone();
+        assert_eq!(buffs.len(), 1);
+        assert_eq!(world.get::<BuffHolder>(unit).unwrap().vec.len(), 1);
+
+        // Healed back above: the marker drops and the buff timer zeroes so
+        // `buff_timer` reaps the surge on its usual path.
+        world.get_mut::<Hitpoints>(unit).unwrap().hp = 80.0;
+        stage.run(&mut world);
+        assert!(world.get::<Berserking>(unit).is_none());
+        assert!(world.get::<BuffTimer>(buffs[0]).unwrap().0 <= 0.0);
+
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+        timers.run(&mut world);
+        assert!(world.get::<BuffHolder>(unit).unwrap().vec.is_empty());
+    }
+
+    #[test]
+    fn stacking_poison_grows_one_buff_to_its_cap() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.0 });
+        let attacker = world.spawn().id();
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(AppliedDamage { vec: Vec::new() })
+            .insert(Speed {
+                base: 50.0,
+                value: 50.0,
+            })
+            .insert(Armor {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(MagicResist {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(Acceleration {
+                base: 10.0,
+                value: 10.0,
+            })
+            .insert(HealEfficacy(1.0))
+            .insert(crate::physics::Mass(4.0))
+            .insert(BaseMass(4.0))
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .id();
+        let poison = |world: &mut World| {
+            world
+                .get_mut::<ResolveEffectsBuffer>(unit)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect: Effect::PoisonEffect {
+                        percent_damage: 0.02,
+                        movement_debuff: 10.0,
+                        duration: 3.0,
+                        texture: Rid::new(),
+                    },
+                    originator: attacker,
+                    execute: None,
+                });
+        };
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut stats = SystemStage::parallel();
+        stats.add_system(apply_stat_buffs);
+
+        poison(&mut world);
+        resolve.run(&mut world);
+        stats.run(&mut world);
+        assert_eq!(world.get::<BuffHolder>(unit).unwrap().vec.len(), 1);
+        let buff = world.get::<BuffHolder>(unit).unwrap().vec[0];
+        assert_eq!(world.get::<BuffStacks>(buff).unwrap().count, 1);
+        assert!((world.get::<Speed>(unit).unwrap().value - 40.0).abs() < 1e-3);
+
+        // Three more hits: still one buff entity, stacks capped at three,
+        // with the slow multiplied per stack.
+        for _ in 0..3 {
+            poison(&mut world);
+            resolve.run(&mut world);
+        }
+        stats.run(&mut world);
+        assert_eq!(world.get::<BuffHolder>(unit).unwrap().vec.len(), 1);
+        assert_eq!(world.get::<BuffStacks>(buff).unwrap().count, 3);
+        assert!((world.get::<Speed>(unit).unwrap().value - 20.0).abs() < 1e-3);
+
+        // The damage-over-time tick scales with the stacks too.
+        let mut dot = SystemStage::parallel();
+        dot.add_system(percent_damage_over_time);
+        dot.run(&mut world);
+        let applied = world.get::<AppliedDamage>(unit).unwrap();
+        assert_eq!(applied.vec.len(), 1);
+        assert!((applied.vec[0].damage - 6.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn independent_stuns_spawn_their_own_timers() {
+        let mut world = World::default();
+        let attacker = world.spawn().id();
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .id();
+        for duration in [1.0, 2.0] {
+            world
+                .get_mut::<ResolveEffectsBuffer>(unit)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect: Effect::StunEffect {
+                        duration,
+                        texture: Rid::new(),
+                    },
+                    originator: attacker,
+                    execute: None,
+                });
+            let mut resolve = SystemStage::parallel();
+            resolve.add_system(resolve_effects);
+            resolve.run(&mut world);
+        }
+
+        // Same source, same kind — but stuns never merge.
+        let holder = world.get::<BuffHolder>(unit).unwrap();
+        assert_eq!(holder.vec.len(), 2);
+        let (first, second) = (holder.vec[0], holder.vec[1]);
+        assert!((world.get::<BuffTimer>(first).unwrap().0 - 1.0).abs() < 1e-3);
+        assert!((world.get::<BuffTimer>(second).unwrap().0 - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn chained_stuns_fall_off_and_hit_immunity() {
+        let mut world = World::default();
+        world.insert_resource(CrowdControlTuning::default());
+        let attacker = world.spawn().id();
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .id();
+        let stun = |world: &mut World| {
+            world
+                .get_mut::<ResolveEffectsBuffer>(unit)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect: Effect::StunEffect {
+                        duration: 2.0,
+                        texture: Rid::new(),
+                    },
+                    originator: attacker,
+                    execute: None,
+                });
+            let mut resolve = SystemStage::parallel();
+            resolve.add_system(resolve_effects);
+            resolve.run(world);
+        };
+
+        // 100% / 50% / 25% of the two-second base, then nothing at all.
+        let expected = [2.0, 1.0, 0.5];
+        for (hits, duration) in expected.iter().enumerate() {
+            stun(&mut world);
+            let holder = world.get::<BuffHolder>(unit).unwrap();
+            assert_eq!(holder.vec.len(), hits + 1);
+            let buff = holder.vec[hits];
+            assert!((world.get::<BuffTimer>(buff).unwrap().0 - duration).abs() < 1e-3);
+        }
+        stun(&mut world);
+        assert_eq!(world.get::<BuffHolder>(unit).unwrap().vec.len(), 3);
+        // The swallowed stun still counted as exposure and refreshed the
+        // window — an immune target cannot be "primed" free by more stuns.
+        let resistance = world.get::<CrowdControlResistance>(unit).unwrap();
+        assert_eq!(resistance.exposures, 4);
+        assert!((resistance.window_remaining - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn crowd_control_window_lapse_resets_the_falloff() {
+        let mut world = World::default();
+        world.insert_resource(CrowdControlTuning::default());
+        world.insert_resource(DeltaPhysics { seconds: 4.0 });
+        let attacker = world.spawn().id();
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .id();
+        let stun = |world: &mut World| {
+            world
+                .get_mut::<ResolveEffectsBuffer>(unit)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect: Effect::StunEffect {
+                        duration: 2.0,
+                        texture: Rid::new(),
+                    },
+                    originator: attacker,
+                    execute: None,
+                });
+            let mut resolve = SystemStage::parallel();
+            resolve.add_system(resolve_effects);
+            resolve.run(world);
+        };
+        let mut decay = SystemStage::parallel();
+        decay.add_system(crowd_control_decay);
+
+        // Four seconds into the window a second stun lands halved.
+        stun(&mut world);
+        decay.run(&mut world);
+        assert!(world.get::<CrowdControlResistance>(unit).is_some());
+        stun(&mut world);
+        let second = *world.get::<BuffHolder>(unit).unwrap().vec.last().unwrap();
+        assert!((world.get::<BuffTimer>(second).unwrap().0 - 1.0).abs() < 1e-3);
+
+        // Let the refreshed window run out; the history comes off and the
+        // next stun starts the falloff over at full duration.
+        world.insert_resource(DeltaPhysics { seconds: 10.5 });
+        decay.run(&mut world);
+        assert!(world.get::<CrowdControlResistance>(unit).is_none());
+        stun(&mut world);
+        let third = *world.get::<BuffHolder>(unit).unwrap().vec.last().unwrap();
+        assert!((world.get::<BuffTimer>(third).unwrap().0 - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn immunity_blocks_the_flagged_debuff_but_not_the_rest() {
+        let mut world = World::default();
+        world.insert_resource(EventQueue::default());
+        let attacker = world.spawn().id();
+        let unit = world
+            .spawn()
+            .insert(DebuffImmunities(DebuffImmunities::STUN))
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(Position {
+                pos: Vector2::new(5.0, 5.0),
+            })
+            .id();
+        // One attack delivering a stun and a slow-poison together.
+        {
+            let mut buffer = world.get_mut::<ResolveEffectsBuffer>(unit).unwrap();
+            buffer.vec.push(QueuedEffect {
+                effect: Effect::StunEffect {
+                    duration: 1.0,
+                    texture: Rid::new(),
+                },
+                originator: attacker,
+                execute: None,
+            });
+            buffer.vec.push(QueuedEffect {
+                effect: Effect::PoisonEffect {
+                    percent_damage: 0.1,
+                    movement_debuff: 10.0,
+                    duration: 3.0,
+                    texture: Rid::new(),
+                },
+                originator: attacker,
+                execute: None,
+            });
+        }
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        resolve.run(&mut world);
+
+        // The stun never landed, the poison did, and the UI got its cue.
+        assert!(world.get::<Stunned>(unit).is_none());
+        assert_eq!(world.get::<BuffHolder>(unit).unwrap().vec.len(), 1);
+        let queue = world.resource::<EventQueue>();
+        assert!(queue
+            .0
+            .iter()
+            .any(|cue| matches!(cue, EventCue::Audio(audio) if audio.kind == "immune")));
+    }
+
+    #[test]
+    fn kills_attribute_to_the_cached_last_hit_after_the_killer_dies() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        world.insert_resource(crate::physics::Clock { tick: 42 });
+        let attacker = world
+            .spawn()
+            .insert(BlueprintId(3))
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(UnitActions { vec: Vec::new() })
+            .id();
+        let victim = damaged_unit(&mut world, 0.0);
+        world.get_mut::<AppliedDamage>(victim).unwrap().vec[0].originator = attacker;
+        world.entity_mut(victim).insert(BlueprintId(7));
+
+        run_damage(&mut world);
+        let last = world.get::<LastDamagedBy>(victim).unwrap();
+        assert_eq!(last.entity, attacker);
+        assert_eq!(last.blueprint, 3);
+        assert_eq!(last.team, 1);
+        assert_eq!(last.at_clock, 42);
+
+        // The killer is gone when the lethal over-time tick lands; the
+        // cached attribution still names it.
+        world.despawn(attacker);
+        world
+            .get_mut::<AppliedDamage>(victim)
+            .unwrap()
+            .vec
+            .push(DamageInstance {
+                damage: 200.0,
+                delay: 0.0,
+                damage_type: DamageType::Poison,
+                originator: attacker,
+                depth: 0,
+                execute: None,
+            });
+        run_damage(&mut world);
+        let mut death = SystemStage::parallel();
+        death.add_system(resolve_death);
+        death.run(&mut world);
+
+        let queue = world.resource::<EventQueue>();
+        let kill = queue
+            .0
+            .iter()
+            .find_map(|cue| match cue {
+                EventCue::Kill(kill) => Some(kill),
+                _ => None,
+            })
+            .expect("a kill cue for the victim");
+        assert_eq!(kill.victim, victim);
+        assert_eq!(kill.victim_blueprint, 7);
+        assert_eq!(kill.killer, attacker);
+        assert_eq!(kill.killer_blueprint, 3);
+        assert_eq!(kill.killer_team, 1);
+        assert_eq!(kill.at_clock, 42);
+        assert!(world.get_entity(victim).is_none());
+    }
+
+    #[test]
+    fn divine_shield_grants_blanket_immunity_while_held() {
+        let mut world = World::default();
+        let attacker = world.spawn().id();
+        let shield = world
+            .spawn()
+            .insert(BuffTimer(2.0))
+            .insert(DivineShieldBuff)
+            .id();
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: vec![shield] })
+            .insert(AppliedDamage { vec: Vec::new() })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        // Hypnosis is blocked outright — no SetAlignment buff, not just no
+        // visual — while the friendly heal still goes through.
+        {
+            let mut buffer = world.get_mut::<ResolveEffectsBuffer>(unit).unwrap();
+            buffer.vec.push(QueuedEffect {
+                effect: Effect::Hypnosis {
+                    new_alignment: 1,
+                    duration: 3.0,
+                },
+                originator: attacker,
+                execute: None,
+            });
+            buffer.vec.push(QueuedEffect {
+                effect: Effect::HealEffect { amount: 10.0 },
+                originator: attacker,
+                execute: None,
+            });
+        }
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        resolve.run(&mut world);
+
+        assert_eq!(world.get::<BuffHolder>(unit).unwrap().vec.len(), 1);
+        let applied = world.get::<AppliedDamage>(unit).unwrap();
+        assert_eq!(applied.vec.len(), 1);
+        assert!(matches!(applied.vec[0].damage_type, DamageType::Heal));
+    }
+
+    #[test]
+    fn overcapped_antiheal_zeroes_healing_instead_of_inverting_it() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let attacker = world.spawn().id();
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(AppliedDamage { vec: Vec::new() })
+            .insert(Speed {
+                base: 50.0,
+                value: 50.0,
+            })
+            .insert(Armor {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(MagicResist {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(Acceleration {
+                base: 10.0,
+                value: 10.0,
+            })
+            .insert(HealEfficacy(1.0))
+            .insert(crate::physics::Mass(4.0))
+            .insert(BaseMass(4.0))
+            .insert(Hitpoints {
+                hp: 50.0,
+                max_hp: 100.0,
+            })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(Position {
+                pos: Vector2::new(0.0, 0.0),
+            })
+            .id();
+
+        // A 150% antiheal from one fast attacker, landing twice: the second
+        // hit refreshes the first buff instead of stacking a twin.
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        for _ in 0..2 {
+            world
+                .get_mut::<ResolveEffectsBuffer>(unit)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect: Effect::AntihealOnHitEffect {
+                        percent: 1.5,
+                        duration: 3.0,
+                        texture: Rid::new(),
+                    },
+                    originator: attacker,
+                    execute: None,
+                });
+            resolve.run(&mut world);
+        }
+        assert_eq!(world.get::<BuffHolder>(unit).unwrap().vec.len(), 1);
+
+        let mut stats = SystemStage::parallel();
+        stats.add_system(apply_stat_buffs);
+        stats.run(&mut world);
+        assert!(world.get::<HealEfficacy>(unit).unwrap().0.abs() < 1e-6);
+
+        // A heal under the clamped efficacy does nothing — and never hurts.
+        world
+            .get_mut::<AppliedDamage>(unit)
+            .unwrap()
+            .vec
+            .push(DamageInstance {
+                damage: 20.0,
+                delay: 0.0,
+                damage_type: DamageType::Heal,
+                originator: attacker,
+                depth: 0,
+                execute: None,
+            });
+        run_damage(&mut world);
+        assert!((world.get::<Hitpoints>(unit).unwrap().hp - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn flat_and_percent_stat_buffs_compose_in_order() {
+        let mut world = World::default();
+        let unit = world
+            .spawn()
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(Speed {
+                base: 50.0,
+                value: 50.0,
+            })
+            .insert(Armor {
+                base: 10.0,
+                value: 10.0,
+            })
+            .insert(MagicResist {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(Acceleration {
+                base: 10.0,
+                value: 10.0,
+            })
+            .insert(HealEfficacy(1.0))
+            .insert(crate::physics::Mass(4.0))
+            .insert(BaseMass(4.0))
+            .id();
+        let flat = world
+            .spawn()
+            .insert(StatBuff {
+                speed_buff: 10.0,
+                armor_buff: 5.0,
+                acceleration_buff: 2.0,
+                ..Default::default()
+            })
+            .id();
+        let percent = world
+            .spawn()
+            .insert(StatBuff {
+                speed_mult: 0.2,
+                armor_mult: -0.5,
+                acceleration_mult: 0.1,
+                mass_mult: 0.25,
+                ..Default::default()
+            })
+            .id();
+        world
+            .get_mut::<BuffHolder>(unit)
+            .unwrap()
+            .vec
+            .extend([flat, percent]);
+
+        let mut stats = SystemStage::parallel();
+        stats.add_system(apply_stat_buffs);
+        stats.run(&mut world);
+
+        // (50 + 10) * 1.2, (10 + 5) * 0.5, (10 + 2) * 1.1 and 4 * 1.25: the
+        // flat pieces sum before any percent factor touches them.
+        assert!((world.get::<Speed>(unit).unwrap().value - 72.0).abs() < 1e-3);
+        assert!((world.get::<Armor>(unit).unwrap().value - 7.5).abs() < 1e-3);
+        assert!((world.get::<Acceleration>(unit).unwrap().value - 13.2).abs() < 1e-3);
+        assert!((world.get::<crate::physics::Mass>(unit).unwrap().0 - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn max_hp_buff_expiry_at_full_and_partial_health() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.1 });
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(Speed {
+                base: 50.0,
+                value: 50.0,
+            })
+            .insert(Armor {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(MagicResist {
+                base: 0.0,
+                value: 0.0,
+            })
+            .insert(Acceleration {
+                base: 10.0,
+                value: 10.0,
+            })
+            .insert(HealEfficacy(1.0))
+            .insert(crate::physics::Mass(4.0))
+            .insert(BaseMass(4.0))
+            .insert(BaseMaxHp(100.0))
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .id();
+        let vitality = |world: &mut World| {
+            world
+                .get_mut::<ResolveEffectsBuffer>(unit)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect: Effect::ApplyStatBuffEffect {
+                        buff: StatBuff {
+                            max_hp_buff: 50.0,
+                            ..Default::default()
+                        },
+                        duration: 1.0,
+                        texture: Rid::new(),
+                    },
+                    originator: Entity::from_raw(9999),
+                    execute: None,
+                });
+        };
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut stats = SystemStage::parallel();
+        stats.add_system(apply_stat_buffs);
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+
+        // A full-health unit stays full through the buff and its expiry.
+        vitality(&mut world);
+        resolve.run(&mut world);
+        stats.run(&mut world);
+        let hitpoints = *world.get::<Hitpoints>(unit).unwrap();
+        assert!((hitpoints.max_hp - 150.0).abs() < 1e-3);
+        assert!((hitpoints.hp - 150.0).abs() < 1e-3);
+        timers.run(&mut world);
+        stats.run(&mut world);
+        let hitpoints = *world.get::<Hitpoints>(unit).unwrap();
+        assert!((hitpoints.max_hp - 100.0).abs() < 1e-3);
+        assert!((hitpoints.hp - 100.0).abs() < 1e-3);
+
+        // A wounded unit keeps its injured fraction: half of 150 becomes
+        // half of 100 when the buff runs out.
+        vitality(&mut world);
+        resolve.run(&mut world);
+        stats.run(&mut world);
+        world.get_mut::<Hitpoints>(unit).unwrap().hp = 75.0;
+        timers.run(&mut world);
+        stats.run(&mut world);
+        let hitpoints = *world.get::<Hitpoints>(unit).unwrap();
+        assert!((hitpoints.max_hp - 100.0).abs() < 1e-3);
+        assert!((hitpoints.hp - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn attack_slow_drags_cooldowns_and_cleanses_away() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.0 });
+        let action = world
+            .spawn()
+            .insert(ActionCooldown(3.0))
+            .insert(Cooldown(2.5))
+            .id();
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(UnitActions { vec: vec![action] })
+            .id();
+        world
+            .get_mut::<ResolveEffectsBuffer>(unit)
+            .unwrap()
+            .vec
+            .push(QueuedEffect {
+                effect: Effect::AttackSlowEffect {
+                    percent: 1.0,
+                    duration: 5.0,
+                },
+                originator: Entity::from_raw(9999),
+                execute: None,
+            });
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut slow = SystemStage::parallel();
+        slow.add_system(percent_cooldown_slowdown);
+
+        resolve.run(&mut world);
+        assert_eq!(world.get::<BuffHolder>(unit).unwrap().vec.len(), 1);
+
+        // One tick gives a full second of cooldown back, but never past the
+        // action's own base cooldown.
+        slow.run(&mut world);
+        assert!((world.get::<Cooldown>(action).unwrap().0 - 3.0).abs() < 1e-3);
+        world.get_mut::<Cooldown>(action).unwrap().0 = 1.0;
+        slow.run(&mut world);
+        assert!((world.get::<Cooldown>(action).unwrap().0 - 2.0).abs() < 1e-3);
+
+        // The slow is an ordinary debuff; cleanse tears it down on the spot
+        // and empties the holder.
+        let buff = world.get::<BuffHolder>(unit).unwrap().vec[0];
+        world
+            .get_mut::<ResolveEffectsBuffer>(unit)
+            .unwrap()
+            .vec
+            .push(QueuedEffect {
+                effect: Effect::CleanseEffect,
+                originator: Entity::from_raw(9999),
+                execute: None,
+            });
+        resolve.run(&mut world);
+        assert!(world.get::<BuffHolder>(unit).unwrap().vec.is_empty());
+        assert!(world.get_entity(buff).is_none());
+    }
+
+    #[test]
+    fn mines_arm_then_detonate_on_enemies_with_falloff() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.5 });
+        let unit = |world: &mut World, x: f32| {
+            world
+                .spawn()
+                .insert(Position {
+                    pos: Vector2::new(x, 0.0),
+                })
+                .insert(Hitpoints {
+                    hp: 100.0,
+                    max_hp: 100.0,
+                })
+                .insert(ResolveEffectsBuffer { vec: Vec::new() })
+                .id()
+        };
+        let enemy_near = unit(&mut world, 2.0);
+        let enemy_far = unit(&mut world, 8.0);
+        let ally = unit(&mut world, 1.0);
+        let mine = world
+            .spawn()
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(Mine {
+                damage: 20.0,
+                trigger_radius: 10.0,
+                armed_in: 0.4,
+            })
+            .insert(Splash {
+                radius: 10.0,
+                min_percent: 0.5,
+            })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .id();
+        let mut spatial = crate::physics::SpatialHashTable::new(64.0);
+        for (entity, x, team) in [(enemy_near, 2.0f32, 2), (enemy_far, 8.0, 2), (ally, 1.0, 1)] {
+            let position = Vector2::new(x, 0.0);
+            let hash = spatial.hash(position);
+            spatial
+                .table
+                .entry(hash)
+                .or_default()
+                .push(crate::physics::SpatialHashEntry {
+                    entity,
+                    position,
+                    radius: 0.0,
+                    team,
+                });
+        }
+        world.insert_resource(spatial);
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(mine_trigger);
+        // The first tick only burns down the arm timer; nobody is hit.
+        stage.run(&mut world);
+        assert!(world.get::<DeathApproaches>(mine).is_none());
+        assert!(world
+            .get::<ResolveEffectsBuffer>(enemy_near)
+            .unwrap()
+            .vec
+            .is_empty());
+
+        // Armed now: the next tick detonates on the enemies only, with the
+        // usual splash falloff from the mine.
+        stage.run(&mut world);
+        assert!(world.get::<DeathApproaches>(mine).is_some());
+        let damage_on = |world: &World, entity| match world
+            .get::<ResolveEffectsBuffer>(entity)
+            .unwrap()
+            .vec
+            .as_slice()
+        {
+            [QueuedEffect {
+                effect: Effect::DamageEffect { damage, .. },
+                ..
+            }] => *damage,
+            other => panic!("expected one damage effect, got {}", other.len()),
+        };
+        assert!((damage_on(&world, enemy_near) - 18.0).abs() < 1e-3);
+        assert!((damage_on(&world, enemy_far) - 12.0).abs() < 1e-3);
+        assert!(world.get::<ResolveEffectsBuffer>(ally).unwrap().vec.is_empty());
+    }
+
+    #[test]
+    fn overlapping_damage_buffs_expire_back_to_baseline() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.6 });
+        world.insert_resource(crate::physics::SpatialNeighborsCache::default());
+        let victim = world
+            .spawn()
+            .insert(Position {
+                pos: Vector2::new(2.0, 0.0),
+            })
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .id();
+        let action = world
+            .spawn()
+            .insert(crate::actions::SwingDetails {
+                impact_time: 0.5,
+                swing_time: 1.0,
+            })
+            .insert(crate::actions::ImpactType::Instant)
+            .insert(crate::actions::OnHitEffects {
+                vec: vec![Effect::DamageEffect {
+                    damage: 10.0,
+                    delay: 0.0,
+                    damage_type: DamageType::Normal,
+                }],
+            })
+            .insert(crate::actions::ChannelingDetails {
+                total_time_channeled: 0.0,
+            })
+            .insert(TargetEntity(victim))
+            .id();
+        let attacker = world
+            .spawn()
+            .insert(Position { pos: Vector2::ZERO })
+            .insert(crate::physics::Radius { r: 1.0 })
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(UnitActions { vec: vec![action] })
+            .insert(crate::actions::PerformingActionState { action })
+            .id();
+        // Two overlapping casts of the same damage buff.
+        for _ in 0..2 {
+            world
+                .get_mut::<ResolveEffectsBuffer>(attacker)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect: Effect::DamageBuffEffect {
+                        percent: 0.5,
+                        duration: 1.0,
+                        texture: Rid::new(),
+                    },
+                    originator: Entity::from_raw(9999),
+                    execute: None,
+                });
+        }
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        resolve.run(&mut world);
+
+        // The weapon's stored damage is untouched by the casts.
+        let stored = match &world.get::<crate::actions::OnHitEffects>(action).unwrap().vec[0] {
+            Effect::DamageEffect { damage, .. } => *damage,
+            _ => unreachable!(),
+        };
+        assert!((stored - 10.0).abs() < 1e-3);
+
+        let landed_damage = |world: &World| match world
+            .get::<ResolveEffectsBuffer>(victim)
+            .unwrap()
+            .vec
+            .as_slice()
+        {
+            [QueuedEffect {
+                effect: Effect::DamageEffect { damage, .. },
+                ..
+            }] => *damage,
+            other => panic!("expected one damage effect, got {}", other.len()),
+        };
+
+        // The hit lands with both buffs read at impact: 10 * (1 + 0.5 + 0.5).
+        let mut swing = SystemStage::parallel();
+        swing.add_system(crate::actions::performing_action_state);
+        swing.run(&mut world);
+        assert!((landed_damage(&world) - 20.0).abs() < 1e-3);
+
+        // Let the buffs run out, then swing again: baseline damage is back.
+        world
+            .get_mut::<ResolveEffectsBuffer>(victim)
+            .unwrap()
+            .vec
+            .clear();
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+        timers.run(&mut world);
+        timers.run(&mut world);
+        world
+            .get_mut::<crate::actions::ChannelingDetails>(action)
+            .unwrap()
+            .total_time_channeled = 0.0;
+        swing.run(&mut world);
+        assert!((landed_damage(&world) - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cleanse_lifts_stun_and_set_stats_immediately() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.5 });
+        let unit = world
+            .spawn()
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .id();
+        let queue = |world: &mut World, effect: Effect| {
+            world
+                .get_mut::<ResolveEffectsBuffer>(unit)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect,
+                    originator: Entity::from_raw(9999),
+                    execute: None,
+                });
+        };
+        queue(
+            &mut world,
+            Effect::StunEffect {
+                duration: 5.0,
+                texture: Rid::new(),
+            },
+        );
+        queue(
+            &mut world,
+            Effect::Hypnosis {
+                new_alignment: 2,
+                duration: 5.0,
+            },
+        );
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut reset = SystemStage::parallel();
+        reset.add_system(reset_alignment);
+        let mut apply = SystemStage::parallel();
+        apply.add_system(set_stats_directly);
+
+        resolve.run(&mut world);
+        reset.run(&mut world);
+        apply.run(&mut world);
+        assert!(world.get::<Stunned>(unit).is_some());
+        assert_eq!(world.get::<TeamAlignment>(unit).unwrap().alignment, 2);
+        assert_eq!(world.get::<BuffHolder>(unit).unwrap().vec.len(), 2);
+
+        // One cleanse lifts the stun marker and the alignment override in
+        // the same resolution, with no one-frame hangover.
+        let buffs = world.get::<BuffHolder>(unit).unwrap().vec.clone();
+        queue(&mut world, Effect::CleanseEffect);
+        resolve.run(&mut world);
+        assert!(world.get::<Stunned>(unit).is_none());
+        assert!(world.get::<BuffHolder>(unit).unwrap().vec.is_empty());
+        for buff in buffs {
+            assert!(world.get_entity(buff).is_none());
+        }
+        reset.run(&mut world);
+        apply.run(&mut world);
+        assert_eq!(world.get::<TeamAlignment>(unit).unwrap().alignment, 0);
+    }
+
+    #[test]
+    fn dots_skip_buffs_their_target_no_longer_holds() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 1.0 });
+        let unit = world
+            .spawn()
+            .insert(BuffHolder { vec: Vec::new() })
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .insert(AppliedDamage { vec: Vec::new() })
+            .id();
+        let dot = world
+            .spawn()
+            .insert(PercentDamageOverTime {
+                percent_per_second: 0.02,
+                originator: Entity::from_raw(9999),
+            })
+            .insert(TargetEntity(unit))
+            .id();
+        let mut stage = SystemStage::parallel();
+        stage.add_system(percent_damage_over_time);
+
+        // Out of the holder (as after a cleanse): no tick.
+        stage.run(&mut world);
+        assert!(world.get::<AppliedDamage>(unit).unwrap().vec.is_empty());
+
+        // Held normally, the poison ticks.
+        world.get_mut::<BuffHolder>(unit).unwrap().vec.push(dot);
+        stage.run(&mut world);
+        assert_eq!(world.get::<AppliedDamage>(unit).unwrap().vec.len(), 1);
+    }
+
+    #[test]
+    fn heal_over_time_totals_amount_times_duration_regardless_of_actions() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.5 });
+        let mut units = Vec::new();
+        for action_count in [0usize, 1, 3] {
+            let actions: Vec<Entity> = (0..action_count)
+                .map(|_| world.spawn().insert(Cooldown(5.0)).id())
+                .collect();
+            let unit = world
+                .spawn()
+                .insert(ResolveEffectsBuffer { vec: Vec::new() })
+                .insert(BuffHolder { vec: Vec::new() })
+                .insert(AppliedDamage { vec: Vec::new() })
+                .insert(Hitpoints {
+                    hp: 50.0,
+                    max_hp: 100.0,
+                })
+                .insert(UnitActions { vec: actions })
+                .id();
+            units.push(unit);
+        }
+        let queue = |world: &mut World, unit: Entity| {
+            world
+                .get_mut::<ResolveEffectsBuffer>(unit)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect: Effect::HealOverTimeEffect {
+                        amount_per_second: 10.0,
+                        duration: 2.0,
+                        texture: Rid::new(),
+                    },
+                    originator: Entity::from_raw(9999),
+                    execute: None,
+                });
+        };
+        for unit in units.iter() {
+            queue(&mut world, *unit);
+        }
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        resolve.run(&mut world);
+        for unit in units.iter() {
+            assert_eq!(world.get::<BuffHolder>(*unit).unwrap().vec.len(), 1);
+        }
+
+        // A repeat cast refreshes the one buff instead of stacking a second.
+        queue(&mut world, units[2]);
+        resolve.run(&mut world);
+        assert_eq!(world.get::<BuffHolder>(units[2]).unwrap().vec.len(), 1);
+
+        let mut heal = SystemStage::parallel();
+        heal.add_system(heal_over_time);
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+        for _ in 0..6 {
+            heal.run(&mut world);
+            timers.run(&mut world);
+        }
+
+        // Total healing is amount_per_second * duration — the same whether
+        // zero, one or three actions sit on cooldown.
+        for unit in units.iter() {
+            let total: f32 = world
+                .get::<AppliedDamage>(*unit)
+                .unwrap()
+                .vec
+                .iter()
+                .map(|instance| instance.damage)
+                .sum();
+            assert!((total - 20.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn rapid_buffs_on_dying_units_leave_no_orphan_canvas_items() {
+        #[derive(Default)]
+        struct CanvasTally {
+            created: i64,
+            freed: i64,
+        }
+        // Stand-in for `flush_pending_canvas_items`: directives become
+        // Renderables, except on entities already headed for the death pass.
+        fn flush(world: &mut World) {
+            let mut query = world.query_filtered::<Entity, (
+                With<NewCanvasItemDirective>,
+                Without<DeathApproaches>,
+            )>();
+            let pending: Vec<Entity> = query.iter(world).collect();
+            for entity in pending {
+                world
+                    .entity_mut(entity)
+                    .insert(Renderable {
+                        canvas_item: Rid::new(),
+                    })
+                    .remove::<NewCanvasItemDirective>();
+                world.resource_mut::<CanvasTally>().created += 1;
+            }
+        }
+        // Stand-in for `_process_cleanup_canvas_items`.
+        fn sweep(world: &mut World) {
+            let mut query = world.query_filtered::<Entity, With<CleanupCanvasItem>>();
+            let cleanups: Vec<Entity> = query.iter(world).collect();
+            for entity in cleanups {
+                world.despawn(entity);
+                world.resource_mut::<CanvasTally>().freed += 1;
+            }
+        }
+
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        world.insert_resource(CanvasTally::default());
+        let mut resolve = SystemStage::parallel();
+        resolve.add_system(resolve_effects);
+        let mut death = SystemStage::parallel();
+        death.add_system(resolve_death);
+        let mut timers = SystemStage::parallel();
+        timers.add_system(buff_timer);
+
+        for frame in 0..5 {
+            let victim = world
+                .spawn()
+                .insert(Position { pos: Vector2::ZERO })
+                .insert(Hitpoints {
+                    hp: 10.0,
+                    max_hp: 10.0,
+                })
+                .insert(ResolveEffectsBuffer { vec: Vec::new() })
+                .insert(BuffHolder { vec: Vec::new() })
+                .insert(AppliedDamage { vec: Vec::new() })
+                .insert(TeamAlignment {
+                    alignment: 1,
+                    alignment_base: 1,
+                })
+                .insert(NewCanvasItemDirective {})
+                .insert(AnimatedSprite::new(Rid::new()))
+                .id();
+            world
+                .get_mut::<ResolveEffectsBuffer>(victim)
+                .unwrap()
+                .vec
+                .push(QueuedEffect {
+                    effect: Effect::StunEffect {
+                        duration: 1.0,
+                        texture: Rid::new(),
+                    },
+                    originator: Entity::from_raw(9999),
+                    execute: None,
+                });
+            resolve.run(&mut world);
+            // The victim dies the very tick the visual debuff landed.
+            world.entity_mut(victim).insert(DeathApproaches);
+            death.run(&mut world);
+            // Alternate the frame boundary: some buffs get their canvas item
+            // before the timer notices the dead target, some never do.
+            if frame % 2 == 0 {
+                flush(&mut world);
+            }
+            timers.run(&mut world);
+            flush(&mut world);
+            sweep(&mut world);
+        }
+        for _ in 0..3 {
+            timers.run(&mut world);
+            flush(&mut world);
+            sweep(&mut world);
+        }
+
+        // Every canvas item ever created is either freed or still attached
+        // to a live entity; nothing floats orphaned at the origin.
+        let mut renderables = world.query_filtered::<Entity, With<Renderable>>();
+        let live = renderables.iter(&world).count() as i64;
+        let tally = world.resource::<CanvasTally>();
+        assert_eq!(tally.created - tally.freed, live);
+    }
+
+    #[test]
+    fn landing_hits_build_threat_but_heals_do_not() {
+        let mut world = World::default();
+        world.insert_resource(DeltaPhysics { seconds: 0.1 });
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        let attacker = world
+            .spawn()
+            .insert(UnitActions { vec: Vec::new() })
+            .insert(BlueprintId(3))
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(ThreatBonus(5.0))
+            .id();
+        let unit = damaged_unit(&mut world, 0.0);
+        world.entity_mut(unit).insert(ThreatTable::default());
+        {
+            let mut applied = world.get_mut::<AppliedDamage>(unit).unwrap();
+            applied.vec[0].originator = attacker;
+            applied.vec.push(DamageInstance {
+                damage: 20.0,
+                delay: 0.0,
+                damage_type: DamageType::Heal,
+                originator: attacker,
+                depth: 0,
+                execute: None,
+            });
+        }
+
+        run_damage(&mut world);
+
+        // 10 unmitigated poison plus the flat bonus; the heal adds nothing.
+        let table = world.get::<ThreatTable>(unit).unwrap();
+        assert!((table.get(attacker) - 15.0).abs() < 1e-3);
+    }
+}
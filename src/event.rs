@@ -0,0 +1,456 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use bevy_ecs::prelude::Entity;
+use gdnative::prelude::Vector2;
+
+use crate::effects::DamageType;
+
+/// One damage application, emitted from `apply_damages` for the UI layer.
+pub struct DamageCue {
+    pub attacker: Entity,
+    pub victim: Entity,
+    pub attacker_blueprint: usize,
+    pub attacker_team: i64,
+    pub victim_team: i64,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    pub position: Vector2,
+    /// Damage before armor or magic resist.
+    pub pre_mitigation: f32,
+    /// Amount removed by armor or magic resist.
+    pub mitigated: f32,
+    /// Amount soaked by absorb shields before hitpoints were touched.
+    pub absorbed: f32,
+    /// For a warded victim, the amount sent to its bodyguard; for the guard,
+    /// the redirected amount it received.
+    pub redirected: f32,
+    /// True when an execute rider amplified the hit; UIs style these like
+    /// crits.
+    pub crit: bool,
+}
+
+/// A swing entered its wind-up; carries the timings for cast-bar UIs.
+pub struct CastStartCue {
+    pub entity: Entity,
+    pub action: Entity,
+    pub impact_time: f32,
+    pub swing_time: f32,
+}
+
+/// A swing finished: landed (`hit`), whiffed, or was interrupted mid-wind-up.
+pub struct CastResolveCue {
+    pub entity: Entity,
+    pub action: Entity,
+    pub hit: bool,
+}
+
+pub struct AudioCue {
+    pub kind: String,
+    pub position: Vector2,
+}
+
+pub struct SpawnCue {
+    pub entity: Entity,
+    pub blueprint: usize,
+    pub team: i64,
+    pub position: Vector2,
+}
+
+pub struct DeathCue {
+    pub entity: Entity,
+    pub blueprint: usize,
+    pub team: i64,
+    pub position: Vector2,
+}
+
+/// A unit died to a tracked hit. Killer identity is cached at damage time by
+/// `apply_damages`, so attribution survives the killer despawning before a
+/// delayed or damage-over-time death lands.
+pub struct KillCue {
+    pub victim: Entity,
+    pub victim_blueprint: usize,
+    pub victim_team: i64,
+    pub killer: Entity,
+    pub killer_blueprint: usize,
+    pub killer_team: i64,
+    pub position: Vector2,
+    /// Physics tick of the killing unit's last hit.
+    pub at_clock: i64,
+}
+
+pub enum EventCue {
+    Damage(DamageCue),
+    Audio(AudioCue),
+    Spawn(SpawnCue),
+    Death(DeathCue),
+    Kill(KillCue),
+    CastStart(CastStartCue),
+    CastResolve(CastResolveCue),
+    /// Winning team id, or -2 for a mutual wipe.
+    BattleEnded(i64),
+}
+
+/// Queue drained by `_process_event_signal_queue` and turned into Godot signals.
+pub struct EventQueue(pub VecDeque<EventCue>);
+
+impl Default for EventQueue {
+    fn default() -> Self {
+        Self(VecDeque::new())
+    }
+}
+
+/// How many damage positions [`MatchStats`] keeps for camera heuristics.
+pub const RECENT_DAMAGE_CAP: usize = 32;
+
+/// Running per-team totals for the scoreboard.
+pub struct MatchStats {
+    pub damage_by_team: HashMap<i64, f32>,
+    pub healing_by_team: HashMap<i64, f32>,
+    /// Damage prevented by a team's armor and magic resist.
+    pub mitigated_by_team: HashMap<i64, f32>,
+    pub deaths_by_team: HashMap<i64, i64>,
+    /// Ring buffer of the latest damage positions, for `get_battle_focus`.
+    pub recent_damage_positions: VecDeque<Vector2>,
+}
+
+impl MatchStats {
+    pub fn record_damage_position(&mut self, position: Vector2) {
+        if self.recent_damage_positions.len() >= RECENT_DAMAGE_CAP {
+            self.recent_damage_positions.pop_front();
+        }
+        self.recent_damage_positions.push_back(position);
+    }
+}
+
+impl Default for MatchStats {
+    fn default() -> Self {
+        Self {
+            damage_by_team: HashMap::new(),
+            healing_by_team: HashMap::new(),
+            mitigated_by_team: HashMap::new(),
+            deaths_by_team: HashMap::new(),
+            recent_damage_positions: VecDeque::new(),
+        }
+    }
+}
+
+/// One entry in the [`MatchLog`]. Times are seconds of simulation time;
+/// entity fields are the stable 64-bit handles the scripting API uses, so
+/// log consumers can correlate them with live queries.
+pub enum MatchLogEvent {
+    Spawn {
+        time: f32,
+        entity: i64,
+        blueprint: usize,
+        team: i64,
+    },
+    Death {
+        time: f32,
+        entity: i64,
+        blueprint: usize,
+        team: i64,
+    },
+    Cast {
+        time: f32,
+        entity: i64,
+        action: i64,
+        /// [`Effect::kind_id`] of the action's first on-hit effect.
+        ability: &'static str,
+    },
+}
+
+/// Headless analytics log, filled from the same hook points as the event cues
+/// but independent of the signal path. Disabled by default; toggled with
+/// `set_match_logging`. Damage is rolled up per (second, attacker team) so the
+/// log stays bounded over long matches.
+///
+/// `to_json` emits:
+/// ```text
+/// {
+///   "victor": <i64, -1 while undecided>,
+///   "duration": <f32 seconds>,
+///   "events": [
+///     {"type": "spawn", "time": f32, "entity": i64, "blueprint": u64, "team": i64},
+///     {"type": "death", "time": f32, "entity": i64, "blueprint": u64, "team": i64},
+///     {"type": "cast", "time": f32, "entity": i64, "action": i64, "ability": str}
+///   ],
+///   "damage_per_second": [{"second": i64, "team": i64, "amount": f32}]
+/// }
+/// ```
+pub struct MatchLog {
+    pub enabled: bool,
+    /// Simulation seconds elapsed, advanced once per logic tick.
+    pub time: f32,
+    pub events: Vec<MatchLogEvent>,
+    /// (second, team) -> damage dealt by that team during that second.
+    pub damage_rollups: BTreeMap<(i64, i64), f32>,
+}
+
+impl Default for MatchLog {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: 0.0,
+            events: Vec::new(),
+            damage_rollups: BTreeMap::new(),
+        }
+    }
+}
+
+impl MatchLog {
+    pub fn record_spawn(&mut self, entity: i64, blueprint: usize, team: i64) {
+        if self.enabled {
+            self.events.push(MatchLogEvent::Spawn {
+                time: self.time,
+                entity,
+                blueprint,
+                team,
+            });
+        }
+    }
+
+    pub fn record_death(&mut self, entity: i64, blueprint: usize, team: i64) {
+        if self.enabled {
+            self.events.push(MatchLogEvent::Death {
+                time: self.time,
+                entity,
+                blueprint,
+                team,
+            });
+        }
+    }
+
+    pub fn record_cast(&mut self, entity: i64, action: i64, ability: &'static str) {
+        if self.enabled {
+            self.events.push(MatchLogEvent::Cast {
+                time: self.time,
+                entity,
+                action,
+                ability,
+            });
+        }
+    }
+
+    pub fn record_damage(&mut self, team: i64, amount: f32) {
+        if self.enabled {
+            let second = self.time as i64;
+            *self.damage_rollups.entry((second, team)).or_insert(0.0) += amount;
+        }
+    }
+
+    pub fn to_json(&self, victor: i64) -> String {
+        let mut out = String::new();
+        out.push_str(&format!(
+            "{{\"victor\":{},\"duration\":{},\"events\":[",
+            victor, self.time
+        ));
+        for (index, event) in self.events.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            match event {
+                MatchLogEvent::Spawn {
+                    time,
+                    entity,
+                    blueprint,
+                    team,
+                } => out.push_str(&format!(
+                    "{{\"type\":\"spawn\",\"time\":{},\"entity\":{},\"blueprint\":{},\"team\":{}}}",
+                    time, entity, blueprint, team
+                )),
+                MatchLogEvent::Death {
+                    time,
+                    entity,
+                    blueprint,
+                    team,
+                } => out.push_str(&format!(
+                    "{{\"type\":\"death\",\"time\":{},\"entity\":{},\"blueprint\":{},\"team\":{}}}",
+                    time, entity, blueprint, team
+                )),
+                MatchLogEvent::Cast {
+                    time,
+                    entity,
+                    action,
+                    ability,
+                } => out.push_str(&format!(
+                    "{{\"type\":\"cast\",\"time\":{},\"entity\":{},\"action\":{},\"ability\":\"{}\"}}",
+                    time, entity, action, ability
+                )),
+            }
+        }
+        out.push_str("],\"damage_per_second\":[");
+        for (index, ((second, team), amount)) in self.damage_rollups.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            out.push_str(&format!(
+                "{{\"second\":{},\"team\":{},\"amount\":{}}}",
+                second, team, amount
+            ));
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+/// Keeps [`MatchLog::time`] in step with the simulation.
+pub fn advance_match_log(
+    delta: bevy_ecs::system::Res<crate::physics::DeltaPhysics>,
+    log: Option<bevy_ecs::system::ResMut<MatchLog>>,
+) {
+    if let Some(mut log) = log {
+        log.time += delta.seconds;
+    }
+}
+
+/// Camera-direction summary computed on demand by `get_battle_focus`.
+pub struct BattleFocus {
+    /// Centroid of the most recent damage positions, if any damage happened.
+    pub damage_centroid: Option<Vector2>,
+    /// Midpoint between the centroids of the two largest teams.
+    pub front_line: Option<Vector2>,
+    /// Highest-remaining-HP unit per team.
+    pub strongest_by_team: HashMap<i64, (Entity, f32)>,
+}
+
+/// Single pass over living units; cheap enough to call every frame.
+pub fn compute_battle_focus(world: &mut bevy_ecs::world::World) -> BattleFocus {
+    let damage_centroid = world.get_resource::<MatchStats>().and_then(|stats| {
+        if stats.recent_damage_positions.is_empty() {
+            None
+        } else {
+            let sum = stats
+                .recent_damage_positions
+                .iter()
+                .fold(Vector2::ZERO, |acc, pos| acc + *pos);
+            Some(sum / stats.recent_damage_positions.len() as f32)
+        }
+    });
+
+    let mut sums: HashMap<i64, (Vector2, usize)> = HashMap::new();
+    let mut strongest_by_team: HashMap<i64, (Entity, f32)> = HashMap::new();
+    let mut query = world.query::<(
+        Entity,
+        &crate::physics::Position,
+        &crate::unit::TeamAlignment,
+        &crate::unit::Hitpoints,
+    )>();
+    for (entity, position, alignment, hitpoints) in query.iter(world) {
+        let entry = sums
+            .entry(alignment.alignment)
+            .or_insert((Vector2::ZERO, 0));
+        entry.0 += position.pos;
+        entry.1 += 1;
+        let strongest = strongest_by_team
+            .entry(alignment.alignment)
+            .or_insert((entity, hitpoints.hp));
+        if hitpoints.hp > strongest.1 {
+            *strongest = (entity, hitpoints.hp);
+        }
+    }
+
+    let mut teams: Vec<(i64, Vector2, usize)> = sums
+        .into_iter()
+        .map(|(team, (sum, count))| (team, sum / count.max(1) as f32, count))
+        .collect();
+    teams.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+    let front_line = if teams.len() >= 2 {
+        Some((teams[0].1 + teams[1].1) / 2.0)
+    } else {
+        None
+    };
+
+    BattleFocus {
+        damage_centroid,
+        front_line,
+        strongest_by_team,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Position;
+    use crate::unit::{Hitpoints, TeamAlignment};
+    use bevy_ecs::world::World;
+
+    fn unit(world: &mut World, team: i64, pos: Vector2, hp: f32) -> Entity {
+        world
+            .spawn()
+            .insert(Position { pos })
+            .insert(TeamAlignment {
+                alignment: team,
+                alignment_base: team,
+            })
+            .insert(Hitpoints { hp, max_hp: hp })
+            .id()
+    }
+
+    #[test]
+    fn battle_focus_finds_front_line_and_strongest_units() {
+        let mut world = World::default();
+        world.insert_resource(MatchStats::default());
+        unit(&mut world, 0, Vector2::new(0.0, 0.0), 50.0);
+        let tank = unit(&mut world, 0, Vector2::new(0.0, 100.0), 80.0);
+        unit(&mut world, 1, Vector2::new(200.0, 0.0), 60.0);
+        unit(&mut world, 1, Vector2::new(200.0, 100.0), 40.0);
+
+        let focus = compute_battle_focus(&mut world);
+        let front = focus.front_line.unwrap();
+        assert!((front.x - 100.0).abs() < 1e-3);
+        assert!((front.y - 50.0).abs() < 1e-3);
+        assert_eq!(focus.strongest_by_team.get(&0).unwrap().0, tank);
+        assert!(focus.damage_centroid.is_none());
+    }
+
+    #[test]
+    fn match_log_exports_parseable_json_with_death_entries() {
+        let mut log = MatchLog {
+            enabled: true,
+            ..Default::default()
+        };
+        log.record_spawn(1, 0, 0);
+        log.record_spawn(2, 1, 1);
+        log.time = 1.5;
+        log.record_damage(0, 12.0);
+        log.record_damage(0, 8.0);
+        log.record_death(2, 1, 1);
+
+        let json = log.to_json(0);
+        assert_eq!(
+            json.matches('{').count(),
+            json.matches('}').count(),
+            "unbalanced braces in {}",
+            json
+        );
+        assert!(json.contains("\"victor\":0"));
+        assert!(json.contains(
+            "{\"type\":\"death\",\"time\":1.5,\"entity\":2,\"blueprint\":1,\"team\":1}"
+        ));
+        assert!(json.contains("{\"second\":1,\"team\":0,\"amount\":20}"));
+    }
+
+    #[test]
+    fn disabled_match_log_records_nothing() {
+        let mut log = MatchLog::default();
+        log.record_spawn(1, 0, 0);
+        log.record_damage(0, 5.0);
+        assert!(log.events.is_empty());
+        assert!(log.damage_rollups.is_empty());
+    }
+
+    #[test]
+    fn battle_focus_defaults_with_one_team() {
+        let mut world = World::default();
+        world.insert_resource(MatchStats::default());
+        unit(&mut world, 0, Vector2::new(10.0, 10.0), 50.0);
+        world
+            .resource_mut::<MatchStats>()
+            .record_damage_position(Vector2::new(4.0, 8.0));
+
+        let focus = compute_battle_focus(&mut world);
+        assert!(focus.front_line.is_none());
+        let centroid = focus.damage_centroid.unwrap();
+        assert!((centroid.x - 4.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::*;
+use gdnative::api::VisualServer;
+use gdnative::prelude::*;
+
+use crate::graphics::{
+    AlphaSprite, FlippableSprite, ModulateSprite, Renderable, ScaleSprite,
+};
+use crate::physics::Position;
+use crate::unit::Hitpoints;
+
+/// Render-side delta, set once per `_process`.
+pub struct Delta {
+    pub seconds: f32,
+}
+
+#[derive(Copy, Clone)]
+pub struct AnimationSetData {
+    pub frame_count: i64,
+    /// Frames per second.
+    pub speed: f32,
+    pub frame_size: Vector2,
+    pub row: i64,
+}
+
+/// Logical animation roles the simulation asks for. Concrete sheet names are
+/// resolved through [`AnimationNameMap`] so projects can rename their assets
+/// without touching the engine.
+#[derive(Component, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum AnimationRole {
+    Run,
+    Idle,
+    Death,
+    Fly,
+    Attack,
+    Cast,
+}
+
+impl AnimationRole {
+    pub fn default_name(&self) -> &'static str {
+        match self {
+            AnimationRole::Run => "run",
+            AnimationRole::Idle => "idle",
+            AnimationRole::Death => "death",
+            AnimationRole::Fly => "fly",
+            AnimationRole::Attack => "attack",
+            AnimationRole::Cast => "cast",
+        }
+    }
+
+    /// Keys accepted by `set_animation_aliases`.
+    pub fn from_alias_key(key: &str) -> Option<Self> {
+        match key {
+            "run" => Some(AnimationRole::Run),
+            "idle" => Some(AnimationRole::Idle),
+            "death" => Some(AnimationRole::Death),
+            "fly" => Some(AnimationRole::Fly),
+            "attack" => Some(AnimationRole::Attack),
+            "cast" => Some(AnimationRole::Cast),
+            _ => None,
+        }
+    }
+}
+
+/// Role-to-sheet-name overrides. Roles without an alias keep their default
+/// name, so projects that never call `set_animation_aliases` are unaffected.
+#[derive(Default)]
+pub struct AnimationNameMap {
+    pub map: HashMap<AnimationRole, String>,
+}
+
+impl AnimationNameMap {
+    pub fn name(&self, role: AnimationRole) -> &str {
+        self.map
+            .get(&role)
+            .map(|name| name.as_str())
+            .unwrap_or_else(|| role.default_name())
+    }
+
+    pub fn set_alias(&mut self, role: AnimationRole, name: String) {
+        self.map.insert(role, name);
+    }
+}
+
+/// Sprite-sheet metadata registered from GDScript, keyed by texture and
+/// concrete animation name.
+pub struct AnimationLibrary {
+    pub map: HashMap<(Rid, String), AnimationSetData>,
+}
+
+impl AnimationLibrary {
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+        }
+    }
+
+    /// Frames per second; an unregistered animation reports 1.0 so callers
+    /// dividing by the speed never hit a zero.
+    pub fn get_animation_speed(&self, texture: Rid, animation_name: &str) -> f32 {
+        self.map
+            .get(&(texture, animation_name.to_string()))
+            .map(|data| data.speed.max(0.001))
+            .unwrap_or(1.0)
+    }
+
+    /// Seconds for one play-through; an unregistered animation reports 1.0
+    /// rather than a zero that poisons downstream timing math.
+    pub fn get_animation_length(&self, texture: Rid, animation_name: &str) -> f32 {
+        match self.map.get(&(texture, animation_name.to_string())) {
+            Some(data) => data.frame_count as f32 / data.speed.max(0.001),
+            None => 1.0,
+        }
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct AnimatedSprite {
+    pub texture: Rid,
+    pub animation_name: String,
+    pub frame: i64,
+    pub timer: f32,
+    pub loops: bool,
+}
+
+impl AnimatedSprite {
+    pub fn new(texture: Rid) -> Self {
+        Self {
+            texture,
+            animation_name: "idle".to_string(),
+            frame: 0,
+            timer: 0.0,
+            loops: true,
+        }
+    }
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct PlayAnimationDirective {
+    pub animation: AnimationRole,
+    pub loops: bool,
+}
+
+/// Advance every sprite and redraw its canvas item. Runs exclusively from
+/// `_process` because it talks to the VisualServer.
+pub fn animate_sprites(world: &mut World, library: &AnimationLibrary) {
+    let delta = world
+        .get_resource::<Delta>()
+        .map(|d| d.seconds)
+        .unwrap_or(0.0);
+
+    let mut directive_entities: Vec<(Entity, PlayAnimationDirective)> = Vec::new();
+    let mut directive_query = world.query::<(Entity, &PlayAnimationDirective)>();
+    for (entity, directive) in directive_query.iter(world) {
+        directive_entities.push((entity, *directive));
+    }
+    for (entity, directive) in directive_entities {
+        let name = world
+            .get_resource::<AnimationNameMap>()
+            .map(|names| names.name(directive.animation).to_string())
+            .unwrap_or_else(|| directive.animation.default_name().to_string());
+        if let Some(mut sprite) = world.get_mut::<AnimatedSprite>(entity) {
+            if sprite.animation_name != name {
+                sprite.animation_name = name;
+                sprite.frame = 0;
+                sprite.timer = 0.0;
+            }
+            sprite.loops = directive.loops;
+        }
+        world.entity_mut(entity).remove::<PlayAnimationDirective>();
+    }
+
+    let server = unsafe { VisualServer::godot_singleton() };
+    let mut sprite_query = world.query::<(
+        Entity,
+        &mut AnimatedSprite,
+        &Renderable,
+        &Position,
+        Option<&FlippableSprite>,
+        Option<&ScaleSprite>,
+        Option<&AlphaSprite>,
+        Option<&ModulateSprite>,
+        Option<&Hitpoints>,
+    )>();
+    for (
+        _entity,
+        mut sprite,
+        renderable,
+        position,
+        flippable,
+        scale,
+        alpha,
+        modulate,
+        hitpoints,
+    ) in sprite_query.iter_mut(world)
+    {
+        let data = match library
+            .map
+            .get(&(sprite.texture, sprite.animation_name.clone()))
+        {
+            Some(data) => *data,
+            None => continue,
+        };
+        sprite.timer += delta;
+        let frame_time = 1.0 / data.speed.max(0.001);
+        while sprite.timer >= frame_time {
+            sprite.timer -= frame_time;
+            sprite.frame += 1;
+        }
+        if sprite.loops {
+            sprite.frame %= data.frame_count.max(1);
+        } else {
+            sprite.frame = sprite.frame.min((data.frame_count - 1).max(0));
+        }
+
+        let mut scale_vector = scale.map(|s| s.0).unwrap_or(Vector2::ONE);
+        if let Some(flip) = flippable {
+            if flip.flipped {
+                scale_vector.x = -scale_vector.x;
+            }
+        }
+        let transform = Transform2D::IDENTITY
+            .then(Transform2D::scale(Transform2D::IDENTITY, scale_vector))
+            .then(Transform2D::translation(
+                Transform2D::IDENTITY,
+                position.pos,
+            ));
+        unsafe {
+            server.canvas_item_clear(renderable.canvas_item);
+            server.canvas_item_set_transform(renderable.canvas_item, transform);
+            let source = Rect2::new(
+                Vector2::new(
+                    sprite.frame as f32 * data.frame_size.x,
+                    data.row as f32 * data.frame_size.y,
+                ),
+                data.frame_size,
+            );
+            let destination = Rect2::new(
+                Vector2::new(-data.frame_size.x / 2.0, -data.frame_size.y / 2.0),
+                data.frame_size,
+            );
+            server.canvas_item_add_texture_rect_region(
+                renderable.canvas_item,
+                destination,
+                sprite.texture,
+                source,
+                Color::from_rgba(1.0, 1.0, 1.0, 1.0),
+                false,
+                Rid::new(),
+                false,
+            );
+            let mut color = modulate
+                .map(|m| Color::from_rgb(m.r, m.g, m.b))
+                .unwrap_or_else(|| Color::from_rgb(1.0, 1.0, 1.0));
+            color.a = alpha.map(|a| a.0).unwrap_or(1.0);
+            server.canvas_item_set_modulate(renderable.canvas_item, color);
+
+            // Health circle under the sprite.
+            if let Some(hitpoints) = hitpoints {
+                let fraction = (hitpoints.hp / hitpoints.max_hp).clamp(0.0, 1.0);
+                server.canvas_item_add_circle(
+                    renderable.canvas_item,
+                    Vector2::new(0.0, data.frame_size.y / 2.0),
+                    4.0,
+                    Color::from_rgb(1.0 - fraction, fraction, 0.0),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_animation_sets_report_safe_timing() {
+        let library = AnimationLibrary::new();
+        let texture = Rid::new();
+        assert!(library.get_animation_speed(texture, "run") > 0.0);
+        assert!(library.get_animation_length(texture, "run") > 0.0);
+    }
+
+    #[test]
+    fn name_map_falls_back_to_default_names() {
+        let mut names = AnimationNameMap::default();
+        assert_eq!(names.name(AnimationRole::Run), "run");
+        names.set_alias(AnimationRole::Run, "walk_cycle".to_string());
+        assert_eq!(names.name(AnimationRole::Run), "walk_cycle");
+        assert_eq!(names.name(AnimationRole::Idle), "idle");
+    }
+}
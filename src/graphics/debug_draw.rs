@@ -0,0 +1,100 @@
+use bevy_ecs::prelude::*;
+use gdnative::prelude::*;
+
+use crate::graphics::TeamColors;
+use crate::terrain::{FlowFieldsTowardsEnemies, TerrainMap};
+
+/// Display color for a team's debug overlay: the team's registered color, or
+/// a distinct fallback when `set_team_color` was never called for it.
+fn team_debug_color(world: &World, team: i64) -> Color {
+    if let Some(colors) = world.get_resource::<TeamColors>() {
+        if let Some(color) = colors.map.get(&team) {
+            return *color;
+        }
+    }
+    match team.rem_euclid(4) {
+        0 => Color::from_rgb(1.0, 0.3, 0.3),
+        1 => Color::from_rgb(0.3, 0.5, 1.0),
+        2 => Color::from_rgb(0.3, 1.0, 0.4),
+        _ => Color::from_rgb(1.0, 0.9, 0.3),
+    }
+}
+
+/// Arrow per cell of each drawn team's flow field. `debug_team` selects one
+/// team, or every team when -1.
+pub fn draw_flow_field(world: &mut World, base: &Node2D, debug_team: i64) {
+    let terrain = match world.get_resource::<TerrainMap>() {
+        Some(terrain) => terrain,
+        None => return,
+    };
+    let fields = match world.get_resource::<FlowFieldsTowardsEnemies>() {
+        Some(fields) => fields,
+        None => return,
+    };
+    for (team, field) in fields.map.iter() {
+        if debug_team != -1 && *team != debug_team {
+            continue;
+        }
+        let color = team_debug_color(world, *team);
+        for y in 0..terrain.height {
+            for x in 0..terrain.width {
+                let index = terrain.cell_index(x, y).unwrap();
+                let center = terrain.cell_center(x, y);
+                let flow = field.flow[index];
+                if flow == Vector2::ZERO {
+                    continue;
+                }
+                base.draw_line(
+                    center,
+                    center + flow * terrain.cell_size * 0.4,
+                    color,
+                    1.0,
+                    false,
+                );
+            }
+        }
+    }
+}
+
+/// Integration cost numbers per cell. Only drawn when a single team is
+/// selected; every team's numbers at once are unreadable.
+pub fn draw_integration_values(
+    world: &mut World,
+    base: &Node2D,
+    font: Ref<gdnative::api::Font>,
+    debug_team: i64,
+) {
+    if debug_team == -1 {
+        return;
+    }
+    let terrain = match world.get_resource::<TerrainMap>() {
+        Some(terrain) => terrain,
+        None => return,
+    };
+    let fields = match world.get_resource::<FlowFieldsTowardsEnemies>() {
+        Some(fields) => fields,
+        None => return,
+    };
+    for (team, field) in fields.map.iter() {
+        if *team != debug_team {
+            continue;
+        }
+        let color = team_debug_color(world, *team);
+        for y in 0..terrain.height {
+            for x in 0..terrain.width {
+                let index = terrain.cell_index(x, y).unwrap();
+                let value = field.integration[index];
+                if value == f32::MAX {
+                    continue;
+                }
+                base.draw_string(
+                    &font,
+                    terrain.cell_center(x, y),
+                    format!("{:.0}", value),
+                    color,
+                    -1,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+pub mod animation;
+pub mod debug_draw;
+
+use bevy_ecs::prelude::*;
+use gdnative::prelude::*;
+
+use crate::graphics::animation::{AnimatedSprite, AnimationRole, PlayAnimationDirective};
+use crate::util::ExpirationTimer;
+
+/// Per-team display colors set from GDScript via `set_team_color`.
+pub struct TeamColors {
+    pub map: std::collections::HashMap<i64, Color>,
+}
+
+impl Default for TeamColors {
+    fn default() -> Self {
+        Self {
+            map: std::collections::HashMap::new(),
+        }
+    }
+}
+
+/// Entity wants a VisualServer canvas item; consumed by
+/// `_process_new_canvas_items` on the Godot side of the frame.
+#[derive(Component)]
+pub struct NewCanvasItemDirective {}
+
+/// Handle to the live canvas item once the directive has been processed.
+#[derive(Component, Copy, Clone)]
+pub struct Renderable {
+    pub canvas_item: Rid,
+}
+
+/// Marker entity carrying a canvas item rid to free on the Godot side.
+#[derive(Component, Copy, Clone)]
+pub struct CleanupCanvasItem(pub Rid);
+
+#[derive(Component, Copy, Clone)]
+pub struct ScaleSprite(pub Vector2);
+
+#[derive(Component, Copy, Clone)]
+pub struct AlphaSprite(pub f32);
+
+#[derive(Component, Copy, Clone)]
+pub struct ModulateSprite {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct FlippableSprite {
+    pub flipped: bool,
+}
+
+/// Buff sprites follow the unit they are attached to.
+#[derive(Component, Copy, Clone)]
+pub struct MirrorTargetPosition(pub Entity);
+
+pub fn spawn_impact_visual(commands: &mut Commands, texture: Rid, position: Vector2) {
+    commands
+        .spawn()
+        .insert(crate::physics::Position { pos: position })
+        .insert(NewCanvasItemDirective {})
+        .insert(AnimatedSprite::new(texture))
+        .insert(PlayAnimationDirective {
+            animation: AnimationRole::Death,
+            loops: false,
+        })
+        .insert(ExpirationTimer(1.0));
+}
+
+/// Keep buff sprites glued to their target's position.
+pub fn mirror_target_position(
+    targets: Query<&crate::physics::Position, Without<MirrorTargetPosition>>,
+    mut query: Query<(&MirrorTargetPosition, &mut crate::physics::Position)>,
+) {
+    for (mirror, mut position) in query.iter_mut() {
+        if let Ok(target_position) = targets.get(mirror.0) {
+            position.pos = target_position.pos;
+        }
+    }
+}
@@ -0,0 +1,5097 @@
+pub mod abilities;
+pub mod actions;
+pub mod boids;
+pub mod effects;
+pub mod event;
+pub mod graphics;
+pub mod physics;
+pub mod projectiles;
+pub mod scenario;
+pub mod terrain;
+pub mod unit;
+pub mod util;
+
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::SystemStage;
+use gdnative::api::VisualServer;
+use gdnative::prelude::*;
+
+use crate::abilities::UnitAbility;
+use crate::actions::{
+    ActionBundle, ActionCooldown, ActionOwner, ActionPriority, ActionProjectileDetails,
+    ActionRange, ChannelingDetails, Cleave, EffectTexture, ImpactType, OnHitEffects, Splash,
+    SwingDetails, TargetEntity, TargetFlags, TargetSelection, ThreatBonus, ThreatTable,
+    UnitActions, BASIC_ATTACK_PRIORITY,
+};
+use crate::boids::*;
+use crate::effects::{
+    AppliedDamage, DamageInstance, DamageType, DeathEffect, Effect, ExecuteDamage, OnDeathEffects,
+    ResolveEffectsBuffer, StatBuff, Victor,
+};
+use crate::event::{EventCue, EventQueue, MatchLog, MatchStats, SpawnCue};
+use crate::graphics::animation::{
+    AnimatedSprite, AnimationLibrary, AnimationNameMap, AnimationRole, AnimationSetData, Delta,
+    PlayAnimationDirective,
+};
+use crate::graphics::{
+    CleanupCanvasItem, FlippableSprite, NewCanvasItemDirective, Renderable, ScaleSprite,
+    TeamColors,
+};
+use crate::physics::{
+    Clock, DeltaPhysics, Mass, Position, Radius, SpatialHashTable, SpatialNeighborsCache, Velocity,
+};
+use crate::terrain::TerrainMap;
+use crate::unit::{
+    Acceleration, Armor, AttackTargetDirective, BaseMass, BaseMaxHp, BlueprintId, HealEfficacy,
+    Hitpoints, MagicResist, MeleeWeapon, ProjectileWeapon, RadiusWeapon, SpatialAwareness, Speed,
+    TeamAlignment, UnitBlueprint, Weapon,
+};
+use crate::util::SimRng;
+
+#[derive(NativeClass)]
+#[inherit(Node2D)]
+#[register_with(Self::register)]
+pub struct ECSWorld {
+    world: World,
+    schedule_logic: Schedule,
+    unit_blueprints: Vec<UnitBlueprint>,
+    animation_library: AnimationLibrary,
+
+    #[property]
+    pub running: bool,
+    #[property]
+    pub victor: i64,
+    #[property]
+    pub draw_debug: bool,
+    /// Enables the entity census / orphan scans; off in shipping builds.
+    #[property]
+    pub debug_tools: bool,
+    /// Team whose flow field the debug overlay draws; -1 draws every team.
+    #[property]
+    pub debug_flow_team: i64,
+    #[property]
+    pub emit_damage_cues: bool,
+    #[property]
+    pub emit_audio_cues: bool,
+    #[property]
+    pub emit_spawn_cues: bool,
+    #[property]
+    pub emit_death_cues: bool,
+    #[property]
+    pub emit_kill_cues: bool,
+    #[property]
+    pub emit_cast_cues: bool,
+    /// Multiplier on the delta fed into the sim and the animations;
+    /// clamped to 0..=4 when read. 0 freezes, 1 is realtime.
+    #[property]
+    pub time_scale: f32,
+
+    /// Why the most recent scripted command returned false.
+    last_error: String,
+    /// Lazily created originator entity for scripted world damage.
+    world_originator: Option<Entity>,
+}
+
+fn build_logic_schedule() -> Schedule {
+    let mut schedule = Schedule::default();
+    schedule.add_stage(
+        "prepare",
+        SystemStage::parallel()
+            .with_system(crate::physics::build_spatial_hash)
+            .with_system(crate::event::advance_match_log),
+    );
+    schedule.add_stage(
+        "cache",
+        SystemStage::parallel()
+            .with_system(crate::physics::build_spatial_neighbors_cache)
+            .with_system(crate::terrain::build_fog_of_war)
+            .with_system(crate::terrain::build_flow_fields)
+            .with_system(update_boid_params_to_stats)
+            .with_system(kite_conductor),
+    );
+    schedule.add_stage(
+        "boids",
+        SystemStage::parallel()
+            .with_system(separation_boid)
+            .with_system(cohesion_boid)
+            .with_system(alignment_boid)
+            .with_system(seek_enemies_boid)
+            .with_system(move_to_point_boid)
+            .with_system(avoid_walls_boid)
+            .with_system(stopping_boid)
+            .with_system(charge_at_enemy_boid)
+            .with_system(kite_enemies_boid),
+    );
+    schedule.add_stage(
+        "boid_apply",
+        SystemStage::parallel().with_system(boid_apply_params),
+    );
+    schedule.add_stage(
+        "target",
+        SystemStage::parallel()
+            .with_system(crate::actions::target_units)
+            .with_system(crate::effects::assign_bodyguard_links),
+    );
+    schedule.add_stage(
+        "perform",
+        SystemStage::parallel().with_system(crate::actions::perform_action),
+    );
+    schedule.add_stage(
+        "channel",
+        SystemStage::parallel()
+            .with_system(crate::actions::performing_action_state)
+            .with_system(crate::actions::tick_cooldowns)
+            .with_system(crate::projectiles::projectile_homing),
+    );
+    schedule.add_stage(
+        "contact",
+        SystemStage::parallel()
+            .with_system(crate::projectiles::projectile_contact)
+            .with_system(crate::effects::apply_dashes)
+            .with_system(crate::actions::detect_interrupted_casts),
+    );
+    schedule.add_stage(
+        "resolve",
+        SystemStage::parallel()
+            .with_system(crate::effects::resolve_effects)
+            .with_system(crate::effects::reset_alignment),
+    );
+    schedule.add_stage(
+        "buffs",
+        SystemStage::parallel()
+            .with_system(crate::effects::apply_auras)
+            .with_system(crate::effects::berserk_passive)
+            .with_system(crate::effects::apply_stat_buffs)
+            .with_system(crate::effects::set_stats_directly)
+            .with_system(crate::effects::percent_damage_over_time)
+            .with_system(crate::effects::flat_damage_over_time)
+            .with_system(crate::effects::heal_over_time)
+            .with_system(crate::effects::percent_cooldown_speedup)
+            .with_system(crate::effects::percent_cooldown_slowdown)
+            .with_system(crate::effects::chill_decay)
+            .with_system(crate::effects::crowd_control_decay)
+            .with_system(crate::actions::threat_decay)
+            .with_system(crate::effects::totem_pulse)
+            .with_system(crate::effects::mine_trigger)
+            .with_system(crate::effects::structure_lifetime)
+            .with_system(crate::effects::resolve_blinks)
+            .with_system(crate::effects::break_stealth_on_action)
+            .with_system(crate::effects::buff_timer)
+            .with_system(crate::util::expire_timers),
+    );
+    schedule.add_stage(
+        "damage",
+        SystemStage::parallel().with_system(crate::effects::apply_damages),
+    );
+    schedule.add_stage(
+        "movement",
+        SystemStage::parallel()
+            .with_system(crate::physics::detect_collisions)
+            .with_system(crate::physics::physics_integrate)
+            .with_system(crate::physics::apply_teleport)
+            .with_system(crate::graphics::mirror_target_position),
+    );
+    schedule.add_stage(
+        "death",
+        SystemStage::parallel()
+            .with_system(crate::effects::resolve_death)
+            .with_system(crate::effects::update_victor),
+    );
+    schedule
+}
+
+/// Script-side unit handle: the full entity bits (index plus generation) as
+/// an i64. A handle to a despawned unit whose index was recycled carries a
+/// stale generation, so lookups fail cleanly instead of silently addressing
+/// the new occupant.
+pub(crate) fn entity_handle(entity: Entity) -> i64 {
+    entity.to_bits() as i64
+}
+
+fn entity_from_handle(handle: i64) -> Entity {
+    Entity::from_bits(handle as u64)
+}
+
+#[methods]
+impl ECSWorld {
+    fn new(_base: &Node2D) -> Self {
+        let mut world = World::default();
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        world.insert_resource(Victor::default());
+        world.insert_resource(Clock { tick: 0 });
+        // Empty spatial defaults so a schedule pass before the first physics
+        // tick (or right after a reset) never hits a missing resource.
+        world.insert_resource(DeltaPhysics { seconds: 0.0 });
+        world.insert_resource(SpatialHashTable::new(64.0));
+        world.insert_resource(SpatialNeighborsCache::default());
+        world.insert_resource(crate::terrain::FlowFieldsTowardsEnemies::default());
+        world.insert_resource(TerrainMap::default());
+        world.insert_resource(TeamColors::default());
+        world.insert_resource(SimRng::default());
+        world.insert_resource(AnimationNameMap::default());
+        world.insert_resource(MatchLog::default());
+        world.insert_resource(actions::TargetStickiness::default());
+        world.insert_resource(actions::ChannelInterruptLeeway::default());
+        world.insert_resource(crate::terrain::FogOfWar::default());
+        world.insert_resource(TeamAIProfiles::default());
+        world.insert_resource(crate::effects::CrowdControlTuning::default());
+        Self {
+            world,
+            schedule_logic: build_logic_schedule(),
+            unit_blueprints: Vec::new(),
+            animation_library: AnimationLibrary::new(),
+            running: false,
+            victor: -1,
+            draw_debug: false,
+            debug_tools: false,
+            debug_flow_team: -1,
+            emit_damage_cues: true,
+            emit_audio_cues: true,
+            emit_spawn_cues: true,
+            emit_death_cues: true,
+            emit_kill_cues: true,
+            emit_cast_cues: true,
+            time_scale: 1.0,
+            last_error: String::new(),
+            world_originator: None,
+        }
+    }
+
+    fn register(builder: &ClassBuilder<Self>) {
+        Self::setup_event_cue_signal(builder);
+    }
+
+    fn setup_event_cue_signal(builder: &ClassBuilder<Self>) {
+        builder.signal("damage_cue").done();
+        builder.signal("audio_cue").done();
+        builder.signal("spawn_cue").done();
+        builder.signal("death_cue").done();
+        builder.signal("kill_cue").done();
+        builder.signal("cast_start_cue").done();
+        builder.signal("cast_resolve_cue").done();
+        builder.signal("battle_ended").done();
+    }
+
+    #[method]
+    fn _ready(&mut self, #[base] base: &Node2D) {
+        base.set_process(true);
+        base.set_physics_process(true);
+        self.flush_pending_canvas_items(base);
+    }
+
+    #[method]
+    fn set_terrain_map(&mut self, width: i64, height: i64, cell_size: f32) {
+        self.world
+            .insert_resource(TerrainMap::new(width as i32, height as i32, cell_size));
+    }
+
+    /// Mark a rectangle of terrain cells unpathable.
+    #[method]
+    fn spawn_obstacle(&mut self, position: Vector2, width: f32, height: f32) {
+        let mut terrain = self.world.resource_mut::<TerrainMap>();
+        let min = Vector2::new(position.x - width / 2.0, position.y - height / 2.0);
+        let max = Vector2::new(position.x + width / 2.0, position.y + height / 2.0);
+        let (min_x, min_y) = terrain.cell_coords(min);
+        let (max_x, max_y) = terrain.cell_coords(max);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                terrain.set_cell(x, y, 0);
+            }
+        }
+    }
+
+    #[method]
+    fn register_animation(
+        &mut self,
+        texture: Rid,
+        animation_name: String,
+        frame_count: i64,
+        speed: f32,
+        frame_width: f32,
+        frame_height: f32,
+        row: i64,
+    ) {
+        self.animation_library.map.insert(
+            (texture, animation_name),
+            AnimationSetData {
+                frame_count,
+                speed,
+                frame_size: Vector2::new(frame_width, frame_height),
+                row,
+            },
+        );
+    }
+
+    /// Map logical animation roles ("run", "idle", "death", "fly", "attack",
+    /// "cast") to the sheet names this project registered. Unknown keys are
+    /// ignored; unset roles keep their default names.
+    #[method]
+    fn set_animation_aliases(&mut self, aliases: Dictionary) {
+        let mut names = self.world.resource_mut::<AnimationNameMap>();
+        for (key, value) in aliases.iter() {
+            if let (Some(key), Some(value)) = (key.to::<String>(), value.to::<String>()) {
+                if let Some(role) = AnimationRole::from_alias_key(&key) {
+                    names.set_alias(role, value);
+                }
+            }
+        }
+    }
+
+    #[method]
+    fn set_team_color(&mut self, team: i64, color: Color) {
+        self.world
+            .resource_mut::<TeamColors>()
+            .map
+            .insert(team, color);
+    }
+
+    /// Tune stun diminishing returns: `window` is the rolling exposure window
+    /// in seconds, `falloff` the duration scale of each successive stun inside
+    /// it (the last entry repeats; end on 0.0 for immunity). An empty falloff
+    /// array is ignored.
+    #[method]
+    fn set_crowd_control_tuning(&mut self, window: f32, falloff: Vec<f32>) {
+        if falloff.is_empty() {
+            return;
+        }
+        self.world
+            .insert_resource(crate::effects::CrowdControlTuning { window, falloff });
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_unit_blueprint(
+        &mut self,
+        texture: Rid,
+        hitpoints: f32,
+        movespeed: f32,
+        acceleration: f32,
+        armor: f32,
+        magic_resist: f32,
+        mass: f32,
+        radius: f32,
+    ) -> i64 {
+        self.unit_blueprints.push(UnitBlueprint::new(
+            texture,
+            hitpoints,
+            movespeed,
+            acceleration,
+            armor,
+            magic_resist,
+            mass,
+            radius,
+        ));
+        (self.unit_blueprints.len() - 1) as i64
+    }
+
+    /// Build a whole blueprint from one Dictionary, for data-driven unit
+    /// definitions kept in JSON resources on the Godot side: base stats at
+    /// the top level plus optional "weapons" and "abilities" arrays whose
+    /// entries use the same field names as the add_*_to_blueprint methods
+    /// (weapons keyed by "type", abilities by "name"). Returns the new
+    /// blueprint id, or -1 after a godot_error naming the first invalid
+    /// field; nothing is registered on failure.
+    #[method]
+    fn add_unit_blueprint_from_dict(&mut self, data: Dictionary) -> i64 {
+        match Self::blueprint_from_dict(&data) {
+            Ok(blueprint) => {
+                self.unit_blueprints.push(blueprint);
+                (self.unit_blueprints.len() - 1) as i64
+            }
+            Err(error) => {
+                godot_error!("add_unit_blueprint_from_dict: {}", error);
+                -1
+            }
+        }
+    }
+
+    fn blueprint_from_dict(data: &Dictionary) -> Result<UnitBlueprint, String> {
+        fn req(dict: &Dictionary, key: &str) -> Result<f32, String> {
+            dict.get(key)
+                .and_then(|value| value.to::<f32>())
+                .ok_or_else(|| format!("missing or non-numeric `{}`", key))
+        }
+        fn opt(dict: &Dictionary, key: &str, default: f32) -> f32 {
+            dict.get(key)
+                .and_then(|value| value.to::<f32>())
+                .unwrap_or(default)
+        }
+        fn opt_i64(dict: &Dictionary, key: &str, default: i64) -> i64 {
+            dict.get(key)
+                .and_then(|value| value.to::<i64>())
+                .unwrap_or(default)
+        }
+        fn opt_bool(dict: &Dictionary, key: &str) -> bool {
+            dict.get(key)
+                .and_then(|value| value.to::<bool>())
+                .unwrap_or(false)
+        }
+        fn texture(dict: &Dictionary, key: &str) -> Rid {
+            dict.get(key)
+                .and_then(|value| value.to::<Rid>())
+                .unwrap_or_else(Rid::new)
+        }
+        fn offset(dict: &Dictionary, key: &str) -> Vector2 {
+            dict.get(key)
+                .and_then(|value| value.to::<Vector2>())
+                .unwrap_or(Vector2::ZERO)
+        }
+        fn entries(dict: &Dictionary, key: &str) -> Result<Vec<Dictionary>, String> {
+            let array = match dict.get(key) {
+                Some(value) => value
+                    .to::<VariantArray>()
+                    .ok_or_else(|| format!("`{}` is not an array", key))?,
+                None => return Ok(Vec::new()),
+            };
+            let mut out = Vec::new();
+            for entry in array.iter() {
+                out.push(
+                    entry
+                        .to::<Dictionary>()
+                        .ok_or_else(|| format!("`{}` entry is not a Dictionary", key))?,
+                );
+            }
+            Ok(out)
+        }
+
+        let mut blueprint = UnitBlueprint::new(
+            texture(data, "texture"),
+            req(data, "hitpoints")?,
+            req(data, "movespeed")?,
+            req(data, "acceleration")?,
+            req(data, "armor")?,
+            req(data, "magic_resist")?,
+            req(data, "mass")?,
+            req(data, "radius")?,
+        );
+        blueprint.evasion = opt(data, "evasion", 0.0).clamp(0.0, 1.0);
+        blueprint.threat_bonus = opt(data, "threat_bonus", 0.0).max(0.0);
+
+        for weapon in entries(data, "weapons")? {
+            let kind = weapon
+                .get("type")
+                .and_then(|value| value.to::<String>())
+                .ok_or("weapon entry without a `type` string")?;
+            match kind.as_str() {
+                "melee" => blueprint.add_weapon(Weapon::Melee(MeleeWeapon {
+                    damage: req(&weapon, "damage")?,
+                    range: req(&weapon, "range")?,
+                    cooldown: req(&weapon, "cooldown")?,
+                    impact_time: req(&weapon, "impact_time")?,
+                    swing_time: req(&weapon, "swing_time")?,
+                    cleave_degrees: opt(&weapon, "cleave_degrees", 0.0),
+                    impact_delay: opt(&weapon, "impact_delay", 0.0),
+                    stationary_while_acting: opt_bool(&weapon, "stationary_while_acting"),
+                    muzzle_offset: offset(&weapon, "muzzle_offset"),
+                    impact_anchor: opt_i64(&weapon, "impact_anchor", 0),
+                    priority: opt_i64(&weapon, "priority", BASIC_ATTACK_PRIORITY),
+                })),
+                "projectile" => blueprint.add_weapon(Weapon::Projectile(ProjectileWeapon {
+                    damage: req(&weapon, "damage")?,
+                    range: req(&weapon, "range")?,
+                    cooldown: req(&weapon, "cooldown")?,
+                    impact_time: req(&weapon, "impact_time")?,
+                    swing_time: req(&weapon, "swing_time")?,
+                    projectile_speed: req(&weapon, "projectile_speed")?,
+                    projectile_texture: texture(&weapon, "projectile_texture"),
+                    projectile_scale: opt(&weapon, "projectile_scale", 1.0),
+                    splash_radius: opt(&weapon, "splash_radius", 0.0),
+                    splash_min_percent: opt(&weapon, "splash_min_percent", 1.0),
+                    impact_delay: opt(&weapon, "impact_delay", 0.0),
+                    stationary_while_acting: weapon
+                        .get("stationary_while_acting")
+                        .and_then(|value| value.to::<bool>())
+                        .unwrap_or(true),
+                    muzzle_offset: offset(&weapon, "muzzle_offset"),
+                    priority: opt_i64(&weapon, "priority", BASIC_ATTACK_PRIORITY),
+                })),
+                "radius" => blueprint.add_weapon(Weapon::Radius(RadiusWeapon {
+                    damage: req(&weapon, "damage")?,
+                    range: req(&weapon, "range")?,
+                    cooldown: req(&weapon, "cooldown")?,
+                    impact_time: req(&weapon, "impact_time")?,
+                    swing_time: req(&weapon, "swing_time")?,
+                    priority: opt_i64(&weapon, "priority", BASIC_ATTACK_PRIORITY),
+                })),
+                other => return Err(format!("unknown weapon type `{}`", other)),
+            }
+        }
+
+        for ability in entries(data, "abilities")? {
+            let name = ability
+                .get("name")
+                .and_then(|value| value.to::<String>())
+                .ok_or("ability entry without a `name` string")?;
+            // Rider abilities attach to a weapon; everything else is a
+            // standalone ability on the unit.
+            let rider = matches!(
+                name.as_str(),
+                "slow_poison"
+                    | "burn"
+                    | "stun_on_hit"
+                    | "confusion"
+                    | "antiheal"
+                    | "shred_armor"
+                    | "chill"
+                    | "execute"
+                    | "disarm"
+                    | "attack_slow"
+            );
+            if rider {
+                // An absent `weapon_index` attaches the rider to every weapon.
+                let index = ability
+                    .get("weapon_index")
+                    .and_then(|value| value.to::<i64>())
+                    .map(|index| index.max(0) as usize);
+                if let Some(index) = index {
+                    if index >= blueprint.weapons.len() {
+                        return Err(format!(
+                            "rider `{}` weapon_index {} out of range ({} weapon(s))",
+                            name,
+                            index,
+                            blueprint.weapons.len()
+                        ));
+                    }
+                }
+                let rider_ability = match name.as_str() {
+                    "slow_poison" => UnitAbility::SlowPoison {
+                        percent_damage: req(&ability, "percent_damage")?,
+                        movement_debuff: req(&ability, "movement_debuff")?,
+                        duration: req(&ability, "duration")?,
+                        texture: texture(&ability, "texture"),
+                    },
+                    "burn" => UnitAbility::Burn {
+                        damage_per_second: req(&ability, "damage_per_second")?,
+                        duration: req(&ability, "duration")?,
+                        texture: texture(&ability, "texture"),
+                    },
+                    "stun_on_hit" => UnitAbility::Stun {
+                        duration: req(&ability, "duration")?,
+                        texture: texture(&ability, "texture"),
+                    },
+                    "confusion" => UnitAbility::Confusion {
+                        duration: req(&ability, "duration")?,
+                        texture: texture(&ability, "texture"),
+                    },
+                    "antiheal" => UnitAbility::AntiHeal {
+                        percent: req(&ability, "percent")?,
+                        duration: req(&ability, "duration")?,
+                        texture: texture(&ability, "texture"),
+                    },
+                    "shred_armor" => UnitAbility::ArmorReduction {
+                        duration: req(&ability, "duration")?,
+                        texture: texture(&ability, "texture"),
+                    },
+                    "execute" => UnitAbility::Execute {
+                        threshold_percent: req(&ability, "threshold_percent")?,
+                        bonus_multiplier: req(&ability, "bonus_multiplier")?,
+                    },
+                    "disarm" => UnitAbility::Disarm {
+                        duration: req(&ability, "duration")?,
+                        texture: texture(&ability, "texture"),
+                    },
+                    "attack_slow" => UnitAbility::AttackSlow {
+                        percent: req(&ability, "percent")?,
+                        duration: req(&ability, "duration")?,
+                    },
+                    _ => UnitAbility::ChillOnHit {
+                        slow_per_stack: req(&ability, "slow_per_stack")?,
+                        max_stacks: opt_i64(&ability, "max_stacks", 1),
+                        freeze_duration: req(&ability, "freeze_duration")?,
+                        duration: req(&ability, "duration")?,
+                        texture: texture(&ability, "texture"),
+                    },
+                };
+                blueprint.add_rider(index, rider_ability);
+                continue;
+            }
+            let standalone = match name.as_str() {
+                "backstab" => UnitAbility::Backstab {
+                    damage: req(&ability, "damage")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "cleanse" => UnitAbility::Cleanse {
+                    heal_amount: req(&ability, "heal_amount")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "heal" => UnitAbility::Heal {
+                    heal_amount: req(&ability, "heal_amount")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "fortify" => UnitAbility::Fortify {
+                    armor_buff: req(&ability, "armor_buff")?,
+                    armor_mult: opt(&ability, "armor_mult", 0.0),
+                    heal_per_second: req(&ability, "heal_per_second")?,
+                    duration: req(&ability, "duration")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "buff_resistance" => UnitAbility::BuffResistance {
+                    armor_buff: req(&ability, "armor_buff")?,
+                    armor_mult: opt(&ability, "armor_mult", 0.0),
+                    magic_resist_buff: req(&ability, "magic_resist_buff")?,
+                    duration: req(&ability, "duration")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "overdrive" => UnitAbility::Overdrive {
+                    percent_cooldown_reduction: req(&ability, "percent_cooldown_reduction")?,
+                    duration: req(&ability, "duration")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "damage_buff" => UnitAbility::DamageBuff {
+                    percent: req(&ability, "percent")?,
+                    duration: req(&ability, "duration")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "speed_mass_buff" => UnitAbility::SpeedAndMassBuffAbility {
+                    speed_buff: req(&ability, "speed_buff")?,
+                    speed_mult: opt(&ability, "speed_mult", 0.0),
+                    mass_buff: req(&ability, "mass_buff")?,
+                    mass_mult: opt(&ability, "mass_mult", 0.0),
+                    duration: req(&ability, "duration")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "heal_totem" => UnitAbility::HealTotem {
+                    heal_per_pulse: req(&ability, "heal_per_pulse")?,
+                    pulse_period: req(&ability, "pulse_period")?,
+                    pulse_radius: req(&ability, "pulse_radius")?,
+                    totem_hitpoints: req(&ability, "totem_hitpoints")?,
+                    totem_radius: opt(&ability, "totem_radius", 8.0),
+                    lifetime: req(&ability, "lifetime")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "damage_totem" => UnitAbility::DamageTotem {
+                    damage_per_pulse: req(&ability, "damage_per_pulse")?,
+                    pulse_period: req(&ability, "pulse_period")?,
+                    pulse_radius: req(&ability, "pulse_radius")?,
+                    totem_hitpoints: req(&ability, "totem_hitpoints")?,
+                    totem_radius: opt(&ability, "totem_radius", 8.0),
+                    lifetime: req(&ability, "lifetime")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "place_mine" => UnitAbility::PlaceMineAbility {
+                    damage: req(&ability, "damage")?,
+                    trigger_radius: req(&ability, "trigger_radius")?,
+                    arm_time: req(&ability, "arm_time")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "bodyguard" => UnitAbility::Bodyguard {
+                    redirect_fraction: req(&ability, "redirect_fraction")?,
+                    radius: req(&ability, "radius")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "whirlwind" => UnitAbility::Whirlwind {
+                    damage: req(&ability, "damage")?,
+                    radius: req(&ability, "radius")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    impact_delay: opt(&ability, "impact_delay", 0.0),
+                    texture: texture(&ability, "texture"),
+                },
+                "magic_missile" => UnitAbility::MagicMissile {
+                    damage: req(&ability, "damage")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    impact_delay: opt(&ability, "impact_delay", 0.0),
+                    projectile_speed: req(&ability, "projectile_speed")?,
+                    projectile_texture: texture(&ability, "projectile_texture"),
+                    splash_radius: opt(&ability, "splash_radius", 0.0),
+                },
+                "bubble_bomb" => UnitAbility::BubbleBombAbility {
+                    damage: req(&ability, "damage")?,
+                    range: req(&ability, "range")?,
+                    radius: req(&ability, "radius")?,
+                    stun_duration: req(&ability, "stun_duration")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    projectile_speed: req(&ability, "projectile_speed")?,
+                    effect_texture: texture(&ability, "effect_texture"),
+                },
+                "instant_stun" => UnitAbility::InstantStunAbility {
+                    damage: req(&ability, "damage")?,
+                    range: req(&ability, "range")?,
+                    stun_duration: req(&ability, "stun_duration")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                    effect_texture: texture(&ability, "effect_texture"),
+                },
+                "ground_aoe" => UnitAbility::GroundAoeAbility {
+                    damage: req(&ability, "damage")?,
+                    radius: req(&ability, "radius")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    telegraph_texture: texture(&ability, "telegraph_texture"),
+                    effect_texture: texture(&ability, "effect_texture"),
+                },
+                "instant_disarm" => UnitAbility::InstantDisarmAbility {
+                    range: req(&ability, "range")?,
+                    duration: req(&ability, "duration")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                    effect_texture: texture(&ability, "effect_texture"),
+                },
+                "hypnosis" => UnitAbility::HypnosisAbility {
+                    range: req(&ability, "range")?,
+                    duration: req(&ability, "duration")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "self_heal" => UnitAbility::SelfHealAbility {
+                    heal_amount: req(&ability, "heal_amount")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "self_overclock" => UnitAbility::SelfOverclockAbility {
+                    percent_cooldown_reduction: req(&ability, "percent_cooldown_reduction")?,
+                    duration: req(&ability, "duration")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "area_cleanse" => UnitAbility::AreaCleanseAbility {
+                    radius: req(&ability, "radius")?,
+                    heal_amount: opt(&ability, "heal_amount", 0.0),
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "baneling_attack" => UnitAbility::BanelingAttack {
+                    damage: req(&ability, "damage")?,
+                    radius: req(&ability, "radius")?,
+                },
+                "heal_baneling" => UnitAbility::HealBaneling {
+                    heal_amount: req(&ability, "heal_amount")?,
+                },
+                "execution_attack" => UnitAbility::ExecutionAttack {
+                    heal_amount: req(&ability, "heal_amount")?,
+                },
+                "poison_baneling" => UnitAbility::PoisonBaneling {
+                    radius: req(&ability, "radius")?,
+                    duration: req(&ability, "duration")?,
+                    percent_damage: req(&ability, "percent_damage")?,
+                    movement_debuff: req(&ability, "movement_debuff")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "lifesteal" => UnitAbility::Lifesteal {
+                    percent: req(&ability, "percent")?,
+                },
+                "thorns" => UnitAbility::ThornsAbility {
+                    percent: opt(&ability, "percent", 0.0),
+                    flat: opt(&ability, "flat", 0.0),
+                    duration: req(&ability, "duration")?,
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    initial_cooldown: opt(&ability, "initial_cooldown", 0.0),
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "thorns_passive" => UnitAbility::ThornsPassive {
+                    percent: opt(&ability, "percent", 0.0),
+                    flat: opt(&ability, "flat", 0.0),
+                },
+                "berserk" => UnitAbility::BerserkPassiveAbility {
+                    hp_threshold_percent: req(&ability, "hp_threshold_percent")?,
+                    attack_speed_bonus: req(&ability, "attack_speed_bonus")?,
+                    speed_bonus: opt(&ability, "speed_bonus", 0.0),
+                },
+                "summon" => UnitAbility::SummonAbility {
+                    summon_blueprint_id: req(&ability, "summon_blueprint_id")? as usize,
+                    count: opt_i64(&ability, "count", 1),
+                    duration: req(&ability, "duration")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "aura" => UnitAbility::AuraAbility {
+                    buff: Self::stat_buff_from_dict(&ability),
+                    radius: req(&ability, "radius")?,
+                    affects_allies: ability
+                        .get("affects_allies")
+                        .and_then(|value| value.to::<bool>())
+                        .unwrap_or(true),
+                },
+                "charge" => UnitAbility::ChargeAbility {
+                    dash_speed: req(&ability, "dash_speed")?,
+                    max_distance: req(&ability, "max_distance")?,
+                    impact_damage: req(&ability, "impact_damage")?,
+                    impact_stun: opt(&ability, "impact_stun", 0.0),
+                    range: req(&ability, "range")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                "blink" => UnitAbility::BlinkAbility {
+                    distance: req(&ability, "distance")?,
+                    cooldown: req(&ability, "cooldown")?,
+                    swing_time: req(&ability, "swing_time")?,
+                    impact_time: req(&ability, "impact_time")?,
+                    texture: texture(&ability, "texture"),
+                },
+                other => return Err(format!("unknown ability name `{}`", other)),
+            };
+            blueprint.add_ability(standalone);
+        }
+
+        Ok(blueprint)
+    }
+
+    /// Select weighted-average boid blending for a blueprint; additive is the
+    /// default.
+    #[method]
+    fn set_blueprint_boid_blend_mode(&mut self, blueprint_id: usize, weighted: bool) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.boid_blend_mode = if weighted {
+                BoidBlendMode::WeightedAverage
+            } else {
+                BoidBlendMode::Additive
+            };
+        }
+    }
+
+    /// Store boid tuning overrides on a blueprint. Recognized keys:
+    /// separation_radius, separation_multiplier, cohesion_radius,
+    /// cohesion_multiplier, alignment_radius, alignment_multiplier,
+    /// avoid_walls_multiplier, stopping_multiplier, seek_multiplier.
+    /// Missing keys keep the spawn defaults; already-spawned units are
+    /// unaffected.
+    #[method]
+    fn set_blueprint_boid_params(&mut self, blueprint_id: usize, params: Dictionary) {
+        match self.unit_blueprints.get_mut(blueprint_id) {
+            Some(blueprint) => blueprint.boid_tuning = Self::boid_tuning_from_dict(&params),
+            None => {
+                godot_error!("set_blueprint_boid_params: no blueprint with id {}", blueprint_id);
+            }
+        }
+    }
+
+    /// Apply the same overrides to one live unit; keys as in
+    /// `set_blueprint_boid_params`. Returns false if the unit is gone.
+    #[method]
+    fn set_unit_boid_params(&mut self, entity_id: i64, params: Dictionary) -> bool {
+        let tuning = Self::boid_tuning_from_dict(&params);
+        let entity = entity_from_handle(entity_id);
+        crate::boids::apply_boid_tuning(&mut self.world, entity, &tuning)
+    }
+
+    /// Flat stat modifiers from a Dictionary; recognized keys are
+    /// armor_buff, magic_resist_buff, speed_buff, acceleration_buff,
+    /// mass_buff, heal_efficacy_mult_buff and max_hp_buff. Missing keys
+    /// stay zero.
+    fn stat_buff_from_dict(params: &Dictionary) -> StatBuff {
+        fn field(params: &Dictionary, key: &str) -> f32 {
+            params
+                .get(key)
+                .and_then(|value| value.to::<f32>())
+                .unwrap_or(0.0)
+        }
+        StatBuff {
+            armor_buff: field(params, "armor_buff"),
+            magic_resist_buff: field(params, "magic_resist_buff"),
+            speed_buff: field(params, "speed_buff"),
+            acceleration_buff: field(params, "acceleration_buff"),
+            mass_buff: field(params, "mass_buff"),
+            heal_efficacy_mult_buff: field(params, "heal_efficacy_mult_buff"),
+            max_hp_buff: field(params, "max_hp_buff"),
+        }
+    }
+
+    fn boid_tuning_from_dict(params: &Dictionary) -> BoidTuningOverrides {
+        fn field(params: &Dictionary, key: &str) -> Option<f32> {
+            params.get(key).and_then(|value| value.to::<f32>())
+        }
+        BoidTuningOverrides {
+            separation_radius: field(params, "separation_radius"),
+            separation_multiplier: field(params, "separation_multiplier"),
+            cohesion_radius: field(params, "cohesion_radius"),
+            cohesion_multiplier: field(params, "cohesion_multiplier"),
+            alignment_radius: field(params, "alignment_radius"),
+            alignment_multiplier: field(params, "alignment_multiplier"),
+            avoid_walls_multiplier: field(params, "avoid_walls_multiplier"),
+            stopping_multiplier: field(params, "stopping_multiplier"),
+            seek_multiplier: field(params, "seek_multiplier"),
+        }
+    }
+
+    /// Toggle one of a unit's built-in boids ("separation", "cohesion",
+    /// "alignment", "seek_enemies", "avoid_walls", "stopping",
+    /// "charge_at_enemy", "kite_nearest_enemy") at runtime. Disabled boid
+    /// parameters are stashed and restored on re-enable. Returns false for
+    /// unknown names or missing entities.
+    #[method]
+    fn set_unit_boid_enabled(&mut self, entity_id: i64, boid_name: String, enabled: bool) -> bool {
+        let entity = entity_from_handle(entity_id);
+        crate::boids::set_boid_enabled(&mut self.world, entity, &boid_name, enabled)
+    }
+
+    /// Rewrite the target-selection mode ("nearest", "furthest",
+    /// "lowest_hp", "highest_threat") on every action of a live unit.
+    /// Highest-threat mode also points the unit's charge boid at its aggro
+    /// leader. Returns false for unknown modes or missing units.
+    #[method]
+    fn set_unit_targeting_mode(&mut self, entity_id: i64, mode: String) -> bool {
+        let selection = match mode.as_str() {
+            "nearest" => TargetSelection::Nearest,
+            "furthest" => TargetSelection::Furthest,
+            "lowest_hp" => TargetSelection::LowestHpFraction,
+            "highest_threat" => TargetSelection::HighestThreat,
+            _ => return false,
+        };
+        let entity = entity_from_handle(entity_id);
+        let unit_actions = match self.world.get::<UnitActions>(entity) {
+            Some(actions) => actions.vec.clone(),
+            None => return false,
+        };
+        for action in unit_actions {
+            if let Some(mut flags) = self.world.get_mut::<TargetFlags>(action) {
+                flags.selection = selection;
+            }
+        }
+        if let Some(mut charge) = self.world.get_mut::<ChargeAtEnemyBoid>(entity) {
+            charge.chase_top_threat = selection == TargetSelection::HighestThreat;
+        }
+        true
+    }
+
+    /// Enable or disable the analytics log; see [`MatchLog`].
+    #[method]
+    fn set_match_logging(&mut self, enabled: bool) {
+        self.world.resource_mut::<MatchLog>().enabled = enabled;
+    }
+
+    /// Export the accumulated [`MatchLog`] as a JSON string.
+    #[method]
+    fn export_match_log(&mut self) -> String {
+        let victor = self.world.resource::<Victor>().team;
+        self.world.resource::<MatchLog>().to_json(victor)
+    }
+
+    /// Manually link a ward to its bodyguard, overriding auto-selection.
+    #[method]
+    fn set_bodyguard_link(
+        &mut self,
+        ward_id: i64,
+        guard_id: i64,
+        redirect_fraction: f32,
+        radius: f32,
+    ) {
+        let ward = entity_from_handle(ward_id);
+        if self.world.get_entity(ward).is_some() {
+            self.world.entity_mut(ward).insert(crate::effects::DamageRedirect {
+                target: entity_from_handle(guard_id),
+                fraction: redirect_fraction,
+                radius,
+            });
+        }
+    }
+
+    #[method]
+    fn clear_bodyguard_link(&mut self, ward_id: i64) {
+        let ward = entity_from_handle(ward_id);
+        if self.world.get_entity(ward).is_some() {
+            self.world.entity_mut(ward).remove::<crate::effects::DamageRedirect>();
+        }
+    }
+
+    /// Read-only camera summary: where damage is happening, where the front
+    /// line sits, and the healthiest unit per team. Keys absent when fewer
+    /// than two teams remain or no damage has landed yet.
+    #[method]
+    fn get_battle_focus(&mut self) -> Dictionary {
+        let focus = crate::event::compute_battle_focus(&mut self.world);
+        let dict = Dictionary::new();
+        if let Some(centroid) = focus.damage_centroid {
+            dict.insert("damage_centroid", centroid);
+        }
+        if let Some(front_line) = focus.front_line {
+            dict.insert("front_line", front_line);
+        }
+        let strongest = Dictionary::new();
+        for (team, (entity, hp)) in focus.strongest_by_team.iter() {
+            let pair = VariantArray::new();
+            pair.push(entity_handle(*entity));
+            pair.push(*hp);
+            strongest.insert(*team, pair.into_shared());
+        }
+        dict.insert("strongest", strongest.into_shared());
+        dict.into_shared()
+    }
+
+    /// Cast a ground-targetable action at a point, bypassing autocast. On
+    /// failure returns false and records the reason in `get_last_error`.
+    #[method]
+    fn command_cast_at_position(
+        &mut self,
+        entity_id: i64,
+        action_index: i64,
+        position: Vector2,
+    ) -> bool {
+        let unit = entity_from_handle(entity_id);
+        match actions::command_cast_at_position(
+            &mut self.world,
+            unit,
+            action_index as usize,
+            position,
+        ) {
+            Ok(()) => true,
+            Err(reason) => {
+                self.last_error = reason.to_string();
+                false
+            }
+        }
+    }
+
+    /// Cast an action at a specific unit, bypassing autocast. On failure
+    /// returns false and records the reason in `get_last_error`.
+    #[method]
+    fn cast_ability(&mut self, caster_id: i64, action_index: i64, target_id: i64) -> bool {
+        let caster = entity_from_handle(caster_id);
+        let target = entity_from_handle(target_id);
+        match actions::command_cast_at_entity(&mut self.world, caster, action_index as usize, target)
+        {
+            Ok(()) => true,
+            Err(reason) => {
+                self.last_error = reason.to_string();
+                false
+            }
+        }
+    }
+
+    /// Turn autocast off (or back on) for one of a unit's actions so it only
+    /// fires through the manual cast commands. Returns false for missing
+    /// units or bad indices.
+    #[method]
+    fn set_action_autocast(&mut self, caster_id: i64, action_index: i64, enabled: bool) -> bool {
+        let caster = entity_from_handle(caster_id);
+        let action = match self.world.get::<UnitActions>(caster) {
+            Some(actions) => match actions.vec.get(action_index as usize) {
+                Some(action) => *action,
+                None => return false,
+            },
+            None => return false,
+        };
+        if enabled {
+            self.world
+                .entity_mut(action)
+                .remove::<actions::AutocastDisabled>();
+        } else {
+            self.world
+                .entity_mut(action)
+                .insert(actions::AutocastDisabled);
+        }
+        true
+    }
+
+    #[method]
+    fn get_last_error(&self) -> String {
+        self.last_error.clone()
+    }
+
+    /// Apply an RTS-style upgrade to a blueprint and to every living unit
+    /// spawned from it. Recognized keys: hitpoints_add, hitpoints_mult,
+    /// armor_add, magic_resist_add, movespeed_mult, damage_add, damage_mult.
+    #[method]
+    fn apply_blueprint_upgrade(&mut self, blueprint_id: usize, upgrade: Dictionary) {
+        fn field(upgrade: &Dictionary, key: &str, default: f32) -> f32 {
+            upgrade
+                .get(key)
+                .and_then(|value| value.to::<f32>())
+                .unwrap_or(default)
+        }
+        let upgrade = crate::unit::BlueprintUpgrade {
+            hitpoints_add: field(&upgrade, "hitpoints_add", 0.0),
+            hitpoints_mult: field(&upgrade, "hitpoints_mult", 1.0),
+            armor_add: field(&upgrade, "armor_add", 0.0),
+            magic_resist_add: field(&upgrade, "magic_resist_add", 0.0),
+            movespeed_mult: field(&upgrade, "movespeed_mult", 1.0),
+            damage_add: field(&upgrade, "damage_add", 0.0),
+            damage_mult: field(&upgrade, "damage_mult", 1.0),
+        };
+        match self.unit_blueprints.get_mut(blueprint_id) {
+            Some(blueprint) => blueprint.apply_upgrade(&upgrade),
+            None => return,
+        }
+        crate::unit::apply_upgrade_to_live_units(&mut self.world, blueprint_id, &upgrade);
+    }
+
+    /// Overwrite a registered blueprint's core stats in place. Units spawned
+    /// afterwards use the new values; already-spawned units keep theirs (see
+    /// `apply_blueprint_to_living`).
+    #[method]
+    fn update_blueprint_stats(
+        &mut self,
+        blueprint_id: usize,
+        hitpoints: f32,
+        movespeed: f32,
+        armor: f32,
+        magic_resist: f32,
+        acceleration: f32,
+    ) {
+        match self.unit_blueprints.get_mut(blueprint_id) {
+            Some(blueprint) => {
+                blueprint.hitpoints = hitpoints;
+                blueprint.movespeed = movespeed;
+                blueprint.armor = armor;
+                blueprint.magic_resist = magic_resist;
+                blueprint.acceleration = acceleration;
+            }
+            None => {
+                godot_error!("update_blueprint_stats: no blueprint with id {}", blueprint_id);
+            }
+        }
+    }
+
+    /// Remove an ability from a blueprint by the index it was added at.
+    /// Units already spawned keep the ability's action entity.
+    #[method]
+    fn remove_blueprint_ability(&mut self, blueprint_id: usize, index: usize) {
+        match self.unit_blueprints.get_mut(blueprint_id) {
+            Some(blueprint) => {
+                if index < blueprint.abilities.len() {
+                    blueprint.abilities.remove(index);
+                } else {
+                    godot_error!(
+                        "remove_blueprint_ability: blueprint {} has no ability {}",
+                        blueprint_id,
+                        index
+                    );
+                }
+            }
+            None => {
+                godot_error!("remove_blueprint_ability: no blueprint with id {}", blueprint_id);
+            }
+        }
+    }
+
+    /// Push a blueprint's current base stats onto its living units, after
+    /// edits via `update_blueprint_stats`. Current hp is clamped to the new
+    /// maximum; buffed values recompute on the next `apply_stat_buffs` pass.
+    #[method]
+    fn apply_blueprint_to_living(&mut self, blueprint_id: usize) {
+        let blueprint = match self.unit_blueprints.get(blueprint_id) {
+            Some(blueprint) => blueprint.clone(),
+            None => return,
+        };
+        crate::unit::apply_blueprint_stats_to_live_units(&mut self.world, blueprint_id, &blueprint);
+    }
+
+    /// Set a team's AI profile (0 defensive, 1 balanced, 2 aggressive) and
+    /// re-apply it to the team's live units immediately.
+    #[method]
+    fn set_team_ai_profile(&mut self, team_id: i64, profile: i64) {
+        let profile = AIProfile::from_i64(profile);
+        self.world
+            .resource_mut::<TeamAIProfiles>()
+            .map
+            .insert(team_id, profile);
+        apply_team_ai_profile(&mut self.world, team_id);
+    }
+
+    /// Toggle fog of war; off (the default) keeps fog-free behavior exactly.
+    #[method]
+    fn set_fog_enabled(&mut self, enabled: bool) {
+        let mut fog = self.world.resource_mut::<crate::terrain::FogOfWar>();
+        fog.enabled = enabled;
+        if !enabled {
+            fog.map.clear();
+        }
+    }
+
+    #[method]
+    fn is_position_visible(&self, team_id: i64, position: Vector2) -> bool {
+        let terrain = self.world.resource::<TerrainMap>();
+        self.world
+            .resource::<crate::terrain::FogOfWar>()
+            .is_visible(team_id, terrain, position)
+    }
+
+    /// Row-major visibility bitmap for the team, one byte per terrain cell
+    /// (1 = visible). Empty while fog is disabled or before the first pass.
+    #[method]
+    fn get_fog_bitmap(&self, team_id: i64) -> ByteArray {
+        let fog = self.world.resource::<crate::terrain::FogOfWar>();
+        match fog.map.get(&team_id) {
+            Some(grid) => ByteArray::from_vec(grid.iter().map(|v| *v as u8).collect()),
+            None => ByteArray::new(),
+        }
+    }
+
+    /// Flip an action's Disabled marker. Re-enabling keeps any remaining
+    /// cooldown; disabling mid-swing lets the current swing finish.
+    #[method]
+    fn set_action_enabled(&mut self, entity_id: i64, action_index: i64, enabled: bool) -> bool {
+        let unit = entity_from_handle(entity_id);
+        let action = match self
+            .world
+            .get::<UnitActions>(unit)
+            .and_then(|actions| actions.vec.get(action_index as usize).copied())
+        {
+            Some(action) => action,
+            None => {
+                self.last_error = "no action at that index".to_string();
+                return false;
+            }
+        };
+        if enabled {
+            self.world.entity_mut(action).remove::<actions::Disabled>();
+        } else {
+            self.world.entity_mut(action).insert(actions::Disabled);
+        }
+        true
+    }
+
+    /// One dictionary per action: range, cooldown state, and role flags.
+    #[method]
+    fn get_unit_actions(&self, entity_id: i64) -> VariantArray {
+        let out = VariantArray::new();
+        let unit = entity_from_handle(entity_id);
+        if let Some(actions) = self.world.get::<UnitActions>(unit) {
+            for action in actions.vec.iter() {
+                let dict = Dictionary::new();
+                dict.insert("entity_id", entity_handle(*action));
+                if let Some(range) = self.world.get::<actions::ActionRange>(*action) {
+                    dict.insert("range", range.0);
+                }
+                if let Some(cooldown) = self.world.get::<actions::ActionCooldown>(*action) {
+                    dict.insert("cooldown", cooldown.0);
+                }
+                dict.insert(
+                    "remaining_cooldown",
+                    self.world
+                        .get::<actions::Cooldown>(*action)
+                        .map(|c| c.0)
+                        .unwrap_or(0.0),
+                );
+                dict.insert(
+                    "basic_attack",
+                    self.world.get::<actions::BasicAttack>(*action).is_some(),
+                );
+                dict.insert(
+                    "disabled",
+                    self.world.get::<actions::Disabled>(*action).is_some(),
+                );
+                let effects = VariantArray::new();
+                if let Some(on_hit) = self.world.get::<OnHitEffects>(*action) {
+                    for effect in on_hit.vec.iter() {
+                        let (kind, params) = effect.describe();
+                        let described = Dictionary::new();
+                        described.insert("kind", kind);
+                        for (name, value) in params {
+                            described.insert(name, value);
+                        }
+                        effects.push(described.into_shared());
+                    }
+                }
+                dict.insert("effects", effects.into_shared());
+                out.push(dict.into_shared());
+            }
+        }
+        out.into_shared()
+    }
+
+    /// Live entity counts bucketed by marker taxonomy. Empty unless
+    /// `debug_tools` is set; meant for a console command, not per-frame use.
+    #[method]
+    fn get_entity_census(&mut self) -> Dictionary {
+        let dict = Dictionary::new();
+        if !self.debug_tools {
+            return dict.into_shared();
+        }
+        let mut units = 0i64;
+        let mut actions = 0i64;
+        let mut buffs = 0i64;
+        let mut projectiles = 0i64;
+        let mut visuals = 0i64;
+        let mut unknown = 0i64;
+        let mut total = 0i64;
+        let mut query = self.world.query::<(
+            Entity,
+            Option<&UnitActions>,
+            Option<&ActionOwner>,
+            Option<&crate::effects::BuffType>,
+            Option<&crate::projectiles::Projectile>,
+            Option<&Renderable>,
+        )>();
+        for (_, unit, action, buff, projectile, renderable) in query.iter(&self.world) {
+            total += 1;
+            if unit.is_some() {
+                units += 1;
+            } else if action.is_some() {
+                actions += 1;
+            } else if buff.is_some() {
+                buffs += 1;
+            } else if projectile.is_some() {
+                projectiles += 1;
+            } else if renderable.is_some() {
+                visuals += 1;
+            } else {
+                unknown += 1;
+            }
+        }
+        dict.insert("total", total);
+        dict.insert("units", units);
+        dict.insert("actions", actions);
+        dict.insert("buffs", buffs);
+        dict.insert("projectiles", projectiles);
+        dict.insert("visuals", visuals);
+        dict.insert("unknown", unknown);
+        dict.into_shared()
+    }
+
+    /// Entities that should have been torn down: actions whose owner is gone,
+    /// buffs with a dead target and no timer to expire them, and renderables
+    /// with no position. One dictionary (entity_id, kind) per finding.
+    #[method]
+    fn debug_detect_orphans(&mut self) -> VariantArray {
+        let out = VariantArray::new();
+        if !self.debug_tools {
+            return out.into_shared();
+        }
+        let mut findings: Vec<(Entity, &'static str)> = Vec::new();
+        let mut owner_query = self.world.query::<(Entity, &ActionOwner)>();
+        for (entity, owner) in owner_query.iter(&self.world) {
+            if self.world.get_entity(owner.0).is_none() {
+                findings.push((entity, "ownerless_action"));
+            }
+        }
+        let mut buff_query = self.world.query_filtered::<(Entity, &TargetEntity), (
+            With<crate::effects::BuffType>,
+            Without<crate::effects::BuffTimer>,
+        )>();
+        for (entity, target) in buff_query.iter(&self.world) {
+            if self.world.get_entity(target.0).is_none() {
+                findings.push((entity, "untimed_orphan_buff"));
+            }
+        }
+        let mut renderable_query =
+            self.world
+                .query_filtered::<Entity, (With<Renderable>, Without<Position>)>();
+        for entity in renderable_query.iter(&self.world) {
+            findings.push((entity, "detached_renderable"));
+        }
+        for (entity, kind) in findings {
+            let dict = Dictionary::new();
+            dict.insert("entity_id", entity_handle(entity));
+            dict.insert("kind", kind);
+            out.push(dict.into_shared());
+        }
+        out.into_shared()
+    }
+
+    /// Tune target stickiness; see [`actions::TargetStickiness`].
+    #[method]
+    fn set_target_stickiness(&mut self, range_margin: f32, switch_factor: f32) {
+        self.world.insert_resource(actions::TargetStickiness {
+            range_margin,
+            switch_factor,
+        });
+    }
+
+    /// Tune how far a channeling unit's target may stray before the swing is
+    /// interrupted; see [`actions::ChannelInterruptLeeway`].
+    #[method]
+    fn set_channel_interrupt_leeway(&mut self, range_margin: f32) {
+        self.world
+            .insert_resource(actions::ChannelInterruptLeeway { range_margin });
+    }
+
+    /// Snapshot of one unit for UI: position, velocity, hitpoints, team and
+    /// whether it is stunned or mid-swing. Direct lookups, cheap enough to
+    /// poll for every on-screen unit each frame. Empty if the id is stale.
+    #[method]
+    fn get_unit_state(&mut self, entity_id: i64) -> Dictionary {
+        let dict = Dictionary::new();
+        let entity = entity_from_handle(entity_id);
+        let (position, hitpoints) = match (
+            self.world.get::<Position>(entity),
+            self.world.get::<Hitpoints>(entity),
+        ) {
+            (Some(position), Some(hitpoints)) => (position, hitpoints),
+            _ => return dict.into_shared(),
+        };
+        dict.insert("position", position.pos);
+        dict.insert("hp", hitpoints.hp);
+        dict.insert("max_hp", hitpoints.max_hp);
+        if let Some(velocity) = self.world.get::<Velocity>(entity) {
+            dict.insert("velocity", velocity.v);
+        }
+        if let Some(alignment) = self.world.get::<TeamAlignment>(entity) {
+            dict.insert("team", alignment.alignment);
+        }
+        if let Some(blueprint) = self.world.get::<BlueprintId>(entity) {
+            dict.insert("blueprint_id", blueprint.0 as i64);
+        }
+        dict.insert(
+            "stunned",
+            self.world.get::<crate::effects::Stunned>(entity).is_some(),
+        );
+        dict.insert(
+            "performing_action",
+            self.world
+                .get::<actions::PerformingActionState>(entity)
+                .is_some(),
+        );
+        dict.into_shared()
+    }
+
+    /// Cast-bar data for the unit's in-flight swing; empty when idle.
+    #[method]
+    fn get_unit_cast_progress(&mut self, entity_id: i64) -> Dictionary {
+        let dict = Dictionary::new();
+        let entity = entity_from_handle(entity_id);
+        if let Some(state) = self.world.get::<actions::PerformingActionState>(entity) {
+            let action = state.action;
+            if let (Some(channeling), Some(swing)) = (
+                self.world.get::<ChannelingDetails>(action),
+                self.world.get::<SwingDetails>(action),
+            ) {
+                dict.insert("action", entity_handle(action));
+                dict.insert("total_time_channeled", channeling.total_time_channeled);
+                dict.insert("impact_time", swing.impact_time);
+                dict.insert("swing_time", swing.swing_time);
+            }
+        }
+        dict.into_shared()
+    }
+
+    /// Debug view of damage queued against a unit but not yet applied.
+    /// Returns an array of `[damage, remaining_delay]` pairs.
+    #[method]
+    fn get_unit_queued_damage(&mut self, entity_id: i64) -> VariantArray {
+        let array = VariantArray::new();
+        let entity = entity_from_handle(entity_id);
+        if let Some(applied) = self.world.get::<AppliedDamage>(entity) {
+            for instance in applied.vec.iter() {
+                let pair = VariantArray::new();
+                pair.push(instance.damage);
+                pair.push(instance.delay);
+                array.push(pair);
+            }
+        }
+        array.into_shared()
+    }
+
+    /// Debug view of the threat one attacker has accumulated against a
+    /// unit, for tuning decay and bonuses. 0.0 when either side is gone.
+    #[method]
+    fn get_unit_threat(&mut self, entity_id: i64, attacker_id: i64) -> f32 {
+        let entity = entity_from_handle(entity_id);
+        let attacker = entity_from_handle(attacker_id);
+        self.world
+            .get::<ThreatTable>(entity)
+            .map(|table| table.get(attacker))
+            .unwrap_or(0.0)
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_melee_weapon_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage: f32,
+        range: f32,
+        cooldown: f32,
+        impact_time: f32,
+        swing_time: f32,
+        cleave_degrees: f32,
+        #[opt] impact_delay: Option<f32>,
+        #[opt] stationary_while_acting: Option<bool>,
+        #[opt] muzzle_offset: Option<Vector2>,
+        #[opt] impact_anchor: Option<i64>,
+        #[opt] priority: Option<i64>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_weapon(Weapon::Melee(MeleeWeapon {
+                damage,
+                range,
+                cooldown,
+                impact_time,
+                swing_time,
+                cleave_degrees,
+                impact_delay: impact_delay.unwrap_or(0.0),
+                stationary_while_acting: stationary_while_acting.unwrap_or(false),
+                muzzle_offset: muzzle_offset.unwrap_or(Vector2::ZERO),
+                impact_anchor: impact_anchor.unwrap_or(0),
+                priority: priority.unwrap_or(BASIC_ATTACK_PRIORITY),
+            }));
+        }
+    }
+
+    /// AoE-around-self weapon: every swing hits everything within range.
+    #[method]
+    fn add_radius_weapon_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage: f32,
+        range: f32,
+        cooldown: f32,
+        impact_time: f32,
+        swing_time: f32,
+        #[opt] priority: Option<i64>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_weapon(Weapon::Radius(RadiusWeapon {
+                damage,
+                range,
+                cooldown,
+                impact_time,
+                swing_time,
+                priority: priority.unwrap_or(BASIC_ATTACK_PRIORITY),
+            }));
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_projectile_weapon_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage: f32,
+        range: f32,
+        cooldown: f32,
+        impact_time: f32,
+        swing_time: f32,
+        projectile_speed: f32,
+        projectile_texture: Rid,
+        projectile_scale: f32,
+        splash_radius: f32,
+        #[opt] impact_delay: Option<f32>,
+        #[opt] stationary_while_acting: Option<bool>,
+        #[opt] muzzle_offset: Option<Vector2>,
+        #[opt] splash_min_percent: Option<f32>,
+        #[opt] priority: Option<i64>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_weapon(Weapon::Projectile(ProjectileWeapon {
+                damage,
+                range,
+                cooldown,
+                impact_time,
+                swing_time,
+                projectile_speed,
+                projectile_texture,
+                projectile_scale,
+                splash_radius,
+                splash_min_percent: splash_min_percent.unwrap_or(1.0),
+                impact_delay: impact_delay.unwrap_or(0.0),
+                stationary_while_acting: stationary_while_acting.unwrap_or(true),
+                muzzle_offset: muzzle_offset.unwrap_or(Vector2::ZERO),
+                priority: priority.unwrap_or(BASIC_ATTACK_PRIORITY),
+            }));
+        }
+    }
+
+    /// Validate a rider's weapon index against the weapons registered so far.
+    /// No index means "every weapon", which needs no validation and is safe
+    /// to register before any weapon exists.
+    fn rider_weapon_index(
+        &mut self,
+        blueprint_id: usize,
+        weapon_index: Option<i64>,
+    ) -> Option<Option<usize>> {
+        let index = match weapon_index {
+            Some(index) => index.max(0) as usize,
+            None => return self.unit_blueprints.get(blueprint_id).map(|_| None),
+        };
+        match self.unit_blueprints.get(blueprint_id) {
+            Some(blueprint) if index < blueprint.weapons.len() => Some(Some(index)),
+            Some(blueprint) => {
+                godot_error!(
+                    "rider weapon index {} out of range; blueprint {} has {} weapon(s)",
+                    index,
+                    blueprint_id,
+                    blueprint.weapons.len()
+                );
+                None
+            }
+            None => None,
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_slow_poison_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        percent_damage: f32,
+        movement_debuff: f32,
+        duration: f32,
+        texture: Rid,
+        #[opt] weapon_index: Option<i64>,
+    ) {
+        if let Some(index) = self.rider_weapon_index(blueprint_id, weapon_index) {
+            self.unit_blueprints[blueprint_id].add_rider(
+                index,
+                UnitAbility::SlowPoison {
+                    percent_damage,
+                    movement_debuff,
+                    duration,
+                    texture,
+                },
+            );
+        }
+    }
+
+    /// On-hit burn rider: flat Magic damage per second for `duration`;
+    /// same-source hits refresh the burn instead of stacking it.
+    #[method]
+    fn add_burn_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage_per_second: f32,
+        duration: f32,
+        texture: Rid,
+        #[opt] weapon_index: Option<i64>,
+    ) {
+        if let Some(index) = self.rider_weapon_index(blueprint_id, weapon_index) {
+            self.unit_blueprints[blueprint_id].add_rider(
+                index,
+                UnitAbility::Burn {
+                    damage_per_second,
+                    duration,
+                    texture,
+                },
+            );
+        }
+    }
+
+    /// Execute rider on one weapon: hits that resolve while the target is
+    /// below `threshold_percent` of max hp deal `bonus_multiplier` times the
+    /// damage.
+    #[method]
+    fn add_execute_to_blueprint_weapon(
+        &mut self,
+        blueprint_id: usize,
+        weapon_index: i64,
+        threshold_percent: f32,
+        bonus_multiplier: f32,
+    ) {
+        if let Some(index) = self.rider_weapon_index(blueprint_id, Some(weapon_index)) {
+            self.unit_blueprints[blueprint_id].add_rider(
+                index,
+                UnitAbility::Execute {
+                    threshold_percent,
+                    bonus_multiplier,
+                },
+            );
+        }
+    }
+
+    #[method]
+    fn add_stun_on_hit_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        duration: f32,
+        texture: Rid,
+        #[opt] weapon_index: Option<i64>,
+    ) {
+        if let Some(index) = self.rider_weapon_index(blueprint_id, weapon_index) {
+            self.unit_blueprints[blueprint_id]
+                .add_rider(index, UnitAbility::Stun { duration, texture });
+        }
+    }
+
+    /// Attack-slow rider: hits make the victim's action cooldowns recover
+    /// `percent` slower for the duration.
+    #[method]
+    fn add_attack_slow_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        percent: f32,
+        duration: f32,
+        #[opt] weapon_index: Option<i64>,
+    ) {
+        if let Some(index) = self.rider_weapon_index(blueprint_id, weapon_index) {
+            self.unit_blueprints[blueprint_id]
+                .add_rider(index, UnitAbility::AttackSlow { percent, duration });
+        }
+    }
+
+    /// Disarm rider: hits shut down the victim's basic attacks for the
+    /// duration while its ability casts continue.
+    #[method]
+    fn add_disarm_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        duration: f32,
+        texture: Rid,
+        #[opt] weapon_index: Option<i64>,
+    ) {
+        if let Some(index) = self.rider_weapon_index(blueprint_id, weapon_index) {
+            self.unit_blueprints[blueprint_id]
+                .add_rider(index, UnitAbility::Disarm { duration, texture });
+        }
+    }
+
+    #[method]
+    fn add_confusion_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        duration: f32,
+        texture: Rid,
+        #[opt] weapon_index: Option<i64>,
+    ) {
+        if let Some(index) = self.rider_weapon_index(blueprint_id, weapon_index) {
+            self.unit_blueprints[blueprint_id]
+                .add_rider(index, UnitAbility::Confusion { duration, texture });
+        }
+    }
+
+    #[method]
+    fn add_antiheal_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        percent: f32,
+        duration: f32,
+        texture: Rid,
+        #[opt] weapon_index: Option<i64>,
+    ) {
+        if let Some(index) = self.rider_weapon_index(blueprint_id, weapon_index) {
+            self.unit_blueprints[blueprint_id].add_rider(
+                index,
+                UnitAbility::AntiHeal {
+                    percent,
+                    duration,
+                    texture,
+                },
+            );
+        }
+    }
+
+    #[method]
+    fn add_shred_armor_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        duration: f32,
+        texture: Rid,
+        #[opt] weapon_index: Option<i64>,
+    ) {
+        if let Some(index) = self.rider_weapon_index(blueprint_id, weapon_index) {
+            self.unit_blueprints[blueprint_id]
+                .add_rider(index, UnitAbility::ArmorReduction { duration, texture });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_chill_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        slow_per_stack: f32,
+        max_stacks: i64,
+        freeze_duration: f32,
+        duration: f32,
+        texture: Rid,
+        #[opt] weapon_index: Option<i64>,
+    ) {
+        if let Some(index) = self.rider_weapon_index(blueprint_id, weapon_index) {
+            self.unit_blueprints[blueprint_id].add_rider(
+                index,
+                UnitAbility::ChillOnHit {
+                    slow_per_stack,
+                    max_stacks,
+                    freeze_duration,
+                    duration,
+                    texture,
+                },
+            );
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_backstab_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] initial_cooldown: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::Backstab {
+                damage,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_cleanse_ability_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        heal_amount: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] initial_cooldown: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::Cleanse {
+                heal_amount,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_heal_ability_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        heal_amount: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] initial_cooldown: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::Heal {
+                heal_amount,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_fortify_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        armor_buff: f32,
+        heal_per_second: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] initial_cooldown: Option<f32>,
+        #[opt] armor_mult: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::Fortify {
+                armor_buff,
+                armor_mult: armor_mult.unwrap_or(0.0),
+                heal_per_second,
+                duration,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_buff_resistance_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        armor_buff: f32,
+        magic_resist_buff: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] initial_cooldown: Option<f32>,
+        #[opt] armor_mult: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::BuffResistance {
+                armor_buff,
+                armor_mult: armor_mult.unwrap_or(0.0),
+                magic_resist_buff,
+                duration,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_overdrive_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        percent_cooldown_reduction: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] initial_cooldown: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::Overdrive {
+                percent_cooldown_reduction,
+                duration,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_damage_buff_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        percent: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] initial_cooldown: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::DamageBuff {
+                percent,
+                duration,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_speed_mass_buff_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        speed_buff: f32,
+        mass_buff: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] initial_cooldown: Option<f32>,
+        #[opt] speed_mult: Option<f32>,
+        #[opt] mass_mult: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::SpeedAndMassBuffAbility {
+                speed_buff,
+                speed_mult: speed_mult.unwrap_or(0.0),
+                mass_buff,
+                mass_mult: mass_mult.unwrap_or(0.0),
+                duration,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_heal_totem_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        heal_per_pulse: f32,
+        pulse_period: f32,
+        pulse_radius: f32,
+        totem_hitpoints: f32,
+        lifetime: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] totem_radius: Option<f32>,
+        #[opt] initial_cooldown: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::HealTotem {
+                heal_per_pulse,
+                pulse_period,
+                pulse_radius,
+                totem_hitpoints,
+                totem_radius: totem_radius.unwrap_or(8.0),
+                lifetime,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_damage_totem_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage_per_pulse: f32,
+        pulse_period: f32,
+        pulse_radius: f32,
+        totem_hitpoints: f32,
+        lifetime: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] totem_radius: Option<f32>,
+        #[opt] initial_cooldown: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::DamageTotem {
+                damage_per_pulse,
+                pulse_period,
+                pulse_radius,
+                totem_hitpoints,
+                totem_radius: totem_radius.unwrap_or(8.0),
+                lifetime,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    fn add_place_mine_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage: f32,
+        trigger_radius: f32,
+        arm_time: f32,
+        cooldown: f32,
+        texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::PlaceMineAbility {
+                damage,
+                trigger_radius,
+                arm_time,
+                cooldown,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    fn add_bodyguard_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        redirect_fraction: f32,
+        radius: f32,
+        texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::Bodyguard {
+                redirect_fraction,
+                radius,
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_whirlwind_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage: f32,
+        radius: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] impact_delay: Option<f32>,
+        #[opt] initial_cooldown: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::Whirlwind {
+                damage,
+                radius,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                impact_delay: impact_delay.unwrap_or(0.0),
+                texture,
+            });
+        }
+    }
+
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_magic_missile_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        projectile_speed: f32,
+        projectile_texture: Rid,
+        splash_radius: f32,
+        #[opt] impact_delay: Option<f32>,
+        #[opt] initial_cooldown: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::MagicMissile {
+                damage,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                impact_delay: impact_delay.unwrap_or(0.0),
+                projectile_speed,
+                projectile_texture,
+                splash_radius,
+            });
+        }
+    }
+
+    /// Lobbed stun grenade: a projectile whose magic damage and stun land on
+    /// everything within `radius` of the impact.
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_bubble_bomb_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage: f32,
+        range: f32,
+        radius: f32,
+        stun_duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        projectile_speed: f32,
+        effect_texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::BubbleBombAbility {
+                damage,
+                range,
+                radius,
+                stun_duration,
+                cooldown,
+                swing_time,
+                impact_time,
+                projectile_speed,
+                effect_texture,
+            });
+        }
+    }
+
+    /// Ranged interrupt: instant magic damage plus a stun, with targeting
+    /// that homes in on enemies mid-cast.
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_instant_stun_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage: f32,
+        range: f32,
+        stun_duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        effect_texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::InstantStunAbility {
+                damage,
+                range,
+                stun_duration,
+                cooldown,
+                swing_time,
+                impact_time,
+                texture,
+                effect_texture,
+            });
+        }
+    }
+
+    /// Ground-targeted artillery strike: aimed at the densest enemy cluster
+    /// in range, telegraphed at the aim point for the whole wind-up.
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_ground_aoe_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        damage: f32,
+        radius: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        telegraph_texture: Rid,
+        effect_texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::GroundAoeAbility {
+                damage,
+                radius,
+                range,
+                cooldown,
+                swing_time,
+                impact_time,
+                telegraph_texture,
+                effect_texture,
+            });
+        }
+    }
+
+    /// Ranged disarm cast: the target's basic attacks shut down for the
+    /// duration while its ability casts continue.
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_instant_disarm_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        range: f32,
+        duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        effect_texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::InstantDisarmAbility {
+                range,
+                duration,
+                cooldown,
+                swing_time,
+                impact_time,
+                texture,
+                effect_texture,
+            });
+        }
+    }
+
+    /// Temporary mind control: the target fights for the caster's team until
+    /// the duration runs out, then `reset_alignment` reverts it.
+    #[method]
+    fn add_hypnosis_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        range: f32,
+        duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::HypnosisAbility {
+                range,
+                duration,
+                cooldown,
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    /// Self-heal, cast whenever the unit is injured and the cooldown is up.
+    /// Goes through the normal heal path, so antiheal and HealEfficacy apply.
+    #[method]
+    fn add_self_heal_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        heal_amount: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::SelfHealAbility {
+                heal_amount,
+                cooldown,
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    /// Self-cast overclock: the unit's own actions recover from cooldown
+    /// faster for the duration, then return to normal.
+    #[method]
+    fn add_self_overclock_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        percent_cooldown_reduction: f32,
+        duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::SelfOverclockAbility {
+                percent_cooldown_reduction,
+                duration,
+                cooldown,
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    /// Group dispel: cleanses every allied unit within `radius` of the
+    /// targeted ally, with an optional heal on top. Only fires while some
+    /// nearby ally is actually debuffed.
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_area_cleanse_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        radius: f32,
+        heal_amount: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::AreaCleanseAbility {
+                radius,
+                heal_amount,
+                range,
+                cooldown,
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    /// Suicide bomber: the unit detonates on reaching an enemy, splashing
+    /// `damage` within `radius` of the corpse; its own side is spared.
+    #[method]
+    fn add_baneling_attack_to_blueprint(&mut self, blueprint_id: usize, damage: f32, radius: f32) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::BanelingAttack { damage, radius });
+        }
+    }
+
+    /// On death, heal every living unit on the side the unit belongs to at
+    /// the moment it dies.
+    #[method]
+    fn add_heal_baneling_to_blueprint(&mut self, blueprint_id: usize, heal_amount: f32) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::HealBaneling { heal_amount });
+        }
+    }
+
+    /// On-kill sustain: the unit heals itself whenever its own basic attack
+    /// lands a killing blow.
+    #[method]
+    fn add_execution_attack_to_blueprint(&mut self, blueprint_id: usize, heal_amount: f32) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::ExecutionAttack { heal_amount });
+        }
+    }
+
+    /// Passive lifesteal: the unit heals for `percent` of the post-mitigation
+    /// damage its hits deal.
+    #[method]
+    fn add_lifesteal_to_blueprint(&mut self, blueprint_id: usize, percent: f32) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::Lifesteal { percent });
+        }
+    }
+
+    /// Dodge chance in [0, 1] for Normal-type attacks, on-hit riders
+    /// included; magic and splash always connect.
+    #[method]
+    fn add_evasion_to_blueprint(&mut self, blueprint_id: usize, evasion: f32) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.evasion = evasion.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Flat threat added to every landing hit on top of the damage dealt,
+    /// so tanks hold aggro against harder hitters.
+    #[method]
+    fn add_threat_bonus_to_blueprint(&mut self, blueprint_id: usize, bonus: f32) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.threat_bonus = bonus.max(0.0);
+        }
+    }
+
+    /// Flag debuff families that never land on this blueprint's units. Slow
+    /// covers chill and attack-slow, silence is the sim's disarm, hypnosis
+    /// immunity blocks the alignment flip itself. Replaces any earlier flags.
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_immunities_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        stun: bool,
+        slow: bool,
+        poison: bool,
+        silence: bool,
+        hypnosis: bool,
+        knockback: bool,
+    ) {
+        use crate::effects::DebuffImmunities;
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            let mut flags = 0;
+            for (on, flag) in [
+                (stun, DebuffImmunities::STUN),
+                (slow, DebuffImmunities::SLOW),
+                (poison, DebuffImmunities::POISON),
+                (silence, DebuffImmunities::SILENCE),
+                (hypnosis, DebuffImmunities::HYPNOSIS),
+                (knockback, DebuffImmunities::KNOCKBACK),
+            ] {
+                if on {
+                    flags |= flag;
+                }
+            }
+            blueprint.immunities = flags;
+        }
+    }
+
+    /// Override the scan priority of the ability at `ability_index` (add
+    /// order). The default already ranks every ability above basic attacks;
+    /// ties between actions go to the longer base cooldown.
+    #[method]
+    fn set_blueprint_ability_priority(
+        &mut self,
+        blueprint_id: usize,
+        ability_index: usize,
+        priority: i64,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint
+                .ability_priorities
+                .retain(|(index, _)| *index != ability_index);
+            blueprint.ability_priorities.push((ability_index, priority));
+        }
+    }
+
+    /// Castable thorns: buffs an ally to reflect `percent` of incoming
+    /// pre-mitigation Normal damage plus `flat` back at attackers for the
+    /// duration.
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_thorns_ability_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        percent: f32,
+        flat: f32,
+        duration: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+        #[opt] initial_cooldown: Option<f32>,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::ThornsAbility {
+                percent,
+                flat,
+                duration,
+                range,
+                cooldown,
+                initial_cooldown: initial_cooldown.unwrap_or(0.0),
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    /// Permanent thorns: the unit always reflects `percent` of incoming
+    /// pre-mitigation Normal damage plus `flat` back at attackers.
+    #[method]
+    fn add_thorns_to_blueprint(&mut self, blueprint_id: usize, percent: f32, flat: f32) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::ThornsPassive { percent, flat });
+        }
+    }
+
+    /// Berserker passive: below `hp_threshold_percent` of max hp the unit
+    /// gains `speed_bonus` movement speed and `attack_speed_bonus` worth of
+    /// cooldown recovery until healed back above the line.
+    #[method]
+    fn add_berserk_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        hp_threshold_percent: f32,
+        attack_speed_bonus: f32,
+        speed_bonus: f32,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::BerserkPassiveAbility {
+                hp_threshold_percent,
+                attack_speed_bonus,
+                speed_bonus,
+            });
+        }
+    }
+
+    /// Necromancer-style summoner: periodically spawns `count` copies of
+    /// `summon_blueprint_id` around the caster, each dying on its own after
+    /// `duration` seconds.
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_summon_ability_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        summon_blueprint_id: usize,
+        count: i64,
+        duration: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::SummonAbility {
+                summon_blueprint_id,
+                count,
+                duration,
+                cooldown,
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    /// Banner carrier: passively grant the stat buffs in `buffs` to every
+    /// unit on the chosen side within `radius`; keys as in
+    /// [`Self::stat_buff_from_dict`].
+    #[method]
+    fn add_aura_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        radius: f32,
+        affects_allies: bool,
+        buffs: Dictionary,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::AuraAbility {
+                buff: Self::stat_buff_from_dict(&buffs),
+                radius,
+                affects_allies,
+            });
+        }
+    }
+
+    /// Charge: dash at an enemy within `range` at `dash_speed`, dealing
+    /// `impact_damage` and stunning on contact; walls and `max_distance`
+    /// stop the dash early.
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_charge_ability_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        dash_speed: f32,
+        max_distance: f32,
+        impact_damage: f32,
+        impact_stun: f32,
+        range: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::ChargeAbility {
+                dash_speed,
+                max_distance,
+                impact_damage,
+                impact_stun,
+                range,
+                cooldown,
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    /// Blink: a short self-teleport `distance` toward the enemy, clamped to
+    /// pathable terrain.
+    #[method]
+    fn add_blink_ability_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        distance: f32,
+        cooldown: f32,
+        swing_time: f32,
+        impact_time: f32,
+        texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::BlinkAbility {
+                distance,
+                cooldown,
+                swing_time,
+                impact_time,
+                texture,
+            });
+        }
+    }
+
+    /// On death, splash a slow poison over everything within `radius` of the
+    /// corpse.
+    #[method]
+    #[allow(clippy::too_many_arguments)]
+    fn add_poison_baneling_to_blueprint(
+        &mut self,
+        blueprint_id: usize,
+        radius: f32,
+        duration: f32,
+        percent_damage: f32,
+        movement_debuff: f32,
+        texture: Rid,
+    ) {
+        if let Some(blueprint) = self.unit_blueprints.get_mut(blueprint_id) {
+            blueprint.add_ability(UnitAbility::PoisonBaneling {
+                radius,
+                duration,
+                percent_damage,
+                movement_debuff,
+                texture,
+            });
+        }
+    }
+
+    fn get_animation_speed(&self, texture: Rid, animation_name: &str) -> f32 {
+        self.animation_library
+            .get_animation_speed(texture, animation_name)
+    }
+
+    fn get_animation_length(&self, texture: Rid, animation_name: &str) -> f32 {
+        self.animation_library
+            .get_animation_length(texture, animation_name)
+    }
+
+    /// Returns a stable unit handle (full entity bits, not just the index),
+    /// or -1 for a bad blueprint id. Handles to dead units fail lookups
+    /// cleanly even after the entity index is recycled.
+    #[method]
+    fn spawn_unit(
+        &mut self,
+        #[base] base: &Node2D,
+        team_id: i64,
+        blueprint_id: usize,
+        position: Vector2,
+    ) -> i64 {
+        // A typo'd blueprint id from GDScript must not panic across the FFI
+        // boundary; -1 is the error sentinel.
+        let blueprint = match self.unit_blueprints.get(blueprint_id) {
+            Some(blueprint) => blueprint.clone(),
+            None => {
+                godot_error!(
+                    "spawn_unit: no blueprint with id {} ({} registered)",
+                    blueprint_id,
+                    self.unit_blueprints.len()
+                );
+                return -1;
+            }
+        };
+        let unit = self.spawn_unit_from_blueprint(team_id, blueprint_id, &blueprint, position);
+        // Make the unit visible immediately even while the sim is paused.
+        self.flush_pending_canvas_items(base);
+        entity_handle(unit)
+    }
+
+    /// Spawn a whole formation in one cross-language call. The blueprint is
+    /// fetched and cloned once; canvas items stay pending and get created
+    /// lazily on the next `_process` as usual. Unit handles come back in the
+    /// same order as `positions`.
+    #[method]
+    fn spawn_unit_batch(
+        &mut self,
+        team_id: i64,
+        blueprint_id: usize,
+        positions: Vec<Vector2>,
+    ) -> Vec<i64> {
+        let blueprint = match self.unit_blueprints.get(blueprint_id) {
+            Some(blueprint) => blueprint.clone(),
+            None => return Vec::new(),
+        };
+        let mut ids = Vec::with_capacity(positions.len());
+        for position in positions {
+            ids.push(entity_handle(self.spawn_unit_from_blueprint(
+                team_id,
+                blueprint_id,
+                &blueprint,
+                position,
+            )));
+        }
+        ids
+    }
+
+    /// Shared spawn path; `spawn_unit` and `spawn_unit_batch` both land here.
+    fn spawn_unit_from_blueprint(
+        &mut self,
+        team_id: i64,
+        blueprint_id: usize,
+        blueprint: &UnitBlueprint,
+        position: Vector2,
+    ) -> Entity {
+        let profile = self.world.resource::<TeamAIProfiles>().get(team_id);
+        let animation_length = self.get_animation_length(blueprint.texture, "run");
+        let _animation_speed = self.get_animation_speed(blueprint.texture, "run");
+        let _ = animation_length;
+
+        let unit = self
+            .world
+            .spawn()
+            .insert(Position { pos: position })
+            .insert(Velocity { v: Vector2::ZERO })
+            .insert(Radius {
+                r: blueprint.radius,
+            })
+            .insert(Mass(blueprint.mass))
+            .insert(BaseMass(blueprint.mass))
+            .insert(BaseMaxHp(blueprint.hitpoints))
+            .insert(Hitpoints {
+                hp: blueprint.hitpoints,
+                max_hp: blueprint.hitpoints,
+            })
+            .insert(Speed {
+                base: blueprint.movespeed,
+                value: blueprint.movespeed,
+            })
+            .insert(Acceleration {
+                base: blueprint.acceleration,
+                value: blueprint.acceleration,
+            })
+            .insert(Armor {
+                base: blueprint.armor,
+                value: blueprint.armor,
+            })
+            .insert(MagicResist {
+                base: blueprint.magic_resist,
+                value: blueprint.magic_resist,
+            })
+            .insert(HealEfficacy(1.0))
+            .insert(TeamAlignment {
+                alignment: team_id,
+                alignment_base: team_id,
+            })
+            .insert(SpatialAwareness { radius: 288.0 })
+            .insert(BlueprintId(blueprint_id))
+            .insert(AppliedDamage { vec: Vec::new() })
+            .insert(ResolveEffectsBuffer { vec: Vec::new() })
+            .insert(crate::effects::BuffHolder { vec: Vec::new() })
+            .insert(ThreatTable::default())
+            .insert(BoidParams {
+                max_speed: blueprint.movespeed,
+                max_force: blueprint.acceleration,
+                blend_mode: blueprint.boid_blend_mode,
+            })
+            .insert(AppliedBoidForces::default())
+            .insert(SeparationBoid {
+                radius: blueprint.boid_tuning.separation_radius.unwrap_or(4.0),
+                multiplier: blueprint.boid_tuning.separation_multiplier.unwrap_or(24.0),
+            })
+            .insert(CohesionBoid {
+                radius: blueprint.boid_tuning.cohesion_radius.unwrap_or(8.0),
+                multiplier: blueprint.boid_tuning.cohesion_multiplier.unwrap_or(1.0),
+            })
+            .insert(AlignmentBoid {
+                radius: blueprint.boid_tuning.alignment_radius.unwrap_or(8.0),
+                multiplier: blueprint.boid_tuning.alignment_multiplier.unwrap_or(1.0),
+            })
+            .insert(AvoidWallsBoid {
+                multiplier: blueprint
+                    .boid_tuning
+                    .avoid_walls_multiplier
+                    .unwrap_or(BASE_AVOID_WALLS_MULTIPLIER)
+                    * profile.caution_factor(),
+                look_ahead: 24.0,
+            })
+            .insert(SeekEnemiesBoid {
+                multiplier: blueprint
+                    .boid_tuning
+                    .seek_multiplier
+                    .unwrap_or(BASE_SEEK_MULTIPLIER)
+                    * profile.seek_factor(),
+            })
+            .insert(ChargeAtEnemyBoid {
+                radius: BASE_CHARGE_RADIUS * profile.charge_radius_factor(),
+                multiplier: 3.0,
+                chase_top_threat: false,
+            })
+            .insert(NewCanvasItemDirective {})
+            .insert(AnimatedSprite::new(blueprint.texture))
+            .insert(FlippableSprite { flipped: false })
+            .insert(ScaleSprite(Vector2::ONE))
+            .insert(PlayAnimationDirective {
+                animation: AnimationRole::Idle,
+                loops: true,
+            })
+            .id();
+
+        // Stopping is opt-in; spawn only adds it when the blueprint tunes it.
+        // Caution-scaled like AvoidWalls above, with the unscaled value kept
+        // as the base for later profile switches.
+        if let Some(multiplier) = blueprint.boid_tuning.stopping_multiplier {
+            self.world.entity_mut(unit).insert(StoppingBoid {
+                multiplier: multiplier * profile.caution_factor(),
+                base_multiplier: multiplier,
+            });
+        }
+
+        // Evasion is opt-in too; most units never roll a dodge.
+        if blueprint.evasion > 0.0 {
+            self.world
+                .entity_mut(unit)
+                .insert(crate::unit::Evasion(blueprint.evasion));
+        }
+
+        // Same for immunities; only boss-flavored blueprints set any.
+        if blueprint.immunities != 0 {
+            self.world
+                .entity_mut(unit)
+                .insert(crate::effects::DebuffImmunities(blueprint.immunities));
+        }
+
+        // Threat bonus likewise; tanks are the usual carriers.
+        if blueprint.threat_bonus > 0.0 {
+            self.world
+                .entity_mut(unit)
+                .insert(ThreatBonus(blueprint.threat_bonus));
+        }
+
+        let mut unit_actions = UnitActions { vec: Vec::new() };
+
+        // Weapon index -> action entity, for per-weapon rider attachment.
+        let mut weapon_actions: Vec<Option<Entity>> = Vec::new();
+        for weapon in blueprint.weapons.iter() {
+            match weapon {
+                Weapon::Melee(melee) => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority: ActionPriority(melee.priority),
+                            range: ActionRange(melee.range),
+                            cooldown: ActionCooldown(melee.cooldown),
+                            swing: SwingDetails {
+                                impact_time: melee.impact_time,
+                                swing_time: melee.swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::DamageEffect {
+                                    damage: melee.damage,
+                                    delay: melee.impact_delay,
+                                    damage_type: DamageType::Normal,
+                                }],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    self.world.entity_mut(action).insert(actions::BasicAttack);
+                    if melee.stationary_while_acting {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::StationaryWhileActing);
+                    }
+                    if melee.muzzle_offset != Vector2::ZERO {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::MuzzleOffset(melee.muzzle_offset));
+                    }
+                    match melee.impact_anchor {
+                        1 => {
+                            self.world
+                                .entity_mut(action)
+                                .insert(actions::ImpactVisualAnchor::Caster);
+                        }
+                        2 => {
+                            self.world
+                                .entity_mut(action)
+                                .insert(actions::ImpactVisualAnchor::Midpoint);
+                        }
+                        _ => {}
+                    }
+                    if melee.cleave_degrees > 0.0 {
+                        self.world.entity_mut(action).insert(Cleave {
+                            angle_degrees: melee.cleave_degrees,
+                        });
+                    }
+                    unit_actions.vec.push(action);
+                    weapon_actions.push(Some(action));
+                }
+                Weapon::Projectile(projectile) => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority: ActionPriority(projectile.priority),
+                            range: ActionRange(projectile.range),
+                            cooldown: ActionCooldown(projectile.cooldown),
+                            swing: SwingDetails {
+                                impact_time: projectile.impact_time,
+                                swing_time: projectile.swing_time,
+                            },
+                            impact_type: ImpactType::Projectile,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::DamageEffect {
+                                    damage: projectile.damage,
+                                    delay: projectile.impact_delay,
+                                    damage_type: DamageType::Normal,
+                                }],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(ActionProjectileDetails {
+                            projectile_speed: projectile.projectile_speed,
+                            projectile_texture: projectile.projectile_texture,
+                            projectile_scale: projectile.projectile_scale,
+                            contact_distance: 8.0,
+                        })
+                        .id();
+                    self.world.entity_mut(action).insert(actions::BasicAttack);
+                    if projectile.stationary_while_acting {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::StationaryWhileActing);
+                    }
+                    if projectile.muzzle_offset != Vector2::ZERO {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::MuzzleOffset(projectile.muzzle_offset));
+                    }
+                    if projectile.splash_radius > 0.0 {
+                        self.world.entity_mut(action).insert(Splash {
+                            radius: projectile.splash_radius,
+                            min_percent: projectile.splash_min_percent,
+                        });
+                    }
+                    self.world.entity_mut(unit).insert(KiteBehavior {
+                        trigger_radius: projectile.range * 0.5,
+                        seek_multiplier: 2.0,
+                        kite_multiplier: 2.5,
+                    });
+                    unit_actions.vec.push(action);
+                    weapon_actions.push(Some(action));
+                }
+                Weapon::Radius(radius) => {
+                    // AoE around self: a full-circle cleave so every enemy in
+                    // range takes the hit, not just the primary target.
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority: ActionPriority(radius.priority),
+                            range: ActionRange(radius.range),
+                            cooldown: ActionCooldown(radius.cooldown),
+                            swing: SwingDetails {
+                                impact_time: radius.impact_time,
+                                swing_time: radius.swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::DamageEffect {
+                                    damage: radius.damage,
+                                    delay: 0.0,
+                                    damage_type: DamageType::Normal,
+                                }],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(actions::BasicAttack)
+                        .insert(Cleave {
+                            angle_degrees: 360.0,
+                        })
+                        .id();
+                    unit_actions.vec.push(action);
+                    weapon_actions.push(Some(action));
+                }
+            }
+        }
+
+        for (weapon_index, rider) in blueprint.riders.iter() {
+            if let Some(effect) = rider.on_hit_effect() {
+                for action in UnitBlueprint::rider_targets(*weapon_index, &weapon_actions) {
+                    if let Some(mut on_hit) = self.world.get_mut::<OnHitEffects>(action) {
+                        on_hit.vec.push(effect.clone());
+                    }
+                }
+            }
+            // The execute rider marks the action entity instead of joining
+            // the effect list; only `apply_damages` knows the victim's hp.
+            if let UnitAbility::Execute {
+                threshold_percent,
+                bonus_multiplier,
+            } = rider
+            {
+                for action in UnitBlueprint::rider_targets(*weapon_index, &weapon_actions) {
+                    self.world.entity_mut(action).insert(ExecuteDamage {
+                        threshold_percent: *threshold_percent,
+                        bonus_multiplier: *bonus_multiplier,
+                    });
+                }
+            }
+        }
+
+        for (ability_index, ability) in blueprint.abilities.iter().enumerate() {
+            let priority = ActionPriority(blueprint.ability_priority(ability_index));
+            match ability {
+                UnitAbility::Backstab {
+                    damage,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![
+                                    Effect::TeleportBehindTargetEffect { distance: 12.0 },
+                                    Effect::DamageEffect {
+                                        damage: *damage,
+                                        delay: 0.0,
+                                        damage_type: DamageType::Normal,
+                                    },
+                                ],
+                            },
+                            flags: TargetFlags::furthest_enemy(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(EffectTexture(*texture))
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::Cleanse {
+                    heal_amount,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![
+                                    Effect::CleanseEffect,
+                                    Effect::HealEffect {
+                                        amount: *heal_amount,
+                                    },
+                                ],
+                            },
+                            flags: TargetFlags::cleanse(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(EffectTexture(*texture))
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::Heal {
+                    heal_amount,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::HealEffect {
+                                    amount: *heal_amount,
+                                }],
+                            },
+                            flags: TargetFlags::heal(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(EffectTexture(*texture))
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::Fortify {
+                    armor_buff,
+                    armor_mult,
+                    heal_per_second,
+                    duration,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![
+                                    Effect::ApplyStatBuffEffect {
+                                        buff: StatBuff {
+                                            armor_buff: *armor_buff,
+                                            armor_mult: *armor_mult,
+                                            ..Default::default()
+                                        },
+                                        duration: *duration,
+                                        texture: *texture,
+                                    },
+                                    Effect::HealOverTimeEffect {
+                                        amount_per_second: *heal_per_second,
+                                        duration: *duration,
+                                        texture: *texture,
+                                    },
+                                ],
+                            },
+                            flags: TargetFlags::normal_buff(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::BuffResistance {
+                    armor_buff,
+                    armor_mult,
+                    magic_resist_buff,
+                    duration,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::ApplyStatBuffEffect {
+                                    buff: StatBuff {
+                                        armor_buff: *armor_buff,
+                                        armor_mult: *armor_mult,
+                                        magic_resist_buff: *magic_resist_buff,
+                                        ..Default::default()
+                                    },
+                                    duration: *duration,
+                                    texture: *texture,
+                                }],
+                            },
+                            flags: TargetFlags::normal_buff(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::Overdrive {
+                    percent_cooldown_reduction,
+                    duration,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::OverdriveEffect {
+                                    percent: *percent_cooldown_reduction,
+                                    duration: *duration,
+                                    texture: *texture,
+                                }],
+                            },
+                            flags: TargetFlags::normal_buff(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::DamageBuff {
+                    percent,
+                    duration,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::DamageBuffEffect {
+                                    percent: *percent,
+                                    duration: *duration,
+                                    texture: *texture,
+                                }],
+                            },
+                            flags: TargetFlags::normal_buff(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::SpeedAndMassBuffAbility {
+                    speed_buff,
+                    speed_mult,
+                    mass_buff,
+                    mass_mult,
+                    duration,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::ApplyStatBuffEffect {
+                                    buff: StatBuff {
+                                        speed_buff: *speed_buff,
+                                        speed_mult: *speed_mult,
+                                        mass_buff: *mass_buff,
+                                        mass_mult: *mass_mult,
+                                        ..Default::default()
+                                    },
+                                    duration: *duration,
+                                    texture: *texture,
+                                }],
+                            },
+                            flags: TargetFlags::normal_buff(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::HealTotem {
+                    heal_per_pulse,
+                    pulse_period,
+                    pulse_radius,
+                    totem_hitpoints,
+                    totem_radius,
+                    lifetime,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::SummonTotemEffect {
+                                    hitpoints: *totem_hitpoints,
+                                    radius: *totem_radius,
+                                    lifetime: *lifetime,
+                                    pulse_amount: *heal_per_pulse,
+                                    pulse_radius: *pulse_radius,
+                                    pulse_period: *pulse_period,
+                                    texture: *texture,
+                                }],
+                            },
+                            flags: TargetFlags::normal_buff(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::DamageTotem {
+                    damage_per_pulse,
+                    pulse_period,
+                    pulse_radius,
+                    totem_hitpoints,
+                    totem_radius,
+                    lifetime,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::SummonTotemEffect {
+                                    hitpoints: *totem_hitpoints,
+                                    radius: *totem_radius,
+                                    lifetime: *lifetime,
+                                    pulse_amount: -*damage_per_pulse,
+                                    pulse_radius: *pulse_radius,
+                                    pulse_period: *pulse_period,
+                                    texture: *texture,
+                                }],
+                            },
+                            flags: TargetFlags::normal_buff(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::PlaceMineAbility {
+                    damage,
+                    trigger_radius,
+                    arm_time,
+                    cooldown,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            // Self-cast: the mine drops at the caster's feet.
+                            range: ActionRange(1.0),
+                            cooldown: ActionCooldown(*cooldown),
+                            // Quick planting motion; the arm timer is the
+                            // real delay.
+                            swing: SwingDetails {
+                                impact_time: 0.25,
+                                swing_time: 0.5,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::PlaceMineEffect {
+                                    damage: *damage,
+                                    trigger_radius: *trigger_radius,
+                                    arm_time: *arm_time,
+                                    texture: *texture,
+                                }],
+                            },
+                            flags: TargetFlags::self_cast(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::Whirlwind {
+                    damage,
+                    radius,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    impact_delay,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*radius),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::DamageEffect {
+                                    damage: *damage,
+                                    delay: *impact_delay,
+                                    damage_type: DamageType::Normal,
+                                }],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(Cleave {
+                            angle_degrees: 360.0,
+                        })
+                        .insert(EffectTexture(*texture))
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::MagicMissile {
+                    damage,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    impact_delay,
+                    projectile_speed,
+                    projectile_texture,
+                    splash_radius,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Projectile,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::DamageEffect {
+                                    damage: *damage,
+                                    delay: *impact_delay,
+                                    damage_type: DamageType::Magic,
+                                }],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(ActionProjectileDetails {
+                            projectile_speed: *projectile_speed,
+                            projectile_texture: *projectile_texture,
+                            projectile_scale: 1.0,
+                            contact_distance: 8.0,
+                        })
+                        .id();
+                    if *splash_radius > 0.0 {
+                        self.world.entity_mut(action).insert(Splash {
+                            radius: *splash_radius,
+                            min_percent: 1.0,
+                        });
+                    }
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::BubbleBombAbility {
+                    damage,
+                    range,
+                    radius,
+                    stun_duration,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    projectile_speed,
+                    effect_texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Projectile,
+                            effects: OnHitEffects {
+                                vec: vec![
+                                    Effect::DamageEffect {
+                                        damage: *damage,
+                                        delay: 0.0,
+                                        damage_type: DamageType::Magic,
+                                    },
+                                    Effect::StunEffect {
+                                        duration: *stun_duration,
+                                        texture: *effect_texture,
+                                    },
+                                ],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(ActionProjectileDetails {
+                            projectile_speed: *projectile_speed,
+                            projectile_texture: *effect_texture,
+                            projectile_scale: 1.0,
+                            contact_distance: 8.0,
+                        })
+                        .insert(Splash {
+                            radius: *radius,
+                            min_percent: 1.0,
+                        })
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::InstantStunAbility {
+                    damage,
+                    range,
+                    stun_duration,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                    effect_texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![
+                                    Effect::DamageEffect {
+                                        damage: *damage,
+                                        delay: 0.0,
+                                        damage_type: DamageType::Magic,
+                                    },
+                                    Effect::StunEffect {
+                                        duration: *stun_duration,
+                                        texture: *effect_texture,
+                                    },
+                                ],
+                            },
+                            flags: TargetFlags::interrupt(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(EffectTexture(*texture))
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::GroundAoeAbility {
+                    damage,
+                    radius,
+                    range,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    telegraph_texture,
+                    effect_texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::GroundArea,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::DamageEffect {
+                                    damage: *damage,
+                                    delay: 0.0,
+                                    damage_type: DamageType::Magic,
+                                }],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(Splash {
+                            radius: *radius,
+                            min_percent: 1.0,
+                        })
+                        .insert(actions::GroundTargetable)
+                        .insert(actions::TelegraphTexture(*telegraph_texture))
+                        .insert(EffectTexture(*effect_texture))
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::InstantDisarmAbility {
+                    range,
+                    duration,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                    effect_texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::Disarm {
+                                    duration: *duration,
+                                    texture: *effect_texture,
+                                }],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(EffectTexture(*texture))
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::HypnosisAbility {
+                    range,
+                    duration,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                // The spawning team is the conversion target;
+                                // a hypnotized hypnotist still converts to
+                                // its original side.
+                                vec: vec![Effect::Hypnosis {
+                                    new_alignment: team_id,
+                                    duration: *duration,
+                                }],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(EffectTexture(*texture))
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::SelfHealAbility {
+                    heal_amount,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(0.0),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::HealEffect {
+                                    amount: *heal_amount,
+                                }],
+                            },
+                            flags: TargetFlags {
+                                needs_injured: true,
+                                ..TargetFlags::self_cast()
+                            },
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(EffectTexture(*texture))
+                        .insert(actions::ImpactVisualAnchor::Caster)
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::SelfOverclockAbility {
+                    percent_cooldown_reduction,
+                    duration,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(0.0),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::AttackSpeedBuff {
+                                    percent: *percent_cooldown_reduction,
+                                    duration: *duration,
+                                }],
+                            },
+                            flags: TargetFlags::self_cast(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(EffectTexture(*texture))
+                        .insert(actions::ImpactVisualAnchor::Caster)
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::AreaCleanseAbility {
+                    radius,
+                    heal_amount,
+                    range,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let mut effects = vec![Effect::CleanseEffect];
+                    if *heal_amount > 0.0 {
+                        effects.push(Effect::HealEffect {
+                            amount: *heal_amount,
+                        });
+                    }
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects { vec: effects },
+                            // Cleanse flags also gate the area version: no
+                            // debuffed ally nearby, no cast.
+                            flags: TargetFlags::cleanse(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(Splash {
+                            radius: *radius,
+                            min_percent: 1.0,
+                        })
+                        .insert(EffectTexture(*texture))
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::BanelingAttack { damage, radius } => {
+                    let effect = DeathEffect::SplashDamage {
+                        damage: *damage,
+                        radius: *radius,
+                    };
+                    if let Some(mut death_effects) = self.world.get_mut::<OnDeathEffects>(unit) {
+                        death_effects.vec.push(effect);
+                    } else {
+                        self.world
+                            .entity_mut(unit)
+                            .insert(OnDeathEffects { vec: vec![effect] });
+                    }
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            // Contact range: the bomber has to physically
+                            // reach its victim.
+                            range: ActionRange(2.0),
+                            // The cooldown never matters; the detonation is
+                            // the unit's last act.
+                            cooldown: ActionCooldown(1.0),
+                            swing: SwingDetails {
+                                impact_time: 0.05,
+                                swing_time: 0.1,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::SuicideEffect],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::HealBaneling { heal_amount } => {
+                    let effect = DeathEffect::HealAllies {
+                        amount: *heal_amount,
+                        alignment: team_id,
+                    };
+                    if let Some(mut death_effects) = self.world.get_mut::<OnDeathEffects>(unit) {
+                        death_effects.vec.push(effect);
+                    } else {
+                        self.world
+                            .entity_mut(unit)
+                            .insert(OnDeathEffects { vec: vec![effect] });
+                    }
+                }
+                UnitAbility::PoisonBaneling {
+                    radius,
+                    duration,
+                    percent_damage,
+                    movement_debuff,
+                    texture,
+                } => {
+                    let effect = DeathEffect::PoisonSplash {
+                        radius: *radius,
+                        duration: *duration,
+                        percent_damage: *percent_damage,
+                        movement_debuff: *movement_debuff,
+                        texture: *texture,
+                    };
+                    if let Some(mut death_effects) = self.world.get_mut::<OnDeathEffects>(unit) {
+                        death_effects.vec.push(effect);
+                    } else {
+                        self.world
+                            .entity_mut(unit)
+                            .insert(OnDeathEffects { vec: vec![effect] });
+                    }
+                }
+                UnitAbility::ExecutionAttack { heal_amount } => {
+                    // Rides the first weapon; the heal pays out only when
+                    // this unit's own hit lands the killing blow.
+                    if let Some(weapon) = weapon_actions.first().copied().flatten() {
+                        if let Some(mut on_hit) = self.world.get_mut::<OnHitEffects>(weapon) {
+                            on_hit.vec.push(Effect::HealOnDeathEffect {
+                                amount: *heal_amount,
+                                target: unit,
+                            });
+                        }
+                    }
+                }
+                UnitAbility::Bodyguard {
+                    redirect_fraction,
+                    radius,
+                    texture,
+                } => {
+                    self.world
+                        .entity_mut(unit)
+                        .insert(crate::effects::BodyguardParams {
+                            fraction: *redirect_fraction,
+                            radius: *radius,
+                            texture: *texture,
+                        });
+                }
+                UnitAbility::Lifesteal { percent } => {
+                    self.world
+                        .entity_mut(unit)
+                        .insert(crate::effects::LifestealOnHit { percent: *percent });
+                }
+                UnitAbility::ThornsAbility {
+                    percent,
+                    flat,
+                    duration,
+                    range,
+                    cooldown,
+                    initial_cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::ApplyThornsEffect {
+                                    percent: *percent,
+                                    flat: *flat,
+                                    duration: *duration,
+                                }],
+                            },
+                            flags: TargetFlags::normal_buff(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(EffectTexture(*texture))
+                        .id();
+                    if *initial_cooldown > 0.0 {
+                        self.world
+                            .entity_mut(action)
+                            .insert(actions::Cooldown(*initial_cooldown));
+                    }
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::ThornsPassive { percent, flat } => {
+                    self.world.entity_mut(unit).insert(crate::effects::ThornsBuff {
+                        percent: *percent,
+                        flat: *flat,
+                    });
+                }
+                UnitAbility::BerserkPassiveAbility {
+                    hp_threshold_percent,
+                    attack_speed_bonus,
+                    speed_bonus,
+                } => {
+                    self.world
+                        .entity_mut(unit)
+                        .insert(crate::effects::BerserkPassive {
+                            hp_threshold_percent: *hp_threshold_percent,
+                            attack_speed_bonus: *attack_speed_bonus,
+                            speed_bonus: *speed_bonus,
+                        });
+                }
+                UnitAbility::AuraAbility {
+                    buff,
+                    radius,
+                    affects_allies,
+                } => {
+                    self.world.entity_mut(unit).insert(crate::effects::Aura {
+                        buff: *buff,
+                        radius: *radius,
+                        affects_allies: *affects_allies,
+                    });
+                }
+                UnitAbility::SummonAbility {
+                    summon_blueprint_id,
+                    count,
+                    duration,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(0.0),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::SummonEffect {
+                                    blueprint_id: *summon_blueprint_id,
+                                    count: *count,
+                                    duration: *duration,
+                                }],
+                            },
+                            flags: TargetFlags::self_cast(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .insert(EffectTexture(*texture))
+                        .insert(actions::ImpactVisualAnchor::Caster)
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::ChargeAbility {
+                    dash_speed,
+                    max_distance,
+                    impact_damage,
+                    impact_stun,
+                    range,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(*range),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::ChargeEffect {
+                                    dash_speed: *dash_speed,
+                                    max_distance: *max_distance,
+                                    impact_damage: *impact_damage,
+                                    impact_stun: *impact_stun,
+                                    texture: *texture,
+                                }],
+                            },
+                            flags: TargetFlags::normal_attack(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                UnitAbility::BlinkAbility {
+                    distance,
+                    cooldown,
+                    swing_time,
+                    impact_time,
+                    texture,
+                } => {
+                    // No EffectTexture on the action: `resolve_blinks` draws
+                    // the visual at both ends of the jump itself.
+                    let action = self
+                        .world
+                        .spawn()
+                        .insert_bundle(ActionBundle {
+                            owner: ActionOwner(unit),
+                            priority,
+                            range: ActionRange(0.0),
+                            cooldown: ActionCooldown(*cooldown),
+                            swing: SwingDetails {
+                                impact_time: *impact_time,
+                                swing_time: *swing_time,
+                            },
+                            impact_type: ImpactType::Instant,
+                            effects: OnHitEffects {
+                                vec: vec![Effect::BlinkEffect {
+                                    distance: *distance,
+                                    texture: *texture,
+                                }],
+                            },
+                            flags: TargetFlags::self_cast(),
+                            channeling: ChannelingDetails {
+                                total_time_channeled: 0.0,
+                            },
+                        })
+                        .id();
+                    unit_actions.vec.push(action);
+                }
+                _ => {}
+            }
+        }
+
+        self.world.entity_mut(unit).insert(unit_actions);
+
+        let blueprint_index = blueprint_id;
+        if let Some(mut events) = self.world.get_resource_mut::<EventQueue>() {
+            events.0.push_back(EventCue::Spawn(SpawnCue {
+                entity: unit,
+                blueprint: blueprint_index,
+                team: team_id,
+                position,
+            }));
+        }
+        if let Some(mut log) = self.world.get_resource_mut::<MatchLog>() {
+            log.record_spawn(entity_handle(unit), blueprint_index, team_id);
+        }
+
+        unit
+    }
+
+    /// Order a unit to walk to a point; normal behavior resumes on arrival.
+    #[method]
+    fn order_move(
+        &mut self,
+        entity_id: i64,
+        destination: Vector2,
+        #[opt] arrive_radius: Option<f32>,
+    ) -> bool {
+        let entity = entity_from_handle(entity_id);
+        if self.world.get::<Hitpoints>(entity).is_none() {
+            return false;
+        }
+        self.world.entity_mut(entity).insert(MoveOrder {
+            destination,
+            arrive_radius: arrive_radius.unwrap_or(8.0),
+        });
+        true
+    }
+
+    /// Living unit count per team, for score UI and reinforcement triggers.
+    /// Only entities with Hitpoints count, so buff entities and corpses that
+    /// might grow a TeamAlignment later can never skew the numbers.
+    #[method]
+    fn get_team_counts(&mut self) -> Dictionary {
+        let dict = Dictionary::new();
+        let mut counts: Vec<(i64, i64)> = Vec::new();
+        let mut query = self
+            .world
+            .query_filtered::<&TeamAlignment, With<Hitpoints>>();
+        for alignment in query.iter(&self.world) {
+            match counts
+                .iter_mut()
+                .find(|(team, _)| *team == alignment.alignment)
+            {
+                Some((_, count)) => *count += 1,
+                None => counts.push((alignment.alignment, 1)),
+            }
+        }
+        for (team, count) in counts {
+            dict.insert(team, count);
+        }
+        dict.into_shared()
+    }
+
+    /// Handles of every living unit on the team.
+    #[method]
+    fn get_team_units(&mut self, team_id: i64) -> Vec<i64> {
+        let mut ids: Vec<i64> = Vec::new();
+        let mut query = self
+            .world
+            .query_filtered::<(Entity, &TeamAlignment), With<Hitpoints>>();
+        for (entity, alignment) in query.iter(&self.world) {
+            if alignment.alignment == team_id {
+                ids.push(entity_handle(entity));
+            }
+        }
+        ids
+    }
+
+    /// Units within `radius` of `center`, for drag-select and AoE previews.
+    /// `team_filter` of -1 returns every team. Reads the spatial hash built
+    /// on the last physics tick; empty before the first tick.
+    #[method]
+    fn get_units_in_radius(&mut self, center: Vector2, radius: f32, team_filter: i64) -> Vec<i64> {
+        let mut ids: Vec<i64> = Vec::new();
+        let mut seen: Vec<Entity> = Vec::new();
+        let spatial = match self.world.get_resource::<SpatialHashTable>() {
+            Some(spatial) => spatial,
+            None => return ids,
+        };
+        for hash in spatial.get_all_spatial_hashes_from_circle(center, radius) {
+            if let Some(entries) = spatial.table.get(&hash) {
+                for entry in entries {
+                    if seen.contains(&entry.entity) {
+                        continue;
+                    }
+                    if team_filter >= 0 && entry.team != team_filter {
+                        continue;
+                    }
+                    if crate::util::true_distance(center, entry.position, 0.0, entry.radius)
+                        > radius
+                    {
+                        continue;
+                    }
+                    seen.push(entry.entity);
+                    ids.push(entity_handle(entry.entity));
+                }
+            }
+        }
+        ids
+    }
+
+    /// Scripted damage or healing for map hazards (lava, shrines). Accepts
+    /// "normal", "magic", "poison" or "heal"; a negative amount heals. Goes
+    /// through the regular damage pipeline, so armor, HealEfficacy and
+    /// damage cues all behave as if a unit dealt it.
+    #[method]
+    fn apply_damage(&mut self, entity_id: i64, amount: f32, damage_type: String) {
+        let entity = entity_from_handle(entity_id);
+        let damage_type = match damage_type.as_str() {
+            "normal" => DamageType::Normal,
+            "magic" => DamageType::Magic,
+            "poison" => DamageType::Poison,
+            "heal" => DamageType::Heal,
+            other => {
+                godot_print!("apply_damage: unknown damage type `{}`", other);
+                return;
+            }
+        };
+        let (amount, damage_type) = if amount < 0.0 && damage_type != DamageType::Heal {
+            (-amount, DamageType::Heal)
+        } else {
+            (amount, damage_type)
+        };
+        let originator = self.world_originator();
+        match self.world.get_mut::<AppliedDamage>(entity) {
+            Some(mut damages) => damages.vec.push(DamageInstance {
+                damage: amount,
+                delay: 0.0,
+                damage_type,
+                originator,
+                depth: 0,
+                execute: None,
+            }),
+            None => godot_print!("apply_damage: no unit with id {}", entity_id),
+        }
+    }
+
+    /// Originator for scripted world damage, shaped enough like a unit that
+    /// `apply_damages` does not drop the damage cue. Team -1 keeps it out of
+    /// every team's stats buckets on the GDScript side.
+    fn world_originator(&mut self) -> Entity {
+        if let Some(entity) = self.world_originator {
+            if self.world.get_entity(entity).is_some() {
+                return entity;
+            }
+        }
+        let entity = self
+            .world
+            .spawn()
+            .insert(BlueprintId(usize::MAX))
+            .insert(TeamAlignment {
+                alignment: -1,
+                alignment_base: -1,
+            })
+            .insert(UnitActions { vec: Vec::new() })
+            .id();
+        self.world_originator = Some(entity);
+        entity
+    }
+
+    /// Focus-fire order: the unit chases and attacks this target until it
+    /// dies or the order is cleared.
+    #[method]
+    fn order_attack(&mut self, attacker_id: i64, target_id: i64) -> bool {
+        let attacker = entity_from_handle(attacker_id);
+        let target = entity_from_handle(target_id);
+        if self.world.get::<Hitpoints>(attacker).is_none()
+            || self.world.get::<Hitpoints>(target).is_none()
+        {
+            return false;
+        }
+        self.world
+            .entity_mut(attacker)
+            .insert(AttackTargetDirective { target });
+        true
+    }
+
+    /// Drop any scripted move or attack orders; the unit goes back to
+    /// nearest-enemy behavior.
+    #[method]
+    fn clear_orders(&mut self, entity_id: i64) {
+        let entity = entity_from_handle(entity_id);
+        if self.world.get_entity(entity).is_none() {
+            return;
+        }
+        self.world
+            .entity_mut(entity)
+            .remove::<AttackTargetDirective>()
+            .remove::<MoveOrder>();
+    }
+
+    /// Permanently move a unit to another team: both `alignment` and
+    /// `alignment_base` change, so an expiring hypnosis cannot revert it.
+    /// Old allies pick the convert up as a target on the next tick.
+    #[method]
+    fn set_unit_team(&mut self, entity_id: i64, team_id: i64) -> bool {
+        let entity = entity_from_handle(entity_id);
+        match self.world.get_mut::<TeamAlignment>(entity) {
+            Some(mut alignment) => {
+                alignment.alignment = team_id;
+                alignment.alignment_base = team_id;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a unit from script. With `spawn_corpse` (the default) the unit
+    /// goes through the regular death path — death cues, corpse animation,
+    /// action teardown. Passing false tears everything down immediately with
+    /// no corpse and no cues. Returns false for unknown or already-dying ids.
+    #[method]
+    fn despawn_unit(&mut self, entity_id: i64, #[opt] spawn_corpse: Option<bool>) -> bool {
+        let entity = entity_from_handle(entity_id);
+        if self.world.get::<Hitpoints>(entity).is_none()
+            || self
+                .world
+                .get::<crate::effects::DeathApproaches>(entity)
+                .is_some()
+        {
+            return false;
+        }
+        if spawn_corpse.unwrap_or(true) {
+            self.world
+                .entity_mut(entity)
+                .insert(crate::effects::DeathApproaches);
+            return true;
+        }
+        // Immediate teardown: action entities, attached buffs, the canvas
+        // item, then the unit itself.
+        let actions = match self.world.get::<UnitActions>(entity) {
+            Some(actions) => actions.vec.clone(),
+            None => Vec::new(),
+        };
+        for action in actions {
+            self.world.despawn(action);
+        }
+        let buffs = match self.world.get::<crate::effects::BuffHolder>(entity) {
+            Some(holder) => holder.vec.clone(),
+            None => Vec::new(),
+        };
+        for buff in buffs {
+            if let Some(renderable) = self.world.get::<Renderable>(buff) {
+                let cleanup = CleanupCanvasItem(renderable.canvas_item);
+                self.world.spawn().insert(cleanup);
+            }
+            self.world.despawn(buff);
+        }
+        if let Some(renderable) = self.world.get::<Renderable>(entity) {
+            let cleanup = CleanupCanvasItem(renderable.canvas_item);
+            self.world.spawn().insert(cleanup);
+        }
+        self.world.despawn(entity);
+        true
+    }
+
+    /// Start a rematch on a clean battlefield without freeing the node:
+    /// every entity and its canvas item goes away and the clock, victor and
+    /// event state reset, while registered blueprints, the animation library
+    /// and the terrain map all survive.
+    #[method]
+    fn reset_battle(&mut self) {
+        // Free every canvas item — live units, corpses, and any pending
+        // CleanupCanvasItem entities — before the entities disappear.
+        let server = unsafe { VisualServer::godot_singleton() };
+        let mut canvas_items: Vec<Rid> = Vec::new();
+        let mut renderables = self.world.query::<&Renderable>();
+        for renderable in renderables.iter(&self.world) {
+            canvas_items.push(renderable.canvas_item);
+        }
+        let mut cleanups = self.world.query::<&CleanupCanvasItem>();
+        for cleanup in cleanups.iter(&self.world) {
+            canvas_items.push(cleanup.0);
+        }
+        for rid in canvas_items {
+            if rid.is_occupied() {
+                server.free_rid(rid);
+            }
+        }
+        let entities: Vec<Entity> = self.world.query::<Entity>().iter(&self.world).collect();
+        for entity in entities {
+            self.world.despawn(entity);
+        }
+        self.world_originator = None;
+
+        self.world.insert_resource(EventQueue::default());
+        self.world.insert_resource(MatchStats::default());
+        self.world.insert_resource(MatchLog::default());
+        self.world.insert_resource(Victor::default());
+        self.world.insert_resource(Clock { tick: 0 });
+        self.world.insert_resource(DeltaPhysics { seconds: 0.0 });
+        self.world.insert_resource(SpatialHashTable::new(64.0));
+        self.world.insert_resource(SpatialNeighborsCache::default());
+        self.world
+            .insert_resource(crate::terrain::FlowFieldsTowardsEnemies::default());
+        self.world
+            .insert_resource(crate::terrain::FogOfWar::default());
+        self.victor = -1;
+        self.running = false;
+    }
+
+    /// `reset_battle` plus wiping the registered blueprints, animations and
+    /// terrain; the node ends up as empty as a freshly added one.
+    #[method]
+    fn clear_all(&mut self) {
+        self.reset_battle();
+        self.unit_blueprints.clear();
+        self.animation_library = AnimationLibrary::new();
+        self.world.insert_resource(TerrainMap::default());
+    }
+
+    /// Run the logic schedule for a number of ticks without rendering;
+    /// Consume the SummonDirectives the last tick's `resolve_effects` left
+    /// behind. Spawning a full unit needs the blueprint list and animation
+    /// library, which systems cannot reach, so this runs between ticks.
+    fn process_summon_directives(&mut self) {
+        let mut directives: Vec<(Entity, crate::effects::SummonDirective)> = Vec::new();
+        let mut query = self
+            .world
+            .query::<(Entity, &crate::effects::SummonDirective)>();
+        for (entity, directive) in query.iter(&self.world) {
+            directives.push((entity, *directive));
+        }
+        for (entity, directive) in directives {
+            self.world.despawn(entity);
+            let blueprint = match self.unit_blueprints.get(directive.blueprint_id) {
+                Some(blueprint) => blueprint.clone(),
+                None => {
+                    godot_error!(
+                        "summon directive references missing blueprint {}",
+                        directive.blueprint_id
+                    );
+                    continue;
+                }
+            };
+            for i in 0..directive.count.max(0) {
+                // Ring the summons around the caster so they do not pile
+                // onto one point.
+                let angle = std::f32::consts::TAU * i as f32 / directive.count.max(1) as f32;
+                let offset =
+                    Vector2::new(angle.cos(), angle.sin()) * (blueprint.radius * 2.0 + 4.0);
+                let summon = self.spawn_unit_from_blueprint(
+                    directive.team,
+                    directive.blueprint_id,
+                    &blueprint,
+                    directive.position + offset,
+                );
+                self.world
+                    .entity_mut(summon)
+                    .insert(crate::util::ExpirationTimer(directive.duration));
+            }
+        }
+    }
+
+    /// headless-mode entry point.
+    #[method]
+    fn run_ticks(&mut self, ticks: i64, delta: f32) {
+        for _ in 0..ticks {
+            self.world.insert_resource(DeltaPhysics { seconds: delta });
+            self.world.resource_mut::<Clock>().tick += 1;
+            self.schedule_logic.run(&mut self.world);
+            self.process_summon_directives();
+        }
+        self.victor = self.world.resource::<Victor>().team;
+    }
+
+    /// Advance the sim exactly one physics tick at a fixed 60 Hz delta,
+    /// regardless of `running` or `time_scale`; debugger single-step.
+    #[method]
+    fn step_once(&mut self) {
+        self.world
+            .insert_resource(DeltaPhysics { seconds: 1.0 / 60.0 });
+        self.world.resource_mut::<Clock>().tick += 1;
+        self.schedule_logic.run(&mut self.world);
+        self.process_summon_directives();
+        self.victor = self.world.resource::<Victor>().team;
+    }
+
+    /// Run a self-contained JSON scenario in a throwaway world and report the
+    /// outcome; the live battle is untouched. See `scenario` for the format.
+    #[method]
+    fn run_scenario(&mut self, json: String) -> Dictionary {
+        let dict = Dictionary::new();
+        let parsed = match crate::scenario::Scenario::from_json(&json) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.last_error = error.clone();
+                dict.insert("passed", false);
+                dict.insert("error", error);
+                return dict.into_shared();
+            }
+        };
+        let report = crate::scenario::run(&parsed);
+        dict.insert("passed", report.passed);
+        dict.insert("victor", report.victor);
+        dict.insert("ticks_run", report.ticks_run);
+        let survivors = Dictionary::new();
+        for (team, count) in report.survivors.iter() {
+            survivors.insert(*team, *count);
+        }
+        dict.insert("survivors", survivors.into_shared());
+        let failures = VariantArray::new();
+        for failure in report.failures.iter() {
+            failures.push(failure);
+        }
+        dict.insert("failures", failures.into_shared());
+        dict.into_shared()
+    }
+
+    #[method]
+    fn _physics_process(&mut self, #[base] _base: &Node2D, delta: f32) {
+        if !self.running {
+            return;
+        }
+        self.world.insert_resource(DeltaPhysics {
+            seconds: delta * self.time_scale.clamp(0.0, 4.0),
+        });
+        self.world.resource_mut::<Clock>().tick += 1;
+        self.schedule_logic.run(&mut self.world);
+        self.process_summon_directives();
+        self.victor = self.world.resource::<Victor>().team;
+    }
+
+    #[method]
+    fn _process(&mut self, #[base] base: &Node2D, delta: f32) {
+        // Canvas item bookkeeping keeps running while paused so nothing
+        // spawned from script sits invisible with a pending directive.
+        self.flush_pending_canvas_items(base);
+        self._process_cleanup_canvas_items();
+        if !self.running {
+            return;
+        }
+        self.world.insert_resource(Delta {
+            seconds: delta * self.time_scale.clamp(0.0, 4.0),
+        });
+        crate::graphics::animation::animate_sprites(&mut self.world, &self.animation_library);
+        self._process_event_signal_queue(base);
+        if self.draw_debug {
+            base.update();
+        }
+    }
+
+    /// Graphics-only update for scenes that drive the logic manually.
+    #[method]
+    fn _process_in_place(&mut self, #[base] base: &Node2D, delta: f32) {
+        self.world.insert_resource(Delta { seconds: delta });
+        self.flush_pending_canvas_items(base);
+        crate::graphics::animation::animate_sprites(&mut self.world, &self.animation_library);
+        self._process_cleanup_canvas_items();
+        self._process_event_signal_queue(base);
+    }
+
+    #[method]
+    fn _draw(&mut self, #[base] base: &Node2D) {
+        if self.draw_debug {
+            crate::graphics::debug_draw::draw_flow_field(
+                &mut self.world,
+                base,
+                self.debug_flow_team,
+            );
+        }
+    }
+
+    /// Create canvas items for every pending directive. Callable from script
+    /// after spawning things while the sim is paused.
+    #[method]
+    fn flush_pending_canvas_items(&mut self, #[base] base: &Node2D) {
+        let server = unsafe { VisualServer::godot_singleton() };
+        let mut pending: Vec<Entity> = Vec::new();
+        // Entities already headed for the death pass never get a canvas
+        // item: creating one here would hand resolve_death a Renderable it
+        // has already decided it does not own, leaking the item.
+        let mut query = self.world.query_filtered::<Entity, (
+            With<NewCanvasItemDirective>,
+            Without<crate::effects::DeathApproaches>,
+        )>();
+        for entity in query.iter(&self.world) {
+            pending.push(entity);
+        }
+        for entity in pending {
+            let canvas_item = server.canvas_item_create();
+            unsafe {
+                server.canvas_item_set_parent(canvas_item, base.get_canvas_item());
+            }
+            self.world
+                .entity_mut(entity)
+                .insert(Renderable { canvas_item })
+                .remove::<NewCanvasItemDirective>();
+        }
+    }
+
+    fn _process_cleanup_canvas_items(&mut self) {
+        let server = unsafe { VisualServer::godot_singleton() };
+        let mut pending: Vec<(Entity, Rid)> = Vec::new();
+        let mut query = self.world.query::<(Entity, &CleanupCanvasItem)>();
+        for (entity, cleanup) in query.iter(&self.world) {
+            pending.push((entity, cleanup.0));
+        }
+        for (entity, rid) in pending {
+            if rid.is_occupied() {
+                server.free_rid(rid);
+            }
+            self.world.despawn(entity);
+        }
+    }
+
+    fn _process_event_signal_queue(&mut self, base: &Node2D) {
+        let mut queue = self.world.resource_mut::<EventQueue>();
+        let cues: Vec<EventCue> = queue.0.drain(..).collect();
+        for cue in cues {
+            match cue {
+                EventCue::Damage(damage) => {
+                    if !self.emit_damage_cues {
+                        continue;
+                    }
+                    let args = VariantArray::new();
+                    args.push(entity_handle(damage.attacker));
+                    args.push(entity_handle(damage.victim));
+                    args.push(damage.attacker_blueprint as i64);
+                    args.push(damage.attacker_team);
+                    args.push(damage.victim_team);
+                    args.push(damage.damage);
+                    args.push(format!("{:?}", damage.damage_type).to_lowercase());
+                    args.push(damage.position);
+                    // Appended after the original fields so existing handlers
+                    // keep their indices.
+                    args.push(damage.pre_mitigation);
+                    args.push(damage.mitigated);
+                    args.push(damage.absorbed);
+                    args.push(damage.redirected);
+                    args.push(damage.crit);
+                    base.emit_signal("damage_cue", &[args.into_shared().to_variant()]);
+                }
+                EventCue::Audio(audio) => {
+                    if !self.emit_audio_cues {
+                        continue;
+                    }
+                    let args = VariantArray::new();
+                    args.push(audio.kind);
+                    args.push(audio.position);
+                    base.emit_signal("audio_cue", &[args.into_shared().to_variant()]);
+                }
+                EventCue::Spawn(spawn) => {
+                    if !self.emit_spawn_cues {
+                        continue;
+                    }
+                    let args = VariantArray::new();
+                    args.push(entity_handle(spawn.entity));
+                    args.push(spawn.blueprint as i64);
+                    args.push(spawn.team);
+                    args.push(spawn.position);
+                    base.emit_signal("spawn_cue", &[args.into_shared().to_variant()]);
+                }
+                EventCue::CastStart(cast) => {
+                    if !self.emit_cast_cues {
+                        continue;
+                    }
+                    let args = VariantArray::new();
+                    args.push(entity_handle(cast.entity));
+                    args.push(entity_handle(cast.action));
+                    args.push(cast.impact_time);
+                    args.push(cast.swing_time);
+                    base.emit_signal("cast_start_cue", &[args.into_shared().to_variant()]);
+                }
+                EventCue::CastResolve(cast) => {
+                    if !self.emit_cast_cues {
+                        continue;
+                    }
+                    let args = VariantArray::new();
+                    args.push(entity_handle(cast.entity));
+                    args.push(entity_handle(cast.action));
+                    args.push(cast.hit);
+                    base.emit_signal("cast_resolve_cue", &[args.into_shared().to_variant()]);
+                }
+                EventCue::Death(death) => {
+                    if !self.emit_death_cues {
+                        continue;
+                    }
+                    let args = VariantArray::new();
+                    args.push(entity_handle(death.entity));
+                    args.push(death.blueprint as i64);
+                    args.push(death.team);
+                    args.push(death.position);
+                    base.emit_signal("death_cue", &[args.into_shared().to_variant()]);
+                }
+                EventCue::Kill(kill) => {
+                    if !self.emit_kill_cues {
+                        continue;
+                    }
+                    let args = VariantArray::new();
+                    args.push(entity_handle(kill.victim));
+                    args.push(kill.victim_blueprint as i64);
+                    args.push(kill.victim_team);
+                    args.push(entity_handle(kill.killer));
+                    args.push(kill.killer_blueprint as i64);
+                    args.push(kill.killer_team);
+                    args.push(kill.position);
+                    args.push(kill.at_clock);
+                    base.emit_signal("kill_cue", &[args.into_shared().to_variant()]);
+                }
+                EventCue::BattleEnded(team) => {
+                    let args = VariantArray::new();
+                    args.push(team);
+                    base.emit_signal("battle_ended", &[args.into_shared().to_variant()]);
+                }
+            }
+        }
+    }
+}
+
+fn init(handle: InitHandle) {
+    handle.add_class::<ECSWorld>();
+}
+
+godot_init!(init);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every system has to tolerate a world fresh out of reset: the spatial
+    /// caches only appear once their `% 6` cadence comes around, so a full
+    /// schedule pass before then must not panic on a missing resource.
+    #[test]
+    fn schedule_survives_a_world_without_spatial_caches() {
+        let mut world = World::default();
+        world.insert_resource(EventQueue::default());
+        world.insert_resource(MatchStats::default());
+        world.insert_resource(Victor::default());
+        // Off the rebuild cadence, so none of the build_* systems fire.
+        world.insert_resource(Clock { tick: 1 });
+        world.insert_resource(DeltaPhysics { seconds: 0.016 });
+        world.insert_resource(TerrainMap::default());
+        world.insert_resource(TeamColors::default());
+        world.insert_resource(SimRng::default());
+        world.insert_resource(AnimationNameMap::default());
+        world.insert_resource(MatchLog::default());
+        world.insert_resource(actions::TargetStickiness::default());
+        world.insert_resource(crate::terrain::FogOfWar::default());
+        world.insert_resource(TeamAIProfiles::default());
+
+        let mut schedule = build_logic_schedule();
+        schedule.run(&mut world);
+    }
+}
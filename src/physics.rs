@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::*;
+use gdnative::prelude::Vector2;
+
+use crate::terrain::TerrainMap;
+use crate::util::true_distance;
+
+/// Fixed-step delta handed to every logic system, set once per `_physics_process`.
+pub struct DeltaPhysics {
+    pub seconds: f32,
+}
+
+/// Physics tick counter; several caches rebuild on a `tick % 6` cadence.
+pub struct Clock {
+    pub tick: i64,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct Position {
+    pub pos: Vector2,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct Velocity {
+    pub v: Vector2,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct Radius {
+    pub r: f32,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct Mass(pub f32);
+
+/// Teleport requests are applied after integration so stage ordering stays sane.
+#[derive(Component)]
+pub struct TeleportDirective {
+    pub destination: Vector2,
+}
+
+#[derive(Copy, Clone)]
+pub struct SpatialHashEntry {
+    pub entity: Entity,
+    pub position: Vector2,
+    pub radius: f32,
+    pub team: i64,
+}
+
+/// Coarse grid of every unit, rebuilt every physics tick.
+pub struct SpatialHashTable {
+    pub table: HashMap<(i32, i32), Vec<SpatialHashEntry>>,
+    pub cell_size: f32,
+}
+
+impl SpatialHashTable {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            table: HashMap::new(),
+            cell_size,
+        }
+    }
+
+    pub fn hash(&self, position: Vector2) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    pub fn get_all_spatial_hashes_from_circle(
+        &self,
+        center: Vector2,
+        radius: f32,
+    ) -> Vec<(i32, i32)> {
+        let min = self.hash(center - Vector2::new(radius, radius));
+        let max = self.hash(center + Vector2::new(radius, radius));
+        let mut out = Vec::new();
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                out.push((x, y));
+            }
+        }
+        out
+    }
+}
+
+pub struct SpatialNeighbor {
+    pub entity: Entity,
+    pub distance: f32,
+    pub team: i64,
+}
+
+/// Per-unit neighbor lists within SpatialAwareness, rebuilt on the `% 6` cadence.
+#[derive(Default)]
+pub struct SpatialNeighborsCache {
+    pub map: HashMap<Entity, Vec<SpatialNeighbor>>,
+}
+
+impl SpatialNeighborsCache {
+    pub fn get_neighbors(&self, entity: &Entity) -> Option<&Vec<SpatialNeighbor>> {
+        self.map.get(entity)
+    }
+}
+
+pub fn build_spatial_hash(
+    mut commands: Commands,
+    query: Query<(
+        Entity,
+        &Position,
+        &Radius,
+        &crate::unit::TeamAlignment,
+        &crate::unit::Hitpoints,
+    )>,
+) {
+    let mut table = SpatialHashTable::new(64.0);
+    for (entity, position, radius, alignment, _) in query.iter() {
+        let hash = table.hash(position.pos);
+        table
+            .table
+            .entry(hash)
+            .or_insert_with(Vec::new)
+            .push(SpatialHashEntry {
+                entity,
+                position: position.pos,
+                radius: radius.r,
+                team: alignment.alignment,
+            });
+    }
+    commands.insert_resource(table);
+}
+
+pub fn build_spatial_neighbors_cache(
+    mut commands: Commands,
+    clock: Res<Clock>,
+    spatial: Option<Res<SpatialHashTable>>,
+    query: Query<(Entity, &Position, &Radius, &crate::unit::SpatialAwareness)>,
+) {
+    let spatial = match spatial {
+        Some(spatial) => spatial,
+        None => return,
+    };
+    if clock.tick % 6 != 0 {
+        return;
+    }
+    let mut cache = SpatialNeighborsCache {
+        map: HashMap::new(),
+    };
+    for (entity, position, radius, awareness) in query.iter() {
+        let mut neighbors = Vec::new();
+        for hash in spatial.get_all_spatial_hashes_from_circle(position.pos, awareness.radius) {
+            if let Some(entries) = spatial.table.get(&hash) {
+                for entry in entries {
+                    if entry.entity == entity {
+                        continue;
+                    }
+                    let distance =
+                        true_distance(position.pos, entry.position, radius.r, entry.radius);
+                    if distance <= awareness.radius {
+                        neighbors.push(SpatialNeighbor {
+                            entity: entry.entity,
+                            distance,
+                            team: entry.team,
+                        });
+                    }
+                }
+            }
+        }
+        neighbors.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        cache.map.insert(entity, neighbors);
+    }
+    commands.insert_resource(cache);
+}
+
+/// Pairwise shove between overlapping units, weighted by mass.
+pub fn detect_collisions(
+    spatial: Option<Res<SpatialHashTable>>,
+    delta: Res<DeltaPhysics>,
+    mut query: Query<(&Position, &Radius, &Mass, &mut Velocity)>,
+) {
+    let spatial = match spatial {
+        Some(spatial) => spatial,
+        None => return,
+    };
+    for bucket in spatial.table.values() {
+        for i in 0..bucket.len() {
+            for j in (i + 1)..bucket.len() {
+                let a = &bucket[i];
+                let b = &bucket[j];
+                let overlap = -true_distance(a.position, b.position, a.radius, b.radius);
+                if overlap <= 0.0 {
+                    continue;
+                }
+                let mass_a;
+                let mass_b;
+                if let Ok((_, _, mass, _)) = query.get(a.entity) {
+                    mass_a = mass.0;
+                } else {
+                    continue;
+                }
+                if let Ok((_, _, mass, _)) = query.get(b.entity) {
+                    mass_b = mass.0;
+                } else {
+                    continue;
+                }
+                let away = crate::util::normalized_or_zero(a.position - b.position);
+                let total = mass_a + mass_b;
+                if let Ok((_, _, _, mut velocity)) = query.get_mut(a.entity) {
+                    velocity.v += away * overlap * (mass_b / total) / delta.seconds.max(0.001);
+                }
+                if let Ok((_, _, _, mut velocity)) = query.get_mut(b.entity) {
+                    velocity.v -= away * overlap * (mass_a / total) / delta.seconds.max(0.001);
+                }
+            }
+        }
+    }
+}
+
+/// Planted entities (structures); the integrator never moves them, though
+/// collisions still shove their neighbors around.
+#[derive(Component)]
+pub struct Immobile;
+
+pub fn physics_integrate(
+    delta: Res<DeltaPhysics>,
+    terrain: Res<TerrainMap>,
+    mut query: Query<(&mut Position, &mut Velocity), Without<Immobile>>,
+) {
+    for (mut position, mut velocity) in query.iter_mut() {
+        let mut next = position.pos + velocity.v * delta.seconds;
+        // Walls: cancel the axis that would enter an unpathable cell.
+        if terrain.get_cell(Vector2::new(next.x, position.pos.y)) == 0 {
+            next.x = position.pos.x;
+            velocity.v.x = 0.0;
+        }
+        if terrain.get_cell(Vector2::new(position.pos.x, next.y)) == 0 {
+            next.y = position.pos.y;
+            velocity.v.y = 0.0;
+        }
+        let (min, max) = terrain.max_bounds();
+        next.x = next.x.clamp(min.x, max.x);
+        next.y = next.y.clamp(min.y, max.y);
+        position.pos = next;
+    }
+}
+
+pub fn apply_teleport(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Position, &TeleportDirective)>,
+) {
+    for (entity, mut position, directive) in query.iter_mut() {
+        position.pos = directive.destination;
+        commands.entity(entity).remove::<TeleportDirective>();
+    }
+}
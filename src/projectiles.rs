@@ -0,0 +1,194 @@
+use bevy_ecs::prelude::*;
+use gdnative::prelude::*;
+use rand::Rng;
+
+use crate::actions::{ActionProjectileDetails, Splash};
+use crate::effects::{Effect, ExecuteDamage, QueuedEffect, ResolveEffectsBuffer};
+use crate::graphics::animation::{AnimatedSprite, AnimationRole, PlayAnimationDirective};
+use crate::graphics::{NewCanvasItemDirective, ScaleSprite};
+use crate::physics::{DeltaPhysics, Position, Radius, SpatialHashTable, Velocity};
+use crate::util::normalized_or_zero;
+
+#[derive(Component, Clone)]
+pub struct Projectile {
+    pub target: Entity,
+    pub last_known_position: Vector2,
+    pub originator: Entity,
+    pub speed: f32,
+    pub contact_distance: f32,
+    pub on_hit: Vec<Effect>,
+    pub splash: Option<Splash>,
+    /// Execute rider of the weapon that fired the shot, if any; carried here
+    /// because the action entity may be gone by contact.
+    pub execute: Option<ExecuteDamage>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_projectile(
+    commands: &mut Commands,
+    origin: Vector2,
+    target: Entity,
+    target_position: Vector2,
+    originator: Entity,
+    details: &ActionProjectileDetails,
+    on_hit: Vec<Effect>,
+    splash: Option<Splash>,
+    execute: Option<ExecuteDamage>,
+) {
+    commands
+        .spawn()
+        .insert(Projectile {
+            target,
+            last_known_position: target_position,
+            originator,
+            speed: details.projectile_speed,
+            contact_distance: details.contact_distance,
+            on_hit,
+            splash,
+            execute,
+        })
+        .insert(Position { pos: origin })
+        .insert(Velocity { v: Vector2::ZERO })
+        .insert(NewCanvasItemDirective {})
+        .insert(AnimatedSprite::new(details.projectile_texture))
+        .insert(ScaleSprite(Vector2::new(
+            details.projectile_scale,
+            details.projectile_scale,
+        )))
+        .insert(PlayAnimationDirective {
+            animation: AnimationRole::Fly,
+            loops: true,
+        });
+}
+
+/// Home toward the target while it lives; dead targets keep the last known
+/// position so the shot still lands somewhere sensible.
+pub fn projectile_homing(
+    mut query: Query<(&mut Projectile, &Position, &mut Velocity)>,
+    target_query: Query<&Position, Without<Projectile>>,
+) {
+    for (mut projectile, position, mut velocity) in query.iter_mut() {
+        if let Ok(target_position) = target_query.get(projectile.target) {
+            projectile.last_known_position = target_position.pos;
+        }
+        let to_target = projectile.last_known_position - position.pos;
+        velocity.v = normalized_or_zero(to_target) * projectile.speed;
+    }
+}
+
+pub fn projectile_contact(
+    mut commands: Commands,
+    spatial: Option<Res<SpatialHashTable>>,
+    query: Query<(Entity, &Projectile, &Position)>,
+    radius_query: Query<&Radius>,
+    alignment_query: Query<&crate::unit::TeamAlignment>,
+    evasion_query: Query<&crate::unit::Evasion>,
+    mut buffer_query: Query<&mut ResolveEffectsBuffer>,
+    mut rng: Option<ResMut<crate::util::SimRng>>,
+    mut events: Option<ResMut<crate::event::EventQueue>>,
+) {
+    let spatial = match spatial {
+        Some(spatial) => spatial,
+        None => return,
+    };
+    for (entity, projectile, position) in query.iter() {
+        let target_radius = radius_query
+            .get(projectile.target)
+            .map(|r| r.r)
+            .unwrap_or(0.0);
+        let reach = projectile.contact_distance + target_radius;
+        if position.pos.distance_to(projectile.last_known_position) > reach {
+            continue;
+        }
+
+        // Evasion: a Normal-damage shot can be dodged at contact, on-hit
+        // riders and all; splash below still lands around the miss.
+        let dodged = crate::effects::attack_is_dodgeable(&projectile.on_hit)
+            && evasion_query
+                .get(projectile.target)
+                .ok()
+                .zip(rng.as_mut())
+                .map(|(evasion, rng)| rng.0.gen::<f32>() < evasion.0)
+                .unwrap_or(false);
+        if dodged {
+            if let Some(events) = events.as_mut() {
+                events.0.push_back(crate::event::EventCue::Audio(
+                    crate::event::AudioCue {
+                        kind: "dodge".to_string(),
+                        position: position.pos,
+                    },
+                ));
+            }
+        } else if let Ok(mut buffer) = buffer_query.get_mut(projectile.target) {
+            for effect in projectile.on_hit.iter() {
+                buffer.vec.push(QueuedEffect {
+                    effect: effect.clone(),
+                    originator: projectile.originator,
+                    execute: projectile.execute,
+                });
+            }
+        }
+
+        if let Some(splash) = projectile.splash {
+            let originator_team = alignment_query
+                .get(projectile.originator)
+                .map(|a| a.alignment)
+                .ok();
+            for hash in spatial.get_all_spatial_hashes_from_circle(position.pos, splash.radius) {
+                if let Some(entries) = spatial.table.get(&hash) {
+                    for entry in entries {
+                        if entry.entity == projectile.target {
+                            continue;
+                        }
+                        // Splash spares the shooter's own side.
+                        if originator_team == Some(entry.team) {
+                            continue;
+                        }
+                        let distance = crate::util::true_distance(
+                            position.pos,
+                            entry.position,
+                            0.0,
+                            entry.radius,
+                        );
+                        if distance > splash.radius {
+                            continue;
+                        }
+                        if let Ok(mut buffer) = buffer_query.get_mut(entry.entity) {
+                            // Splash carries damage, stuns and poisons —
+                            // buffs and heals stay single-target — with
+                            // damage thinned by the blast's falloff.
+                            for effect in projectile.on_hit.iter() {
+                                let effect = match effect {
+                                    Effect::DamageEffect {
+                                        damage,
+                                        delay,
+                                        damage_type,
+                                    } => Effect::DamageEffect {
+                                        damage: damage * splash.damage_scale(distance),
+                                        delay: *delay,
+                                        damage_type: *damage_type,
+                                    },
+                                    Effect::StunEffect { .. } | Effect::PoisonEffect { .. }
+                                        if splash.carries_riders(distance) =>
+                                    {
+                                        effect.clone()
+                                    }
+                                    _ => continue,
+                                };
+                                buffer.vec.push(QueuedEffect {
+                                    effect,
+                                    originator: projectile.originator,
+                                    execute: projectile.execute,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        commands
+            .entity(entity)
+            .insert(crate::effects::DeathApproaches);
+    }
+}
@@ -0,0 +1,769 @@
+use bevy_ecs::prelude::*;
+use gdnative::prelude::{Rid, Vector2};
+
+use crate::actions::{
+    ActionBundle, ActionCooldown, ActionOwner, ActionPriority, ActionProjectileDetails,
+    ActionRange, ChannelingDetails, ImpactType, OnHitEffects, SwingDetails, TargetFlags,
+    ThreatTable, UnitActions, BASIC_ATTACK_PRIORITY,
+};
+use crate::boids::{
+    AlignmentBoid, AppliedBoidForces, AvoidWallsBoid, BoidParams, ChargeAtEnemyBoid, CohesionBoid,
+    SeekEnemiesBoid, SeparationBoid, TeamAIProfiles, BASE_AVOID_WALLS_MULTIPLIER,
+    BASE_CHARGE_RADIUS, BASE_SEEK_MULTIPLIER,
+};
+use crate::effects::{
+    AppliedDamage, BuffHolder, DamageType, Effect, ResolveEffectsBuffer, Victor,
+};
+use crate::event::{EventQueue, MatchLog, MatchStats};
+use crate::graphics::TeamColors;
+use crate::physics::{
+    Clock, DeltaPhysics, Mass, Position, Radius, SpatialHashTable, SpatialNeighborsCache, Velocity,
+};
+use crate::terrain::TerrainMap;
+use crate::unit::{
+    Acceleration, Armor, BaseMass, BaseMaxHp, BlueprintId, HealEfficacy, Hitpoints, MagicResist,
+    MeleeWeapon, ProjectileWeapon, RadiusWeapon, SpatialAwareness, Speed, TeamAlignment,
+    UnitBlueprint, Weapon,
+};
+
+// ---------------------------------------------------------------------------
+// Minimal JSON reader. The match log already hand-rolls its JSON output, so
+// scenario files get a matching hand-rolled parser instead of a serde
+// dependency.
+// ---------------------------------------------------------------------------
+
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(pairs) => pairs
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        self.as_f64().map(|value| value as f32)
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_f64().map(|value| value as i64)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_json(text: &str) -> Result<JsonValue, String> {
+    let bytes = text.as_bytes();
+    let mut pos = 0usize;
+    let value = parse_value(bytes, &mut pos)?;
+    skip_whitespace(bytes, &mut pos);
+    if pos != bytes.len() {
+        return Err(format!("trailing characters at byte {}", pos));
+    }
+    Ok(value)
+}
+
+fn skip_whitespace(bytes: &[u8], pos: &mut usize) {
+    while *pos < bytes.len() && (bytes[*pos] as char).is_ascii_whitespace() {
+        *pos += 1;
+    }
+}
+
+fn parse_value(bytes: &[u8], pos: &mut usize) -> Result<JsonValue, String> {
+    skip_whitespace(bytes, pos);
+    match bytes.get(*pos) {
+        None => Err("unexpected end of input".to_string()),
+        Some(b'{') => parse_object(bytes, pos),
+        Some(b'[') => parse_array(bytes, pos),
+        Some(b'"') => parse_string(bytes, pos).map(JsonValue::String),
+        Some(b't') => parse_literal(bytes, pos, "true", JsonValue::Bool(true)),
+        Some(b'f') => parse_literal(bytes, pos, "false", JsonValue::Bool(false)),
+        Some(b'n') => parse_literal(bytes, pos, "null", JsonValue::Null),
+        Some(_) => parse_number(bytes, pos),
+    }
+}
+
+fn parse_literal(
+    bytes: &[u8],
+    pos: &mut usize,
+    literal: &str,
+    value: JsonValue,
+) -> Result<JsonValue, String> {
+    if bytes[*pos..].starts_with(literal.as_bytes()) {
+        *pos += literal.len();
+        Ok(value)
+    } else {
+        Err(format!("expected `{}` at byte {}", literal, pos))
+    }
+}
+
+fn parse_number(bytes: &[u8], pos: &mut usize) -> Result<JsonValue, String> {
+    let start = *pos;
+    while *pos < bytes.len() && matches!(bytes[*pos], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+    {
+        *pos += 1;
+    }
+    std::str::from_utf8(&bytes[start..*pos])
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .map(JsonValue::Number)
+        .ok_or_else(|| format!("malformed number at byte {}", start))
+}
+
+fn parse_string(bytes: &[u8], pos: &mut usize) -> Result<String, String> {
+    *pos += 1; // opening quote
+    let mut out = String::new();
+    while let Some(&byte) = bytes.get(*pos) {
+        match byte {
+            b'"' => {
+                *pos += 1;
+                return Ok(out);
+            }
+            b'\\' => {
+                *pos += 1;
+                match bytes.get(*pos) {
+                    Some(b'n') => out.push('\n'),
+                    Some(b't') => out.push('\t'),
+                    Some(b'r') => out.push('\r'),
+                    Some(&escaped) => out.push(escaped as char),
+                    None => return Err("unterminated escape".to_string()),
+                }
+                *pos += 1;
+            }
+            _ => {
+                out.push(byte as char);
+                *pos += 1;
+            }
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+fn parse_array(bytes: &[u8], pos: &mut usize) -> Result<JsonValue, String> {
+    *pos += 1; // [
+    let mut values = Vec::new();
+    loop {
+        skip_whitespace(bytes, pos);
+        if bytes.get(*pos) == Some(&b']') {
+            *pos += 1;
+            return Ok(JsonValue::Array(values));
+        }
+        values.push(parse_value(bytes, pos)?);
+        skip_whitespace(bytes, pos);
+        match bytes.get(*pos) {
+            Some(b',') => *pos += 1,
+            Some(b']') => {}
+            _ => return Err(format!("expected `,` or `]` at byte {}", pos)),
+        }
+    }
+}
+
+fn parse_object(bytes: &[u8], pos: &mut usize) -> Result<JsonValue, String> {
+    *pos += 1; // {
+    let mut pairs = Vec::new();
+    loop {
+        skip_whitespace(bytes, pos);
+        if bytes.get(*pos) == Some(&b'}') {
+            *pos += 1;
+            return Ok(JsonValue::Object(pairs));
+        }
+        if bytes.get(*pos) != Some(&b'"') {
+            return Err(format!("expected object key at byte {}", pos));
+        }
+        let key = parse_string(bytes, pos)?;
+        skip_whitespace(bytes, pos);
+        if bytes.get(*pos) != Some(&b':') {
+            return Err(format!("expected `:` at byte {}", pos));
+        }
+        *pos += 1;
+        pairs.push((key, parse_value(bytes, pos)?));
+        skip_whitespace(bytes, pos);
+        match bytes.get(*pos) {
+            Some(b',') => *pos += 1,
+            Some(b'}') => {}
+            _ => return Err(format!("expected `,` or `}}` at byte {}", pos)),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Scenario model.
+// ---------------------------------------------------------------------------
+
+pub struct ScenarioSpawn {
+    pub blueprint: usize,
+    pub team: i64,
+    pub position: Vector2,
+    pub count: i64,
+}
+
+pub enum ScenarioCommandKind {
+    CastAtPosition {
+        /// Index into the scenario's spawn order.
+        unit: usize,
+        action: usize,
+        point: Vector2,
+    },
+}
+
+pub struct ScenarioCommand {
+    pub tick: i64,
+    pub kind: ScenarioCommandKind,
+}
+
+pub struct ScenarioExpectations {
+    /// Expected winner; None means the scenario must still be undecided.
+    pub victor: Option<i64>,
+    pub max_ticks: i64,
+    /// (team, min survivors, max survivors).
+    pub survivors: Vec<(i64, i64, i64)>,
+}
+
+/// A headless regression scenario: blueprints, spawns, scripted commands and
+/// the outcome the designer expects. Weapons cover melee and projectile;
+/// abilities and riders go through the regular blueprint API instead.
+pub struct Scenario {
+    pub name: String,
+    pub terrain_width: i32,
+    pub terrain_height: i32,
+    pub cell_size: f32,
+    pub delta: f32,
+    pub blueprints: Vec<UnitBlueprint>,
+    pub spawns: Vec<ScenarioSpawn>,
+    pub commands: Vec<ScenarioCommand>,
+    pub expect: ScenarioExpectations,
+}
+
+fn field_f32(value: &JsonValue, key: &str, default: f32) -> f32 {
+    value.get(key).and_then(|v| v.as_f32()).unwrap_or(default)
+}
+
+fn field_i64(value: &JsonValue, key: &str, default: i64) -> i64 {
+    value.get(key).and_then(|v| v.as_i64()).unwrap_or(default)
+}
+
+impl Scenario {
+    pub fn from_json(text: &str) -> Result<Scenario, String> {
+        let root = parse_json(text)?;
+        let name = root
+            .get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unnamed")
+            .to_string();
+        let terrain = root.get("terrain").ok_or("missing `terrain`")?;
+
+        let mut blueprints = Vec::new();
+        for entry in root
+            .get("blueprints")
+            .and_then(|v| v.as_array())
+            .ok_or("missing `blueprints`")?
+        {
+            blueprints.push(parse_blueprint(entry)?);
+        }
+
+        let mut spawns = Vec::new();
+        for entry in root
+            .get("spawns")
+            .and_then(|v| v.as_array())
+            .ok_or("missing `spawns`")?
+        {
+            let blueprint = field_i64(entry, "blueprint", 0) as usize;
+            if blueprint >= blueprints.len() {
+                return Err(format!("spawn references unknown blueprint {}", blueprint));
+            }
+            spawns.push(ScenarioSpawn {
+                blueprint,
+                team: field_i64(entry, "team", 0),
+                position: Vector2::new(field_f32(entry, "x", 0.0), field_f32(entry, "y", 0.0)),
+                count: field_i64(entry, "count", 1).max(1),
+            });
+        }
+
+        let mut commands = Vec::new();
+        if let Some(entries) = root.get("commands").and_then(|v| v.as_array()) {
+            for entry in entries {
+                let kind = match entry.get("command").and_then(|v| v.as_str()) {
+                    Some("cast_at_position") => ScenarioCommandKind::CastAtPosition {
+                        unit: field_i64(entry, "unit", 0) as usize,
+                        action: field_i64(entry, "action", 0) as usize,
+                        point: Vector2::new(field_f32(entry, "x", 0.0), field_f32(entry, "y", 0.0)),
+                    },
+                    Some(other) => return Err(format!("unknown command `{}`", other)),
+                    None => return Err("command entry without `command`".to_string()),
+                };
+                commands.push(ScenarioCommand {
+                    tick: field_i64(entry, "tick", 0),
+                    kind,
+                });
+            }
+        }
+
+        let expect_value = root.get("expect").ok_or("missing `expect`")?;
+        let mut survivors = Vec::new();
+        if let Some(JsonValue::Object(pairs)) = expect_value.get("survivors") {
+            for (team, range) in pairs {
+                let team: i64 = team
+                    .parse()
+                    .map_err(|_| format!("survivor team `{}` is not a number", team))?;
+                survivors.push((
+                    team,
+                    field_i64(range, "min", 0),
+                    field_i64(range, "max", i64::MAX),
+                ));
+            }
+        }
+        let expect = ScenarioExpectations {
+            victor: expect_value.get("victor").and_then(|v| v.as_i64()),
+            max_ticks: field_i64(expect_value, "max_ticks", 3000),
+            survivors,
+        };
+
+        Ok(Scenario {
+            name,
+            terrain_width: field_i64(terrain, "width", 16) as i32,
+            terrain_height: field_i64(terrain, "height", 16) as i32,
+            cell_size: field_f32(terrain, "cell_size", 32.0),
+            delta: field_f32(&root, "delta", 1.0 / 30.0),
+            blueprints,
+            spawns,
+            commands,
+            expect,
+        })
+    }
+}
+
+fn parse_blueprint(entry: &JsonValue) -> Result<UnitBlueprint, String> {
+    let mut blueprint = UnitBlueprint::new(
+        Rid::new(),
+        field_f32(entry, "hitpoints", 50.0),
+        field_f32(entry, "movespeed", 60.0),
+        field_f32(entry, "acceleration", 120.0),
+        field_f32(entry, "armor", 0.0),
+        field_f32(entry, "magic_resist", 0.0),
+        field_f32(entry, "mass", 4.0),
+        field_f32(entry, "radius", 8.0),
+    );
+    if let Some(weapons) = entry.get("weapons").and_then(|v| v.as_array()) {
+        for weapon in weapons {
+            match weapon.get("type").and_then(|v| v.as_str()) {
+                Some("melee") => blueprint.add_weapon(Weapon::Melee(MeleeWeapon {
+                    damage: field_f32(weapon, "damage", 5.0),
+                    range: field_f32(weapon, "range", 14.0),
+                    cooldown: field_f32(weapon, "cooldown", 1.0),
+                    impact_time: field_f32(weapon, "impact_time", 0.2),
+                    swing_time: field_f32(weapon, "swing_time", 0.4),
+                    cleave_degrees: field_f32(weapon, "cleave_degrees", 0.0),
+                    impact_delay: 0.0,
+                    stationary_while_acting: false,
+                    muzzle_offset: Vector2::ZERO,
+                    impact_anchor: 0,
+                    priority: BASIC_ATTACK_PRIORITY,
+                })),
+                Some("projectile") => blueprint.add_weapon(Weapon::Projectile(ProjectileWeapon {
+                    damage: field_f32(weapon, "damage", 4.0),
+                    range: field_f32(weapon, "range", 120.0),
+                    cooldown: field_f32(weapon, "cooldown", 1.5),
+                    impact_time: field_f32(weapon, "impact_time", 0.2),
+                    swing_time: field_f32(weapon, "swing_time", 0.4),
+                    projectile_speed: field_f32(weapon, "projectile_speed", 240.0),
+                    projectile_texture: Rid::new(),
+                    projectile_scale: 1.0,
+                    splash_radius: field_f32(weapon, "splash_radius", 0.0),
+                    splash_min_percent: field_f32(weapon, "splash_min_percent", 1.0),
+                    impact_delay: 0.0,
+                    stationary_while_acting: true,
+                    muzzle_offset: Vector2::ZERO,
+                    priority: BASIC_ATTACK_PRIORITY,
+                })),
+                Some("radius") => blueprint.add_weapon(Weapon::Radius(RadiusWeapon {
+                    damage: field_f32(weapon, "damage", 5.0),
+                    range: field_f32(weapon, "range", 24.0),
+                    cooldown: field_f32(weapon, "cooldown", 1.0),
+                    impact_time: field_f32(weapon, "impact_time", 0.2),
+                    swing_time: field_f32(weapon, "swing_time", 0.4),
+                    priority: BASIC_ATTACK_PRIORITY,
+                })),
+                Some(other) => return Err(format!("unknown weapon type `{}`", other)),
+                None => return Err("weapon entry without `type`".to_string()),
+            }
+        }
+    }
+    Ok(blueprint)
+}
+
+// ---------------------------------------------------------------------------
+// Execution.
+// ---------------------------------------------------------------------------
+
+pub struct ScenarioReport {
+    pub passed: bool,
+    pub victor: i64,
+    pub ticks_run: i64,
+    /// (team, living units) at the end of the run.
+    pub survivors: Vec<(i64, i64)>,
+    pub failures: Vec<String>,
+}
+
+/// Spawn a unit without any of the canvas-item plumbing `spawn_unit` does;
+/// everything the logic schedule touches, nothing the renderer needs.
+pub fn spawn_headless_unit(
+    world: &mut World,
+    blueprint: &UnitBlueprint,
+    blueprint_id: usize,
+    team: i64,
+    position: Vector2,
+) -> Entity {
+    let unit = world
+        .spawn()
+        .insert(Position { pos: position })
+        .insert(Velocity { v: Vector2::ZERO })
+        .insert(Radius {
+            r: blueprint.radius,
+        })
+        .insert(Mass(blueprint.mass))
+        .insert(BaseMass(blueprint.mass))
+        .insert(BaseMaxHp(blueprint.hitpoints))
+        .insert(Hitpoints {
+            hp: blueprint.hitpoints,
+            max_hp: blueprint.hitpoints,
+        })
+        .insert(Speed {
+            base: blueprint.movespeed,
+            value: blueprint.movespeed,
+        })
+        .insert(Acceleration {
+            base: blueprint.acceleration,
+            value: blueprint.acceleration,
+        })
+        .insert(Armor {
+            base: blueprint.armor,
+            value: blueprint.armor,
+        })
+        .insert(MagicResist {
+            base: blueprint.magic_resist,
+            value: blueprint.magic_resist,
+        })
+        .insert(HealEfficacy(1.0))
+        .insert(TeamAlignment {
+            alignment: team,
+            alignment_base: team,
+        })
+        .insert(SpatialAwareness { radius: 288.0 })
+        .insert(BlueprintId(blueprint_id))
+        .insert(AppliedDamage { vec: Vec::new() })
+        .insert(ResolveEffectsBuffer { vec: Vec::new() })
+        .insert(BuffHolder { vec: Vec::new() })
+        .insert(ThreatTable::default())
+        .insert(BoidParams {
+            max_speed: blueprint.movespeed,
+            max_force: blueprint.acceleration,
+            blend_mode: blueprint.boid_blend_mode,
+        })
+        .insert(AppliedBoidForces::default())
+        .insert(SeparationBoid {
+            radius: 4.0,
+            multiplier: 24.0,
+        })
+        .insert(CohesionBoid {
+            radius: 8.0,
+            multiplier: 1.0,
+        })
+        .insert(AlignmentBoid {
+            radius: 8.0,
+            multiplier: 1.0,
+        })
+        .insert(AvoidWallsBoid {
+            multiplier: BASE_AVOID_WALLS_MULTIPLIER,
+            look_ahead: 24.0,
+        })
+        .insert(SeekEnemiesBoid {
+            multiplier: BASE_SEEK_MULTIPLIER,
+        })
+        .insert(ChargeAtEnemyBoid {
+            radius: BASE_CHARGE_RADIUS,
+            multiplier: 3.0,
+            chase_top_threat: false,
+        })
+        .id();
+
+    let mut unit_actions = UnitActions { vec: Vec::new() };
+    for weapon in blueprint.weapons.iter() {
+        let (range, cooldown, impact_time, swing_time, damage, impact_type, priority) =
+            match weapon {
+                Weapon::Melee(melee) => (
+                    melee.range,
+                    melee.cooldown,
+                    melee.impact_time,
+                    melee.swing_time,
+                    melee.damage,
+                    ImpactType::Instant,
+                    melee.priority,
+                ),
+                Weapon::Projectile(projectile) => (
+                    projectile.range,
+                    projectile.cooldown,
+                    projectile.impact_time,
+                    projectile.swing_time,
+                    projectile.damage,
+                    ImpactType::Projectile,
+                    projectile.priority,
+                ),
+                Weapon::Radius(radius) => (
+                    radius.range,
+                    radius.cooldown,
+                    radius.impact_time,
+                    radius.swing_time,
+                    radius.damage,
+                    ImpactType::Instant,
+                    radius.priority,
+                ),
+            };
+        let action = world
+            .spawn()
+            .insert_bundle(ActionBundle {
+                owner: ActionOwner(unit),
+                range: ActionRange(range),
+                cooldown: ActionCooldown(cooldown),
+                swing: SwingDetails {
+                    impact_time,
+                    swing_time,
+                },
+                impact_type,
+                effects: OnHitEffects {
+                    vec: vec![Effect::DamageEffect {
+                        damage,
+                        delay: 0.0,
+                        damage_type: DamageType::Normal,
+                    }],
+                },
+                flags: TargetFlags::normal_attack(),
+                channeling: ChannelingDetails {
+                    total_time_channeled: 0.0,
+                },
+                priority: ActionPriority(priority),
+            })
+            .insert(crate::actions::BasicAttack)
+            .id();
+        match weapon {
+            Weapon::Projectile(projectile) => {
+                world.entity_mut(action).insert(ActionProjectileDetails {
+                    projectile_speed: projectile.projectile_speed,
+                    projectile_texture: projectile.projectile_texture,
+                    projectile_scale: projectile.projectile_scale,
+                    contact_distance: 8.0,
+                });
+            }
+            Weapon::Radius(_) => {
+                world.entity_mut(action).insert(crate::actions::Cleave {
+                    angle_degrees: 360.0,
+                });
+            }
+            Weapon::Melee(_) => {}
+        }
+        unit_actions.vec.push(action);
+    }
+    world.entity_mut(unit).insert(unit_actions);
+    unit
+}
+
+pub fn run(scenario: &Scenario) -> ScenarioReport {
+    let mut world = World::default();
+    world.insert_resource(EventQueue::default());
+    world.insert_resource(MatchStats::default());
+    world.insert_resource(Victor::default());
+    world.insert_resource(Clock { tick: 0 });
+    world.insert_resource(DeltaPhysics {
+        seconds: scenario.delta,
+    });
+    world.insert_resource(TerrainMap::new(
+        scenario.terrain_width,
+        scenario.terrain_height,
+        scenario.cell_size,
+    ));
+    world.insert_resource(TeamColors::default());
+    world.insert_resource(crate::util::SimRng::default());
+    world.insert_resource(crate::graphics::animation::AnimationNameMap::default());
+    world.insert_resource(MatchLog::default());
+    world.insert_resource(crate::actions::TargetStickiness::default());
+    world.insert_resource(crate::terrain::FogOfWar::default());
+    world.insert_resource(TeamAIProfiles::default());
+    world.insert_resource(SpatialHashTable::new(64.0));
+    world.insert_resource(SpatialNeighborsCache::default());
+    world.insert_resource(crate::terrain::FlowFieldsTowardsEnemies::default());
+
+    let mut spawned: Vec<Entity> = Vec::new();
+    for spawn in scenario.spawns.iter() {
+        for index in 0..spawn.count {
+            // Fan stacked spawns out slightly so they are not coincident.
+            let offset = Vector2::new(0.0, index as f32 * 4.0);
+            spawned.push(spawn_headless_unit(
+                &mut world,
+                &scenario.blueprints[spawn.blueprint],
+                spawn.blueprint,
+                spawn.team,
+                spawn.position + offset,
+            ));
+        }
+    }
+
+    let mut schedule = crate::build_logic_schedule();
+    let mut ticks_run = 0;
+    for tick in 1..=scenario.expect.max_ticks {
+        for command in scenario.commands.iter().filter(|c| c.tick == tick) {
+            match &command.kind {
+                ScenarioCommandKind::CastAtPosition {
+                    unit,
+                    action,
+                    point,
+                } => {
+                    if let Some(&unit) = spawned.get(*unit) {
+                        let _ = crate::actions::command_cast_at_position(
+                            &mut world, unit, *action, *point,
+                        );
+                    }
+                }
+            }
+        }
+        world.insert_resource(DeltaPhysics {
+            seconds: scenario.delta,
+        });
+        world.resource_mut::<Clock>().tick += 1;
+        schedule.run(&mut world);
+        ticks_run = tick;
+        if world.resource::<Victor>().team != -1 {
+            break;
+        }
+    }
+
+    let victor = world.resource::<Victor>().team;
+    let mut survivors: Vec<(i64, i64)> = Vec::new();
+    let mut query = world.query_filtered::<&TeamAlignment, With<Hitpoints>>();
+    for alignment in query.iter(&world) {
+        match survivors
+            .iter_mut()
+            .find(|(team, _)| *team == alignment.alignment)
+        {
+            Some((_, count)) => *count += 1,
+            None => survivors.push((alignment.alignment, 1)),
+        }
+    }
+
+    let mut failures = Vec::new();
+    if let Some(expected) = scenario.expect.victor {
+        if victor != expected {
+            failures.push(format!("expected victor {}, got {}", expected, victor));
+        }
+    }
+    for (team, min, max) in scenario.expect.survivors.iter() {
+        let count = survivors
+            .iter()
+            .find(|(t, _)| t == team)
+            .map(|(_, count)| *count)
+            .unwrap_or(0);
+        if count < *min || count > *max {
+            failures.push(format!(
+                "team {} ended with {} survivors, expected {}..={}",
+                team, count, min, max
+            ));
+        }
+    }
+
+    ScenarioReport {
+        passed: failures.is_empty(),
+        victor,
+        ticks_run,
+        survivors,
+        failures,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parser_round_trips_a_scenario_shape() {
+        let json = r#"{
+            "name": "smoke",
+            "terrain": {"width": 8, "height": 8, "cell_size": 32.0},
+            "blueprints": [{"hitpoints": 40, "weapons": [{"type": "melee", "damage": 6}]}],
+            "spawns": [{"blueprint": 0, "team": 0, "x": 64, "y": 64, "count": 2}],
+            "expect": {"victor": 0, "max_ticks": 100}
+        }"#;
+        let scenario = Scenario::from_json(json).unwrap();
+        assert_eq!(scenario.name, "smoke");
+        assert_eq!(scenario.blueprints.len(), 1);
+        assert_eq!(scenario.blueprints[0].weapons.len(), 1);
+        assert_eq!(scenario.spawns[0].count, 2);
+        assert_eq!(scenario.expect.victor, Some(0));
+        assert_eq!(scenario.expect.max_ticks, 100);
+    }
+
+    #[test]
+    fn malformed_scenarios_report_an_error() {
+        assert!(Scenario::from_json("{").is_err());
+        assert!(Scenario::from_json("{\"name\": \"x\"}").is_err());
+        assert!(Scenario::from_json(
+            "{\"terrain\": {}, \"blueprints\": [], \"spawns\": [{\"blueprint\": 3}], \"expect\": {}}"
+        )
+        .is_err());
+    }
+
+    /// Every bundled scenario in scenarios/ must pass; designers add new
+    /// regression cases by dropping a file there.
+    #[test]
+    fn bundled_scenarios_pass() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        let mut ran = 0;
+        let mut entries: Vec<_> = std::fs::read_dir(dir)
+            .expect("scenarios/ directory")
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        entries.sort();
+        for path in entries {
+            if path.extension().map(|e| e == "json").unwrap_or(false) {
+                let text = std::fs::read_to_string(&path).unwrap();
+                let scenario = Scenario::from_json(&text)
+                    .unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+                let report = run(&scenario);
+                assert!(
+                    report.passed,
+                    "{} failed after {} ticks: {:?}",
+                    scenario.name, report.ticks_run, report.failures
+                );
+                ran += 1;
+            }
+        }
+        assert_eq!(ran, 3, "expected the three bundled scenarios");
+    }
+}
@@ -0,0 +1,434 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy_ecs::prelude::*;
+use gdnative::prelude::Vector2;
+
+use crate::physics::Clock;
+use crate::unit::{Hitpoints, SpatialAwareness, TeamAlignment};
+
+/// Grid map of pathable space. A cell value of 0 is unpathable; any other bit
+/// in the mask is walkable terrain of that class.
+pub struct TerrainMap {
+    pub width: i32,
+    pub height: i32,
+    pub cell_size: f32,
+    pub cells: Vec<u8>,
+}
+
+impl TerrainMap {
+    pub fn new(width: i32, height: i32, cell_size: f32) -> Self {
+        Self {
+            width,
+            height,
+            cell_size,
+            cells: vec![1u8; (width * height) as usize],
+        }
+    }
+
+    pub fn cell_coords(&self, position: Vector2) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    pub fn cell_index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        Some((y * self.width + x) as usize)
+    }
+
+    /// Pathable mask at a world position; out of bounds reads as unpathable.
+    pub fn get_cell(&self, position: Vector2) -> u8 {
+        let (x, y) = self.cell_coords(position);
+        match self.cell_index(x, y) {
+            Some(index) => self.cells[index],
+            None => 0,
+        }
+    }
+
+    pub fn set_cell(&mut self, x: i32, y: i32, mask: u8) {
+        if let Some(index) = self.cell_index(x, y) {
+            self.cells[index] = mask;
+        }
+    }
+
+    pub fn cell_center(&self, x: i32, y: i32) -> Vector2 {
+        Vector2::new(
+            (x as f32 + 0.5) * self.cell_size,
+            (y as f32 + 0.5) * self.cell_size,
+        )
+    }
+
+    pub fn max_bounds(&self) -> (Vector2, Vector2) {
+        (
+            Vector2::ZERO,
+            Vector2::new(
+                self.width as f32 * self.cell_size,
+                self.height as f32 * self.cell_size,
+            ),
+        )
+    }
+}
+
+impl Default for TerrainMap {
+    fn default() -> Self {
+        Self::new(32, 32, 36.0)
+    }
+}
+
+/// Per-team visibility grids stamped from friendly SpatialAwareness. While
+/// `enabled` is false the grids stay empty and every query reads as visible,
+/// preserving the fog-free behavior exactly.
+pub struct FogOfWar {
+    pub enabled: bool,
+    pub map: HashMap<i64, Vec<bool>>,
+}
+
+impl Default for FogOfWar {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            map: HashMap::new(),
+        }
+    }
+}
+
+impl FogOfWar {
+    pub fn is_visible(&self, team: i64, terrain: &TerrainMap, position: Vector2) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let (x, y) = terrain.cell_coords(position);
+        match (self.map.get(&team), terrain.cell_index(x, y)) {
+            (Some(grid), Some(index)) => grid[index],
+            _ => false,
+        }
+    }
+}
+
+/// Mark every cell within awareness range of a friendly unit as visible to
+/// that unit's team; one grid pass per team, on the cache cadence.
+pub fn build_fog_of_war(
+    clock: Res<Clock>,
+    terrain: Res<TerrainMap>,
+    mut fog: ResMut<FogOfWar>,
+    query: Query<(&crate::physics::Position, &SpatialAwareness, &TeamAlignment), With<Hitpoints>>,
+) {
+    if !fog.enabled || clock.tick % 6 != 0 {
+        return;
+    }
+    let cell_count = (terrain.width * terrain.height) as usize;
+    let mut map: HashMap<i64, Vec<bool>> = HashMap::new();
+    for (position, awareness, alignment) in query.iter() {
+        let grid = map
+            .entry(alignment.alignment)
+            .or_insert_with(|| vec![false; cell_count]);
+        let reach = Vector2::new(awareness.radius, awareness.radius);
+        let (min_x, min_y) = terrain.cell_coords(position.pos - reach);
+        let (max_x, max_y) = terrain.cell_coords(position.pos + reach);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                if let Some(index) = terrain.cell_index(x, y) {
+                    if !grid[index]
+                        && terrain.cell_center(x, y).distance_to(position.pos)
+                            <= awareness.radius + terrain.cell_size * 0.5
+                    {
+                        grid[index] = true;
+                    }
+                }
+            }
+        }
+    }
+    fog.map = map;
+}
+
+pub struct FlowField {
+    pub flow: Vec<Vector2>,
+    pub integration: Vec<f32>,
+}
+
+/// One flow field per team, pointing each cell toward that team's enemies.
+pub struct FlowFieldsTowardsEnemies {
+    pub map: HashMap<i64, FlowField>,
+}
+
+impl Default for FlowFieldsTowardsEnemies {
+    fn default() -> Self {
+        Self {
+            map: HashMap::new(),
+        }
+    }
+}
+
+impl FlowFieldsTowardsEnemies {
+    pub fn sample(&self, team: i64, terrain: &TerrainMap, position: Vector2) -> Vector2 {
+        if let Some(field) = self.map.get(&team) {
+            let (x, y) = terrain.cell_coords(position);
+            if let Some(index) = terrain.cell_index(x, y) {
+                return field.flow[index];
+            }
+        }
+        Vector2::ZERO
+    }
+
+    /// Flow sample that accounts for the unit's body. Units wider than a cell
+    /// also sample at their lateral edges; when one edge overlaps a wall the
+    /// open edge's flow wins, steering the body away from the corner instead
+    /// of clipping into it.
+    pub fn sample_with_radius(
+        &self,
+        team: i64,
+        terrain: &TerrainMap,
+        position: Vector2,
+        radius: f32,
+        heading: Vector2,
+    ) -> Vector2 {
+        let center = self.sample(team, terrain, position);
+        if radius <= terrain.cell_size * 0.5 || heading == Vector2::ZERO {
+            return center;
+        }
+        let side = Vector2::new(-heading.y, heading.x);
+        let left_pos = position + side * radius;
+        let right_pos = position - side * radius;
+        let left_open = terrain.get_cell(left_pos) != 0;
+        let right_open = terrain.get_cell(right_pos) != 0;
+        match (left_open, right_open) {
+            (true, true) => {
+                let left = self.sample(team, terrain, left_pos);
+                let right = self.sample(team, terrain, right_pos);
+                (center + left + right) / 3.0
+            }
+            (true, false) => self.sample(team, terrain, left_pos),
+            (false, true) => self.sample(team, terrain, right_pos),
+            (false, false) => center,
+        }
+    }
+}
+
+/// Integration-field BFS from every enemy-occupied cell, per team, on the
+/// cache cadence.
+pub fn build_flow_fields(
+    mut commands: Commands,
+    clock: Res<Clock>,
+    terrain: Res<TerrainMap>,
+    fog: Option<Res<FogOfWar>>,
+    query: Query<(&crate::physics::Position, &TeamAlignment), With<Hitpoints>>,
+) {
+    if clock.tick % 6 != 0 {
+        return;
+    }
+    let mut teams: Vec<i64> = Vec::new();
+    for (_, alignment) in query.iter() {
+        if !teams.contains(&alignment.alignment) {
+            teams.push(alignment.alignment);
+        }
+    }
+
+    let cell_count = (terrain.width * terrain.height) as usize;
+    let mut fields = FlowFieldsTowardsEnemies::default();
+    for team in teams.iter() {
+        let mut integration = vec![f32::MAX; cell_count];
+        let mut frontier: VecDeque<(i32, i32)> = VecDeque::new();
+        for (position, alignment) in query.iter() {
+            if alignment.alignment == *team {
+                continue;
+            }
+            // Under fog, only seed from enemies this team can actually see.
+            if let Some(fog) = fog.as_ref() {
+                if !fog.is_visible(*team, &terrain, position.pos) {
+                    continue;
+                }
+            }
+            let (x, y) = terrain.cell_coords(position.pos);
+            if let Some(index) = terrain.cell_index(x, y) {
+                if integration[index] != 0.0 {
+                    integration[index] = 0.0;
+                    frontier.push_back((x, y));
+                }
+            }
+        }
+        while let Some((x, y)) = frontier.pop_front() {
+            let here = integration[terrain.cell_index(x, y).unwrap()];
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let (nx, ny) = (x + dx, y + dy);
+                if let Some(index) = terrain.cell_index(nx, ny) {
+                    if terrain.cells[index] == 0 {
+                        continue;
+                    }
+                    if integration[index] > here + 1.0 {
+                        integration[index] = here + 1.0;
+                        frontier.push_back((nx, ny));
+                    }
+                }
+            }
+        }
+        let mut flow = vec![Vector2::ZERO; cell_count];
+        for y in 0..terrain.height {
+            for x in 0..terrain.width {
+                let index = terrain.cell_index(x, y).unwrap();
+                if terrain.cells[index] == 0 || integration[index] == f32::MAX {
+                    continue;
+                }
+                let mut best = integration[index];
+                let mut direction = Vector2::ZERO;
+                for (dx, dy) in [
+                    (1, 0),
+                    (-1, 0),
+                    (0, 1),
+                    (0, -1),
+                    (1, 1),
+                    (1, -1),
+                    (-1, 1),
+                    (-1, -1),
+                ] {
+                    if let Some(neighbor) = terrain.cell_index(x + dx, y + dy) {
+                        if terrain.cells[neighbor] != 0 && integration[neighbor] < best {
+                            best = integration[neighbor];
+                            direction = Vector2::new(dx as f32, dy as f32).normalized();
+                        }
+                    }
+                }
+                flow[index] = direction;
+            }
+        }
+        fields.map.insert(*team, FlowField { flow, integration });
+    }
+    commands.insert_resource(fields);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radius_aware_sampling_follows_the_open_edge_around_corners() {
+        // 36 px corridor cells; the wall sits below the unit's path.
+        let mut map = TerrainMap::new(3, 3, 36.0);
+        map.set_cell(1, 2, 0);
+
+        let mut field = FlowField {
+            flow: vec![Vector2::ZERO; 9],
+            integration: vec![f32::MAX; 9],
+        };
+        // Center cell pulls straight right, toward the corner; the open cell
+        // above pulls right and slightly up.
+        field.flow[map.cell_index(1, 1).unwrap()] = Vector2::new(1.0, 0.0);
+        field.flow[map.cell_index(1, 0).unwrap()] = Vector2::new(1.0, -0.5);
+        let mut fields = FlowFieldsTowardsEnemies {
+            map: std::collections::HashMap::new(),
+        };
+        fields.map.insert(0, field);
+
+        let position = map.cell_center(1, 1);
+        let heading = Vector2::new(1.0, 0.0);
+
+        // A radius-20 body overlaps the wall cell below and follows the open
+        // edge's flow away from the corner.
+        let big = fields.sample_with_radius(0, &map, position, 20.0, heading);
+        assert!((big - Vector2::new(1.0, -0.5)).length() < 1e-4);
+
+        // A small unit keeps the plain center sample.
+        let small = fields.sample_with_radius(0, &map, position, 10.0, heading);
+        assert!((small - Vector2::new(1.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn fog_marks_cells_within_friendly_awareness() {
+        let mut world = World::default();
+        world.insert_resource(Clock { tick: 0 });
+        world.insert_resource(TerrainMap::new(8, 8, 36.0));
+        let mut fog = FogOfWar::default();
+        fog.enabled = true;
+        world.insert_resource(fog);
+        world
+            .spawn()
+            .insert(crate::physics::Position {
+                pos: Vector2::new(50.0, 50.0),
+            })
+            .insert(SpatialAwareness { radius: 60.0 })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            });
+
+        let mut stage = bevy_ecs::schedule::SystemStage::parallel();
+        stage.add_system(build_fog_of_war);
+        stage.run(&mut world);
+
+        let terrain = TerrainMap::new(8, 8, 36.0);
+        let fog = world.resource::<FogOfWar>();
+        assert!(fog.is_visible(0, &terrain, Vector2::new(50.0, 50.0)));
+        assert!(!fog.is_visible(0, &terrain, Vector2::new(280.0, 280.0)));
+        // Another team has no units and sees nothing.
+        assert!(!fog.is_visible(1, &terrain, Vector2::new(50.0, 50.0)));
+    }
+
+    #[test]
+    fn fogged_flow_fields_ignore_unseen_enemies() {
+        let mut world = World::default();
+        world.insert_resource(Clock { tick: 0 });
+        world.insert_resource(TerrainMap::new(8, 8, 36.0));
+        let mut fog = FogOfWar::default();
+        fog.enabled = true;
+        // Team 0 has seen nothing at all; team 1 has full vision.
+        fog.map.insert(0, vec![false; 64]);
+        fog.map.insert(1, vec![true; 64]);
+        world.insert_resource(fog);
+        // An enemy of team 0 stands mid-map.
+        world
+            .spawn()
+            .insert(crate::physics::Position {
+                pos: Vector2::new(150.0, 150.0),
+            })
+            .insert(TeamAlignment {
+                alignment: 1,
+                alignment_base: 1,
+            })
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            });
+        world
+            .spawn()
+            .insert(crate::physics::Position {
+                pos: Vector2::new(30.0, 30.0),
+            })
+            .insert(TeamAlignment {
+                alignment: 0,
+                alignment_base: 0,
+            })
+            .insert(Hitpoints {
+                hp: 10.0,
+                max_hp: 10.0,
+            });
+
+        let mut stage = bevy_ecs::schedule::SystemStage::parallel();
+        stage.add_system(build_flow_fields);
+        stage.run(&mut world);
+
+        let terrain = TerrainMap::new(8, 8, 36.0);
+        let fields = world.resource::<FlowFieldsTowardsEnemies>();
+        // Unseen enemy seeds nothing for team 0; team 1 still flows normally.
+        assert_eq!(
+            fields.sample(0, &terrain, Vector2::new(100.0, 150.0)),
+            Vector2::ZERO
+        );
+        assert_ne!(
+            fields.sample(1, &terrain, Vector2::new(100.0, 100.0)),
+            Vector2::ZERO
+        );
+    }
+
+    #[test]
+    fn out_of_bounds_is_unpathable() {
+        let map = TerrainMap::new(4, 4, 36.0);
+        assert_eq!(map.get_cell(Vector2::new(-1.0, 0.0)), 0);
+        assert_eq!(map.get_cell(Vector2::new(10.0, 10.0)), 1);
+    }
+}
@@ -0,0 +1,616 @@
+use bevy_ecs::prelude::*;
+use gdnative::prelude::*;
+
+use crate::abilities::UnitAbility;
+
+#[derive(Component, Copy, Clone)]
+pub struct Hitpoints {
+    pub hp: f32,
+    pub max_hp: f32,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct Speed {
+    pub base: f32,
+    pub value: f32,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct Acceleration {
+    pub base: f32,
+    pub value: f32,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct Armor {
+    pub base: f32,
+    pub value: f32,
+}
+
+/// Chance in [0, 1] to fully dodge a Normal-type attack, on-hit riders
+/// included. Magic and splash damage always connect.
+#[derive(Component, Copy, Clone)]
+pub struct Evasion(pub f32);
+
+#[derive(Component, Copy, Clone)]
+pub struct MagicResist {
+    pub base: f32,
+    pub value: f32,
+}
+
+/// Mass before buffs; `apply_stat_buffs` recomputes `physics::Mass` from this.
+#[derive(Component, Copy, Clone)]
+pub struct BaseMass(pub f32);
+
+/// Max hp before buffs; `apply_stat_buffs` recomputes `Hitpoints.max_hp`
+/// from this, scaling current hp so the injured fraction stays put.
+#[derive(Component, Copy, Clone)]
+pub struct BaseMaxHp(pub f32);
+
+/// Multiplier on incoming heals, recomputed each frame from antiheal buffs.
+#[derive(Component, Copy, Clone)]
+pub struct HealEfficacy(pub f32);
+
+#[derive(Component, Copy, Clone)]
+pub struct TeamAlignment {
+    pub alignment: i64,
+    /// The team this unit reverts to when a SetAlignment buff expires.
+    pub alignment_base: i64,
+}
+
+#[derive(Component, Copy, Clone)]
+pub struct SpatialAwareness {
+    pub radius: f32,
+}
+
+/// Summoned stationary entity (totems). Targetable, but attackers only pick
+/// one when no ordinary unit scores better.
+#[derive(Component)]
+pub struct Structure;
+
+#[derive(Component, Copy, Clone)]
+pub struct BlueprintId(pub usize);
+
+/// Scripted focus-fire order: `target_units` prefers this target while it is
+/// alive and in range, and the charge boid chases it across the map.
+#[derive(Component, Copy, Clone)]
+pub struct AttackTargetDirective {
+    pub target: Entity,
+}
+
+#[derive(Clone)]
+pub struct MeleeWeapon {
+    pub damage: f32,
+    pub range: f32,
+    pub cooldown: f32,
+    pub impact_time: f32,
+    pub swing_time: f32,
+    pub cleave_degrees: f32,
+    /// Seconds between the swing landing and the damage applying.
+    pub impact_delay: f32,
+    /// Plant the unit for the whole swing; melee defaults to move-and-hit.
+    pub stationary_while_acting: bool,
+    /// Visual origin offset from the unit's center; x mirrors with facing.
+    pub muzzle_offset: Vector2,
+    /// Impact visual anchor: 0 target, 1 caster, 2 midpoint.
+    pub impact_anchor: i64,
+    /// Scan priority in `target_units`; see `actions::ActionPriority`.
+    pub priority: i64,
+}
+
+#[derive(Clone)]
+pub struct ProjectileWeapon {
+    pub damage: f32,
+    pub range: f32,
+    pub cooldown: f32,
+    pub impact_time: f32,
+    pub swing_time: f32,
+    pub projectile_speed: f32,
+    pub projectile_texture: Rid,
+    pub projectile_scale: f32,
+    pub splash_radius: f32,
+    /// Damage fraction left at the splash edge; 1.0 disables falloff.
+    pub splash_min_percent: f32,
+    pub impact_delay: f32,
+    /// Plant the unit for the whole swing; ranged defaults to standing still.
+    pub stationary_while_acting: bool,
+    /// Where the projectile spawns relative to the unit; x mirrors with facing.
+    pub muzzle_offset: Vector2,
+    /// Scan priority in `target_units`; see `actions::ActionPriority`.
+    pub priority: i64,
+}
+
+#[derive(Clone)]
+pub struct RadiusWeapon {
+    pub damage: f32,
+    pub range: f32,
+    pub cooldown: f32,
+    pub impact_time: f32,
+    pub swing_time: f32,
+    /// Scan priority in `target_units`; see `actions::ActionPriority`.
+    pub priority: i64,
+}
+
+#[derive(Clone)]
+pub enum Weapon {
+    Melee(MeleeWeapon),
+    Projectile(ProjectileWeapon),
+    Radius(RadiusWeapon),
+}
+
+/// Everything needed to stamp out a unit; registered once, spawned many times.
+#[derive(Clone)]
+pub struct UnitBlueprint {
+    pub texture: Rid,
+    pub hitpoints: f32,
+    pub movespeed: f32,
+    pub acceleration: f32,
+    pub armor: f32,
+    pub magic_resist: f32,
+    pub mass: f32,
+    pub radius: f32,
+    /// Dodge chance in [0, 1] for Normal-type attacks; 0 means none.
+    pub evasion: f32,
+    /// Bitmask of `crate::effects::DebuffImmunities` flags; 0 means none.
+    pub immunities: u32,
+    /// Flat threat added per landing hit on top of the damage; 0 means none.
+    pub threat_bonus: f32,
+    pub weapons: Vec<Weapon>,
+    pub abilities: Vec<UnitAbility>,
+    /// On-hit riders keyed by the index of the weapon they attach to;
+    /// `None` attaches to every weapon.
+    pub riders: Vec<(Option<usize>, UnitAbility)>,
+    /// Priority overrides keyed by ability index, for the rare ability that
+    /// should rank below (or above) the default `ABILITY_PRIORITY`.
+    pub ability_priorities: Vec<(usize, i64)>,
+    pub boid_blend_mode: crate::boids::BoidBlendMode,
+    /// Optional per-blueprint boid tuning; `None` fields use spawn defaults.
+    pub boid_tuning: crate::boids::BoidTuningOverrides,
+}
+
+/// Parsed upgrade modifiers. Additive fields default to 0, multiplicative to
+/// 1, so an empty upgrade is a no-op.
+#[derive(Copy, Clone)]
+pub struct BlueprintUpgrade {
+    pub hitpoints_add: f32,
+    pub hitpoints_mult: f32,
+    pub armor_add: f32,
+    pub magic_resist_add: f32,
+    pub movespeed_mult: f32,
+    pub damage_add: f32,
+    pub damage_mult: f32,
+}
+
+impl Default for BlueprintUpgrade {
+    fn default() -> Self {
+        Self {
+            hitpoints_add: 0.0,
+            hitpoints_mult: 1.0,
+            armor_add: 0.0,
+            magic_resist_add: 0.0,
+            movespeed_mult: 1.0,
+            damage_add: 0.0,
+            damage_mult: 1.0,
+        }
+    }
+}
+
+/// Walk live units of the blueprint and push the upgrade into their base
+/// stats and their basic attacks' damage effects. Buffed values recompute
+/// from the new bases on the next `apply_stat_buffs` pass.
+pub fn apply_upgrade_to_live_units(
+    world: &mut World,
+    blueprint_id: usize,
+    upgrade: &BlueprintUpgrade,
+) {
+    let mut units: Vec<Entity> = Vec::new();
+    let mut query = world.query::<(Entity, &BlueprintId)>();
+    for (entity, id) in query.iter(world) {
+        if id.0 == blueprint_id {
+            units.push(entity);
+        }
+    }
+    for unit in units {
+        if let Some(mut hitpoints) = world.get_mut::<Hitpoints>(unit) {
+            let new_max = (hitpoints.max_hp + upgrade.hitpoints_add) * upgrade.hitpoints_mult;
+            let gained = (new_max - hitpoints.max_hp).max(0.0);
+            hitpoints.max_hp = new_max;
+            hitpoints.hp = (hitpoints.hp + gained).min(new_max);
+        }
+        if let Some(mut base_max_hp) = world.get_mut::<BaseMaxHp>(unit) {
+            base_max_hp.0 = (base_max_hp.0 + upgrade.hitpoints_add) * upgrade.hitpoints_mult;
+        }
+        if let Some(mut armor) = world.get_mut::<Armor>(unit) {
+            armor.base += upgrade.armor_add;
+        }
+        if let Some(mut magic_resist) = world.get_mut::<MagicResist>(unit) {
+            magic_resist.base += upgrade.magic_resist_add;
+        }
+        if let Some(mut speed) = world.get_mut::<Speed>(unit) {
+            speed.base *= upgrade.movespeed_mult;
+        }
+        let actions: Vec<Entity> = world
+            .get::<crate::actions::UnitActions>(unit)
+            .map(|actions| actions.vec.clone())
+            .unwrap_or_default();
+        for action in actions {
+            if world.get::<crate::actions::BasicAttack>(action).is_none() {
+                continue;
+            }
+            if let Some(mut on_hit) = world.get_mut::<crate::actions::OnHitEffects>(action) {
+                for effect in on_hit.vec.iter_mut() {
+                    if let crate::effects::Effect::DamageEffect { damage, .. } = effect {
+                        *damage = *damage * upgrade.damage_mult + upgrade.damage_add;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Overwrite the base stats of live units with the blueprint's current
+/// values, after a blueprint edit. Current hp is clamped to the new maximum;
+/// buffed values recompute from the new bases on the next `apply_stat_buffs`
+/// pass.
+pub fn apply_blueprint_stats_to_live_units(
+    world: &mut World,
+    blueprint_id: usize,
+    blueprint: &UnitBlueprint,
+) {
+    let mut units: Vec<Entity> = Vec::new();
+    let mut query = world.query::<(Entity, &BlueprintId)>();
+    for (entity, id) in query.iter(world) {
+        if id.0 == blueprint_id {
+            units.push(entity);
+        }
+    }
+    for unit in units {
+        if let Some(mut hitpoints) = world.get_mut::<Hitpoints>(unit) {
+            hitpoints.max_hp = blueprint.hitpoints;
+            hitpoints.hp = hitpoints.hp.min(blueprint.hitpoints);
+        }
+        if let Some(mut base_max_hp) = world.get_mut::<BaseMaxHp>(unit) {
+            base_max_hp.0 = blueprint.hitpoints;
+        }
+        if let Some(mut speed) = world.get_mut::<Speed>(unit) {
+            speed.base = blueprint.movespeed;
+        }
+        if let Some(mut acceleration) = world.get_mut::<Acceleration>(unit) {
+            acceleration.base = blueprint.acceleration;
+        }
+        if let Some(mut armor) = world.get_mut::<Armor>(unit) {
+            armor.base = blueprint.armor;
+        }
+        if let Some(mut magic_resist) = world.get_mut::<MagicResist>(unit) {
+            magic_resist.base = blueprint.magic_resist;
+        }
+    }
+}
+
+impl UnitBlueprint {
+    pub fn new(
+        texture: Rid,
+        hitpoints: f32,
+        movespeed: f32,
+        acceleration: f32,
+        armor: f32,
+        magic_resist: f32,
+        mass: f32,
+        radius: f32,
+    ) -> Self {
+        Self {
+            texture,
+            hitpoints,
+            movespeed,
+            acceleration,
+            armor,
+            magic_resist,
+            mass,
+            radius,
+            evasion: 0.0,
+            immunities: 0,
+            threat_bonus: 0.0,
+            weapons: Vec::new(),
+            abilities: Vec::new(),
+            riders: Vec::new(),
+            ability_priorities: Vec::new(),
+            boid_blend_mode: crate::boids::BoidBlendMode::Additive,
+            boid_tuning: crate::boids::BoidTuningOverrides::default(),
+        }
+    }
+
+    pub fn add_weapon(&mut self, weapon: Weapon) {
+        self.weapons.push(weapon);
+    }
+
+    pub fn add_ability(&mut self, ability: UnitAbility) {
+        self.abilities.push(ability);
+    }
+
+    /// Priority for the ability at `index`, falling back to the default.
+    pub fn ability_priority(&self, index: usize) -> i64 {
+        self.ability_priorities
+            .iter()
+            .find(|(overridden, _)| *overridden == index)
+            .map(|(_, priority)| *priority)
+            .unwrap_or(crate::actions::ABILITY_PRIORITY)
+    }
+
+    pub fn add_rider(&mut self, weapon_index: Option<usize>, ability: UnitAbility) {
+        self.riders.push((weapon_index, ability));
+    }
+
+    /// Resolve a rider's weapon index against the action entities spawned for
+    /// this unit's weapons: a keyed rider hits its one slot, an unkeyed rider
+    /// hits every weapon.
+    pub fn rider_targets(
+        weapon_index: Option<usize>,
+        weapon_actions: &[Option<Entity>],
+    ) -> Vec<Entity> {
+        match weapon_index {
+            Some(index) => weapon_actions.get(index).copied().flatten().into_iter().collect(),
+            None => weapon_actions.iter().copied().flatten().collect(),
+        }
+    }
+
+    /// Fold an upgrade into the stored stats so future spawns include it.
+    pub fn apply_upgrade(&mut self, upgrade: &BlueprintUpgrade) {
+        self.hitpoints = (self.hitpoints + upgrade.hitpoints_add) * upgrade.hitpoints_mult;
+        self.armor += upgrade.armor_add;
+        self.magic_resist += upgrade.magic_resist_add;
+        self.movespeed *= upgrade.movespeed_mult;
+        for weapon in self.weapons.iter_mut() {
+            let damage = match weapon {
+                Weapon::Melee(melee) => &mut melee.damage,
+                Weapon::Projectile(projectile) => &mut projectile.damage,
+                Weapon::Radius(radius) => &mut radius.damage,
+            };
+            *damage = *damage * upgrade.damage_mult + upgrade.damage_add;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn riders_are_stored_against_their_weapon_index() {
+        let mut blueprint =
+            UnitBlueprint::new(Rid::new(), 100.0, 50.0, 10.0, 0.0, 0.0, 4.0, 8.0);
+        blueprint.add_weapon(Weapon::Melee(MeleeWeapon {
+            damage: 5.0,
+            range: 16.0,
+            cooldown: 1.0,
+            impact_time: 0.2,
+            swing_time: 0.5,
+            cleave_degrees: 0.0,
+            impact_delay: 0.0,
+            stationary_while_acting: false,
+            muzzle_offset: Vector2::ZERO,
+            impact_anchor: 0,
+            priority: crate::actions::BASIC_ATTACK_PRIORITY,
+        }));
+        blueprint.add_weapon(Weapon::Projectile(ProjectileWeapon {
+            damage: 3.0,
+            range: 120.0,
+            cooldown: 1.5,
+            impact_time: 0.2,
+            swing_time: 0.5,
+            projectile_speed: 200.0,
+            projectile_texture: Rid::new(),
+            projectile_scale: 1.0,
+            splash_radius: 0.0,
+            splash_min_percent: 1.0,
+            impact_delay: 0.0,
+            stationary_while_acting: true,
+            muzzle_offset: Vector2::ZERO,
+            priority: crate::actions::BASIC_ATTACK_PRIORITY,
+        }));
+        // Poison arrows, clean sword.
+        blueprint.add_rider(
+            Some(1),
+            UnitAbility::SlowPoison {
+                percent_damage: 0.02,
+                movement_debuff: 10.0,
+                duration: 3.0,
+                texture: Rid::new(),
+            },
+        );
+
+        assert_eq!(blueprint.riders.len(), 1);
+        let (weapon_index, rider) = &blueprint.riders[0];
+        assert_eq!(*weapon_index, Some(1));
+        assert!(matches!(
+            rider.on_hit_effect(),
+            Some(crate::effects::Effect::PoisonEffect { .. })
+        ));
+    }
+
+    #[test]
+    fn riders_can_be_registered_before_any_weapon() {
+        let mut blueprint =
+            UnitBlueprint::new(Rid::new(), 100.0, 50.0, 10.0, 0.0, 0.0, 4.0, 8.0);
+        // Abilities first, weapons second; the unkeyed rider still covers
+        // both weapons because resolution happens at spawn time.
+        blueprint.add_rider(
+            None,
+            UnitAbility::Stun {
+                duration: 1.0,
+                texture: Rid::new(),
+            },
+        );
+        blueprint.add_weapon(Weapon::Melee(MeleeWeapon {
+            damage: 5.0,
+            range: 16.0,
+            cooldown: 1.0,
+            impact_time: 0.2,
+            swing_time: 0.5,
+            cleave_degrees: 0.0,
+            impact_delay: 0.0,
+            stationary_while_acting: false,
+            muzzle_offset: Vector2::ZERO,
+            impact_anchor: 0,
+            priority: crate::actions::BASIC_ATTACK_PRIORITY,
+        }));
+        blueprint.add_weapon(Weapon::Projectile(ProjectileWeapon {
+            damage: 3.0,
+            range: 120.0,
+            cooldown: 1.5,
+            impact_time: 0.2,
+            swing_time: 0.5,
+            projectile_speed: 200.0,
+            projectile_texture: Rid::new(),
+            projectile_scale: 1.0,
+            splash_radius: 0.0,
+            splash_min_percent: 1.0,
+            impact_delay: 0.0,
+            stationary_while_acting: true,
+            muzzle_offset: Vector2::ZERO,
+            priority: crate::actions::BASIC_ATTACK_PRIORITY,
+        }));
+
+        let sword = Entity::from_raw(1);
+        let bow = Entity::from_raw(2);
+        let weapon_actions = vec![Some(sword), Some(bow)];
+        let (weapon_index, _) = &blueprint.riders[0];
+        assert_eq!(
+            UnitBlueprint::rider_targets(*weapon_index, &weapon_actions),
+            vec![sword, bow]
+        );
+    }
+
+    #[test]
+    fn keyed_riders_resolve_to_a_single_weapon_action() {
+        let sword = Entity::from_raw(1);
+        let bow = Entity::from_raw(2);
+        let weapon_actions = vec![Some(sword), Some(bow)];
+
+        assert_eq!(
+            UnitBlueprint::rider_targets(Some(1), &weapon_actions),
+            vec![bow]
+        );
+        assert_eq!(
+            UnitBlueprint::rider_targets(None, &weapon_actions),
+            vec![sword, bow]
+        );
+        // Out-of-range indices resolve to nothing rather than falling back
+        // to weapon 0.
+        assert!(UnitBlueprint::rider_targets(Some(2), &weapon_actions).is_empty());
+    }
+
+    #[test]
+    fn upgrades_stack_on_the_stored_blueprint() {
+        let mut blueprint =
+            UnitBlueprint::new(Rid::new(), 100.0, 50.0, 10.0, 2.0, 0.0, 4.0, 8.0);
+        blueprint.add_weapon(Weapon::Melee(MeleeWeapon {
+            damage: 10.0,
+            range: 16.0,
+            cooldown: 1.0,
+            impact_time: 0.2,
+            swing_time: 0.5,
+            cleave_degrees: 0.0,
+            impact_delay: 0.0,
+            stationary_while_acting: false,
+            muzzle_offset: Vector2::ZERO,
+            impact_anchor: 0,
+            priority: crate::actions::BASIC_ATTACK_PRIORITY,
+        }));
+
+        blueprint.apply_upgrade(&BlueprintUpgrade {
+            armor_add: 2.0,
+            hitpoints_add: 15.0,
+            ..Default::default()
+        });
+        blueprint.apply_upgrade(&BlueprintUpgrade {
+            damage_mult: 1.1,
+            hitpoints_add: 15.0,
+            ..Default::default()
+        });
+
+        assert_eq!(blueprint.armor, 4.0);
+        assert_eq!(blueprint.hitpoints, 130.0);
+        match &blueprint.weapons[0] {
+            Weapon::Melee(melee) => assert!((melee.damage - 11.0).abs() < 1e-4),
+            _ => panic!("weapon changed variant"),
+        }
+    }
+
+    #[test]
+    fn live_units_pick_up_blueprint_upgrades() {
+        use crate::actions::{BasicAttack, OnHitEffects, UnitActions};
+        use crate::effects::{DamageType, Effect};
+
+        let mut world = World::new();
+        let attack = world
+            .spawn()
+            .insert(BasicAttack)
+            .insert(OnHitEffects {
+                vec: vec![Effect::DamageEffect {
+                    damage: 10.0,
+                    delay: 0.0,
+                    damage_type: DamageType::Normal,
+                }],
+            })
+            .id();
+        let ability = world
+            .spawn()
+            .insert(OnHitEffects {
+                vec: vec![Effect::DamageEffect {
+                    damage: 20.0,
+                    delay: 0.0,
+                    damage_type: DamageType::Magic,
+                }],
+            })
+            .id();
+        let unit = world
+            .spawn()
+            .insert(BlueprintId(0))
+            .insert(Hitpoints {
+                hp: 80.0,
+                max_hp: 100.0,
+            })
+            .insert(Armor {
+                base: 2.0,
+                value: 2.0,
+            })
+            .insert(UnitActions {
+                vec: vec![attack, ability],
+            })
+            .id();
+        let bystander = world
+            .spawn()
+            .insert(BlueprintId(1))
+            .insert(Hitpoints {
+                hp: 100.0,
+                max_hp: 100.0,
+            })
+            .id();
+
+        apply_upgrade_to_live_units(
+            &mut world,
+            0,
+            &BlueprintUpgrade {
+                hitpoints_add: 15.0,
+                armor_add: 2.0,
+                damage_mult: 1.1,
+                ..Default::default()
+            },
+        );
+
+        let hitpoints = world.get::<Hitpoints>(unit).unwrap();
+        assert_eq!(hitpoints.max_hp, 115.0);
+        assert_eq!(hitpoints.hp, 95.0);
+        assert_eq!(world.get::<Armor>(unit).unwrap().base, 4.0);
+        match world.get::<OnHitEffects>(attack).unwrap().vec[0] {
+            Effect::DamageEffect { damage, .. } => assert!((damage - 11.0).abs() < 1e-4),
+            _ => panic!("effect changed variant"),
+        }
+        // Non-attack actions and other blueprints are untouched.
+        match world.get::<OnHitEffects>(ability).unwrap().vec[0] {
+            Effect::DamageEffect { damage, .. } => assert_eq!(damage, 20.0),
+            _ => panic!("effect changed variant"),
+        }
+        assert_eq!(world.get::<Hitpoints>(bystander).unwrap().max_hp, 100.0);
+    }
+}
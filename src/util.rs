@@ -0,0 +1,114 @@
+use bevy_ecs::prelude::*;
+use gdnative::prelude::Vector2;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Edge-to-edge distance between two circles. Negative when overlapping.
+pub fn true_distance(pos_a: Vector2, pos_b: Vector2, radius_a: f32, radius_b: f32) -> f32 {
+    pos_a.distance_to(pos_b) - radius_a - radius_b
+}
+
+pub fn normalized_or_zero(v: Vector2) -> Vector2 {
+    if v.length_squared() > 0.0 {
+        v.normalized()
+    } else {
+        Vector2::ZERO
+    }
+}
+
+/// Distances closer than this are treated as a tie for target selection.
+pub const DISTANCE_TIE_EPSILON: f32 = 1e-3;
+
+/// Nearest candidate out of (entity, distance) pairs. Ties within
+/// [`DISTANCE_TIE_EPSILON`] go to the lower entity index, so every
+/// target-selection site breaks them the same way on every machine.
+pub fn select_nearest(
+    candidates: impl Iterator<Item = (Entity, f32)>,
+) -> Option<(Entity, f32)> {
+    let mut best: Option<(Entity, f32)> = None;
+    for (entity, distance) in candidates {
+        best = match best {
+            None => Some((entity, distance)),
+            Some((best_entity, best_distance)) => {
+                let closer = distance < best_distance - DISTANCE_TIE_EPSILON;
+                let tied = (distance - best_distance).abs() <= DISTANCE_TIE_EPSILON;
+                if closer || (tied && entity.id() < best_entity.id()) {
+                    Some((entity, distance))
+                } else {
+                    Some((best_entity, best_distance))
+                }
+            }
+        };
+    }
+    best
+}
+
+/// Lowest-fraction candidate out of (entity, fraction, distance) triples.
+/// Fractions tied within [`DISTANCE_TIE_EPSILON`] fall back to the nearer
+/// candidate, then to the lower entity index like [`select_nearest`].
+pub fn select_lowest_fraction(
+    candidates: impl Iterator<Item = (Entity, f32, f32)>,
+) -> Option<(Entity, f32)> {
+    let mut best: Option<(Entity, f32, f32)> = None;
+    for (entity, fraction, distance) in candidates {
+        best = match best {
+            None => Some((entity, fraction, distance)),
+            Some((best_entity, best_fraction, best_distance)) => {
+                let lower = fraction < best_fraction - DISTANCE_TIE_EPSILON;
+                let tied = (fraction - best_fraction).abs() <= DISTANCE_TIE_EPSILON;
+                let closer = distance < best_distance - DISTANCE_TIE_EPSILON;
+                let matched = (distance - best_distance).abs() <= DISTANCE_TIE_EPSILON;
+                if lower || (tied && closer) || (tied && matched && entity.id() < best_entity.id())
+                {
+                    Some((entity, fraction, distance))
+                } else {
+                    Some((best_entity, best_fraction, best_distance))
+                }
+            }
+        };
+    }
+    best.map(|(entity, fraction, _)| (entity, fraction))
+}
+
+/// Shared deterministic RNG for anything that needs randomness inside the sim.
+pub struct SimRng(pub StdRng);
+
+impl Default for SimRng {
+    fn default() -> Self {
+        Self(StdRng::seed_from_u64(0xBEEF))
+    }
+}
+
+/// Generic countdown; the entity is despawned when it lapses.
+#[derive(Component)]
+pub struct ExpirationTimer(pub f32);
+
+pub fn expire_timers(
+    mut commands: Commands,
+    delta: Res<crate::physics::DeltaPhysics>,
+    mut query: Query<(Entity, &mut ExpirationTimer)>,
+) {
+    for (entity, mut timer) in query.iter_mut() {
+        timer.0 -= delta.seconds;
+        if timer.0 <= 0.0 {
+            commands.entity(entity).insert(crate::effects::DeathApproaches);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn true_distance_subtracts_radii() {
+        let a = Vector2::new(0.0, 0.0);
+        let b = Vector2::new(10.0, 0.0);
+        assert!((true_distance(a, b, 2.0, 3.0) - 5.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn normalized_or_zero_handles_zero_vector() {
+        assert_eq!(normalized_or_zero(Vector2::ZERO), Vector2::ZERO);
+    }
+}